@@ -14,7 +14,7 @@ use crate::automaton::{
 /// This CFG is implemented without some underlying graph structure.
 #[derive(Debug, Clone)]
 pub struct ModuloCFG {
-    mu: Vec<i32>,
+    mu: Vec<i64>,
     dimension: usize,
     alphabet: Vec<CFGCounterUpdate>,
     initial_valuation: VASSCounterValuation,
@@ -25,7 +25,7 @@ pub struct ModuloCFG {
 
 impl ModuloCFG {
     pub fn new(
-        mu: Vec<i32>,
+        mu: Vec<i64>,
         mut initial_valuation: VASSCounterValuation,
         mut final_valuation: VASSCounterValuation,
     ) -> Self {
@@ -81,11 +81,11 @@ impl ModuloCFG {
         )
     }
 
-    pub fn mu(&self) -> &[i32] {
+    pub fn mu(&self) -> &[i64] {
         &self.mu
     }
 
-    pub fn get_mu(&self, index: VASSCounterIndex) -> i32 {
+    pub fn get_mu(&self, index: VASSCounterIndex) -> i64 {
         self.mu[index.to_usize()]
     }
 
@@ -99,14 +99,14 @@ impl ModuloCFG {
             assert!(val < mu, "Counter value {} exceeds modulo {}", val, mu);
             assert!(val >= 0, "Counter value {} is negative", val);
 
-            index += val * self.mu[..i].iter().product::<i32>();
+            index += val * self.mu[..i].iter().product::<i64>();
         }
         (index as u32).into()
     }
 
     pub fn index_to_counter(&self, index: NodeIndex) -> VASSCounterValuation {
-        let mut counter = vec![0_i32; self.dimension];
-        let mut remaining = index.index() as i32;
+        let mut counter = vec![0_i64; self.dimension];
+        let mut remaining = index.index() as i64;
         for (counter, mu) in counter.iter_mut().zip(&self.mu) {
             *counter = remaining % mu;
             remaining /= mu;
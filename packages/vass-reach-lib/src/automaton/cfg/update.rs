@@ -5,7 +5,9 @@ use std::{
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::automaton::vass::counter::{VASSCounterIndex, VASSCounterValuation};
+use crate::automaton::vass::counter::{
+    CounterOverflowError, VASSCounterIndex, VASSCounterValuation, checked_counter_add,
+};
 
 /// Macro to create a cfg increment update
 #[macro_export]
@@ -23,53 +25,74 @@ macro_rules! cfg_dec {
     };
 }
 
-/// A counter update in a CFG.
+/// A counter update in a CFG. Usually it adds or removes one token, with the
+/// binary encoding of [`InitializedVASS::to_binary_cfg`] it may also carry a
+/// weight `2^exponent`.
+///
+/// A weighted update is applied at once. Since all tokens of one counter move
+/// in the same direction, the counter stays non-negative exactly if it would
+/// with the unit updates the weight stands for.
+///
+/// [`InitializedVASS::to_binary_cfg`]: crate::automaton::vass::initialized::InitializedVASS::to_binary_cfg
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct CFGCounterUpdate {
     counter: VASSCounterIndex,
     positive: bool,
+    exponent: u8,
 }
 
 impl CFGCounterUpdate {
+    /// The largest exponent of a weighted update, the weight still fits into
+    /// an `i64`.
+    pub const MAX_EXPONENT: u8 = 62;
+
     pub fn new(index: u32, positive: bool) -> Self {
         CFGCounterUpdate {
             counter: VASSCounterIndex::new(index),
             positive,
+            exponent: 0,
         }
     }
 
-    pub fn positive(counter: VASSCounterIndex) -> Self {
+    /// An update by `2^exponent` tokens.
+    pub fn weighted(counter: VASSCounterIndex, positive: bool, exponent: u8) -> Self {
+        assert!(
+            exponent <= Self::MAX_EXPONENT,
+            "update weight 2^{exponent} does not fit into an i64"
+        );
         CFGCounterUpdate {
             counter,
-            positive: true,
+            positive,
+            exponent,
         }
     }
 
+    pub fn positive(counter: VASSCounterIndex) -> Self {
+        CFGCounterUpdate::weighted(counter, true, 0)
+    }
+
     pub fn negative(counter: VASSCounterIndex) -> Self {
-        CFGCounterUpdate {
-            counter,
-            positive: false,
-        }
+        CFGCounterUpdate::weighted(counter, false, 0)
     }
 
     pub fn to_positive(&self) -> Self {
         CFGCounterUpdate {
-            counter: self.counter,
             positive: true,
+            ..*self
         }
     }
 
     pub fn to_negative(&self) -> Self {
         CFGCounterUpdate {
-            counter: self.counter,
             positive: false,
+            ..*self
         }
     }
 
     pub fn reverse(&self) -> Self {
         CFGCounterUpdate {
-            counter: self.counter,
             positive: !self.positive,
+            ..*self
         }
     }
 
@@ -77,9 +100,20 @@ impl CFGCounterUpdate {
     /// counters. Meaning all counter updates from `1` to `counter_count`
     /// and `-1` to `-counter_count`.
     pub fn alphabet(counter_count: usize) -> Vec<CFGCounterUpdate> {
-        (0..counter_count)
-            .map(|c| CFGCounterUpdate::new(c as u32, true))
-            .chain((0..counter_count).map(|c| CFGCounterUpdate::new(c as u32, false)))
+        Self::binary_alphabet(counter_count, 0)
+    }
+
+    /// Constructs the alphabet of [`alphabet`](Self::alphabet) with the
+    /// weights `2^0` to `2^max_exponent` for every update.
+    pub fn binary_alphabet(counter_count: usize, max_exponent: u8) -> Vec<CFGCounterUpdate> {
+        (0..=max_exponent)
+            .flat_map(|exponent| {
+                let counters = (0..counter_count).map(|c| VASSCounterIndex::new(c as u32));
+                counters
+                    .clone()
+                    .map(move |c| CFGCounterUpdate::weighted(c, true, exponent))
+                    .chain(counters.map(move |c| CFGCounterUpdate::weighted(c, false, exponent)))
+            })
             .collect()
     }
 
@@ -88,25 +122,37 @@ impl CFGCounterUpdate {
         self.counter
     }
 
+    /// Returns the number of tokens the update adds or removes.
+    pub fn weight(&self) -> i64 {
+        1 << self.exponent
+    }
+
+    pub fn exponent(&self) -> u8 {
+        self.exponent
+    }
+
     /// Returns the increment or decrement value of the counter update.
-    pub fn op(&self) -> i32 {
-        if self.positive { 1 } else { -1 }
+    pub fn op(&self) -> i64 {
+        if self.positive {
+            self.weight()
+        } else {
+            -self.weight()
+        }
     }
 
     /// Returns the increment or decrement value of the counter update.
     pub fn op_i64(&self) -> i64 {
-        if self.positive { 1 } else { -1 }
+        self.op()
     }
 }
 
 impl Display for CFGCounterUpdate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}{}",
-            if self.positive { '+' } else { '-' },
-            self.counter
-        )
+        write!(f, "{}", if self.positive { '+' } else { '-' })?;
+        if self.exponent > 0 {
+            write!(f, "{}", self.weight())?;
+        }
+        write!(f, "{}", self.counter)
     }
 }
 
@@ -154,16 +200,38 @@ impl FromStr for CFGCounterUpdate {
                 first
             )
         };
+        // an optional weight comes before the counter
+        let weight_digits = chars
+            .as_str()
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .count();
+        let exponent = if weight_digits == 0 {
+            0
+        } else {
+            let weight = chars.as_str()[..weight_digits].parse::<i64>()?;
+            if weight <= 0 || weight.count_ones() != 1 {
+                anyhow::bail!("expected a power of two as weight, received {}", weight)
+            }
+            weight.trailing_zeros() as u8
+        };
+        let mut chars = chars.as_str()[weight_digits..].chars();
+
+        let position = 1 + weight_digits;
         let second = chars.next();
         let Some(second) = second else {
-            anyhow::bail!("expected \"c\" at position 1, received eof")
+            anyhow::bail!("expected \"c\" at position {}, received eof", position)
         };
         if second != 'c' {
-            anyhow::bail!("expected \"c\" at position 1, received \"{}\"", second)
+            anyhow::bail!(
+                "expected \"c\" at position {}, received \"{}\"",
+                position,
+                second
+            )
         }
 
         let mut number = 0;
-        for (index, char) in (2..).zip(chars) {
+        for (index, char) in (position + 1..).zip(chars) {
             if let Some(digit) = char.to_digit(10) {
                 number = number * 10 + digit;
             } else {
@@ -175,7 +243,11 @@ impl FromStr for CFGCounterUpdate {
             }
         }
 
-        Ok(CFGCounterUpdate::new(number, positive))
+        Ok(CFGCounterUpdate::weighted(
+            VASSCounterIndex::new(number),
+            positive,
+            exponent,
+        ))
     }
 }
 
@@ -194,6 +266,8 @@ fn test_cfg_counter_update_parser() {
         CFGCounterUpdate::new(0, false),
         CFGCounterUpdate::new(123, true),
         CFGCounterUpdate::new(123, false),
+        CFGCounterUpdate::weighted(VASSCounterIndex::new(2), true, 3),
+        CFGCounterUpdate::weighted(VASSCounterIndex::new(2), false, 10),
     ];
 
     for c in counters {
@@ -209,37 +283,63 @@ fn test_cfg_counter_update_parser() {
 }
 
 pub trait CFGCounterUpdatable {
+    /// Panics if the counter overflows, see
+    /// [`try_apply_cfg_update`](Self::try_apply_cfg_update).
     fn apply_cfg_update(&mut self, update: CFGCounterUpdate);
-    fn apply_cfg_update_times(&mut self, update: CFGCounterUpdate, times: i32);
-    fn apply_cfg_update_mod(&mut self, update: CFGCounterUpdate, modulo: i32);
-    fn apply_cfg_update_mod_slice(&mut self, update: CFGCounterUpdate, modulo: &[i32]);
+    fn try_apply_cfg_update(
+        &mut self,
+        update: CFGCounterUpdate,
+    ) -> Result<(), CounterOverflowError>;
+    /// Panics if the counter overflows.
+    fn apply_cfg_update_times(&mut self, update: CFGCounterUpdate, times: i64);
+    fn apply_cfg_update_mod(&mut self, update: CFGCounterUpdate, modulo: i64);
+    fn apply_cfg_update_mod_slice(&mut self, update: CFGCounterUpdate, modulo: &[i64]);
     fn can_apply_cfg_update(&self, update: &CFGCounterUpdate) -> bool;
 }
 
 impl CFGCounterUpdatable for VASSCounterValuation {
     fn apply_cfg_update(&mut self, update: CFGCounterUpdate) {
-        self[update.counter()] += update.op();
+        self.try_apply_cfg_update(update)
+            .unwrap_or_else(|err| panic!("{err}"));
     }
 
-    fn apply_cfg_update_times(&mut self, update: CFGCounterUpdate, times: i32) {
-        self[update.counter()] += update.op() * times;
+    fn try_apply_cfg_update(
+        &mut self,
+        update: CFGCounterUpdate,
+    ) -> Result<(), CounterOverflowError> {
+        let counter = update.counter();
+        self[counter] = checked_counter_add(counter, self[counter], update.op())?;
+        Ok(())
+    }
+
+    fn apply_cfg_update_times(&mut self, update: CFGCounterUpdate, times: i64) {
+        let counter = update.counter();
+        self[counter] = update
+            .op()
+            .checked_mul(times)
+            .ok_or(CounterOverflowError { counter })
+            .and_then(|delta| checked_counter_add(counter, self[counter], delta))
+            .unwrap_or_else(|err| panic!("{err}"));
     }
 
-    fn apply_cfg_update_mod(&mut self, update: CFGCounterUpdate, modulo: i32) {
+    // computed in i128, so adding the update cannot overflow
+    fn apply_cfg_update_mod(&mut self, update: CFGCounterUpdate, modulo: i64) {
         let counter = update.counter();
-        self[counter] = (self[counter] + update.op()).rem_euclid(modulo);
+        let value =
+            (i128::from(self[counter]) + i128::from(update.op())).rem_euclid(i128::from(modulo));
+        self[counter] = value as i64;
     }
 
-    fn apply_cfg_update_mod_slice(&mut self, update: CFGCounterUpdate, modulo: &[i32]) {
+    fn apply_cfg_update_mod_slice(&mut self, update: CFGCounterUpdate, modulo: &[i64]) {
         let counter = update.counter();
-        self[counter] = (self[counter] + update.op()).rem_euclid(modulo[counter.to_usize()]);
+        self.apply_cfg_update_mod(update, modulo[counter.to_usize()]);
     }
 
     fn can_apply_cfg_update(&self, update: &CFGCounterUpdate) -> bool {
         if update.positive {
             true
         } else {
-            self[update.counter()] > 0
+            self[update.counter()] >= update.weight()
         }
     }
 }
@@ -24,7 +24,7 @@ impl<N: AutomatonNode> VASSCFG<N> {
     /// is guaranteed to terminate.
     pub fn modulo_reach(
         &self,
-        mu: i32,
+        mu: i64,
         initial_valuation: &VASSCounterValuation,
         final_valuation: &VASSCounterValuation,
     ) -> Option<Path<NodeIndex, CFGCounterUpdate>> {
@@ -82,53 +82,56 @@ impl<N: AutomatonNode> VASSCFG<N> {
     }
 }
 
+/// Tracks `counter` from `start` up to `limit` and accepts if it ends in
+/// `end`. Going below zero is a trap, going above `limit` accepts everything.
+/// Letters of other counters do not change the state.
 pub fn build_bounded_counting_cfg(
-    dimension: usize,
+    alphabet: &[CFGCounterUpdate],
     counter: VASSCounterIndex,
     limit: u32,
-    start: i32,
-    end: i32,
+    start: i64,
+    end: i64,
 ) -> VASSCFG<()> {
-    // if limit == 0 {
-    //     panic!("Limit must be greater than 0");
-    // }
-
-    let counter_up = CFGCounterUpdate::positive(counter);
-    let counter_down = CFGCounterUpdate::negative(counter);
-
-    let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(dimension));
+    let mut cfg = VASSCFG::new(alphabet.to_vec());
 
     let negative = cfg.add_node(DfaNode::new(false, true, ()));
     let overflow = cfg.add_node(DfaNode::accepting(()));
 
     // once negative always stays negative
-    for c in CFGCounterUpdate::alphabet(dimension) {
-        cfg.add_edge(&negative, &negative, c);
-        cfg.add_edge(&overflow, &overflow, c);
+    for c in alphabet {
+        cfg.add_edge(&negative, &negative, *c);
+        cfg.add_edge(&overflow, &overflow, *c);
     }
 
-    let mut states = vec![negative];
-    states.extend((0..=limit).map(|i| cfg.add_node(DfaNode::new(i == end as u32, false, ()))));
-    states.push(overflow);
-
-    for i in 1..states.len() - 1 {
-        let prev = states[i - 1];
-        let current = states[i];
-        let next = states[i + 1];
-        cfg.add_edge(&current, &prev, counter_down);
-        cfg.add_edge(&current, &next, counter_up);
-
-        // if we see some symbol that is not our counter updates, we stay in the same
-        // state
-        for c in CFGCounterUpdate::alphabet(dimension) {
-            if c != counter_up && c != counter_down {
-                cfg.add_edge(&current, &current, c);
-            }
+    let values = (0..=limit)
+        .map(|i| cfg.add_node(DfaNode::new(i64::from(i) == end, false, ())))
+        .collect::<Vec<_>>();
+
+    for (value, current) in values.iter().enumerate() {
+        for c in alphabet {
+            let target = if c.counter() == counter {
+                let next = value as i64 + c.op();
+                if next < 0 {
+                    negative
+                } else if next > i64::from(limit) {
+                    overflow
+                } else {
+                    values[next as usize]
+                }
+            } else {
+                // if we see some symbol that is not our counter updates, we stay in the same
+                // state
+                *current
+            };
+            cfg.add_edge(current, &target, *c);
         }
+    }
 
-        if i as i32 == start + 1 {
-            cfg.set_initial(current);
-        }
+    if let Some(initial) = usize::try_from(start)
+        .ok()
+        .and_then(|start| values.get(start))
+    {
+        cfg.set_initial(*initial);
     }
 
     #[cfg(debug_assertions)]
@@ -140,13 +143,13 @@ pub fn build_bounded_counting_cfg(
 }
 
 pub fn build_rev_bounded_counting_cfg(
-    dimension: usize,
+    alphabet: &[CFGCounterUpdate],
     counter: VASSCounterIndex,
     limit: u32,
-    start: i32,
-    end: i32,
+    start: i64,
+    end: i64,
 ) -> DFA<(), CFGCounterUpdate> {
-    let cfg = build_bounded_counting_cfg(dimension, counter, limit, end, start);
+    let cfg = build_bounded_counting_cfg(alphabet, counter, limit, end, start);
 
     let mut cfg = cfg.reverse();
     cfg.reverse_counter_updates();
@@ -154,41 +157,32 @@ pub fn build_rev_bounded_counting_cfg(
     cfg
 }
 
+/// Tracks `counter` modulo `mu` and accepts if it ends in `end` modulo `mu`.
 pub fn build_modulo_counting_cfg(
-    dimension: usize,
+    alphabet: &[CFGCounterUpdate],
     counter: VASSCounterIndex,
-    mu: i32,
-    start: i32,
-    end: i32,
+    mu: i64,
+    start: i64,
+    end: i64,
 ) -> DFA<(), CFGCounterUpdate> {
     let start = start.rem_euclid(mu);
     let end = end.rem_euclid(mu);
 
-    let counter_up = CFGCounterUpdate::positive(counter);
-    let counter_down = CFGCounterUpdate::negative(counter);
-
-    let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(dimension));
+    let mut cfg = VASSCFG::new(alphabet.to_vec());
 
     let mut states = vec![];
     states.extend((0..mu).map(|i| cfg.add_node(DfaNode::new(i == end, false, ()))));
 
-    for i in 0..states.len() {
-        let current = states[i];
-
-        let up_index = (i as i32 + 1).rem_euclid(mu);
-        let down_index = (i as i32 - 1).rem_euclid(mu);
-        let up = states[up_index as usize];
-        let down = states[down_index as usize];
-
-        cfg.add_edge(&current, &up, counter_up);
-        cfg.add_edge(&current, &down, counter_down);
-
-        // if we see some symbol that is not our counter updates, we stay in the same
-        // state
-        for c in CFGCounterUpdate::alphabet(dimension) {
-            if c != counter_up && c != counter_down {
-                cfg.add_edge(&current, &current, c);
-            }
+    for (value, current) in states.iter().enumerate() {
+        for c in alphabet {
+            let target = if c.counter() == counter {
+                states[(value as i64 + c.op()).rem_euclid(mu) as usize]
+            } else {
+                // if we see some symbol that is not our counter updates, we stay in the same
+                // state
+                *current
+            };
+            cfg.add_edge(current, &target, *c);
         }
     }
 
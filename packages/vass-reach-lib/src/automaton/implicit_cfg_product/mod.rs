@@ -70,7 +70,7 @@ pub struct ImplicitCFGProduct {
     pub initial_valuation: VASSCounterValuation,
     pub final_valuation: VASSCounterValuation,
     pub bounded_counting_enabled: bool,
-    pub mu: Box<[i32]>,
+    pub mu: Box<[i64]>,
     pub forward_bound: Box<[u32]>,
     pub backward_bound: Box<[u32]>,
     pub cfgs: Vec<VASSCFG<()>>,
@@ -89,12 +89,15 @@ impl ImplicitCFGProduct {
         let forward_bound = vec![2; dimension];
         let backward_bound = vec![2; dimension];
 
+        // the counting CFGs share the alphabet of the main CFG, which may have
+        // weighted updates
+        let alphabet = cfg.alphabet().to_vec();
         let mut cfgs = Vec::with_capacity(dimension * 3 + 1);
         cfgs.push(cfg);
 
         for i in 0..dimension {
             cfgs.push(build_modulo_counting_cfg(
-                dimension,
+                &alphabet,
                 VASSCounterIndex::new(i as u32),
                 mu[i],
                 initial_valuation[i],
@@ -107,7 +110,7 @@ impl ImplicitCFGProduct {
                 BoundedCFGDirection::Forward,
                 forward_bound[i],
                 VASSCounterIndex::new(i as u32),
-                dimension,
+                &alphabet,
                 initial_valuation[i],
                 final_valuation[i],
                 bounded_counting_enabled,
@@ -118,7 +121,7 @@ impl ImplicitCFGProduct {
                 BoundedCFGDirection::Backward,
                 backward_bound[i],
                 VASSCounterIndex::new(i as u32),
-                dimension,
+                &alphabet,
                 initial_valuation[i],
                 final_valuation[i],
                 bounded_counting_enabled,
@@ -184,7 +187,7 @@ impl ImplicitCFGProduct {
         1 + self.dimension * 2 + counter.to_usize()
     }
 
-    pub fn set_mu(&mut self, counter: VASSCounterIndex, mu: i32) {
+    pub fn set_mu(&mut self, counter: VASSCounterIndex, mu: i64) {
         assert!(mu > 0);
 
         self.reset_explicit();
@@ -192,7 +195,7 @@ impl ImplicitCFGProduct {
         self.mu[counter.to_usize()] = mu;
         let index = self.get_modulo_cfg_index(counter);
         self.cfgs[index] = build_modulo_counting_cfg(
-            self.dimension,
+            self.main_cfg().alphabet(),
             counter,
             mu,
             self.initial_valuation[counter],
@@ -205,7 +208,7 @@ impl ImplicitCFGProduct {
         self.set_mu(counter, new_mu);
     }
 
-    pub fn get_mu(&self, counter: VASSCounterIndex) -> i32 {
+    pub fn get_mu(&self, counter: VASSCounterIndex) -> i64 {
         self.mu[counter.to_usize()]
     }
 
@@ -219,7 +222,7 @@ impl ImplicitCFGProduct {
             BoundedCFGDirection::Forward,
             bound,
             counter,
-            self.dimension,
+            self.main_cfg().alphabet(),
             self.initial_valuation[counter],
             self.final_valuation[counter],
             self.bounded_counting_enabled,
@@ -236,7 +239,7 @@ impl ImplicitCFGProduct {
             BoundedCFGDirection::Backward,
             bound,
             counter,
-            self.dimension,
+            self.main_cfg().alphabet(),
             self.initial_valuation[counter],
             self.final_valuation[counter],
             self.bounded_counting_enabled,
@@ -576,29 +579,29 @@ fn build_counting_automaton(
     direction: BoundedCFGDirection,
     bound: u32,
     counter: VASSCounterIndex,
-    dimension: usize,
-    initial_valuation: i32,
-    final_valuation: i32,
+    alphabet: &[CFGCounterUpdate],
+    initial_valuation: i64,
+    final_valuation: i64,
     bounded_counting_enabled: bool,
 ) -> VASSCFG<()> {
     if !bounded_counting_enabled {
-        return build_accepting_single_state_cfg(dimension);
+        return build_accepting_single_state_cfg(alphabet);
     }
 
     let min_bound = bound
-        .max(initial_valuation.unsigned_abs())
-        .max(final_valuation.unsigned_abs());
+        .max(u32::try_from(initial_valuation.unsigned_abs()).unwrap_or(u32::MAX))
+        .max(u32::try_from(final_valuation.unsigned_abs()).unwrap_or(u32::MAX));
 
     match direction {
         BoundedCFGDirection::Forward => build_bounded_counting_cfg(
-            dimension,
+            alphabet,
             counter,
             min_bound,
             initial_valuation,
             final_valuation,
         ),
         BoundedCFGDirection::Backward => build_rev_bounded_counting_cfg(
-            dimension,
+            alphabet,
             counter,
             min_bound,
             initial_valuation,
@@ -607,13 +610,13 @@ fn build_counting_automaton(
     }
 }
 
-fn build_accepting_single_state_cfg(dimension: usize) -> VASSCFG<()> {
-    let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(dimension));
+fn build_accepting_single_state_cfg(alphabet: &[CFGCounterUpdate]) -> VASSCFG<()> {
+    let mut cfg = VASSCFG::new(alphabet.to_vec());
     let state = cfg.add_node(DfaNode::accepting(()));
     cfg.set_initial(state);
 
-    for letter in CFGCounterUpdate::alphabet(dimension) {
-        cfg.add_edge(&state, &state, letter);
+    for letter in alphabet {
+        cfg.add_edge(&state, &state, *letter);
     }
 
     cfg.set_complete_unchecked();
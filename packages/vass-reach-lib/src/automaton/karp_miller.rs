@@ -1,10 +1,16 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use petgraph::graph::NodeIndex;
 
 use crate::automaton::{
     cfg::{ExplicitEdgeCFG, update::CFGCounterUpdate},
-    vass::{counter::VASSCounterValuation, omega::OmegaCounterValuation},
+    vass::{
+        counter::{CounterOverflowError, VASSCounterValuation},
+        omega::OmegaCounterValuation,
+    },
 };
 
 /// A node in a Karp-Miller coverability tree over a CFG control graph.
@@ -44,6 +50,20 @@ impl KarpMillerCoverabilityTree {
         &self.nodes[index]
     }
 
+    /// Whether some node with an accepting control location of `cfg` covers
+    /// `valuation`. If not, no configuration `(q, valuation)` with accepting
+    /// `q` is reachable from the root.
+    pub fn covers_accepting<C: ExplicitEdgeCFG>(
+        &self,
+        cfg: &C,
+        valuation: &VASSCounterValuation,
+    ) -> bool {
+        let valuation = OmegaCounterValuation::from_finite(valuation);
+        self.nodes
+            .iter()
+            .any(|node| cfg.is_accepting(&node.control) && valuation.leq(&node.valuation))
+    }
+
     pub fn to_graphviz(&self) -> String {
         let mut dot = String::new();
         dot.push_str("digraph karp_miller_tree {\n");
@@ -101,11 +121,29 @@ impl KarpMillerCoverabilityTree {
 ///
 /// Configurations are `(control, valuation)` where `control` is a CFG node and
 /// `valuation` is in `N^d`. The resulting tree labels valuations in
-/// `N^d U {omega}^d`.
+/// `N^d U {omega}^d`. Fails if a finite counter leaves the range of `i64`.
 pub fn build_karp_miller_coverability_tree<C: ExplicitEdgeCFG>(
     cfg: &C,
     initial_valuation: &VASSCounterValuation,
-) -> KarpMillerCoverabilityTree {
+) -> Result<KarpMillerCoverabilityTree, CounterOverflowError> {
+    build_tree(cfg, initial_valuation, None).map(|tree| tree.expect("the build is never stopped"))
+}
+
+/// Like [`build_karp_miller_coverability_tree`], but gives up with `None` once
+/// `stop_signal` is raised.
+pub fn build_karp_miller_coverability_tree_with_stop_signal<C: ExplicitEdgeCFG>(
+    cfg: &C,
+    initial_valuation: &VASSCounterValuation,
+    stop_signal: &AtomicBool,
+) -> Result<Option<KarpMillerCoverabilityTree>, CounterOverflowError> {
+    build_tree(cfg, initial_valuation, Some(stop_signal))
+}
+
+fn build_tree<C: ExplicitEdgeCFG>(
+    cfg: &C,
+    initial_valuation: &VASSCounterValuation,
+    stop_signal: Option<&AtomicBool>,
+) -> Result<Option<KarpMillerCoverabilityTree>, CounterOverflowError> {
     assert_eq!(
         initial_valuation.dimension(),
         cfg.alphabet().len() / 2,
@@ -130,6 +168,10 @@ pub fn build_karp_miller_coverability_tree<C: ExplicitEdgeCFG>(
     queue.push_back(0usize);
 
     while let Some(current_index) = queue.pop_front() {
+        if stop_signal.is_some_and(|stop| stop.load(Ordering::SeqCst)) {
+            return Ok(None);
+        }
+
         if tree.nodes[current_index].closed {
             continue;
         }
@@ -147,7 +189,7 @@ pub fn build_karp_miller_coverability_tree<C: ExplicitEdgeCFG>(
 
             let target = cfg.edge_target_unchecked(&edge);
             let mut next_valuation = valuation.clone();
-            next_valuation.apply_cfg_update(update);
+            next_valuation.try_apply_cfg_update(update)?;
 
             for ancestor in iter_ancestors(current_index, &tree.nodes) {
                 let ancestor_node = &tree.nodes[ancestor];
@@ -178,7 +220,7 @@ pub fn build_karp_miller_coverability_tree<C: ExplicitEdgeCFG>(
         }
     }
 
-    tree
+    Ok(Some(tree))
 }
 
 fn iter_ancestors(
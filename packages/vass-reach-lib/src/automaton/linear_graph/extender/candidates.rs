@@ -0,0 +1,307 @@
+//! Candidate LinearGraph reachability checks, optionally run concurrently.
+//!
+//! Every check is an independent `LinearGraphReachSolver` query against a
+//! fixed template domain, so several candidates can be solved on worker
+//! threads. Z3 contexts are thread-local, hence every worker has its own.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+
+use super::{
+    ProductViewLinearGraph, ProductViewReachSession,
+    congruences::add_linear_graph_boundary_congruences,
+    options::LinearGraphExtenderOptions,
+    templates::{MainCFGTemplateLowerBounds, path_sensitive_linear_graph_template_bounds},
+};
+use crate::{
+    automaton::{
+        implicit_cfg_product::{state::MultiGraphState, view::ImplicitCFGProductView},
+        vass::counter::VASSCounterValuation,
+    },
+    solver::{
+        SolverStatus,
+        linear_graph_reach::{
+            LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearGraphReachSolverError,
+            LinearGraphReachSolverOptions, LinearGraphReachSolverResult,
+            LinearGraphReachSolverStatistics,
+        },
+    },
+};
+
+/// Everything a single candidate check reads from the extender.
+pub(super) struct CandidateQuery<'q, 'a> {
+    pub(super) product: &'a ImplicitCFGProductView<'a>,
+    pub(super) options: &'q LinearGraphExtenderOptions,
+    pub(super) initial_valuation: &'q VASSCounterValuation,
+    pub(super) final_valuation: &'q VASSCounterValuation,
+    pub(super) template_lower_bounds: &'q MainCFGTemplateLowerBounds,
+    pub(super) time_limit: Option<Duration>,
+}
+
+impl<'a> CandidateQuery<'_, 'a> {
+    /// Solves one candidate with the template and congruence constraints of
+    /// the current domain. Raising `stop_signal` interrupts the solver.
+    pub(super) fn solve(
+        &self,
+        linear_graph: &ProductViewLinearGraph<'a>,
+        stop_signal: Option<Arc<AtomicBool>>,
+    ) -> LinearGraphReachSolverResult {
+        self.solve_with(
+            linear_graph,
+            stop_signal,
+            |boundary_constraints, options| {
+                options
+                    .into_solver_with_boundary_lower_bounds(
+                        linear_graph,
+                        self.initial_valuation,
+                        self.final_valuation,
+                        boundary_constraints,
+                    )
+                    .solve()
+            },
+        )
+    }
+
+    /// Like [`Self::solve`], but checks the candidate built from `mask` in an
+    /// incremental session of its layout.
+    pub(super) fn solve_in_session(
+        &self,
+        linear_graph: &ProductViewLinearGraph<'a>,
+        session: &mut ProductViewReachSession<'a>,
+        mask: &[bool],
+    ) -> LinearGraphReachSolverResult {
+        self.solve_with(linear_graph, None, |boundary_constraints, options| {
+            session.check_with_boundary_lower_bounds(mask, &boundary_constraints, options)
+        })
+    }
+
+    fn solve_with(
+        &self,
+        linear_graph: &ProductViewLinearGraph<'a>,
+        stop_signal: Option<Arc<AtomicBool>>,
+        check: impl FnOnce(
+            HashMap<LinearGraphBoundPoint<MultiGraphState>, LinearGraphBoundaryConstraints>,
+            LinearGraphReachSolverOptions,
+        ) -> LinearGraphReachSolverResult,
+    ) -> LinearGraphReachSolverResult {
+        if self.time_limit == Some(Duration::ZERO) {
+            return LinearGraphReachSolverResult::new(
+                SolverStatus::Unknown(LinearGraphReachSolverError::Timeout),
+                LinearGraphReachSolverStatistics::new(0, Duration::ZERO),
+            );
+        }
+
+        let candidate_timer = Instant::now();
+        let lower_bound_timer = Instant::now();
+        let mut boundary_constraints = path_sensitive_linear_graph_template_bounds(
+            linear_graph,
+            self.template_lower_bounds,
+            self.initial_valuation,
+            self.final_valuation,
+            self.options.template_exact_transfer_enabled,
+            self.options.template_exact_transfer_max_templates,
+            self.options.template_interval_bounds_enabled,
+            &self.options.arithmetic,
+        );
+        if self.options.modulo_congruences_enabled {
+            add_linear_graph_boundary_congruences(
+                linear_graph,
+                &self.product.product.mu,
+                self.initial_valuation,
+                self.final_valuation,
+                &mut boundary_constraints,
+            );
+        }
+        let lower_bound_elapsed = lower_bound_timer.elapsed();
+        let lower_bound_count = boundary_constraints
+            .values()
+            .map(|constraints| constraints.lower_bounds.len())
+            .sum::<usize>();
+        let congruence_count = boundary_constraints
+            .values()
+            .map(|constraints| constraints.congruences.len())
+            .sum::<usize>();
+
+        let mut solver_options = LinearGraphReachSolverOptions::default()
+            .with_optional_iteration_limit(self.options.reach_solver_max_iterations)
+            .with_optional_time_limit(self.time_limit)
+            .with_arithmetic_context(self.options.arithmetic.clone());
+        if let Some(stop_signal) = stop_signal {
+            solver_options = solver_options.with_stop_signal(stop_signal);
+        }
+
+        let solve_timer = Instant::now();
+        let result = check(boundary_constraints, solver_options);
+        let solve_elapsed = solve_timer.elapsed();
+
+        tracing::debug!(
+            total_elapsed_ms = candidate_timer.elapsed().as_millis(),
+            lower_bounds_elapsed_ms = lower_bound_elapsed.as_millis(),
+            reach_solver_elapsed_ms = solve_elapsed.as_millis(),
+            candidate_size = linear_graph.size(),
+            parts = linear_graph.sequence.len(),
+            templates = self.template_lower_bounds.templates.len(),
+            boundaries_with_constraints = lower_bound_count,
+            congruences = congruence_count,
+            status = ?result.status,
+            "Solved LinearGraph candidate with template bounds"
+        );
+
+        result
+    }
+
+    /// Solves `candidates` on up to `threads` workers, picking them up in
+    /// order.
+    ///
+    /// Callers consume the results in order and stop at the first unreachable
+    /// candidate, so an unreachable candidate cancels every later sibling.
+    /// Cancelled and skipped candidates have no result.
+    pub(super) fn solve_concurrently(
+        &self,
+        candidates: &[&ProductViewLinearGraph<'a>],
+        threads: usize,
+    ) -> Vec<Option<LinearGraphReachSolverResult>> {
+        solve_until_first_unreachable(
+            candidates.len(),
+            threads,
+            |index, stop_signal| self.solve(candidates[index], Some(stop_signal)),
+            |result| matches!(result.status, SolverStatus::False(_)),
+        )
+    }
+}
+
+/// Runs `solve` for the indices `0..count` on up to `threads` workers. Once
+/// `is_unreachable` holds for a result, later indices are not started and the
+/// stop signals of running ones are raised.
+fn solve_until_first_unreachable<R: Send>(
+    count: usize,
+    threads: usize,
+    solve: impl Fn(usize, Arc<AtomicBool>) -> R + Sync,
+    is_unreachable: impl Fn(&R) -> bool + Sync,
+) -> Vec<Option<R>> {
+    let stop_signals = (0..count)
+        .map(|_| Arc::new(AtomicBool::new(false)))
+        .collect::<Vec<_>>();
+    let first_unreachable = AtomicUsize::new(usize::MAX);
+    let queue = Mutex::new(0..count);
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<R>>>());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, count.max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let Some(index) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    if index > first_unreachable.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let result = solve(index, stop_signals[index].clone());
+                    if is_unreachable(&result) {
+                        first_unreachable.fetch_min(index, Ordering::SeqCst);
+                        for signal in &stop_signals[index + 1..] {
+                            signal.store(true, Ordering::SeqCst);
+                        }
+                    }
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    // Later siblings may have been interrupted, so their unknowns are not
+    // genuine results.
+    let first_unreachable = first_unreachable.into_inner();
+    let mut results = results.into_inner().unwrap();
+    for result in results.iter_mut().skip(first_unreachable.saturating_add(1)) {
+        *result = None;
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Waits until `condition` holds, giving up after a few seconds so a
+    /// broken scheduler fails the test instead of hanging it.
+    fn wait_for(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+
+    #[test]
+    fn unreachable_candidates_cancel_running_siblings() {
+        let started = AtomicUsize::new(0);
+        let cancelled = AtomicUsize::new(0);
+
+        // Candidate 0 is unreachable once all three workers run a check, the
+        // others only finish when they are cancelled.
+        let results = solve_until_first_unreachable(
+            8,
+            3,
+            |index, stop_signal| {
+                started.fetch_add(1, Ordering::SeqCst);
+                if index == 0 {
+                    return wait_for(|| started.load(Ordering::SeqCst) >= 3);
+                }
+                if wait_for(|| stop_signal.load(Ordering::SeqCst)) {
+                    cancelled.fetch_add(1, Ordering::SeqCst);
+                }
+                false
+            },
+            |unreachable| *unreachable,
+        );
+
+        assert_eq!(results[0], Some(true));
+        assert!(results[1..].iter().all(Option::is_none));
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+        assert_eq!(cancelled.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn unreachable_candidates_keep_earlier_siblings_running() {
+        let started = AtomicUsize::new(0);
+        let finished = AtomicBool::new(false);
+        let cancelled = AtomicUsize::new(0);
+
+        // Candidate 2 is unreachable once all three workers run a check, the
+        // earlier ones finish after it and must not see a stop signal.
+        let results = solve_until_first_unreachable(
+            3,
+            3,
+            |index, stop_signal| {
+                started.fetch_add(1, Ordering::SeqCst);
+                if index == 2 {
+                    let all_started = wait_for(|| started.load(Ordering::SeqCst) >= 3);
+                    finished.store(true, Ordering::SeqCst);
+                    return all_started;
+                }
+                wait_for(|| finished.load(Ordering::SeqCst));
+                if stop_signal.load(Ordering::SeqCst) {
+                    cancelled.fetch_add(1, Ordering::SeqCst);
+                }
+                false
+            },
+            |unreachable| *unreachable,
+        );
+
+        assert_eq!(results, [Some(false), Some(false), Some(true)]);
+        assert_eq!(cancelled.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,355 @@
+//! Residue analysis that replaces the modulo counting CFGs in LinearGraphs.
+//!
+//! The modulo counting CFGs of the implicit product only track `c mod mu` for
+//! every counter. Instead of multiplying every LinearGraph node by these
+//! residues, the extender builds LinearGraphs from a view without the modulo
+//! CFGs and hands the residue information to Z3 as congruences on boundary
+//! sums. Residue sets are propagated forward from the initial valuation and
+//! backward from the final valuation; their intersection holds for every run
+//! through the LinearGraph, so the congruences never exclude a real N-run.
+//! They do exclude spurious models, e.g. region Parikh images whose
+//! disconnected cycles have an effect no connected path can produce.
+
+use std::collections::VecDeque;
+
+use hashbrown::HashMap;
+
+use super::ProductViewLinearGraph;
+use crate::{
+    automaton::{
+        Automaton, ExplicitEdgeAutomaton,
+        cfg::update::CFGCounterUpdate,
+        implicit_cfg_product::state::MultiGraphState,
+        linear_graph::part::{LinearGraphPart, LinearGraphRegion},
+        vass::counter::VASSCounterValuation,
+    },
+    solver::linear_graph_reach::{
+        LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearGraphCongruence,
+    },
+};
+
+/// Adds the residue congruences of every boundary point to `constraints`.
+///
+/// `mu` holds the modulus of every counter, usually the `mu` of the implicit
+/// product whose modulo CFGs were left out of the LinearGraph.
+pub(super) fn add_linear_graph_boundary_congruences(
+    linear_graph: &ProductViewLinearGraph<'_>,
+    mu: &[i64],
+    initial_valuation: &VASSCounterValuation,
+    final_valuation: &VASSCounterValuation,
+    constraints: &mut HashMap<
+        LinearGraphBoundPoint<MultiGraphState>,
+        LinearGraphBoundaryConstraints,
+    >,
+) {
+    if linear_graph.sequence.is_empty() || mu.iter().all(|modulus| *modulus <= 1) {
+        return;
+    }
+
+    let analysis = ResidueAnalysis { linear_graph, mu };
+    let forward = analysis.forward_boundary_residues(initial_valuation);
+    let backward = analysis.backward_boundary_residues(final_valuation);
+
+    for (index, (forward, backward)) in forward.into_iter().zip(backward).enumerate() {
+        let congruences = forward.intersect(&backward).congruences(mu);
+        if congruences.is_empty() {
+            continue;
+        }
+
+        constraints
+            .entry(LinearGraphBoundPoint::Boundary {
+                index,
+                state: analysis.boundary_state(index).clone(),
+            })
+            .or_default()
+            .congruences
+            .extend(congruences);
+    }
+}
+
+struct ResidueAnalysis<'a> {
+    linear_graph: &'a ProductViewLinearGraph<'a>,
+    mu: &'a [i64],
+}
+
+impl ResidueAnalysis<'_> {
+    fn boundary_state(&self, index: usize) -> &MultiGraphState {
+        match self.linear_graph.sequence.get(index) {
+            Some(part) => part.start(self.linear_graph),
+            None => self
+                .linear_graph
+                .sequence
+                .last()
+                .expect("boundary analysis requires a non-empty LinearGraph")
+                .end(self.linear_graph),
+        }
+    }
+
+    /// Residues at boundaries `0..=sequence.len()` reachable from the initial
+    /// valuation.
+    fn forward_boundary_residues(&self, initial_valuation: &VASSCounterValuation) -> Vec<Residues> {
+        let mut current = Residues::for_valuation(initial_valuation, self.mu);
+        let mut boundaries = vec![current.clone()];
+
+        for part in &self.linear_graph.sequence {
+            current = self.transfer_part(part, current, false);
+            boundaries.push(current.clone());
+        }
+
+        boundaries
+    }
+
+    /// Residues at boundaries `0..=sequence.len()` that can still reach the
+    /// final valuation.
+    fn backward_boundary_residues(&self, final_valuation: &VASSCounterValuation) -> Vec<Residues> {
+        let mut current = Residues::for_valuation(final_valuation, self.mu);
+        let mut boundaries = vec![current.clone()];
+
+        for part in self.linear_graph.sequence.iter().rev() {
+            current = self.transfer_part(part, current, true);
+            boundaries.push(current.clone());
+        }
+
+        boundaries.reverse();
+        boundaries
+    }
+
+    fn transfer_part(
+        &self,
+        part: &LinearGraphPart,
+        residues: Residues,
+        backwards: bool,
+    ) -> Residues {
+        match part {
+            LinearGraphPart::Path(index) => residues.shift_by_updates(
+                self.linear_graph.path(*index).path.transitions.iter(),
+                backwards,
+            ),
+            LinearGraphPart::RepeatPath(index) => residues.close_under_updates(
+                self.linear_graph
+                    .repeat_path(*index)
+                    .path
+                    .transitions
+                    .iter(),
+                backwards,
+            ),
+            LinearGraphPart::Graph(index) => {
+                self.transfer_graph_region(self.linear_graph.graph(*index), residues, backwards)
+            }
+        }
+    }
+
+    fn transfer_graph_region(
+        &self,
+        graph: &LinearGraphRegion<MultiGraphState>,
+        residues: Residues,
+        backwards: bool,
+    ) -> Residues {
+        let (source, target) = if backwards {
+            (graph.end, graph.start)
+        } else {
+            (graph.start, graph.end)
+        };
+
+        let mut state_residues = vec![None; graph.node_count()];
+        state_residues[source.index()] = Some(residues);
+        let mut queue = VecDeque::from([source]);
+
+        while let Some(node) = queue.pop_front() {
+            let node_residues = state_residues[node.index()]
+                .clone()
+                .expect("queued graph-region states have residues");
+
+            let edges = if backwards {
+                graph.incoming_edge_indices(&node).collect::<Vec<_>>()
+            } else {
+                graph.outgoing_edge_indices(&node).collect::<Vec<_>>()
+            };
+
+            for edge in edges {
+                let (next, update) = if backwards {
+                    (
+                        graph.edge_source_unchecked(&edge),
+                        graph.get_edge_unchecked(&edge).reverse(),
+                    )
+                } else {
+                    (
+                        graph.edge_target_unchecked(&edge),
+                        *graph.get_edge_unchecked(&edge),
+                    )
+                };
+
+                let candidate = node_residues.shift_by_update(&update);
+                if Residues::merge_state(&mut state_residues[next.index()], candidate) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        state_residues[target.index()]
+            .take()
+            .unwrap_or_else(|| Residues::empty(self.mu))
+    }
+}
+
+/// Per-counter sets of residues, indexed by `counter` and then by residue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Residues(Box<[Box<[bool]>]>);
+
+impl Residues {
+    fn for_valuation(valuation: &VASSCounterValuation, mu: &[i64]) -> Self {
+        Self(
+            valuation
+                .iter()
+                .zip(mu.iter())
+                .map(|(value, modulus)| {
+                    let mut residues = vec![false; *modulus as usize].into_boxed_slice();
+                    residues[value.rem_euclid(*modulus) as usize] = true;
+                    residues
+                })
+                .collect(),
+        )
+    }
+
+    fn empty(mu: &[i64]) -> Self {
+        Self(
+            mu.iter()
+                .map(|modulus| vec![false; *modulus as usize].into_boxed_slice())
+                .collect(),
+        )
+    }
+
+    fn shift_by_update(&self, update: &CFGCounterUpdate) -> Self {
+        let mut shifted = self.clone();
+        let counter = update.counter().to_usize();
+        shifted.0[counter] = Self::shift_counter(&self.0[counter], update.op());
+        shifted
+    }
+
+    fn shift_by_updates<'a>(
+        mut self,
+        updates: impl Iterator<Item = &'a CFGCounterUpdate>,
+        backwards: bool,
+    ) -> Self {
+        for (counter, effect) in Self::effects(self.0.len(), updates, backwards)
+            .into_iter()
+            .enumerate()
+        {
+            self.0[counter] = Self::shift_counter(&self.0[counter], effect);
+        }
+
+        self
+    }
+
+    /// Closes every counter's residues under zero or more repetitions of the
+    /// updates' effect.
+    fn close_under_updates<'a>(
+        mut self,
+        updates: impl Iterator<Item = &'a CFGCounterUpdate>,
+        backwards: bool,
+    ) -> Self {
+        for (counter, effect) in Self::effects(self.0.len(), updates, backwards)
+            .into_iter()
+            .enumerate()
+        {
+            let mut closed = self.0[counter].clone();
+            loop {
+                let shifted = Self::shift_counter(&closed, effect);
+                if !Self::union_into(&mut closed, &shifted) {
+                    break;
+                }
+            }
+            self.0[counter] = closed;
+        }
+
+        self
+    }
+
+    fn effects<'a>(
+        dimension: usize,
+        updates: impl Iterator<Item = &'a CFGCounterUpdate>,
+        backwards: bool,
+    ) -> Vec<i64> {
+        let mut effects = vec![0; dimension];
+        for update in updates {
+            effects[update.counter().to_usize()] += update.op();
+        }
+
+        if backwards {
+            effects.iter_mut().for_each(|effect| *effect = -*effect);
+        }
+
+        effects
+    }
+
+    fn shift_counter(residues: &[bool], effect: i64) -> Box<[bool]> {
+        let modulus = residues.len() as i64;
+        let mut shifted = vec![false; residues.len()].into_boxed_slice();
+
+        for residue in (0..modulus).filter(|residue| residues[*residue as usize]) {
+            shifted[(residue + effect).rem_euclid(modulus) as usize] = true;
+        }
+
+        shifted
+    }
+
+    fn union_into(target: &mut [bool], other: &[bool]) -> bool {
+        let mut changed = false;
+
+        for (target, other) in target.iter_mut().zip(other.iter()) {
+            if *other && !*target {
+                *target = true;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn merge_state(state: &mut Option<Self>, candidate: Self) -> bool {
+        let Some(current) = state else {
+            *state = Some(candidate);
+            return true;
+        };
+
+        let mut changed = false;
+        for (current, candidate) in current.0.iter_mut().zip(candidate.0.iter()) {
+            changed |= Self::union_into(current, candidate);
+        }
+
+        changed
+    }
+
+    fn intersect(&self, other: &Self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(left, right)| {
+                    left.iter()
+                        .zip(right.iter())
+                        .map(|(left, right)| *left && *right)
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Congruences for all counters whose residues are restricted. Counters
+    /// with every residue possible carry no information and are skipped.
+    fn congruences(&self, mu: &[i64]) -> Vec<LinearGraphCongruence> {
+        self.0
+            .iter()
+            .zip(mu.iter())
+            .enumerate()
+            .filter(|(_, (residues, _))| !residues.iter().all(|residue| *residue))
+            .map(|(counter, (residues, modulus))| LinearGraphCongruence {
+                counter,
+                modulus: *modulus,
+                residues: (0..*modulus)
+                    .filter(|residue| residues[*residue as usize])
+                    .collect(),
+            })
+            .collect()
+    }
+}
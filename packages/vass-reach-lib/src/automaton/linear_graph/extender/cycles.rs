@@ -41,7 +41,7 @@ pub(super) fn preferred_rooted_cycle(
         let nonzero_effect = cycle
             .transitions
             .iter()
-            .fold(vec![0i32; product.dimension()], |mut effect, update| {
+            .fold(vec![0i64; product.dimension()], |mut effect, update| {
                 effect[update.counter().to_usize()] += update.op();
                 effect
             })
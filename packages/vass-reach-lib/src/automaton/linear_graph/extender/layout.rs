@@ -15,7 +15,7 @@ use crate::{
         path::Path,
         scc::{SCCDag, SCCDagEdge},
     },
-    solver::linear_graph_reach::LinearGraphSolution,
+    solver::linear_graph_reach::{LinearGraphSessionSlot, LinearGraphSolution},
 };
 
 type ProductViewLinearGraph<'a> = LinearGraph<'a, MultiGraphState, ImplicitCFGProductView<'a>>;
@@ -238,6 +238,44 @@ impl<'a> InterpolationLayout<'a> {
         self.build_candidate(&mask).linear_graph
     }
 
+    /// Slots of an incremental reach session covering every candidate of
+    /// this layout. Regions toggle between their seed and full graph.
+    pub(super) fn reach_session_slots(
+        &self,
+    ) -> Vec<LinearGraphSessionSlot<'a, MultiGraphState, ImplicitCFGProductView<'a>>> {
+        self.items
+            .iter()
+            .map(|item| match item {
+                InterpolationItem::FixedPath(path) => {
+                    let mut linear_graph = LinearGraph::empty(self.automaton, self.dimension);
+                    linear_graph.add_path(path.clone().into());
+                    LinearGraphSessionSlot::Fixed(linear_graph)
+                }
+                InterpolationItem::Region(region_index) => {
+                    let region = &self.regions[*region_index];
+                    let mut full = LinearGraph::empty(self.automaton, self.dimension);
+                    full.add_graph(Arc::clone(&region.full_graph));
+                    LinearGraphSessionSlot::Toggle {
+                        seed: region.seed.clone(),
+                        full,
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Translates a region mask into the toggle mask of
+    /// [`Self::reach_session_slots`].
+    pub(super) fn session_mask(&self, mask: &[bool]) -> Vec<bool> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                InterpolationItem::FixedPath(_) => None,
+                InterpolationItem::Region(region_index) => Some(mask[*region_index]),
+            })
+            .collect()
+    }
+
     /// Materializes a candidate LinearGraph from a region mask and records
     /// which graph parts correspond to full regions.
     pub(super) fn build_candidate(&self, mask: &[bool]) -> CandidateBuildResult<'a> {
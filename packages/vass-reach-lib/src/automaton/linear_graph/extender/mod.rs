@@ -1,10 +1,10 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, Ref, RefCell},
     fmt::Debug,
     time::{Duration, Instant},
 };
 
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 
 use crate::{
     automaton::{
@@ -19,41 +19,59 @@ use crate::{
     config::{LinearGraphConfig, LinearGraphRegionOrder, LinearGraphSeedOrder},
     solver::{
         SolverStatus,
+        backend::ArithmeticContext,
         linear_graph_reach::{
-            LinearGraphReachSolverError, LinearGraphReachSolverOptions,
-            LinearGraphReachSolverResult, LinearGraphReachSolverStatistics,
+            LinearGraphReachSession, LinearGraphReachSolverError, LinearGraphReachSolverResult,
+            LinearGraphSolution,
         },
     },
 };
 
+mod candidates;
+mod congruences;
 mod cycles;
 mod layout;
 mod options;
 mod strategy;
+mod sub_scc;
+mod template_store;
 mod templates;
 #[doc(hidden)]
 pub mod template_testing {
     pub use super::templates::testing::*;
 }
 
+use candidates::CandidateQuery;
 use cycles::preferred_rooted_cycle;
 use layout::{CandidateSeed, InterpolationLayout};
 use options::LinearGraphExtenderOptions;
 use strategy::interpolation_strategy;
+use sub_scc::{linear_graph_without_edge, removable_model_edges};
+pub use template_store::{LinearGraphTemplateStore, LinearGraphTemplateStoreStatistics};
 use templates::{
     LinearTemplate, MainCFGTemplateLowerBounds, TemplateSynthesisOptions,
-    main_cfg_template_lower_bounds_with_deadline,
-    path_sensitive_linear_graph_template_lower_bounds, synthesize_template_for_boundaries,
+    main_cfg_template_lower_bounds_with_deadline, synthesize_template_for_boundaries,
 };
 
 pub type LinearGraphExtenderWitness = Path<MultiGraphState, CFGCounterUpdate>;
 type MultiGraphPath = LinearGraphExtenderWitness;
 type ProductViewLinearGraph<'a> = LinearGraph<'a, MultiGraphState, ImplicitCFGProductView<'a>>;
+type ProductViewReachSession<'a> =
+    LinearGraphReachSession<'a, MultiGraphState, ImplicitCFGProductView<'a>>;
+/// An incremental session together with the toggle mask of the candidate.
+type SessionCheck<'s, 'a> = (&'s mut ProductViewReachSession<'a>, &'s [bool]);
 type ExtenderSearchResult<T> = Result<T, ExtenderStop>;
 
 enum ExtenderStop {
     Reachable(MultiGraphPath),
     Timeout,
+    CounterOverflow,
+}
+
+enum FullSccCheck<'a> {
+    Unreachable(ProductViewLinearGraph<'a>),
+    Reachable(ProductViewLinearGraph<'a>, LinearGraphSolution),
+    Unknown,
 }
 
 #[derive(Debug)]
@@ -61,6 +79,8 @@ pub enum LinearGraphExtenderOutput {
     Refinement(VASSCFG<()>),
     Reachable(LinearGraphExtenderWitness),
     Timeout,
+    /// The counters of a candidate model left the `i64` range.
+    CounterOverflow,
 }
 
 /// Builds a large unreachable LinearGraph between one or more seed-language
@@ -80,7 +100,18 @@ pub struct LinearGraphExtender<'a> {
     overall_deadline: Option<Instant>,
     /// Optional SCC DAG supplied by a caller that already computed it.
     scc_dag: Option<SCCDag<MultiGraphState, CFGCounterUpdate>>,
-    template_lower_bounds: RefCell<MainCFGTemplateLowerBounds>,
+    /// Built on first use unless seeded from a template store.
+    template_lower_bounds: RefCell<Option<MainCFGTemplateLowerBounds>>,
+    /// Synthesized templates in the domain, including seeded ones.
+    learned_templates: Cell<usize>,
+    seeded_learned_templates: usize,
+    /// Candidate checks refuted by reused templates without a synthesis round.
+    avoided_syntheses: Cell<usize>,
+    /// Synthesis calls made for spurious candidate models.
+    syntheses: Cell<usize>,
+    /// Set when the initial domain timed out and fell back to an empty one,
+    /// which must not be saved as the domain of the main CFG.
+    template_lower_bounds_incomplete: Cell<bool>,
 }
 
 impl<'a> LinearGraphExtender<'a> {
@@ -168,30 +199,9 @@ impl<'a> LinearGraphExtender<'a> {
         final_valuation: VASSCounterValuation,
         options: LinearGraphExtenderOptions,
     ) -> Self {
-        let timer = Instant::now();
         let overall_deadline = options
             .overall_time_limit
             .and_then(|limit| Instant::now().checked_add(limit));
-        let template_lower_bounds = main_cfg_template_lower_bounds_with_deadline(
-            product.product.main_cfg(),
-            &initial_valuation,
-            options.template_exact_transfer_enabled,
-            options.template_exact_transfer_max_templates,
-            &options.initial_template_families,
-            overall_deadline,
-        )
-        .unwrap_or_else(|| {
-            MainCFGTemplateLowerBounds::empty(product.product.main_cfg().node_count())
-        });
-        tracing::debug!(
-            elapsed_ms = timer.elapsed().as_millis(),
-            templates = template_lower_bounds.templates.len(),
-            primary_path_len = primary_path.len(),
-            auxiliary_paths = auxiliary_paths.len(),
-            exact_transfer_enabled = options.template_exact_transfer_enabled,
-            exact_transfer_max_templates = options.template_exact_transfer_max_templates,
-            "Initialized LinearGraph template lower bounds"
-        );
 
         LinearGraphExtender {
             primary_path,
@@ -203,8 +213,67 @@ impl<'a> LinearGraphExtender<'a> {
             options,
             overall_deadline,
             scc_dag: None,
-            template_lower_bounds: RefCell::new(template_lower_bounds),
+            template_lower_bounds: RefCell::new(None),
+            learned_templates: Cell::new(0),
+            seeded_learned_templates: 0,
+            avoided_syntheses: Cell::new(0),
+            syntheses: Cell::new(0),
+            template_lower_bounds_incomplete: Cell::new(false),
+        }
+    }
+
+    /// Seeds the template domain with bounds saved by earlier extender runs on
+    /// the same main CFG.
+    pub fn with_template_store(mut self, store: &mut LinearGraphTemplateStore) -> Self {
+        let Some((bounds, learned_templates)) =
+            store.lookup(self.product.product.main_cfg(), &self.initial_valuation)
+        else {
+            return self;
+        };
+
+        tracing::debug!(
+            templates = bounds.templates.len(),
+            learned_templates,
+            "Seeded LinearGraph template lower bounds from the template store"
+        );
+        *self.template_lower_bounds.get_mut() = Some(bounds);
+        self.learned_templates.set(learned_templates);
+        self.seeded_learned_templates = learned_templates;
+        self
+    }
+
+    /// Saves the template domain, including templates synthesized by this
+    /// extender, for later runs. A domain whose initialization timed out is
+    /// not saved, so a later run computes it again. The synthesis counts are
+    /// added to the store statistics either way.
+    pub fn save_templates(&self, store: &mut LinearGraphTemplateStore) {
+        // A run without the store synthesizes each reused template at most
+        // once, since it stays in the domain afterwards.
+        let avoided = self
+            .avoided_syntheses
+            .get()
+            .min(self.seeded_learned_templates);
+        store.record_synthesis(avoided, self.syntheses.get());
+        if self.template_lower_bounds_incomplete.get() {
+            return;
         }
+        let Some(bounds) = self.template_lower_bounds.borrow().clone() else {
+            return;
+        };
+
+        store.record(
+            self.product.product.main_cfg(),
+            &self.initial_valuation,
+            bounds,
+            self.learned_templates.get(),
+            self.learned_templates.get() - self.seeded_learned_templates,
+        );
+    }
+
+    /// Checks queries in the context of the calling solver.
+    pub(crate) fn with_arithmetic_context(mut self, context: ArithmeticContext) -> Self {
+        self.options.arithmetic = context;
+        self
     }
 
     /// Reuses a precomputed SCC DAG for route-compatible LinearGraph layout
@@ -377,6 +446,7 @@ impl<'a> LinearGraphExtender<'a> {
             Ok(linear_graph) => LinearGraphExtenderOutput::Refinement(linear_graph.to_cfg()),
             Err(ExtenderStop::Reachable(run)) => LinearGraphExtenderOutput::Reachable(run),
             Err(ExtenderStop::Timeout) => LinearGraphExtenderOutput::Timeout,
+            Err(ExtenderStop::CounterOverflow) => LinearGraphExtenderOutput::CounterOverflow,
         }
     }
 
@@ -415,11 +485,24 @@ impl<'a> LinearGraphExtender<'a> {
             "Starting interpolated LinearGraph search"
         );
 
-        if self.options.check_full_scc_upper_bound
-            && checks < self.options.max_interpolation_steps
-            && let Some(full) = self.try_full_scc_upper_bound(&layout, &mut checks)?
+        if self.options.check_full_scc_upper_bound && checks < self.options.max_interpolation_steps
         {
-            return Ok(full);
+            match self.try_full_scc_upper_bound(&layout, &mut checks)? {
+                FullSccCheck::Unreachable(full) => return Ok(full),
+                FullSccCheck::Reachable(full, solution)
+                    if self.options.sub_scc_refinement_enabled =>
+                {
+                    if let Some(sub_scc) = self.try_sub_scc_refinement(
+                        full,
+                        solution,
+                        &mut checks,
+                        self.options.max_interpolation_steps,
+                    )? {
+                        return Ok(sub_scc);
+                    }
+                }
+                FullSccCheck::Reachable(..) | FullSccCheck::Unknown => {}
+            }
         }
 
         self.search_interpolated_regions(
@@ -526,34 +609,37 @@ impl<'a> LinearGraphExtender<'a> {
             }
         }
 
-        for (position, cycle) in &repeated_paths {
-            if *checks >= max_checks {
-                break;
-            }
-
-            let candidate = LinearGraph::from_path_with_repeat_at(
-                self.primary_path.clone(),
-                cycle.clone(),
-                *position,
-                self.product,
-                self.dimension,
-            );
-            let result = self.solve_candidate(&candidate)?;
-            *checks += 1;
-
-            if matches!(result.status, SolverStatus::False(_)) {
-                tracing::debug!(
-                    repeat_position = *position,
-                    repeat_length = cycle.len(),
-                    first_negative_position,
-                    checks = *checks,
-                    "Witness-aligned repeated-path LinearGraph is unreachable"
-                );
-                return Ok(Some(candidate));
-            }
-        }
+        let mut candidates = repeated_paths
+            .iter()
+            .take(max_checks.saturating_sub(*checks))
+            .map(|(position, cycle)| {
+                LinearGraph::from_path_with_repeat_at(
+                    self.primary_path.clone(),
+                    cycle.clone(),
+                    *position,
+                    self.product,
+                    self.dimension,
+                )
+            })
+            .collect::<Vec<_>>();
+        let Some(selected) = self.first_unreachable_candidate(
+            &candidates.iter().collect::<Vec<_>>(),
+            max_checks,
+            checks,
+        )?
+        else {
+            return Ok(None);
+        };
 
-        Ok(None)
+        let (position, cycle) = &repeated_paths[selected];
+        tracing::debug!(
+            repeat_position = *position,
+            repeat_length = cycle.len(),
+            first_negative_position,
+            checks = *checks,
+            "Witness-aligned repeated-path LinearGraph is unreachable"
+        );
+        Ok(Some(candidates.swap_remove(selected)))
     }
 
     /// Records the selected seed as the lower bound for interpolation.
@@ -603,19 +689,77 @@ impl<'a> LinearGraphExtender<'a> {
         &self,
         layout: &InterpolationLayout<'a>,
         checks: &mut usize,
-    ) -> ExtenderSearchResult<Option<ProductViewLinearGraph<'a>>> {
+    ) -> ExtenderSearchResult<FullSccCheck<'a>> {
         let full_mask = vec![true; layout.regions.len()];
         let full = layout.build_candidate(&full_mask);
         let full_result = self.solve_candidate(&full.linear_graph)?;
         *checks += 1;
 
-        if matches!(full_result.status, SolverStatus::False(_)) {
-            tracing::debug!(
-                size = full.linear_graph.size(),
-                checks = *checks,
-                "Full-SCC LinearGraph is unreachable"
-            );
-            return Ok(Some(full.linear_graph));
+        match full_result.status {
+            SolverStatus::False(_) => {
+                tracing::debug!(
+                    size = full.linear_graph.size(),
+                    checks = *checks,
+                    "Full-SCC LinearGraph is unreachable"
+                );
+                Ok(FullSccCheck::Unreachable(full.linear_graph))
+            }
+            SolverStatus::True(solution) => {
+                Ok(FullSccCheck::Reachable(full.linear_graph, solution))
+            }
+            SolverStatus::Unknown(_) => Ok(FullSccCheck::Unknown),
+        }
+    }
+
+    /// Shrinks a reachable full-SCC LinearGraph to strongly connected subsets
+    /// of its SCC regions until it becomes unreachable.
+    ///
+    /// Every step removes one region edge that the current model uses, so the
+    /// model is cut, while edges of the primary path stay in the candidate.
+    fn try_sub_scc_refinement(
+        &self,
+        mut candidate: ProductViewLinearGraph<'a>,
+        mut solution: LinearGraphSolution,
+        checks: &mut usize,
+        max_checks: usize,
+    ) -> ExtenderSearchResult<Option<ProductViewLinearGraph<'a>>> {
+        while *checks < max_checks {
+            let Some(next) = removable_model_edges(&candidate, &solution, &self.primary_path)
+                .into_iter()
+                .find_map(|edge| linear_graph_without_edge(&candidate, edge))
+            else {
+                tracing::debug!(
+                    checks = *checks,
+                    "No removable model edge left for sub-SCC refinement"
+                );
+                return Ok(None);
+            };
+
+            let result = self.solve_candidate(&next)?;
+            *checks += 1;
+
+            match result.status {
+                SolverStatus::False(_) => {
+                    tracing::debug!(
+                        size = next.size(),
+                        checks = *checks,
+                        "Sub-SCC LinearGraph is unreachable"
+                    );
+                    return Ok(Some(next));
+                }
+                SolverStatus::True(next_solution) => {
+                    candidate = next;
+                    solution = next_solution;
+                }
+                SolverStatus::Unknown(reason) => {
+                    tracing::debug!(
+                        ?reason,
+                        checks = *checks,
+                        "Sub-SCC LinearGraph returned unknown"
+                    );
+                    return Ok(None);
+                }
+            }
         }
 
         Ok(None)
@@ -634,6 +778,16 @@ impl<'a> LinearGraphExtender<'a> {
         let mut pending = self.ordered_regions(layout);
         let mut strategy =
             interpolation_strategy(self.options.interpolation_strategy, pending.len());
+        let mut session = self.options.incremental_sessions_enabled.then(|| {
+            LinearGraphReachSession::with_arithmetic_context(
+                layout.reach_session_slots(),
+                self.initial_valuation.clone(),
+                self.final_valuation.clone(),
+                &self.options.arithmetic,
+            )
+        });
+        let mut speculated = HashMap::new();
+        let mut speculated_templates = self.learned_templates.get();
 
         while checks < max_checks && !pending.is_empty() {
             let batch = strategy.next_batch(&pending);
@@ -644,8 +798,20 @@ impl<'a> LinearGraphExtender<'a> {
 
             // Try one extra region batch on top of the known-unreachable mask.
             let candidate_mask = mask_with_batch(&accepted, &batch);
+            if speculated_templates != self.learned_templates.get() {
+                speculated.clear();
+                speculated_templates = self.learned_templates.get();
+            }
+            if self.options.candidate_threads > 1 && !speculated.contains_key(&candidate_mask) {
+                speculated = self.speculate_batches(layout, &accepted, &batch, &pending);
+            }
             let candidate = layout.build_candidate(&candidate_mask);
-            let candidate_result = self.solve_candidate(&candidate.linear_graph)?;
+            let session_mask = layout.session_mask(&candidate_mask);
+            let candidate_result = self.solve_candidate_from(
+                &candidate.linear_graph,
+                speculated.remove(&candidate_mask),
+                session.as_mut().map(|session| (session, &session_mask[..])),
+            )?;
             checks += 1;
 
             match candidate_result.status {
@@ -659,6 +825,7 @@ impl<'a> LinearGraphExtender<'a> {
 
                     accepted = candidate_mask;
                     best = candidate.linear_graph;
+                    speculated.clear();
                     strategy.on_unreachable(&mut pending, &batch);
                 }
                 SolverStatus::True(solution) => {
@@ -702,6 +869,54 @@ impl<'a> LinearGraphExtender<'a> {
         Ok(best)
     }
 
+    /// Solves the current batch concurrently with the batches the strategy
+    /// tries next when it is not unreachable: its halving prefixes, then the
+    /// following pending regions one at a time. Results are keyed by mask and
+    /// become stale once a batch is accepted.
+    fn speculate_batches(
+        &self,
+        layout: &InterpolationLayout<'a>,
+        accepted: &[bool],
+        batch: &[usize],
+        pending: &[usize],
+    ) -> HashMap<Vec<bool>, LinearGraphReachSolverResult> {
+        let mut batches = vec![batch.to_vec()];
+        let mut prefix_len = batch.len();
+        while prefix_len > 1 {
+            prefix_len = prefix_len.div_ceil(2);
+            batches.push(batch[..prefix_len].to_vec());
+        }
+        batches.extend(
+            pending
+                .iter()
+                .filter(|region| !batch.contains(region))
+                .map(|region| vec![*region]),
+        );
+        batches.truncate(self.options.candidate_threads);
+
+        let masks = batches
+            .iter()
+            .map(|batch| mask_with_batch(accepted, batch))
+            .collect::<Vec<_>>();
+        let candidates = masks
+            .iter()
+            .map(|mask| layout.build_candidate(mask).linear_graph)
+            .collect::<Vec<_>>();
+        let template_lower_bounds = self.template_lower_bounds();
+        let results = self
+            .candidate_query(&template_lower_bounds)
+            .solve_concurrently(
+                &candidates.iter().collect::<Vec<_>>(),
+                self.options.candidate_threads,
+            );
+
+        masks
+            .into_iter()
+            .zip(results)
+            .filter_map(|(mask, result)| Some((mask, result?)))
+            .collect()
+    }
+
     /// Finds a large path-compatible seed-language LinearGraph that is still
     /// unreachable within this phase's solver-check budget.
     ///
@@ -748,26 +963,24 @@ impl<'a> LinearGraphExtender<'a> {
             }
         });
 
-        for candidate in candidates {
-            if *checks >= max_checks {
-                break;
-            }
-
-            let result = self.solve_candidate(&candidate.seed_linear_graph)?;
-            *checks += 1;
-
-            if matches!(result.status, SolverStatus::False(_)) {
-                tracing::debug!(
-                    selected_paths = candidate.path_indices.len(),
-                    size = candidate.seed_linear_graph.size(),
-                    checks,
-                    "Selected path subset for initial LinearGraph"
-                );
-                return Ok(Some(candidate));
-            }
-        }
+        let seed_linear_graphs = candidates
+            .iter()
+            .map(|candidate| &candidate.seed_linear_graph)
+            .collect::<Vec<_>>();
+        let Some(selected) =
+            self.first_unreachable_candidate(&seed_linear_graphs, max_checks, checks)?
+        else {
+            return Ok(None);
+        };
 
-        Ok(None)
+        let candidate = candidates.swap_remove(selected);
+        tracing::debug!(
+            selected_paths = candidate.path_indices.len(),
+            size = candidate.seed_linear_graph.size(),
+            checks,
+            "Selected path subset for initial LinearGraph"
+        );
+        Ok(Some(candidate))
     }
 
     /// Checks whether a candidate LinearGraph is reachable between the
@@ -775,7 +988,19 @@ impl<'a> LinearGraphExtender<'a> {
     fn solve_candidate(
         &self,
         linear_graph: &ProductViewLinearGraph<'a>,
-    ) -> ExtenderSearchResult<crate::solver::linear_graph_reach::LinearGraphReachSolverResult> {
+    ) -> ExtenderSearchResult<LinearGraphReachSolverResult> {
+        self.solve_candidate_from(linear_graph, None, None)
+    }
+
+    /// Like [`Self::solve_candidate`], but starts from a result a worker
+    /// already computed for the current template domain, if there is one, and
+    /// checks in an incremental session when one is given.
+    fn solve_candidate_from(
+        &self,
+        linear_graph: &ProductViewLinearGraph<'a>,
+        mut precomputed: Option<LinearGraphReachSolverResult>,
+        mut session: Option<SessionCheck<'_, 'a>>,
+    ) -> ExtenderSearchResult<LinearGraphReachSolverResult> {
         if self.overall_time_expired() {
             return Err(ExtenderStop::Timeout);
         }
@@ -787,7 +1012,12 @@ impl<'a> LinearGraphExtender<'a> {
         };
 
         for synthesis_step in 0..=synthesis_round_limit {
-            let result = self.solve_candidate_once(linear_graph);
+            let result = precomputed.take().unwrap_or_else(|| {
+                let session = session
+                    .as_mut()
+                    .map(|(session, mask)| (&mut **session, *mask));
+                self.solve_candidate_once(linear_graph, session)
+            });
             if matches!(
                 result.status,
                 SolverStatus::Unknown(LinearGraphReachSolverError::Timeout)
@@ -796,11 +1026,19 @@ impl<'a> LinearGraphExtender<'a> {
                 return Err(ExtenderStop::Timeout);
             }
             let Some(solution) = result.get_solution() else {
+                if synthesis_step == 0
+                    && synthesis_round_limit > 0
+                    && self.seeded_learned_templates > 0
+                    && matches!(result.status, SolverStatus::False(_))
+                {
+                    self.avoided_syntheses.set(self.avoided_syntheses.get() + 1);
+                }
                 return Ok(result);
             };
 
-            if let Some(run) =
-                solution.build_run_with_deadline(linear_graph, true, self.overall_deadline)
+            if let Some(run) = solution
+                .build_run_with_deadline(linear_graph, true, self.overall_deadline)
+                .map_err(|_| ExtenderStop::CounterOverflow)?
             {
                 tracing::info!(
                     run_length = run.len(),
@@ -817,7 +1055,10 @@ impl<'a> LinearGraphExtender<'a> {
                 return Ok(result);
             }
 
-            let model_boundaries = solution.boundary_valuations(linear_graph);
+            let model_boundaries = solution
+                .boundary_valuations(linear_graph)
+                .map_err(|_| ExtenderStop::CounterOverflow)?;
+            self.syntheses.set(self.syntheses.get() + 1);
             let Some((template, analysis)) =
                 self.synthesize_template_excluding_boundaries(&model_boundaries)
             else {
@@ -829,7 +1070,8 @@ impl<'a> LinearGraphExtender<'a> {
                 synthesis_step,
                 "Synthesized relational lower-bound template from spurious LinearGraph model"
             );
-            *self.template_lower_bounds.borrow_mut() = analysis;
+            *self.template_lower_bounds.borrow_mut() = Some(analysis);
+            self.learned_templates.set(self.learned_templates.get() + 1);
         }
 
         unreachable!("template synthesis loop always returns")
@@ -838,57 +1080,110 @@ impl<'a> LinearGraphExtender<'a> {
     fn solve_candidate_once(
         &self,
         linear_graph: &ProductViewLinearGraph<'a>,
-    ) -> crate::solver::linear_graph_reach::LinearGraphReachSolverResult {
-        if self.candidate_time_limit() == Some(Duration::ZERO) {
-            return LinearGraphReachSolverResult::new(
-                SolverStatus::Unknown(LinearGraphReachSolverError::Timeout),
-                LinearGraphReachSolverStatistics::new(0, Duration::ZERO),
-            );
+        session: Option<SessionCheck<'_, 'a>>,
+    ) -> LinearGraphReachSolverResult {
+        let template_lower_bounds = self.template_lower_bounds();
+        let query = self.candidate_query(&template_lower_bounds);
+        match session {
+            Some((session, mask)) => query.solve_in_session(linear_graph, session, mask),
+            None => query.solve(linear_graph, None),
         }
+    }
+
+    /// Solves candidates in order until the first unreachable one and returns
+    /// its index. With more than one candidate thread, the candidates within
+    /// the remaining check budget are first solved concurrently.
+    fn first_unreachable_candidate(
+        &self,
+        candidates: &[&ProductViewLinearGraph<'a>],
+        max_checks: usize,
+        checks: &mut usize,
+    ) -> ExtenderSearchResult<Option<usize>> {
+        let budget = candidates.len().min(max_checks.saturating_sub(*checks));
+        let (mut precomputed, domain_templates) = if self.options.candidate_threads > 1 {
+            let template_lower_bounds = self.template_lower_bounds();
+            (
+                self.candidate_query(&template_lower_bounds)
+                    .solve_concurrently(&candidates[..budget], self.options.candidate_threads),
+                self.learned_templates.get(),
+            )
+        } else {
+            (Vec::new(), self.learned_templates.get())
+        };
 
-        let candidate_timer = Instant::now();
-        let template_lower_bounds = self.template_lower_bounds.borrow();
-        let lower_bound_timer = Instant::now();
-        let boundary_lower_bounds = path_sensitive_linear_graph_template_lower_bounds(
-            linear_graph,
-            &template_lower_bounds,
+        for (index, candidate) in candidates.iter().take(budget).enumerate() {
+            // Results computed before a template was synthesized are still
+            // sound, but re-solving with the stronger domain matches the
+            // sequential search.
+            let result = precomputed
+                .get_mut(index)
+                .and_then(Option::take)
+                .filter(|_| self.learned_templates.get() == domain_templates);
+            let result = self.solve_candidate_from(candidate, result, None)?;
+            *checks += 1;
+
+            if matches!(result.status, SolverStatus::False(_)) {
+                return Ok(Some(index));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn candidate_query<'q>(
+        &'q self,
+        template_lower_bounds: &'q MainCFGTemplateLowerBounds,
+    ) -> CandidateQuery<'q, 'a> {
+        CandidateQuery {
+            product: self.product,
+            options: &self.options,
+            initial_valuation: &self.initial_valuation,
+            final_valuation: &self.final_valuation,
+            template_lower_bounds,
+            time_limit: self.candidate_time_limit(),
+        }
+    }
+
+    fn template_lower_bounds(&self) -> Ref<'_, MainCFGTemplateLowerBounds> {
+        if self.template_lower_bounds.borrow().is_none() {
+            let bounds = self.initial_template_lower_bounds();
+            *self.template_lower_bounds.borrow_mut() = Some(bounds);
+        }
+
+        Ref::map(self.template_lower_bounds.borrow(), |bounds| {
+            bounds
+                .as_ref()
+                .expect("template lower bounds were just initialized")
+        })
+    }
+
+    fn initial_template_lower_bounds(&self) -> MainCFGTemplateLowerBounds {
+        let timer = Instant::now();
+        let main_cfg = self.product.product.main_cfg();
+        let template_lower_bounds = main_cfg_template_lower_bounds_with_deadline(
+            main_cfg,
             &self.initial_valuation,
-            &self.final_valuation,
             self.options.template_exact_transfer_enabled,
             self.options.template_exact_transfer_max_templates,
-        );
-        let lower_bound_elapsed = lower_bound_timer.elapsed();
-        let lower_bound_count = boundary_lower_bounds
-            .values()
-            .map(|constraints| constraints.lower_bounds.len())
-            .sum::<usize>();
-
-        let solve_timer = Instant::now();
-        let result = LinearGraphReachSolverOptions::default()
-            .with_optional_iteration_limit(self.options.reach_solver_max_iterations)
-            .with_optional_time_limit(self.candidate_time_limit())
-            .into_solver_with_boundary_lower_bounds(
-                linear_graph,
-                &self.initial_valuation,
-                &self.final_valuation,
-                boundary_lower_bounds,
-            )
-            .solve();
-        let solve_elapsed = solve_timer.elapsed();
-
+            &self.options.initial_template_families,
+            self.overall_deadline,
+            &self.options.arithmetic,
+        )
+        .unwrap_or_else(|| {
+            self.template_lower_bounds_incomplete.set(true);
+            MainCFGTemplateLowerBounds::empty(main_cfg.node_count())
+        });
         tracing::debug!(
-            total_elapsed_ms = candidate_timer.elapsed().as_millis(),
-            lower_bounds_elapsed_ms = lower_bound_elapsed.as_millis(),
-            reach_solver_elapsed_ms = solve_elapsed.as_millis(),
-            candidate_size = linear_graph.size(),
-            parts = linear_graph.sequence.len(),
+            elapsed_ms = timer.elapsed().as_millis(),
             templates = template_lower_bounds.templates.len(),
-            boundaries_with_constraints = lower_bound_count,
-            status = ?result.status,
-            "Solved LinearGraph candidate with template lower bounds"
+            primary_path_len = self.primary_path.len(),
+            auxiliary_paths = self.auxiliary_paths.len(),
+            exact_transfer_enabled = self.options.template_exact_transfer_enabled,
+            exact_transfer_max_templates = self.options.template_exact_transfer_max_templates,
+            "Initialized LinearGraph template lower bounds"
         );
 
-        result
+        template_lower_bounds
     }
 
     fn candidate_time_limit(&self) -> Option<Duration> {
@@ -925,13 +1220,16 @@ impl<'a> LinearGraphExtender<'a> {
         synthesize_template_for_boundaries(
             self.product.product.main_cfg(),
             &self.initial_valuation,
-            &self.template_lower_bounds.borrow(),
+            &self.template_lower_bounds(),
             &main_boundaries,
             TemplateSynthesisOptions {
                 max_coefficient: self.options.template_synthesis_max_coefficient,
                 max_candidates: self.options.template_synthesis_candidate_limit,
                 exact_transfer_enabled: self.options.template_exact_transfer_enabled,
                 exact_transfer_max_templates: self.options.template_exact_transfer_max_templates,
+                mode: self.options.template_synthesis_mode,
+                time_limit: self.candidate_time_limit(),
+                arithmetic: self.options.arithmetic.clone(),
             },
         )
     }
@@ -1,8 +1,11 @@
 use std::time::Duration;
 
-use crate::config::{
-    LinearGraphConfig, LinearGraphInterpolationStrategy, LinearGraphRegionOrder,
-    LinearGraphSeedOrder, LinearGraphTemplateFamily,
+use crate::{
+    config::{
+        LinearGraphConfig, LinearGraphInterpolationStrategy, LinearGraphRegionOrder,
+        LinearGraphSeedOrder, LinearGraphTemplateFamily, LinearGraphTemplateSynthesisMode,
+    },
+    solver::backend::ArithmeticContext,
 };
 
 #[derive(Debug, Clone)]
@@ -11,18 +14,25 @@ pub(super) struct LinearGraphExtenderOptions {
     pub(super) max_seed_checks: usize,
     pub(super) max_interpolation_steps: usize,
     pub(super) check_full_scc_upper_bound: bool,
+    pub(super) sub_scc_refinement_enabled: bool,
     pub(super) interpolation_strategy: LinearGraphInterpolationStrategy,
     pub(super) region_order: LinearGraphRegionOrder,
     pub(super) seed_order: LinearGraphSeedOrder,
     pub(super) reach_solver_max_iterations: Option<u32>,
     pub(super) reach_solver_timeout: Option<Duration>,
+    pub(super) candidate_threads: usize,
+    pub(super) incremental_sessions_enabled: bool,
+    pub(super) modulo_congruences_enabled: bool,
+    pub(super) template_interval_bounds_enabled: bool,
     pub(super) template_exact_transfer_enabled: bool,
     pub(super) template_exact_transfer_max_templates: usize,
     pub(super) template_synthesis_enabled: bool,
-    pub(super) template_synthesis_max_coefficient: i32,
+    pub(super) template_synthesis_mode: LinearGraphTemplateSynthesisMode,
+    pub(super) template_synthesis_max_coefficient: i64,
     pub(super) template_synthesis_candidate_limit: usize,
     pub(super) template_synthesis_round_limit: usize,
     pub(super) initial_template_families: Vec<LinearGraphTemplateFamily>,
+    pub(super) arithmetic: ArithmeticContext,
 }
 
 impl LinearGraphExtenderOptions {
@@ -34,14 +44,20 @@ impl LinearGraphExtenderOptions {
             max_seed_checks: max_checks,
             max_interpolation_steps: max_checks,
             check_full_scc_upper_bound: true,
+            sub_scc_refinement_enabled: true,
             interpolation_strategy: LinearGraphInterpolationStrategy::AdaptiveBatch,
             region_order: LinearGraphRegionOrder::GainDescending,
             seed_order: LinearGraphSeedOrder::MorePathsThenSize,
             reach_solver_max_iterations: None,
             reach_solver_timeout: None,
+            candidate_threads: 1,
+            incremental_sessions_enabled: false,
+            modulo_congruences_enabled: true,
+            template_interval_bounds_enabled: true,
             template_exact_transfer_enabled: true,
             template_exact_transfer_max_templates: 8,
             template_synthesis_enabled: true,
+            template_synthesis_mode: LinearGraphTemplateSynthesisMode::Enumeration,
             template_synthesis_max_coefficient: 2,
             template_synthesis_candidate_limit: 256,
             template_synthesis_round_limit: 8,
@@ -50,6 +66,7 @@ impl LinearGraphExtenderOptions {
                 LinearGraphTemplateFamily::Pair,
                 LinearGraphTemplateFamily::All,
             ],
+            arithmetic: ArithmeticContext::default(),
         }
     }
 
@@ -62,21 +79,29 @@ impl LinearGraphExtenderOptions {
             max_interpolation_steps: (*config.get_max_interpolation_steps())
                 .unwrap_or(default_checks),
             check_full_scc_upper_bound: *config.get_check_full_scc_upper_bound(),
+            sub_scc_refinement_enabled: *config.get_sub_scc_refinement_enabled(),
             interpolation_strategy: *config.get_interpolation_strategy(),
             region_order: *config.get_region_order(),
             seed_order: *config.get_seed_order(),
             reach_solver_max_iterations: *config.get_reach_solver_max_iterations(),
             reach_solver_timeout: *config.get_reach_solver_timeout(),
+            candidate_threads: (*config.get_candidate_threads()).max(1),
+            incremental_sessions_enabled: *config.get_incremental_sessions_enabled(),
+            modulo_congruences_enabled: *config.get_modulo_congruences_enabled(),
+            template_interval_bounds_enabled: *config.get_template_interval_bounds_enabled(),
             template_exact_transfer_enabled: *config.get_template_exact_transfer_enabled(),
             template_exact_transfer_max_templates: *config
                 .get_template_exact_transfer_max_templates(),
             template_synthesis_enabled: *config.get_template_synthesis_enabled(),
-            template_synthesis_max_coefficient: *config
-                .get_template_synthesis_max_coefficient()
-                .max(&0),
+            template_synthesis_mode: *config.get_template_synthesis_mode(),
+            template_synthesis_max_coefficient: i64::from(
+                *config.get_template_synthesis_max_coefficient(),
+            )
+            .max(0),
             template_synthesis_candidate_limit: *config.get_template_synthesis_candidate_limit(),
             template_synthesis_round_limit: *config.get_template_synthesis_round_limit(),
             initial_template_families: config.get_initial_template_families().clone(),
+            arithmetic: ArithmeticContext::default(),
         }
     }
 }
@@ -0,0 +1,148 @@
+//! Sub-SCC candidates for full-SCC LinearGraphs that turned out reachable.
+//!
+//! A reachable Parikh model of the full-SCC candidate tells us which region
+//! edges the relaxation relies on. Removing one of those edges cuts the model,
+//! and pruning the nodes that no longer lie between the region boundaries
+//! keeps each region a connected subset of its SCC. Edges taken by the primary
+//! path are never removed, so every candidate still covers the spurious path.
+
+use std::cmp::Reverse;
+
+use hashbrown::{HashMap, HashSet};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+
+use super::{MultiGraphPath, ProductViewLinearGraph};
+use crate::{
+    automaton::{
+        Automaton, AutomatonIterators, ExplicitEdgeAutomaton,
+        implicit_cfg_product::state::MultiGraphState,
+        linear_graph::part::{LinearGraphPart, LinearGraphRegion},
+    },
+    solver::linear_graph_reach::LinearGraphSolution,
+};
+
+/// A region edge that the reachable model uses and that may be removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct RemovableEdge {
+    pub(super) graph_index: usize,
+    pub(super) edge: EdgeIndex,
+    pub(super) count: u32,
+}
+
+/// Returns the region edges used by `solution` that are not taken by
+/// `protected_path`, most frequently used edges first.
+pub(super) fn removable_model_edges(
+    linear_graph: &ProductViewLinearGraph<'_>,
+    solution: &LinearGraphSolution,
+    protected_path: &MultiGraphPath,
+) -> Vec<RemovableEdge> {
+    let graph_indices = linear_graph
+        .sequence
+        .iter()
+        .filter_map(|part| match part {
+            LinearGraphPart::Graph(index) => Some(*index),
+            LinearGraphPart::Path(_) | LinearGraphPart::RepeatPath(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut edges = graph_indices
+        .iter()
+        .zip(solution.sub_graph_parikh_images.iter())
+        .flat_map(|(graph_index, image)| {
+            let protected = region_edges_on_path(linear_graph.graph(*graph_index), protected_path);
+            image
+                .iter()
+                .filter(|(edge, count)| *count > 0 && !protected.contains(edge))
+                .map(|(edge, count)| RemovableEdge {
+                    graph_index: *graph_index,
+                    edge,
+                    count,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    edges.sort_by_key(|edge| (Reverse(edge.count), edge.graph_index, edge.edge));
+    edges
+}
+
+/// Removes `edge` from a copy of `linear_graph` and drops the region nodes
+/// that are no longer between the region start and end. Returns `None` when
+/// the removal disconnects the region end from its start.
+pub(super) fn linear_graph_without_edge<'a>(
+    linear_graph: &ProductViewLinearGraph<'a>,
+    edge: RemovableEdge,
+) -> Option<ProductViewLinearGraph<'a>> {
+    let mut candidate = linear_graph.clone();
+    candidate.remove_edge_from_graph(edge.graph_index, edge.edge);
+
+    let live = live_region_nodes(candidate.graph(edge.graph_index))?;
+    if live.len() < candidate.graph(edge.graph_index).node_count() {
+        candidate.restrict_graph_to_subset(edge.graph_index, live);
+    }
+
+    Some(candidate)
+}
+
+/// Nodes reachable from the region start that can still reach the region end.
+fn live_region_nodes(region: &LinearGraphRegion<MultiGraphState>) -> Option<HashSet<NodeIndex>> {
+    let forward = region_closure(region, region.start, false);
+    if !forward.contains(&region.end) {
+        return None;
+    }
+
+    let backward = region_closure(region, region.end, true);
+    Some(forward.intersection(&backward).copied().collect())
+}
+
+fn region_closure(
+    region: &LinearGraphRegion<MultiGraphState>,
+    root: NodeIndex,
+    backwards: bool,
+) -> HashSet<NodeIndex> {
+    let mut visited = HashSet::from([root]);
+    let mut stack = vec![root];
+
+    while let Some(node) = stack.pop() {
+        let neighbours = if backwards {
+            region
+                .incoming_edge_indices(&node)
+                .map(|edge| region.edge_source_unchecked(&edge))
+                .collect::<Vec<_>>()
+        } else {
+            region
+                .outgoing_edge_indices(&node)
+                .map(|edge| region.edge_target_unchecked(&edge))
+                .collect::<Vec<_>>()
+        };
+
+        for neighbour in neighbours {
+            if visited.insert(neighbour) {
+                stack.push(neighbour);
+            }
+        }
+    }
+
+    visited
+}
+
+fn region_edges_on_path(
+    region: &LinearGraphRegion<MultiGraphState>,
+    path: &MultiGraphPath,
+) -> HashSet<EdgeIndex> {
+    let nodes = region
+        .iter_node_indices()
+        .map(|node| (region.map_node_to_product(node).clone(), node))
+        .collect::<HashMap<_, _>>();
+
+    path.states
+        .iter()
+        .zip(path.transitions.iter())
+        .filter_map(|(state, letter)| {
+            let node = nodes.get(state)?;
+            region
+                .outgoing_edge_indices(node)
+                .find(|edge| region.get_edge_unchecked(edge) == letter)
+        })
+        .collect()
+}
@@ -0,0 +1,141 @@
+//! Template invariants shared between LinearGraph extender runs.
+//!
+//! Every refinement step creates a fresh extender, but the main CFG only
+//! changes during preprocessing. Bounds proved for the main CFG therefore stay
+//! valid across steps, and templates synthesized for one spurious model can
+//! seed the domain of the next extender instead of being rediscovered.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
+
+use super::templates::MainCFGTemplateLowerBounds;
+use crate::automaton::{
+    InitializedAutomaton, cfg::vasscfg::VASSCFG, vass::counter::VASSCounterValuation,
+};
+
+/// Reuse counters of a [`LinearGraphTemplateStore`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinearGraphTemplateStoreStatistics {
+    /// Synthesis calls saved by reused templates: candidate checks that a
+    /// seeded domain refuted without a synthesis round, at most one per reused
+    /// template and extender run. A check the initial domain alone refutes
+    /// also counts, so this is an upper bound.
+    pub hits: u64,
+    /// Synthesis calls made because the domain did not refute a candidate.
+    pub misses: u64,
+    /// Stored domains dropped because the main CFG or initial valuation
+    /// changed.
+    pub invalidations: u64,
+    /// Templates synthesized by extenders and saved to the store.
+    pub learned_templates: u64,
+}
+
+impl LinearGraphTemplateStoreStatistics {
+    /// Adds the counters of another store, e.g. one owned by a route solver.
+    pub fn merge(&mut self, other: &Self) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.invalidations += other.invalidations;
+        self.learned_templates += other.learned_templates;
+    }
+}
+
+/// Main-CFG template bounds kept alive across LinearGraph extender runs.
+///
+/// Pass the store to
+/// [`LinearGraphExtender::with_template_store`](super::LinearGraphExtender::with_template_store)
+/// before running an extender and save the extended domain afterwards with
+/// [`LinearGraphExtender::save_templates`](super::LinearGraphExtender::save_templates).
+#[derive(Debug, Clone, Default)]
+pub struct LinearGraphTemplateStore {
+    entry: Option<StoredTemplateDomain>,
+    statistics: LinearGraphTemplateStoreStatistics,
+}
+
+#[derive(Debug, Clone)]
+struct StoredTemplateDomain {
+    key: TemplateStoreKey,
+    bounds: MainCFGTemplateLowerBounds,
+    learned_templates: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TemplateStoreKey {
+    main_cfg_fingerprint: u64,
+    initial_valuation: VASSCounterValuation,
+}
+
+impl TemplateStoreKey {
+    fn new(main_cfg: &VASSCFG<()>, initial_valuation: &VASSCounterValuation) -> Self {
+        let mut hasher = DefaultHasher::new();
+        main_cfg.get_initial().index().hash(&mut hasher);
+        for node in main_cfg.graph.node_weights() {
+            node.hash(&mut hasher);
+        }
+        for edge in main_cfg.graph.edge_references() {
+            (edge.source().index(), edge.target().index(), edge.weight()).hash(&mut hasher);
+        }
+
+        Self {
+            main_cfg_fingerprint: hasher.finish(),
+            initial_valuation: initial_valuation.clone(),
+        }
+    }
+}
+
+impl LinearGraphTemplateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn statistics(&self) -> &LinearGraphTemplateStoreStatistics {
+        &self.statistics
+    }
+
+    /// Resets the reuse counters but keeps the stored domain.
+    pub fn reset_statistics(&mut self) {
+        self.statistics = LinearGraphTemplateStoreStatistics::default();
+    }
+
+    /// Returns the stored domain together with the number of synthesized
+    /// templates in it, or `None` when nothing valid is stored.
+    pub(super) fn lookup(
+        &mut self,
+        main_cfg: &VASSCFG<()>,
+        initial_valuation: &VASSCounterValuation,
+    ) -> Option<(MainCFGTemplateLowerBounds, usize)> {
+        let key = TemplateStoreKey::new(main_cfg, initial_valuation);
+        if self.entry.as_ref().is_some_and(|entry| entry.key != key) {
+            self.entry = None;
+            self.statistics.invalidations += 1;
+        }
+
+        self.entry
+            .as_ref()
+            .map(|entry| (entry.bounds.clone(), entry.learned_templates))
+    }
+
+    /// Adds the synthesis calls an extender saved and made.
+    pub(super) fn record_synthesis(&mut self, avoided: usize, made: usize) {
+        self.statistics.hits += avoided as u64;
+        self.statistics.misses += made as u64;
+    }
+
+    pub(super) fn record(
+        &mut self,
+        main_cfg: &VASSCFG<()>,
+        initial_valuation: &VASSCounterValuation,
+        bounds: MainCFGTemplateLowerBounds,
+        learned_templates: usize,
+        new_templates: usize,
+    ) {
+        self.statistics.learned_templates += new_templates as u64;
+        self.entry = Some(StoredTemplateDomain {
+            key: TemplateStoreKey::new(main_cfg, initial_valuation),
+            bounds,
+            learned_templates,
+        });
+    }
+}
@@ -3,6 +3,17 @@
 //! This module implements the "Abstract Domain", "Initial Bounds", "Exact SMT
 //! Transfer", "Joining Control-Flow Paths", and "LinearGraph Integration"
 //! sections of `docs/linear-template-invariants.md`.
+//!
+//! Along LinearGraphs the templates are also bounded from above, which turns
+//! them into intervals. Upper bounds join with max and are widened to
+//! unbounded once they keep growing. Intervals that collapse to a single value,
+//! e.g. conservation laws like `c_0 + c_1 = 3`, become equalities.
+//!
+//! Only the path-sensitive analysis of a LinearGraph candidate computes upper
+//! bounds and equalities, and they only reach the solver as
+//! [`LinearGraphBoundaryConstraints`]. The main CFG analysis, whose results
+//! seed the boundary lower bounds, and the learned templates stay lower
+//! bounds only.
 
 use std::{cell::RefCell, collections::VecDeque, time::Instant};
 
@@ -21,17 +32,26 @@ use crate::{
         vass::counter::VASSCounterValuation,
     },
     config::LinearGraphTemplateFamily,
-    solver::linear_graph_reach::{
-        LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearTemplateLowerBound,
+    solver::{
+        backend::ArithmeticContext,
+        linear_graph_reach::{
+            LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearTemplateEquality,
+            LinearTemplateLowerBound, LinearTemplateUpperBound,
+        },
     },
 };
 
+/// Number of times an upper bound may grow at the same point before it is
+/// widened to unbounded.
+const UPPER_BOUND_WIDENING_DELAY: usize = 3;
+
 pub(in crate::automaton::linear_graph::extender) fn main_cfg_template_lower_bounds(
     cfg: &VASSCFG<()>,
     initial_valuation: &VASSCounterValuation,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
     initial_template_families: &[LinearGraphTemplateFamily],
+    arithmetic: &ArithmeticContext,
 ) -> MainCFGTemplateLowerBounds {
     main_cfg_template_lower_bounds_with_deadline(
         cfg,
@@ -40,6 +60,7 @@ pub(in crate::automaton::linear_graph::extender) fn main_cfg_template_lower_boun
         exact_transfer_max_templates,
         initial_template_families,
         None,
+        arithmetic,
     )
     .expect("template analysis without a deadline cannot time out")
 }
@@ -51,6 +72,7 @@ pub(in crate::automaton::linear_graph::extender) fn main_cfg_template_lower_boun
     exact_transfer_max_templates: usize,
     initial_template_families: &[LinearGraphTemplateFamily],
     deadline: Option<Instant>,
+    arithmetic: &ArithmeticContext,
 ) -> Option<MainCFGTemplateLowerBounds> {
     let timer = Instant::now();
     let templates = default_templates(initial_valuation.dimension(), initial_template_families);
@@ -70,6 +92,7 @@ pub(in crate::automaton::linear_graph::extender) fn main_cfg_template_lower_boun
         exact_transfer_enabled,
         exact_transfer_max_templates,
         deadline,
+        arithmetic,
     )
     .run();
 
@@ -104,6 +127,7 @@ pub(super) fn analyze_templates(
     templates: Vec<LinearTemplate>,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
+    arithmetic: &ArithmeticContext,
 ) -> MainCFGTemplateLowerBounds {
     let timer = Instant::now();
     let template_count = templates.len();
@@ -123,6 +147,7 @@ pub(super) fn analyze_templates(
         exact_transfer_enabled,
         exact_transfer_max_templates,
         None,
+        arithmetic,
     )
     .run()
     .expect("template analysis without a deadline cannot time out");
@@ -151,6 +176,7 @@ pub(super) fn analyze_with_incremental_template(
     template: LinearTemplate,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
+    arithmetic: &ArithmeticContext,
 ) -> MainCFGTemplateLowerBounds {
     let timer = Instant::now();
     tracing::debug!(
@@ -169,6 +195,7 @@ pub(super) fn analyze_with_incremental_template(
         template,
         exact_transfer_enabled,
         exact_transfer_max_templates,
+        arithmetic,
     )
     .run();
 
@@ -198,13 +225,16 @@ pub(super) fn default_templates(
     DefaultTemplateDomain::new(dimension, families).templates()
 }
 
-pub(in crate::automaton::linear_graph::extender) fn path_sensitive_linear_graph_template_lower_bounds(
+#[allow(clippy::too_many_arguments)]
+pub(in crate::automaton::linear_graph::extender) fn path_sensitive_linear_graph_template_bounds(
     linear_graph: &ProductViewLinearGraph<'_>,
     main_bounds: &MainCFGTemplateLowerBounds,
     initial_valuation: &VASSCounterValuation,
     final_valuation: &VASSCounterValuation,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
+    interval_bounds_enabled: bool,
+    arithmetic: &ArithmeticContext,
 ) -> HashMap<LinearGraphBoundPoint<MultiGraphState>, LinearGraphBoundaryConstraints> {
     let timer = Instant::now();
     tracing::debug!(
@@ -213,7 +243,8 @@ pub(in crate::automaton::linear_graph::extender) fn path_sensitive_linear_graph_
         templates = main_bounds.templates.len(),
         exact_transfer_enabled,
         exact_transfer_max_templates,
-        "Starting path-sensitive LinearGraph template lower-bound analysis"
+        interval_bounds_enabled,
+        "Starting path-sensitive LinearGraph template bound analysis"
     );
 
     let constraints = LinearGraphTemplateBounder::new(
@@ -223,23 +254,25 @@ pub(in crate::automaton::linear_graph::extender) fn path_sensitive_linear_graph_
         final_valuation,
         exact_transfer_enabled,
         exact_transfer_max_templates,
+        arithmetic,
     )
-    .boundary_constraints();
+    .boundary_constraints(interval_bounds_enabled);
 
-    let constraint_count = constraints
-        .values()
-        .map(|constraints| constraints.lower_bounds.len())
-        .sum::<usize>();
+    let count = |select: fn(&LinearGraphBoundaryConstraints) -> usize| {
+        constraints.values().map(select).sum::<usize>()
+    };
     tracing::debug!(
         elapsed_ms = timer.elapsed().as_millis(),
         linear_graph_size = linear_graph.size(),
         parts = linear_graph.sequence.len(),
         boundaries = constraints.len(),
-        lower_bound_constraints = constraint_count,
+        lower_bound_constraints = count(|constraints| constraints.lower_bounds.len()),
+        upper_bound_constraints = count(|constraints| constraints.upper_bounds.len()),
+        equality_constraints = count(|constraints| constraints.equalities.len()),
         templates = main_bounds.templates.len(),
         exact_transfer_enabled,
         exact_transfer_max_templates,
-        "Finished path-sensitive LinearGraph template lower-bound analysis"
+        "Finished path-sensitive LinearGraph template bound analysis"
     );
 
     constraints
@@ -249,7 +282,7 @@ struct TemplateAnalysis<'a> {
     cfg: &'a VASSCFG<()>,
     initial_valuation: &'a VASSCounterValuation,
     templates: Vec<LinearTemplate>,
-    cap: i32,
+    cap: i64,
     transfer: TemplateTransfer,
     deadline: Option<Instant>,
 }
@@ -262,13 +295,18 @@ impl<'a> TemplateAnalysis<'a> {
         exact_transfer_enabled: bool,
         exact_transfer_max_templates: usize,
         deadline: Option<Instant>,
+        arithmetic: &ArithmeticContext,
     ) -> Self {
         Self {
             cfg,
             initial_valuation,
             templates,
             cap: AnalysisCap::for_cfg(cfg),
-            transfer: TemplateTransfer::new(exact_transfer_enabled, exact_transfer_max_templates),
+            transfer: TemplateTransfer::new(
+                exact_transfer_enabled,
+                exact_transfer_max_templates,
+                arithmetic,
+            ),
             deadline,
         }
     }
@@ -289,7 +327,7 @@ impl<'a> TemplateAnalysis<'a> {
         ))
     }
 
-    fn initial_state_bounds(&self) -> Vec<Option<Box<[i32]>>> {
+    fn initial_state_bounds(&self) -> Vec<Option<Box<[i64]>>> {
         let mut state_bounds = vec![None; self.cfg.node_count()];
         state_bounds[self.cfg.get_initial().index()] = Some(TemplateBounds::for_valuation(
             &self.templates,
@@ -299,7 +337,7 @@ impl<'a> TemplateAnalysis<'a> {
         state_bounds
     }
 
-    fn propagate_bounds(&self, state_bounds: &mut [Option<Box<[i32]>>]) -> bool {
+    fn propagate_bounds(&self, state_bounds: &mut [Option<Box<[i64]>>]) -> bool {
         let timer = Instant::now();
         let initial = self.cfg.get_initial();
         let mut queue = VecDeque::from([initial]);
@@ -371,30 +409,45 @@ impl<'a> TemplateAnalysis<'a> {
 struct AnalysisCap;
 
 impl AnalysisCap {
-    fn for_cfg(cfg: &VASSCFG<()>) -> i32 {
+    fn for_cfg(cfg: &VASSCFG<()>) -> i64 {
         // Clamping makes the abstract domain finite and only weakens each lower bound.
         Self::for_size(cfg.node_count())
     }
 
-    fn for_size(size: usize) -> i32 {
-        i32::try_from(size).unwrap_or(i32::MAX)
+    fn for_size(size: usize) -> i64 {
+        i64::try_from(size).unwrap_or(i64::MAX)
+    }
+
+    fn for_size_and_values<'v>(
+        size: usize,
+        templates: &[LinearTemplate],
+        valuations: impl IntoIterator<Item = &'v VASSCounterValuation>,
+    ) -> i64 {
+        // Synthesized templates may have large coefficients. Keeping the
+        // boundary values representable avoids clamping away the very bound
+        // that separates a spurious model.
+        valuations
+            .into_iter()
+            .flat_map(|valuation| templates.iter().map(|template| template.value(valuation)))
+            .fold(Self::for_size(size), i64::max)
     }
 }
 
 pub(super) fn successor_bounds(
     templates: &[LinearTemplate],
-    source_bounds: &[i32],
+    source_bounds: &[i64],
     update: &CFGCounterUpdate,
-    cap: i32,
+    cap: i64,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
-) -> Box<[i32]> {
-    TemplateTransfer::new(exact_transfer_enabled, exact_transfer_max_templates).successor_bounds(
-        templates,
-        source_bounds,
-        update,
-        cap,
+    arithmetic: &ArithmeticContext,
+) -> Box<[i64]> {
+    TemplateTransfer::new(
+        exact_transfer_enabled,
+        exact_transfer_max_templates,
+        arithmetic,
     )
+    .successor_bounds(templates, source_bounds, update, cap)
 }
 
 struct IncrementalTemplateAnalysis<'a> {
@@ -403,7 +456,7 @@ struct IncrementalTemplateAnalysis<'a> {
     current: &'a MainCFGTemplateLowerBounds,
     templates: Vec<LinearTemplate>,
     new_template_index: usize,
-    cap: i32,
+    cap: i64,
     transfer: TemplateTransfer,
 }
 
@@ -415,8 +468,13 @@ impl<'a> IncrementalTemplateAnalysis<'a> {
         template: LinearTemplate,
         exact_transfer_enabled: bool,
         exact_transfer_max_templates: usize,
+        arithmetic: &ArithmeticContext,
     ) -> Self {
-        let cap = AnalysisCap::for_cfg(cfg);
+        let cap = AnalysisCap::for_size_and_values(
+            cfg.node_count(),
+            std::slice::from_ref(&template),
+            [initial_valuation],
+        );
         let mut templates = current.templates.clone();
         templates.push(template);
         let new_template_index = templates.len() - 1;
@@ -428,7 +486,11 @@ impl<'a> IncrementalTemplateAnalysis<'a> {
             templates,
             new_template_index,
             cap,
-            transfer: TemplateTransfer::new(exact_transfer_enabled, exact_transfer_max_templates),
+            transfer: TemplateTransfer::new(
+                exact_transfer_enabled,
+                exact_transfer_max_templates,
+                arithmetic,
+            ),
         }
     }
 
@@ -451,7 +513,7 @@ impl<'a> IncrementalTemplateAnalysis<'a> {
         MainCFGTemplateLowerBounds::new(self.templates, state_bounds)
     }
 
-    fn fixed_point(&self) -> Vec<Option<i32>> {
+    fn fixed_point(&self) -> Vec<Option<i64>> {
         let timer = Instant::now();
         let initial = self.cfg.get_initial();
         let mut new_bounds = vec![None; self.cfg.node_count()];
@@ -515,9 +577,9 @@ impl<'a> IncrementalTemplateAnalysis<'a> {
 
     fn source_bounds(
         &self,
-        new_bounds: &[Option<i32>],
+        new_bounds: &[Option<i64>],
         source: petgraph::graph::NodeIndex,
-    ) -> Option<Box<[i32]>> {
+    ) -> Option<Box<[i64]>> {
         let mut source_bounds = self.current.state_bounds(source)?.to_vec();
         source_bounds.push(
             self.new_template()
@@ -534,14 +596,20 @@ impl<'a> IncrementalTemplateAnalysis<'a> {
 struct TemplateTransfer {
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
-    exact_cache: RefCell<HashMap<ExactTransferCacheKey, Box<[i32]>>>,
+    arithmetic: ArithmeticContext,
+    exact_cache: RefCell<HashMap<ExactTransferCacheKey, Box<[i64]>>>,
 }
 
 impl TemplateTransfer {
-    fn new(exact_transfer_enabled: bool, exact_transfer_max_templates: usize) -> Self {
+    fn new(
+        exact_transfer_enabled: bool,
+        exact_transfer_max_templates: usize,
+        arithmetic: &ArithmeticContext,
+    ) -> Self {
         Self {
             exact_transfer_enabled,
             exact_transfer_max_templates,
+            arithmetic: arithmetic.clone(),
             exact_cache: RefCell::new(HashMap::new()),
         }
     }
@@ -549,10 +617,10 @@ impl TemplateTransfer {
     fn successor_bounds(
         &self,
         templates: &[LinearTemplate],
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         update: &CFGCounterUpdate,
-        cap: i32,
-    ) -> Box<[i32]> {
+        cap: i64,
+    ) -> Box<[i64]> {
         let timer = Instant::now();
         let exact_transfer_enabled = self.should_use_exact_transfer(templates);
         let result = if exact_transfer_enabled {
@@ -577,11 +645,11 @@ impl TemplateTransfer {
     fn successor_template_bound(
         &self,
         templates: &[LinearTemplate],
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         update: &CFGCounterUpdate,
         objective_index: usize,
-        cap: i32,
-    ) -> i32 {
+        cap: i64,
+    ) -> i64 {
         let timer = Instant::now();
         let exact_transfer_enabled = self.should_use_exact_transfer(templates);
         let result = if exact_transfer_enabled {
@@ -618,16 +686,17 @@ impl TemplateTransfer {
     fn exact_successor_bounds(
         &self,
         templates: &[LinearTemplate],
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         update: &CFGCounterUpdate,
-        cap: i32,
-    ) -> Box<[i32]> {
+        cap: i64,
+    ) -> Box<[i64]> {
         let key = ExactTransferCacheKey::new(source_bounds, *update, cap);
         if let Some(bounds) = self.exact_cache.borrow().get(&key) {
             return bounds.clone();
         }
 
-        let exact_transfer = ExactTemplateTransfer::new(templates, source_bounds, update);
+        let exact_transfer =
+            ExactTemplateTransfer::new(&self.arithmetic, templates, source_bounds, update);
         let bounds = templates
             .iter()
             .map(|template| exact_transfer.successor_template_bound(template, update, cap))
@@ -639,10 +708,10 @@ impl TemplateTransfer {
     fn independent_successor_bounds(
         &self,
         templates: &[LinearTemplate],
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         update: &CFGCounterUpdate,
-        cap: i32,
-    ) -> Box<[i32]> {
+        cap: i64,
+    ) -> Box<[i64]> {
         templates
             .iter()
             .zip(source_bounds.iter())
@@ -655,10 +724,10 @@ impl TemplateTransfer {
     fn independent_successor_template_bound(
         &self,
         template: &LinearTemplate,
-        source_bound: i32,
+        source_bound: i64,
         update: &CFGCounterUpdate,
-        cap: i32,
-    ) -> i32 {
+        cap: i64,
+    ) -> i64 {
         let counter = update.counter().to_usize();
         let coefficient = template.coefficients[counter];
         let delta = coefficient * update.op();
@@ -669,13 +738,13 @@ impl TemplateTransfer {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct ExactTransferCacheKey {
-    source_bounds: Box<[i32]>,
+    source_bounds: Box<[i64]>,
     update: CFGCounterUpdate,
-    cap: i32,
+    cap: i64,
 }
 
 impl ExactTransferCacheKey {
-    fn new(source_bounds: &[i32], update: CFGCounterUpdate, cap: i32) -> Self {
+    fn new(source_bounds: &[i64], update: CFGCounterUpdate, cap: i64) -> Self {
         Self {
             source_bounds: source_bounds.to_vec().into_boxed_slice(),
             update,
@@ -704,7 +773,7 @@ impl<'a> DefaultTemplateDomain<'a> {
             .collect()
     }
 
-    fn coefficients(self) -> Vec<Vec<i32>> {
+    fn coefficients(self) -> Vec<Vec<i64>> {
         let mut coefficients = Vec::new();
 
         for family in self.families {
@@ -718,7 +787,7 @@ impl<'a> DefaultTemplateDomain<'a> {
         Self::deduplicate(coefficients)
     }
 
-    fn add_singletons(&self, coefficients: &mut Vec<Vec<i32>>) {
+    fn add_singletons(&self, coefficients: &mut Vec<Vec<i64>>) {
         coefficients.extend((0..self.dimension).map(|counter| {
             let mut template = vec![0; self.dimension];
             template[counter] = 1;
@@ -726,7 +795,7 @@ impl<'a> DefaultTemplateDomain<'a> {
         }));
     }
 
-    fn add_pairs(&self, coefficients: &mut Vec<Vec<i32>>) {
+    fn add_pairs(&self, coefficients: &mut Vec<Vec<i64>>) {
         coefficients.extend((0..self.dimension).flat_map(|left| {
             (left + 1..self.dimension).map(move |right| {
                 let mut template = vec![0; self.dimension];
@@ -737,13 +806,13 @@ impl<'a> DefaultTemplateDomain<'a> {
         }));
     }
 
-    fn add_all_counter_sum(&self, coefficients: &mut Vec<Vec<i32>>) {
+    fn add_all_counter_sum(&self, coefficients: &mut Vec<Vec<i64>>) {
         if self.dimension > 2 {
             coefficients.push(vec![1; self.dimension]);
         }
     }
 
-    fn deduplicate(templates: Vec<Vec<i32>>) -> Vec<Vec<i32>> {
+    fn deduplicate(templates: Vec<Vec<i64>>) -> Vec<Vec<i64>> {
         let mut unique = Vec::new();
         for template in templates {
             if !unique.contains(&template) {
@@ -759,7 +828,7 @@ struct LinearGraphTemplateBounder<'a> {
     main_bounds: &'a MainCFGTemplateLowerBounds,
     initial_valuation: &'a VASSCounterValuation,
     final_valuation: &'a VASSCounterValuation,
-    cap: i32,
+    cap: i64,
     transfer: TemplateTransfer,
 }
 
@@ -771,23 +840,36 @@ impl<'a> LinearGraphTemplateBounder<'a> {
         final_valuation: &'a VASSCounterValuation,
         exact_transfer_enabled: bool,
         exact_transfer_max_templates: usize,
+        arithmetic: &ArithmeticContext,
     ) -> Self {
         Self {
             linear_graph,
             main_bounds,
             initial_valuation,
             final_valuation,
-            cap: AnalysisCap::for_size(linear_graph.size()),
-            transfer: TemplateTransfer::new(exact_transfer_enabled, exact_transfer_max_templates),
+            cap: AnalysisCap::for_size_and_values(
+                linear_graph.size(),
+                &main_bounds.templates,
+                [initial_valuation, final_valuation],
+            ),
+            transfer: TemplateTransfer::new(
+                exact_transfer_enabled,
+                exact_transfer_max_templates,
+                arithmetic,
+            ),
         }
     }
 
     fn boundary_constraints(
         self,
+        interval_bounds_enabled: bool,
     ) -> HashMap<LinearGraphBoundPoint<MultiGraphState>, LinearGraphBoundaryConstraints> {
         let mut boundary_constraints = HashMap::new();
         self.add_forward_boundary_bounds(&mut boundary_constraints);
         self.add_backward_boundary_bounds(&mut boundary_constraints);
+        if interval_bounds_enabled {
+            self.add_boundary_upper_bounds(&mut boundary_constraints);
+        }
         boundary_constraints
     }
 
@@ -893,7 +975,7 @@ impl<'a> LinearGraphTemplateBounder<'a> {
         );
     }
 
-    fn transfer_part(&self, part: &LinearGraphPart, source_bounds: &[i32]) -> Box<[i32]> {
+    fn transfer_part(&self, part: &LinearGraphPart, source_bounds: &[i64]) -> Box<[i64]> {
         let timer = Instant::now();
         let result = match part {
             LinearGraphPart::Path(index) => self.transfer_path_updates(
@@ -925,7 +1007,7 @@ impl<'a> LinearGraphTemplateBounder<'a> {
         result
     }
 
-    fn transfer_part_backwards(&self, part: &LinearGraphPart, target_bounds: &[i32]) -> Box<[i32]> {
+    fn transfer_part_backwards(&self, part: &LinearGraphPart, target_bounds: &[i64]) -> Box<[i64]> {
         let timer = Instant::now();
         let result = match part {
             LinearGraphPart::Path(index) => self.transfer_path_updates_backwards(
@@ -960,9 +1042,9 @@ impl<'a> LinearGraphTemplateBounder<'a> {
 
     fn transfer_path_updates<'b>(
         &self,
-        mut bounds: Box<[i32]>,
+        mut bounds: Box<[i64]>,
         updates: impl Iterator<Item = &'b CFGCounterUpdate>,
-    ) -> Box<[i32]> {
+    ) -> Box<[i64]> {
         for update in updates {
             bounds = self
                 .transfer
@@ -974,9 +1056,9 @@ impl<'a> LinearGraphTemplateBounder<'a> {
 
     fn transfer_path_updates_backwards<'b>(
         &self,
-        mut bounds: Box<[i32]>,
+        mut bounds: Box<[i64]>,
         updates: impl Iterator<Item = &'b CFGCounterUpdate>,
-    ) -> Box<[i32]> {
+    ) -> Box<[i64]> {
         for update in updates {
             bounds = self.transfer.successor_bounds(
                 self.templates(),
@@ -991,9 +1073,9 @@ impl<'a> LinearGraphTemplateBounder<'a> {
 
     fn transfer_repeat_path<'b>(
         &self,
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         updates: impl Iterator<Item = &'b CFGCounterUpdate> + Clone,
-    ) -> Box<[i32]> {
+    ) -> Box<[i64]> {
         let timer = Instant::now();
         let mut bounds = source_bounds.to_vec().into_boxed_slice();
         let mut iterations = 0usize;
@@ -1022,9 +1104,9 @@ impl<'a> LinearGraphTemplateBounder<'a> {
 
     fn transfer_repeat_path_backwards<'b>(
         &self,
-        target_bounds: &[i32],
+        target_bounds: &[i64],
         updates: impl Iterator<Item = &'b CFGCounterUpdate> + Clone,
-    ) -> Box<[i32]> {
+    ) -> Box<[i64]> {
         let timer = Instant::now();
         let mut bounds = target_bounds.to_vec().into_boxed_slice();
         let mut iterations = 0usize;
@@ -1055,8 +1137,8 @@ impl<'a> LinearGraphTemplateBounder<'a> {
     fn transfer_graph_region(
         &self,
         graph: &LinearGraphRegion<MultiGraphState>,
-        source_bounds: &[i32],
-    ) -> Box<[i32]> {
+        source_bounds: &[i64],
+    ) -> Box<[i64]> {
         let timer = Instant::now();
         let mut state_bounds = vec![None; graph.node_count()];
         state_bounds[graph.start.index()] = Some(source_bounds.to_vec().into_boxed_slice());
@@ -1120,8 +1202,8 @@ impl<'a> LinearGraphTemplateBounder<'a> {
     fn transfer_graph_region_backwards(
         &self,
         graph: &LinearGraphRegion<MultiGraphState>,
-        target_bounds: &[i32],
-    ) -> Box<[i32]> {
+        target_bounds: &[i64],
+    ) -> Box<[i64]> {
         let timer = Instant::now();
         let mut state_bounds = vec![None; graph.node_count()];
         state_bounds[graph.end.index()] = Some(target_bounds.to_vec().into_boxed_slice());
@@ -1182,6 +1264,224 @@ impl<'a> LinearGraphTemplateBounder<'a> {
             .unwrap_or_else(|| TemplateBounds::bottom(self.templates()).into_boxed_slice())
     }
 
+    /// Intersects the forward and backward upper bounds at every boundary and
+    /// adds them as upper bounds, or as equalities where they meet the lower
+    /// bounds already collected for the same template.
+    fn add_boundary_upper_bounds(
+        &self,
+        boundary_constraints: &mut HashMap<
+            LinearGraphBoundPoint<MultiGraphState>,
+            LinearGraphBoundaryConstraints,
+        >,
+    ) {
+        let timer = Instant::now();
+        if self.linear_graph.sequence.is_empty() {
+            return;
+        }
+
+        let forward = self.boundary_upper_bounds(false);
+        let backward = self.boundary_upper_bounds(true);
+
+        for (index, (forward, backward)) in forward.into_iter().zip(backward).enumerate() {
+            let bounds = TemplateUpperBounds::intersect(&forward, &backward);
+            let point = LinearGraphBoundPoint::Boundary {
+                index,
+                state: self.boundary_state(index).clone(),
+            };
+            let entry = boundary_constraints.entry(point).or_default();
+
+            for (template, bound) in self.templates().iter().zip(bounds.iter()) {
+                let Some(bound) = *bound else {
+                    continue;
+                };
+
+                let lower_bound = entry
+                    .lower_bounds
+                    .iter()
+                    .filter(|lower| lower.coefficients == template.coefficients)
+                    .map(|lower| lower.bound)
+                    .max()
+                    .unwrap_or_else(|| template.bottom_bound());
+
+                if lower_bound == bound {
+                    entry
+                        .lower_bounds
+                        .retain(|lower| lower.coefficients != template.coefficients);
+                    entry.equalities.push(LinearTemplateEquality {
+                        coefficients: template.coefficients.clone(),
+                        value: bound,
+                    });
+                } else {
+                    entry.upper_bounds.push(LinearTemplateUpperBound {
+                        coefficients: template.coefficients.clone(),
+                        bound,
+                    });
+                }
+            }
+        }
+
+        tracing::debug!(
+            elapsed_ms = timer.elapsed().as_millis(),
+            parts = self.linear_graph.sequence.len(),
+            templates = self.templates().len(),
+            "Finished LinearGraph boundary upper-bound propagation"
+        );
+    }
+
+    fn boundary_state(&self, index: usize) -> &MultiGraphState {
+        match self.linear_graph.sequence.get(index) {
+            Some(part) => part.start(self.linear_graph),
+            None => self
+                .linear_graph
+                .sequence
+                .last()
+                .expect("boundary states require a non-empty LinearGraph")
+                .end(self.linear_graph),
+        }
+    }
+
+    /// Upper bounds at boundaries `0..=sequence.len()`, propagated from the
+    /// initial valuation or, with `backwards`, from the final valuation.
+    fn boundary_upper_bounds(&self, backwards: bool) -> Vec<Box<[Option<i64>]>> {
+        let valuation = if backwards {
+            self.final_valuation
+        } else {
+            self.initial_valuation
+        };
+        let mut current = TemplateUpperBounds::for_valuation(self.templates(), valuation);
+        let mut boundaries = vec![current.clone()];
+
+        let parts: Box<dyn Iterator<Item = &LinearGraphPart>> = if backwards {
+            Box::new(self.linear_graph.sequence.iter().rev())
+        } else {
+            Box::new(self.linear_graph.sequence.iter())
+        };
+
+        for part in parts {
+            current = self.transfer_part_upper(part, current, backwards);
+            boundaries.push(current.clone());
+        }
+
+        if backwards {
+            boundaries.reverse();
+        }
+
+        boundaries
+    }
+
+    fn transfer_part_upper(
+        &self,
+        part: &LinearGraphPart,
+        bounds: Box<[Option<i64>]>,
+        backwards: bool,
+    ) -> Box<[Option<i64>]> {
+        match part {
+            LinearGraphPart::Path(index) => self.transfer_path_upper(
+                bounds,
+                &self.linear_graph.path(*index).path.transitions,
+                backwards,
+            ),
+            LinearGraphPart::RepeatPath(index) => {
+                let updates = &self.linear_graph.repeat_path(*index).path.transitions;
+                let mut bounds = bounds;
+
+                for iteration in 0.. {
+                    let after_one_iteration =
+                        self.transfer_path_upper(bounds.clone(), updates, backwards);
+                    let widen = iteration >= UPPER_BOUND_WIDENING_DELAY;
+                    if !TemplateUpperBounds::merge_into(&mut bounds, &after_one_iteration, widen) {
+                        break;
+                    }
+                }
+
+                bounds
+            }
+            LinearGraphPart::Graph(index) => {
+                self.transfer_graph_region_upper(self.linear_graph.graph(*index), bounds, backwards)
+            }
+        }
+    }
+
+    fn transfer_path_upper(
+        &self,
+        mut bounds: Box<[Option<i64>]>,
+        updates: &[CFGCounterUpdate],
+        backwards: bool,
+    ) -> Box<[Option<i64>]> {
+        if backwards {
+            for update in updates.iter().rev() {
+                bounds =
+                    TemplateUpperBounds::successor(self.templates(), &bounds, &update.reverse());
+            }
+        } else {
+            for update in updates {
+                bounds = TemplateUpperBounds::successor(self.templates(), &bounds, update);
+            }
+        }
+
+        bounds
+    }
+
+    fn transfer_graph_region_upper(
+        &self,
+        graph: &LinearGraphRegion<MultiGraphState>,
+        bounds: Box<[Option<i64>]>,
+        backwards: bool,
+    ) -> Box<[Option<i64>]> {
+        let (source, target) = if backwards {
+            (graph.end, graph.start)
+        } else {
+            (graph.start, graph.end)
+        };
+
+        let mut state_bounds = vec![None; graph.node_count()];
+        let mut changes = vec![0usize; graph.node_count()];
+        state_bounds[source.index()] = Some(bounds);
+        let mut queue = VecDeque::from([source]);
+
+        while let Some(node) = queue.pop_front() {
+            let node_bounds = state_bounds[node.index()]
+                .clone()
+                .expect("queued graph-region states have an upper bound");
+
+            let edges = if backwards {
+                graph.incoming_edge_indices(&node).collect::<Vec<_>>()
+            } else {
+                graph.outgoing_edge_indices(&node).collect::<Vec<_>>()
+            };
+
+            for edge in edges {
+                let (next, update) = if backwards {
+                    (
+                        graph.edge_source_unchecked(&edge),
+                        graph.get_edge_unchecked(&edge).reverse(),
+                    )
+                } else {
+                    (
+                        graph.edge_target_unchecked(&edge),
+                        *graph.get_edge_unchecked(&edge),
+                    )
+                };
+
+                let candidate =
+                    TemplateUpperBounds::successor(self.templates(), &node_bounds, &update);
+                let widen = changes[next.index()] >= UPPER_BOUND_WIDENING_DELAY;
+                if TemplateUpperBounds::merge_state(
+                    &mut state_bounds[next.index()],
+                    candidate,
+                    widen,
+                ) {
+                    changes[next.index()] += 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        state_bounds[target.index()]
+            .take()
+            .unwrap_or_else(|| TemplateUpperBounds::unbounded(self.templates()))
+    }
+
     fn insert_boundary_lower_bounds(
         &self,
         boundary_constraints: &mut HashMap<
@@ -1189,7 +1489,7 @@ impl<'a> LinearGraphTemplateBounder<'a> {
             LinearGraphBoundaryConstraints,
         >,
         point: LinearGraphBoundPoint<MultiGraphState>,
-        bounds: &[i32],
+        bounds: &[i64],
     ) {
         let entry = boundary_constraints.entry(point).or_default();
         entry
@@ -1204,19 +1504,19 @@ impl TemplateBounds {
     fn for_valuation(
         templates: &[LinearTemplate],
         valuation: &VASSCounterValuation,
-        cap: i32,
-    ) -> Box<[i32]> {
+        cap: i64,
+    ) -> Box<[i64]> {
         templates
             .iter()
             .map(|template| template.clamp_lower_bound(template.value(valuation), cap))
             .collect()
     }
 
-    fn bottom(templates: &[LinearTemplate]) -> Vec<i32> {
+    fn bottom(templates: &[LinearTemplate]) -> Vec<i64> {
         templates.iter().map(LinearTemplate::bottom_bound).collect()
     }
 
-    fn merge_template(current: &mut Option<i32>, candidate: i32) -> bool {
+    fn merge_template(current: &mut Option<i64>, candidate: i64) -> bool {
         let Some(current) = current else {
             *current = Some(candidate);
             return true;
@@ -1227,7 +1527,7 @@ impl TemplateBounds {
         *current != previous
     }
 
-    fn merge_state(current: &mut Option<Box<[i32]>>, candidate: Box<[i32]>) -> bool {
+    fn merge_state(current: &mut Option<Box<[i64]>>, candidate: Box<[i64]>) -> bool {
         let Some(current) = current else {
             *current = Some(candidate);
             return true;
@@ -1238,7 +1538,7 @@ impl TemplateBounds {
         *current != previous
     }
 
-    fn merge_into(current: &mut [i32], candidate: &[i32]) -> bool {
+    fn merge_into(current: &mut [i64], candidate: &[i64]) -> bool {
         let previous = current.to_vec();
         for (current_bound, candidate_bound) in current.iter_mut().zip(candidate.iter()) {
             *current_bound = (*current_bound).min(*candidate_bound);
@@ -1249,7 +1549,7 @@ impl TemplateBounds {
 
     fn constraints(
         main_bounds: &MainCFGTemplateLowerBounds,
-        bounds: &[i32],
+        bounds: &[i64],
     ) -> LinearGraphBoundaryConstraints {
         let lower_bounds = main_bounds
             .templates
@@ -1262,6 +1562,90 @@ impl TemplateBounds {
             })
             .collect::<Vec<_>>();
 
-        LinearGraphBoundaryConstraints { lower_bounds }
+        LinearGraphBoundaryConstraints {
+            lower_bounds,
+            ..Default::default()
+        }
+    }
+}
+
+/// Upper bounds aligned with the templates. `None` means unbounded.
+struct TemplateUpperBounds;
+
+impl TemplateUpperBounds {
+    fn for_valuation(
+        templates: &[LinearTemplate],
+        valuation: &VASSCounterValuation,
+    ) -> Box<[Option<i64>]> {
+        templates
+            .iter()
+            .map(|template| Some(template.value(valuation)))
+            .collect()
+    }
+
+    fn unbounded(templates: &[LinearTemplate]) -> Box<[Option<i64>]> {
+        vec![None; templates.len()].into_boxed_slice()
+    }
+
+    fn successor(
+        templates: &[LinearTemplate],
+        bounds: &[Option<i64>],
+        update: &CFGCounterUpdate,
+    ) -> Box<[Option<i64>]> {
+        let counter = update.counter().to_usize();
+
+        templates
+            .iter()
+            .zip(bounds.iter())
+            .map(|(template, bound)| {
+                bound.and_then(|bound| {
+                    bound.checked_add(template.coefficients[counter] * update.op())
+                })
+            })
+            .collect()
+    }
+
+    /// Joins `candidate` into `current` with max. With `widen`, every bound
+    /// that grows becomes unbounded instead.
+    fn merge_into(current: &mut [Option<i64>], candidate: &[Option<i64>], widen: bool) -> bool {
+        let mut changed = false;
+
+        for (current, candidate) in current.iter_mut().zip(candidate.iter()) {
+            let joined = match (*current, *candidate) {
+                (Some(current), Some(candidate)) => Some(current.max(candidate)),
+                _ => None,
+            };
+
+            if joined != *current {
+                *current = if widen { None } else { joined };
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn merge_state(
+        current: &mut Option<Box<[Option<i64>]>>,
+        candidate: Box<[Option<i64>]>,
+        widen: bool,
+    ) -> bool {
+        let Some(current) = current else {
+            *current = Some(candidate);
+            return true;
+        };
+
+        Self::merge_into(current, &candidate, widen)
+    }
+
+    fn intersect(left: &[Option<i64>], right: &[Option<i64>]) -> Box<[Option<i64>]> {
+        left.iter()
+            .zip(right.iter())
+            .map(|(left, right)| match (left, right) {
+                (Some(left), Some(right)) => Some(*left.min(right)),
+                (Some(bound), None) | (None, Some(bound)) => Some(*bound),
+                (None, None) => None,
+            })
+            .collect()
     }
 }
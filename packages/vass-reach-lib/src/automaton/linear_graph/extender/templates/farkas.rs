@@ -0,0 +1,232 @@
+//! Farkas-style synthesis of one separating template.
+//!
+//! Instead of enumerating bounded coefficient vectors, the optimizer solves for
+//! non-negative coefficients `a` together with one inductive lower bound `b_s`
+//! per main-CFG state. For an edge `s --u--> t` updating counter `k` by `op`,
+//! consecution holds when either `b_t <= b_s + a_k op` (the source bound is
+//! used) or `b_t <= max(0, a_k op)` (only counter non-negativity is used).
+//! These are the two Farkas multiplier choices for a single edge constraint. At
+//! least one modeled boundary valuation must violate its state bound. The
+//! optimizer minimizes the coefficient sum, so the coefficients are not capped.
+//!
+//! The search is restricted in three ways:
+//!
+//! - The coefficients are non-negative. The second choice is only sound for
+//!   them, since a negative coefficient has no lower bound over the
+//!   non-negative counters. Mixed-sign templates are left to enumeration.
+//! - At least two coefficients are positive. Singleton templates are already
+//!   part of the default domain, so the optimizer never spends a call on them.
+//! - Each edge picks one of the two multiplier choices. A full Farkas encoding
+//!   would allow any non-negative combination of the source bound and the
+//!   counter bounds, which is non-linear in the unknowns. The disjunction is
+//!   therefore a heuristic: it is sound, but an unsatisfiable problem does not
+//!   prove that no separating template exists, and invariants that need a
+//!   combined multiplier are missed.
+
+use std::time::{Duration, Instant};
+
+use petgraph::graph::NodeIndex;
+
+use super::{LinearTemplate, MainCFGTemplateLowerBounds};
+use crate::{
+    automaton::{
+        Alphabet, Automaton, InitializedAutomaton, TransitionSystem, cfg::vasscfg::VASSCFG,
+        vass::counter::VASSCounterValuation,
+    },
+    solver::backend::{
+        ArithmeticContext, ArithmeticSolver, CheckLimits, SatResult,
+        ast::{Bool, Int},
+    },
+};
+
+pub(super) fn farkas_template(
+    cfg: &VASSCFG<()>,
+    initial_valuation: &VASSCounterValuation,
+    current: &MainCFGTemplateLowerBounds,
+    model_boundaries: &[(NodeIndex, VASSCounterValuation)],
+    time_limit: Option<Duration>,
+    arithmetic: &ArithmeticContext,
+) -> Option<LinearTemplate> {
+    FarkasTemplateProblem::new(
+        cfg,
+        initial_valuation,
+        current,
+        model_boundaries,
+        arithmetic,
+    )
+    .solve(time_limit)
+}
+
+struct FarkasTemplateProblem<'a> {
+    cfg: &'a VASSCFG<()>,
+    initial_valuation: &'a VASSCounterValuation,
+    current: &'a MainCFGTemplateLowerBounds,
+    model_boundaries: &'a [(NodeIndex, VASSCounterValuation)],
+    optimizer: ArithmeticSolver,
+    coefficients: Vec<Int>,
+    state_bounds: Vec<Int>,
+}
+
+impl<'a> FarkasTemplateProblem<'a> {
+    fn new(
+        cfg: &'a VASSCFG<()>,
+        initial_valuation: &'a VASSCounterValuation,
+        current: &'a MainCFGTemplateLowerBounds,
+        model_boundaries: &'a [(NodeIndex, VASSCounterValuation)],
+        arithmetic: &ArithmeticContext,
+    ) -> Self {
+        Self {
+            cfg,
+            initial_valuation,
+            current,
+            model_boundaries,
+            optimizer: ArithmeticSolver::new_optimizer(arithmetic),
+            coefficients: (0..initial_valuation.dimension())
+                .map(|counter| Int::new_const(format!("farkas_a{counter}")))
+                .collect(),
+            state_bounds: (0..cfg.node_count())
+                .map(|state| Int::new_const(format!("farkas_b{state}")))
+                .collect(),
+        }
+    }
+
+    fn solve(self, time_limit: Option<Duration>) -> Option<LinearTemplate> {
+        if self.model_boundaries.is_empty() {
+            return None;
+        }
+
+        self.assert_non_negative_coefficients();
+        self.assert_relational_support();
+        self.assert_new_direction();
+        self.assert_initiation();
+        self.assert_consecution();
+        self.assert_model_exclusion();
+        self.optimizer
+            .minimize(&Int::add(&self.coefficients.iter().collect::<Vec<_>>()));
+
+        let limits = CheckLimits {
+            deadline: time_limit.map(|limit| Instant::now() + limit),
+            stop_signal: None,
+        };
+
+        match self.optimizer.check_with(&[], limits) {
+            SatResult::Sat => self.model_template(),
+            SatResult::Unsat | SatResult::Unknown => None,
+        }
+    }
+
+    fn assert_non_negative_coefficients(&self) {
+        for coefficient in &self.coefficients {
+            self.optimizer.assert(coefficient.ge(Int::from_i64(0)));
+        }
+    }
+
+    fn assert_relational_support(&self) {
+        // Singleton templates are already part of the default domain.
+        let support = self
+            .coefficients
+            .iter()
+            .map(|coefficient| {
+                coefficient
+                    .gt(Int::from_i64(0))
+                    .ite(&Int::from_i64(1), &Int::from_i64(0))
+            })
+            .collect::<Vec<_>>();
+        self.optimizer
+            .assert(Int::add(&support.iter().collect::<Vec<_>>()).ge(Int::from_i64(2)));
+    }
+
+    fn assert_new_direction(&self) {
+        // Proportional templates prove the same facts, so each existing
+        // template needs at least one pair of counters with a different ratio.
+        for template in &self.current.templates {
+            let mut differing_ratios = Vec::new();
+            for i in 0..self.coefficients.len() {
+                for j in i + 1..self.coefficients.len() {
+                    let left = &self.coefficients[i] * Int::from_i64(template.coefficients[j]);
+                    let right = &self.coefficients[j] * Int::from_i64(template.coefficients[i]);
+                    differing_ratios.push(left.eq(&right).not());
+                }
+            }
+            self.optimizer
+                .assert(Bool::or(&differing_ratios.iter().collect::<Vec<_>>()));
+        }
+    }
+
+    fn assert_initiation(&self) {
+        let initial = self.cfg.get_initial();
+        self.optimizer
+            .assert(self.state_bounds[initial.index()].le(self.value(self.initial_valuation)));
+    }
+
+    fn assert_consecution(&self) {
+        for source in self.reachable_states() {
+            for update in self.cfg.alphabet() {
+                let Some(target) = self.cfg.successor(&source, update) else {
+                    continue;
+                };
+
+                let delta = &self.coefficients[update.counter().to_usize()]
+                    * Int::from_i64(update.op_i64());
+                let non_negative_bound = if update.op() > 0 {
+                    &self.coefficients[update.counter().to_usize()] * Int::from_i64(update.op())
+                } else {
+                    Int::from_i64(0)
+                };
+                let target_bound = &self.state_bounds[target.index()];
+
+                self.optimizer.assert(Bool::or(&[
+                    &target_bound.le(&self.state_bounds[source.index()] + delta),
+                    &target_bound.le(&non_negative_bound),
+                ]));
+            }
+        }
+    }
+
+    fn assert_model_exclusion(&self) {
+        let violations = self
+            .model_boundaries
+            .iter()
+            .filter(|(state, _)| self.current.state_bounds(*state).is_some())
+            .map(|(state, valuation)| {
+                (self.value(valuation) + Int::from_i64(1)).le(&self.state_bounds[state.index()])
+            })
+            .collect::<Vec<_>>();
+        self.optimizer
+            .assert(Bool::or(&violations.iter().collect::<Vec<_>>()));
+    }
+
+    fn reachable_states(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        (0..self.cfg.node_count())
+            .map(NodeIndex::new)
+            .filter(|state| self.current.state_bounds(*state).is_some())
+    }
+
+    fn value(&self, valuation: &VASSCounterValuation) -> Int {
+        self.coefficients
+            .iter()
+            .zip(valuation.iter())
+            .fold(Int::from_i64(0), |sum, (coefficient, value)| {
+                sum + coefficient * Int::from_i64(*value)
+            })
+    }
+
+    fn model_template(&self) -> Option<LinearTemplate> {
+        let coefficients = self
+            .optimizer
+            .eval_many(&self.coefficients.iter().collect::<Vec<_>>())?;
+
+        // Scaling a template scales every bound, so the gcd carries no information.
+        let divisor = coefficients.iter().copied().fold(0, gcd).max(1);
+        Some(LinearTemplate::from_coefficients(
+            coefficients
+                .into_iter()
+                .map(|coefficient| coefficient / divisor)
+                .collect(),
+        ))
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
@@ -3,31 +3,33 @@
 //! The domain tracks lower bounds for expressions of the form
 //! `a_0 c_0 + ... + a_n c_n` at main-CFG states. Bounds are propagated forward
 //! from the initial valuation and candidate-local boundary bounds are also
-//! propagated backward from the final valuation before reachability checks. See
+//! propagated backward from the final valuation before reachability checks.
+//! At LinearGraph boundaries, and only there, the templates additionally get
+//! upper bounds, and equalities where both bounds meet. See
 //! `docs/linear-template-invariants.md` for the full algorithm and soundness
 //! argument.
 
 mod analysis;
+mod farkas;
 mod synthesis;
 pub mod testing;
 mod transfer;
 
 pub(super) use analysis::{
-    main_cfg_template_lower_bounds_with_deadline, path_sensitive_linear_graph_template_lower_bounds,
+    main_cfg_template_lower_bounds_with_deadline, path_sensitive_linear_graph_template_bounds,
 };
 pub(super) use synthesis::{TemplateSynthesisOptions, synthesize_template_for_boundaries};
-use z3::ast::Int;
 
-use crate::automaton::vass::counter::VASSCounterValuation;
+use crate::{automaton::vass::counter::VASSCounterValuation, solver::backend::ast::Int};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct LinearTemplate {
     /// Non-negative coefficients for the counter vector dot product.
-    pub(super) coefficients: Box<[i32]>,
+    pub(super) coefficients: Box<[i64]>,
 }
 
 impl LinearTemplate {
-    fn from_coefficients(coefficients: Vec<i32>) -> Self {
+    fn from_coefficients(coefficients: Vec<i64>) -> Self {
         debug_assert!(
             coefficients.iter().all(|coefficient| *coefficient >= 0),
             "signed templates are not currently supported"
@@ -37,29 +39,31 @@ impl LinearTemplate {
         }
     }
 
-    fn value(&self, valuation: &VASSCounterValuation) -> i32 {
+    fn value(&self, valuation: &VASSCounterValuation) -> i64 {
         self.coefficients
             .iter()
             .zip(valuation.iter())
-            .map(|(coefficient, value)| coefficient * value)
-            .sum()
+            .try_fold(0_i64, |sum, (coefficient, value)| {
+                sum.checked_add(coefficient.checked_mul(*value)?)
+            })
+            .expect("template value overflowed the range of i64")
     }
 
-    fn bottom_bound(&self) -> i32 {
+    fn bottom_bound(&self) -> i64 {
         0
     }
 
-    fn clamp_lower_bound(&self, bound: i32, cap: i32) -> i32 {
+    fn clamp_lower_bound(&self, bound: i64, cap: i64) -> i64 {
         bound.clamp(0, cap)
     }
 
-    fn z3_expression(&self, counters: &[Int]) -> Int {
+    fn smt_expression(&self, counters: &[Int]) -> Int {
         counters
             .iter()
             .zip(self.coefficients.iter())
             .filter(|(_, coefficient)| **coefficient != 0)
             .fold(Int::from_i64(0), |sum, (counter, coefficient)| {
-                sum + counter * Int::from_i64(*coefficient as i64)
+                sum + counter * Int::from_i64(*coefficient)
             })
     }
 }
@@ -70,18 +74,18 @@ pub(super) struct MainCFGTemplateLowerBounds {
     pub(super) templates: Vec<LinearTemplate>,
     /// `None` means the CFG state has not been reached by the forward analysis.
     /// Otherwise the vector is aligned with `templates`.
-    state_bounds: Vec<Option<Box<[i32]>>>,
+    state_bounds: Vec<Option<Box<[i64]>>>,
 }
 
 impl MainCFGTemplateLowerBounds {
-    fn new(templates: Vec<LinearTemplate>, state_bounds: Vec<Option<Box<[i32]>>>) -> Self {
+    fn new(templates: Vec<LinearTemplate>, state_bounds: Vec<Option<Box<[i64]>>>) -> Self {
         Self {
             templates,
             state_bounds,
         }
     }
 
-    fn state_bounds(&self, state: petgraph::graph::NodeIndex) -> Option<&[i32]> {
+    fn state_bounds(&self, state: petgraph::graph::NodeIndex) -> Option<&[i64]> {
         self.state_bounds[state.index()].as_deref()
     }
 
@@ -1,24 +1,33 @@
 //! Counterexample-guided synthesis of additional weighted templates.
 //!
 //! Synthesis runs after a LinearGraph SMT model cannot be turned into an
-//! concrete N-run. Candidate coefficient vectors are accepted only after the
-//! forward analysis proves a bound that excludes at least one modeled boundary
-//! valuation.
+//! concrete N-run. Candidates come from bounded enumeration or from the Farkas
+//! encoding in `farkas`, depending on the configured mode. Candidate
+//! coefficient vectors are accepted only after the forward analysis proves a
+//! bound that excludes at least one modeled boundary valuation.
 
-use std::cmp::Reverse;
+use std::{cmp::Reverse, time::Duration};
 
 use petgraph::graph::NodeIndex;
 
 use super::{
     LinearTemplate, MainCFGTemplateLowerBounds, analysis::analyze_with_incremental_template,
+    farkas::farkas_template,
+};
+use crate::{
+    automaton::{cfg::vasscfg::VASSCFG, vass::counter::VASSCounterValuation},
+    config::LinearGraphTemplateSynthesisMode,
+    solver::backend::ArithmeticContext,
 };
-use crate::automaton::{cfg::vasscfg::VASSCFG, vass::counter::VASSCounterValuation};
 
 pub(in crate::automaton::linear_graph::extender) struct TemplateSynthesisOptions {
-    pub(in crate::automaton::linear_graph::extender) max_coefficient: i32,
+    pub(in crate::automaton::linear_graph::extender) max_coefficient: i64,
     pub(in crate::automaton::linear_graph::extender) max_candidates: usize,
     pub(in crate::automaton::linear_graph::extender) exact_transfer_enabled: bool,
     pub(in crate::automaton::linear_graph::extender) exact_transfer_max_templates: usize,
+    pub(in crate::automaton::linear_graph::extender) mode: LinearGraphTemplateSynthesisMode,
+    pub(in crate::automaton::linear_graph::extender) time_limit: Option<Duration>,
+    pub(in crate::automaton::linear_graph::extender) arithmetic: ArithmeticContext,
 }
 
 pub(in crate::automaton::linear_graph::extender) fn synthesize_template_for_boundaries(
@@ -34,7 +43,7 @@ pub(in crate::automaton::linear_graph::extender) fn synthesize_template_for_boun
 
 pub(super) fn candidate_templates(
     dimension: usize,
-    max_coefficient: i32,
+    max_coefficient: i64,
     max_candidates: usize,
     existing: &[LinearTemplate],
 ) -> Vec<LinearTemplate> {
@@ -47,7 +56,7 @@ pub(super) fn candidate_templates(
 
 pub(super) fn candidate_templates_for_boundaries(
     dimension: usize,
-    max_coefficient: i32,
+    max_coefficient: i64,
     max_candidates: usize,
     current: &MainCFGTemplateLowerBounds,
     model_boundaries: &[(NodeIndex, VASSCounterValuation)],
@@ -67,10 +76,13 @@ struct TemplateSynthesizer<'a> {
     initial_valuation: &'a VASSCounterValuation,
     current: &'a MainCFGTemplateLowerBounds,
     model_boundaries: &'a [(NodeIndex, VASSCounterValuation)],
-    max_coefficient: i32,
+    max_coefficient: i64,
     max_candidates: usize,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
+    mode: LinearGraphTemplateSynthesisMode,
+    time_limit: Option<Duration>,
+    arithmetic: ArithmeticContext,
 }
 
 impl<'a> TemplateSynthesizer<'a> {
@@ -90,6 +102,9 @@ impl<'a> TemplateSynthesizer<'a> {
             max_candidates: options.max_candidates,
             exact_transfer_enabled: options.exact_transfer_enabled,
             exact_transfer_max_templates: options.exact_transfer_max_templates,
+            mode: options.mode,
+            time_limit: options.time_limit,
+            arithmetic: options.arithmetic,
         }
     }
 
@@ -102,6 +117,31 @@ impl<'a> TemplateSynthesizer<'a> {
     }
 
     fn candidate_templates(&self) -> Vec<LinearTemplate> {
+        match self.mode {
+            LinearGraphTemplateSynthesisMode::Enumeration => self.enumerated_templates(),
+            LinearGraphTemplateSynthesisMode::Farkas => self.farkas_templates(),
+            LinearGraphTemplateSynthesisMode::FarkasThenEnumeration => {
+                let mut candidates = self.farkas_templates();
+                candidates.extend(self.enumerated_templates());
+                candidates
+            }
+        }
+    }
+
+    fn farkas_templates(&self) -> Vec<LinearTemplate> {
+        farkas_template(
+            self.cfg,
+            self.initial_valuation,
+            self.current,
+            self.model_boundaries,
+            self.time_limit,
+            &self.arithmetic,
+        )
+        .into_iter()
+        .collect()
+    }
+
+    fn enumerated_templates(&self) -> Vec<LinearTemplate> {
         // The initial domain already contains singleton templates. Synthesis
         // looks only for relational templates and stops at the first candidate
         // that cuts the current spurious model. Boundary valuations guide the
@@ -125,6 +165,7 @@ impl<'a> TemplateSynthesizer<'a> {
             template.clone(),
             self.exact_transfer_enabled,
             self.exact_transfer_max_templates,
+            &self.arithmetic,
         )
     }
 
@@ -147,7 +188,7 @@ impl<'a> TemplateSynthesizer<'a> {
 
 struct CandidateTemplateSearch<'a> {
     dimension: usize,
-    max_coefficient: i32,
+    max_coefficient: i64,
     max_candidates: usize,
     existing: &'a [LinearTemplate],
     scores: Vec<u64>,
@@ -156,7 +197,7 @@ struct CandidateTemplateSearch<'a> {
 impl<'a> CandidateTemplateSearch<'a> {
     fn unguided(
         dimension: usize,
-        max_coefficient: i32,
+        max_coefficient: i64,
         max_candidates: usize,
         existing: &'a [LinearTemplate],
     ) -> Self {
@@ -171,7 +212,7 @@ impl<'a> CandidateTemplateSearch<'a> {
 
     fn guided(
         dimension: usize,
-        max_coefficient: i32,
+        max_coefficient: i64,
         max_candidates: usize,
         current: &'a MainCFGTemplateLowerBounds,
         model_boundaries: &[(NodeIndex, VASSCounterValuation)],
@@ -204,7 +245,7 @@ impl<'a> CandidateTemplateSearch<'a> {
         counter_order
     }
 
-    fn coefficient_orders(&self) -> Vec<Vec<i32>> {
+    fn coefficient_orders(&self) -> Vec<Vec<i64>> {
         (0..self.dimension)
             .map(|counter| {
                 let coefficients = 0..=self.max_coefficient;
@@ -219,9 +260,9 @@ impl<'a> CandidateTemplateSearch<'a> {
 }
 
 struct CandidateTemplateEnumerator<'a> {
-    coefficients: Vec<i32>,
+    coefficients: Vec<i64>,
     counter_order: Vec<usize>,
-    coefficient_orders: Vec<Vec<i32>>,
+    coefficient_orders: Vec<Vec<i64>>,
     max_candidates: usize,
     existing: &'a [LinearTemplate],
     candidates: Vec<LinearTemplate>,
@@ -309,19 +350,19 @@ impl<'a> CounterPriorityScorer<'a> {
         scores
     }
 
-    fn add_value_spread_scores(&self, scores: &mut [u64], values: &[i32]) {
+    fn add_value_spread_scores(&self, scores: &mut [u64], values: &[i64]) {
         let max_value = values.iter().copied().max().unwrap_or(0);
 
         for counter in 0..self.dimension {
-            scores[counter] += i32::saturating_sub(max_value, values[counter]).max(0) as u64;
+            scores[counter] += i64::saturating_sub(max_value, values[counter]).max(0) as u64;
         }
     }
 
     fn add_bound_margin_scores(
         &self,
         scores: &mut [u64],
-        values: &[i32],
-        bounds: &[i32],
+        values: &[i64],
+        bounds: &[i64],
         singleton_indices: &[Option<usize>],
     ) {
         let margins = singleton_indices
@@ -331,7 +372,7 @@ impl<'a> CounterPriorityScorer<'a> {
                 template_index.map(|template_index| {
                     (
                         counter,
-                        i32::saturating_sub(values[counter], bounds[template_index]),
+                        i64::saturating_sub(values[counter], bounds[template_index]),
                     )
                 })
             })
@@ -341,7 +382,7 @@ impl<'a> CounterPriorityScorer<'a> {
         };
 
         for (counter, margin) in margins {
-            scores[counter] += i32::saturating_sub(max_margin, margin).max(0) as u64;
+            scores[counter] += i64::saturating_sub(max_margin, margin).max(0) as u64;
         }
     }
 
@@ -5,13 +5,17 @@
 //! can cover the algorithm described in `docs/linear-template-invariants.md`
 //! without making the full invariant domain part of the public API.
 
+use std::sync::Arc;
+
 use petgraph::graph::NodeIndex;
 
 use super::{
+    super::ProductViewLinearGraph,
     LinearTemplate, MainCFGTemplateLowerBounds,
     analysis::{
         analyze_templates, analyze_with_incremental_template, default_templates,
-        main_cfg_template_lower_bounds, successor_bounds,
+        main_cfg_template_lower_bounds, path_sensitive_linear_graph_template_bounds,
+        successor_bounds,
     },
     synthesis::{
         TemplateSynthesisOptions, candidate_templates, synthesize_template_for_boundaries,
@@ -23,13 +27,26 @@ use crate::{
         cfg::{update::CFGCounterUpdate, vasscfg::VASSCFG},
         vass::counter::VASSCounterValuation,
     },
-    config::LinearGraphTemplateFamily,
+    config::{LinearGraphTemplateFamily, LinearGraphTemplateSynthesisMode},
+    solver::{
+        backend::ArithmeticContext, linear_graph_reach::LinearGraphBoundPoint,
+        smt_dump::SMTQueryDump,
+    },
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TemplateAnalysisSnapshot {
-    pub templates: Vec<Vec<i32>>,
-    pub state_bounds: Vec<Option<Vec<i32>>>,
+    pub templates: Vec<Vec<i64>>,
+    pub state_bounds: Vec<Option<Vec<i64>>>,
+}
+
+/// Template bounds at one LinearGraph boundary as `(coefficients, bound)`
+/// pairs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoundaryTemplateBoundsSnapshot {
+    pub lower_bounds: Vec<(Vec<i64>, i64)>,
+    pub upper_bounds: Vec<(Vec<i64>, i64)>,
+    pub equalities: Vec<(Vec<i64>, i64)>,
 }
 
 pub fn main_cfg_template_lower_bounds_snapshot(
@@ -42,10 +59,61 @@ pub fn main_cfg_template_lower_bounds_snapshot(
         true,
         usize::MAX,
         &DefaultTemplateFamilies::all(),
+        &ArithmeticContext::default(),
     ))
 }
 
-pub fn default_template_coefficients(dimension: usize) -> Vec<Vec<i32>> {
+/// Boundary bounds of `linear_graph` for the default templates, indexed by
+/// boundary.
+pub fn linear_graph_boundary_template_bounds_snapshot(
+    linear_graph: &ProductViewLinearGraph<'_>,
+    initial_valuation: &VASSCounterValuation,
+    final_valuation: &VASSCounterValuation,
+) -> Vec<BoundaryTemplateBoundsSnapshot> {
+    let templates = default_templates(
+        initial_valuation.dimension(),
+        &DefaultTemplateFamilies::all(),
+    );
+    let constraints = path_sensitive_linear_graph_template_bounds(
+        linear_graph,
+        &MainCFGTemplateLowerBounds::new(templates, Vec::new()),
+        initial_valuation,
+        final_valuation,
+        true,
+        usize::MAX,
+        true,
+        &ArithmeticContext::default(),
+    );
+
+    let mut boundaries =
+        vec![BoundaryTemplateBoundsSnapshot::default(); linear_graph.sequence.len() + 1];
+    for (point, constraints) in constraints {
+        let LinearGraphBoundPoint::Boundary { index, .. } = point;
+        let boundary = &mut boundaries[index];
+        boundary.lower_bounds.extend(
+            constraints
+                .lower_bounds
+                .into_iter()
+                .map(|bound| (bound.coefficients.into_vec(), bound.bound)),
+        );
+        boundary.upper_bounds.extend(
+            constraints
+                .upper_bounds
+                .into_iter()
+                .map(|bound| (bound.coefficients.into_vec(), bound.bound)),
+        );
+        boundary.equalities.extend(
+            constraints
+                .equalities
+                .into_iter()
+                .map(|equality| (equality.coefficients.into_vec(), equality.value)),
+        );
+    }
+
+    boundaries
+}
+
+pub fn default_template_coefficients(dimension: usize) -> Vec<Vec<i64>> {
     TemplateTestCodec::coefficients(default_templates(
         dimension,
         &DefaultTemplateFamilies::all(),
@@ -55,16 +123,16 @@ pub fn default_template_coefficients(dimension: usize) -> Vec<Vec<i32>> {
 pub fn default_template_coefficients_with_families(
     dimension: usize,
     families: &[LinearGraphTemplateFamily],
-) -> Vec<Vec<i32>> {
+) -> Vec<Vec<i64>> {
     TemplateTestCodec::coefficients(default_templates(dimension, families))
 }
 
 pub fn candidate_template_coefficients(
     dimension: usize,
-    max_coefficient: i32,
+    max_coefficient: i64,
     max_candidates: usize,
-    existing: &[Vec<i32>],
-) -> Vec<Vec<i32>> {
+    existing: &[Vec<i64>],
+) -> Vec<Vec<i64>> {
     let existing = TemplateTestCodec::templates_from_coefficients(existing);
     TemplateTestCodec::coefficients(candidate_templates(
         dimension,
@@ -78,15 +146,16 @@ pub fn guided_candidate_template_coefficients(
     cfg: &VASSCFG<()>,
     initial_valuation: &VASSCounterValuation,
     model_boundaries: &[(NodeIndex, VASSCounterValuation)],
-    max_coefficient: i32,
+    max_coefficient: i64,
     max_candidates: usize,
-) -> Vec<Vec<i32>> {
+) -> Vec<Vec<i64>> {
     let current = main_cfg_template_lower_bounds(
         cfg,
         initial_valuation,
         true,
         usize::MAX,
         &DefaultTemplateFamilies::all(),
+        &ArithmeticContext::default(),
     );
     TemplateTestCodec::coefficients(super::synthesis::candidate_templates_for_boundaries(
         initial_valuation.dimension(),
@@ -98,24 +167,52 @@ pub fn guided_candidate_template_coefficients(
 }
 
 pub fn exact_successor_bound_from_coefficients(
-    templates: &[Vec<i32>],
-    source_bounds: &[i32],
+    templates: &[Vec<i64>],
+    source_bounds: &[i64],
+    update: &CFGCounterUpdate,
+    objective_index: usize,
+    cap: i64,
+) -> i64 {
+    let templates = TemplateTestCodec::templates_from_coefficients(templates);
+    exact_successor_template_bound(
+        &ArithmeticContext::default(),
+        &templates,
+        source_bounds,
+        update,
+        objective_index,
+        cap,
+    )
+}
+
+/// Like [`exact_successor_bound_from_coefficients`], but writes the optimizer
+/// query to `dump`.
+pub fn exact_successor_bound_from_coefficients_with_dump(
+    dump: Arc<SMTQueryDump>,
+    templates: &[Vec<i64>],
+    source_bounds: &[i64],
     update: &CFGCounterUpdate,
     objective_index: usize,
-    cap: i32,
-) -> i32 {
+    cap: i64,
+) -> i64 {
     let templates = TemplateTestCodec::templates_from_coefficients(templates);
-    exact_successor_template_bound(&templates, source_bounds, update, objective_index, cap)
+    exact_successor_template_bound(
+        &ArithmeticContext::default().with_dump(Some(dump)),
+        &templates,
+        source_bounds,
+        update,
+        objective_index,
+        cap,
+    )
 }
 
 pub fn successor_bound_from_coefficients_with_exact_transfer(
-    templates: &[Vec<i32>],
-    source_bounds: &[i32],
+    templates: &[Vec<i64>],
+    source_bounds: &[i64],
     update: &CFGCounterUpdate,
     objective_index: usize,
-    cap: i32,
+    cap: i64,
     exact_transfer_enabled: bool,
-) -> i32 {
+) -> i64 {
     successor_bound_from_coefficients_with_exact_transfer_limit(
         templates,
         source_bounds,
@@ -128,14 +225,14 @@ pub fn successor_bound_from_coefficients_with_exact_transfer(
 }
 
 pub fn successor_bound_from_coefficients_with_exact_transfer_limit(
-    templates: &[Vec<i32>],
-    source_bounds: &[i32],
+    templates: &[Vec<i64>],
+    source_bounds: &[i64],
     update: &CFGCounterUpdate,
     objective_index: usize,
-    cap: i32,
+    cap: i64,
     exact_transfer_enabled: bool,
     exact_transfer_max_templates: usize,
-) -> i32 {
+) -> i64 {
     let templates = TemplateTestCodec::templates_from_coefficients(templates);
     successor_bounds(
         &templates,
@@ -144,13 +241,14 @@ pub fn successor_bound_from_coefficients_with_exact_transfer_limit(
         cap,
         exact_transfer_enabled,
         exact_transfer_max_templates,
+        &ArithmeticContext::default(),
     )[objective_index]
 }
 
 pub fn analyze_template_bounds_snapshot(
     cfg: &VASSCFG<()>,
     initial_valuation: &VASSCounterValuation,
-    templates: &[Vec<i32>],
+    templates: &[Vec<i64>],
 ) -> TemplateAnalysisSnapshot {
     TemplateTestCodec::snapshot(analyze_templates(
         cfg,
@@ -158,14 +256,15 @@ pub fn analyze_template_bounds_snapshot(
         TemplateTestCodec::templates_from_coefficients(templates),
         true,
         usize::MAX,
+        &ArithmeticContext::default(),
     ))
 }
 
 pub fn analyze_incremental_template_bounds_snapshot(
     cfg: &VASSCFG<()>,
     initial_valuation: &VASSCounterValuation,
-    current_templates: &[Vec<i32>],
-    extra_template: Vec<i32>,
+    current_templates: &[Vec<i64>],
+    extra_template: Vec<i64>,
 ) -> TemplateAnalysisSnapshot {
     let current = analyze_templates(
         cfg,
@@ -173,6 +272,7 @@ pub fn analyze_incremental_template_bounds_snapshot(
         TemplateTestCodec::templates_from_coefficients(current_templates),
         true,
         usize::MAX,
+        &ArithmeticContext::default(),
     );
     TemplateTestCodec::snapshot(analyze_with_incremental_template(
         cfg,
@@ -181,6 +281,7 @@ pub fn analyze_incremental_template_bounds_snapshot(
         LinearTemplate::from_coefficients(extra_template),
         true,
         usize::MAX,
+        &ArithmeticContext::default(),
     ))
 }
 
@@ -188,15 +289,34 @@ pub fn synthesize_template_coefficients(
     cfg: &VASSCFG<()>,
     initial_valuation: &VASSCounterValuation,
     model_boundaries: &[(NodeIndex, VASSCounterValuation)],
-    max_coefficient: i32,
+    max_coefficient: i64,
+    max_candidates: usize,
+) -> Option<Vec<i64>> {
+    synthesize_template_coefficients_with_mode(
+        cfg,
+        initial_valuation,
+        model_boundaries,
+        max_coefficient,
+        max_candidates,
+        LinearGraphTemplateSynthesisMode::Enumeration,
+    )
+}
+
+pub fn synthesize_template_coefficients_with_mode(
+    cfg: &VASSCFG<()>,
+    initial_valuation: &VASSCounterValuation,
+    model_boundaries: &[(NodeIndex, VASSCounterValuation)],
+    max_coefficient: i64,
     max_candidates: usize,
-) -> Option<Vec<i32>> {
+    mode: LinearGraphTemplateSynthesisMode,
+) -> Option<Vec<i64>> {
     let current = main_cfg_template_lower_bounds(
         cfg,
         initial_valuation,
         true,
         usize::MAX,
         &DefaultTemplateFamilies::all(),
+        &ArithmeticContext::default(),
     );
     synthesize_template_for_boundaries(
         cfg,
@@ -208,6 +328,9 @@ pub fn synthesize_template_coefficients(
             max_candidates,
             exact_transfer_enabled: true,
             exact_transfer_max_templates: usize::MAX,
+            mode,
+            time_limit: None,
+            arithmetic: ArithmeticContext::default(),
         },
     )
     .map(|(template, _)| template.coefficients.into_vec())
@@ -227,14 +350,14 @@ impl TemplateTestCodec {
         }
     }
 
-    fn coefficients(templates: Vec<LinearTemplate>) -> Vec<Vec<i32>> {
+    fn coefficients(templates: Vec<LinearTemplate>) -> Vec<Vec<i64>> {
         templates
             .into_iter()
             .map(|template| template.coefficients.into_vec())
             .collect()
     }
 
-    fn templates_from_coefficients(coefficients: &[Vec<i32>]) -> Vec<LinearTemplate> {
+    fn templates_from_coefficients(coefficients: &[Vec<i64>]) -> Vec<LinearTemplate> {
         coefficients
             .iter()
             .map(|coefficients| LinearTemplate::from_coefficients(coefficients.clone()))
@@ -2,41 +2,44 @@
 //!
 //! The optimizer minimizes the target template after applying the edge update,
 //! subject to source-state template lower bounds, counter non-negativity, and
-//! the decrement guard. When Z3 cannot provide a usable optimum, transfer falls
-//! back to the template's bottom lower bound.
-
-use z3::{Optimize, SatResult, ast::Int};
+//! the decrement guard. When the solver cannot provide a usable optimum,
+//! transfer falls back to the template's bottom lower bound.
 
 use super::LinearTemplate;
-use crate::automaton::cfg::update::CFGCounterUpdate;
+use crate::{
+    automaton::cfg::update::CFGCounterUpdate,
+    solver::{
+        backend::{ArithmeticContext, ArithmeticSolver, CheckLimits, SatResult, ast::Int},
+        smt_dump::check_solver,
+    },
+};
 
 pub(super) fn exact_successor_template_bound(
+    arithmetic: &ArithmeticContext,
     templates: &[LinearTemplate],
-    source_bounds: &[i32],
+    source_bounds: &[i64],
     update: &CFGCounterUpdate,
     objective_index: usize,
-    cap: i32,
-) -> i32 {
-    ExactTemplateTransfer::new(templates, source_bounds, update).successor_template_bound(
-        &templates[objective_index],
-        update,
-        cap,
-    )
+    cap: i64,
+) -> i64 {
+    ExactTemplateTransfer::new(arithmetic, templates, source_bounds, update)
+        .successor_template_bound(&templates[objective_index], update, cap)
 }
 
 pub(super) struct ExactTemplateTransfer {
-    optimizer: Optimize,
+    optimizer: ArithmeticSolver,
     counters: Vec<Int>,
 }
 
 impl ExactTemplateTransfer {
     pub(super) fn new(
+        arithmetic: &ArithmeticContext,
         templates: &[LinearTemplate],
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         update: &CFGCounterUpdate,
     ) -> Self {
-        let optimizer = Optimize::new();
-        let counters = Self::z3_counter_variables(templates[0].coefficients.len());
+        let optimizer = ArithmeticSolver::new_optimizer(arithmetic);
+        let counters = Self::counter_variables(templates[0].coefficients.len());
 
         // These assertions describe an over-approximation of all valuations
         // that can appear before this edge. Objective-specific minimization can
@@ -55,8 +58,8 @@ impl ExactTemplateTransfer {
         &self,
         objective_template: &LinearTemplate,
         update: &CFGCounterUpdate,
-        cap: i32,
-    ) -> i32 {
+        cap: i64,
+    ) -> i64 {
         self.optimizer.push();
         let objective = Self::successor_objective(objective_template, update, &self.counters);
         self.optimizer.minimize(&objective);
@@ -66,37 +69,39 @@ impl ExactTemplateTransfer {
         value
     }
 
-    fn z3_counter_variables(dimension: usize) -> Vec<Int> {
+    fn counter_variables(dimension: usize) -> Vec<Int> {
         (0..dimension)
             .map(|counter| Int::new_const(format!("template_transfer_c{counter}")))
             .collect()
     }
 
-    fn assert_non_negative_counters(optimizer: &Optimize, counters: &[Int]) {
+    fn assert_non_negative_counters(optimizer: &ArithmeticSolver, counters: &[Int]) {
         for counter in counters {
             optimizer.assert(counter.ge(Int::from_i64(0)));
         }
     }
 
-    fn assert_enabled_update(optimizer: &Optimize, update: &CFGCounterUpdate, counters: &[Int]) {
-        // VASS decrements are enabled only when the decremented counter has credit.
+    fn assert_enabled_update(
+        optimizer: &ArithmeticSolver,
+        update: &CFGCounterUpdate,
+        counters: &[Int],
+    ) {
+        // VASS decrements are enabled only when the decremented counter has
+        // enough credit for the whole weight.
         if update.op() < 0 {
-            optimizer.assert(counters[update.counter().to_usize()].ge(Int::from_i64(1)));
+            optimizer
+                .assert(counters[update.counter().to_usize()].ge(Int::from_i64(update.weight())));
         }
     }
 
     fn assert_source_bounds(
-        optimizer: &Optimize,
+        optimizer: &ArithmeticSolver,
         templates: &[LinearTemplate],
-        source_bounds: &[i32],
+        source_bounds: &[i64],
         counters: &[Int],
     ) {
         for (template, bound) in templates.iter().zip(source_bounds.iter()) {
-            optimizer.assert(
-                template
-                    .z3_expression(counters)
-                    .ge(Int::from_i64(*bound as i64)),
-            );
+            optimizer.assert(template.smt_expression(counters).ge(Int::from_i64(*bound)));
         }
     }
 
@@ -105,24 +110,27 @@ impl ExactTemplateTransfer {
         update: &CFGCounterUpdate,
         counters: &[Int],
     ) -> Int {
-        objective_template.z3_expression(counters)
+        objective_template.smt_expression(counters)
             + Int::from_i64(
-                (objective_template.coefficients[update.counter().to_usize()] * update.op()) as i64,
+                objective_template.coefficients[update.counter().to_usize()] * update.op(),
             )
     }
 
     fn minimized_objective_value(
-        optimizer: &Optimize,
+        optimizer: &ArithmeticSolver,
         template: &LinearTemplate,
         objective: &Int,
-        cap: i32,
-    ) -> i32 {
-        match optimizer.check(&[]) {
+        cap: i64,
+    ) -> i64 {
+        match check_solver(
+            optimizer,
+            &[],
+            CheckLimits::default(),
+            "template_transfer",
+            None,
+        ) {
             SatResult::Sat => optimizer
-                .get_model()
-                .and_then(|model| model.eval(objective, true))
-                .and_then(|value| value.as_i64())
-                .and_then(|value| i32::try_from(value).ok())
+                .eval(objective)
                 .map(|value| template.clamp_lower_bound(value, cap))
                 .unwrap_or_else(|| template.bottom_bound()),
             SatResult::Unsat | SatResult::Unknown => template.bottom_bound(),
@@ -717,8 +717,7 @@ where
     pub fn to_nfa(&self) -> NFA<(), CFGCounterUpdate> {
         self.assert_consistent();
 
-        let mut nfa: NFA<(), CFGCounterUpdate> =
-            NFA::new(CFGCounterUpdate::alphabet(self.dimension));
+        let mut nfa: NFA<(), CFGCounterUpdate> = NFA::new(self.automaton.alphabet().to_vec());
         let start_state = nfa.add_node(DfaNode::non_accepting(()));
         nfa.set_initial(start_state);
 
@@ -274,7 +274,23 @@ impl<NIndex: GIndex> ModifiableAutomaton<Deterministic> for LinearGraphRegion<NI
     }
 
     fn remove_node(&mut self, node: &Self::NIndex) {
+        assert!(
+            *node != self.start && *node != self.end,
+            "Cannot remove the boundary node {:?} of a linear graph region",
+            node
+        );
+
+        // petgraph moves the last node into the freed index, so boundary
+        // indices have to follow it.
+        let last = NodeIndex::new(self.graph.node_count() - 1);
         self.graph.remove_node(*node);
+
+        if self.start == last {
+            self.start = *node;
+        }
+        if self.end == last {
+            self.end = *node;
+        }
     }
 
     fn remove_edge(&mut self, edge: &Self::EIndex) {
@@ -1,10 +1,15 @@
 use itertools::Itertools;
-use z3::{
-    Solver,
-    ast::{Bool, Int},
-};
 
-use crate::automaton::vass::counter::{VASSCounterUpdate, VASSCounterValuation};
+use crate::{
+    automaton::vass::counter::{VASSCounterUpdate, VASSCounterValuation},
+    solver::{
+        backend::{
+            ArithmeticContext, ArithmeticSolver, CheckLimits, SatResult,
+            ast::{Bool, Int},
+        },
+        smt_dump::check_solver,
+    },
+};
 
 pub mod translation;
 
@@ -102,7 +107,14 @@ impl LTC {
         initial_valuation: &VASSCounterValuation,
         final_valuation: &VASSCounterValuation,
     ) -> LTCSolverResult {
-        self.reach(false, false, initial_valuation, final_valuation)
+        self.reach(
+            &ArithmeticContext::default(),
+            CheckLimits::default(),
+            false,
+            false,
+            initial_valuation,
+            final_valuation,
+        )
     }
 
     /// Reachability from 0 to 0 in the natural numbers, so no intermediate
@@ -112,7 +124,12 @@ impl LTC {
         initial_valuation: &VASSCounterValuation,
         final_valuation: &VASSCounterValuation,
     ) -> LTCSolverResult {
-        self.reach(true, true, initial_valuation, final_valuation)
+        self.reach_n_with_context(
+            &ArithmeticContext::default(),
+            CheckLimits::default(),
+            initial_valuation,
+            final_valuation,
+        )
     }
 
     pub fn reach_n_relaxed(
@@ -120,11 +137,56 @@ impl LTC {
         initial_valuation: &VASSCounterValuation,
         final_valuation: &VASSCounterValuation,
     ) -> LTCSolverResult {
-        self.reach(true, false, initial_valuation, final_valuation)
+        self.reach_n_relaxed_with_context(
+            &ArithmeticContext::default(),
+            CheckLimits::default(),
+            initial_valuation,
+            final_valuation,
+        )
+    }
+
+    /// Like [`Self::reach_n`], with the solver created from `context`. The
+    /// result is unknown if the check exceeds `limits`.
+    pub(crate) fn reach_n_with_context(
+        &self,
+        context: &ArithmeticContext,
+        limits: CheckLimits<'_>,
+        initial_valuation: &VASSCounterValuation,
+        final_valuation: &VASSCounterValuation,
+    ) -> LTCSolverResult {
+        self.reach(
+            context,
+            limits,
+            true,
+            true,
+            initial_valuation,
+            final_valuation,
+        )
+    }
+
+    /// Like [`Self::reach_n_relaxed`], with the solver created from
+    /// `context`. The result is unknown if the check exceeds `limits`.
+    pub(crate) fn reach_n_relaxed_with_context(
+        &self,
+        context: &ArithmeticContext,
+        limits: CheckLimits<'_>,
+        initial_valuation: &VASSCounterValuation,
+        final_valuation: &VASSCounterValuation,
+    ) -> LTCSolverResult {
+        self.reach(
+            context,
+            limits,
+            true,
+            false,
+            initial_valuation,
+            final_valuation,
+        )
     }
 
     fn reach(
         &self,
+        context: &ArithmeticContext,
+        limits: CheckLimits<'_>,
         n_reach: bool,
         assert_n_loops: bool,
         initial_valuation: &VASSCounterValuation,
@@ -132,10 +194,11 @@ impl LTC {
     ) -> LTCSolverResult {
         let time = std::time::Instant::now();
 
-        let solver = Solver::new();
+        let solver = ArithmeticSolver::new(context);
 
         let result = self.reach_inner(
             &solver,
+            limits,
             n_reach,
             assert_n_loops,
             initial_valuation,
@@ -147,17 +210,18 @@ impl LTC {
 
     fn reach_inner(
         &self,
-        solver: &Solver,
+        solver: &ArithmeticSolver,
+        limits: CheckLimits<'_>,
         n_reach: bool,
         assert_n_loops: bool,
         initial_valuation: &VASSCounterValuation,
         final_valuation: &VASSCounterValuation,
-    ) -> bool {
+    ) -> Option<bool> {
         let zero = Int::from_i64(0);
 
         let mut sums = initial_valuation
             .iter()
-            .map(|&x| Int::from_i64(x as i64))
+            .map(|&x| Int::from_i64(x))
             .collect_vec();
         // currently unused, for path extraction later
         let mut loop_variables = vec![];
@@ -179,8 +243,8 @@ impl LTC {
                             if assert_n_loops {
                                 for (j, (subtract, add)) in loops.iter().enumerate() {
                                     let l = &ls[j];
-                                    let sub_i = &Int::from_i64(subtract[i] as i64);
-                                    let add_i = &Int::from_i64(add[i] as i64);
+                                    let sub_i = &Int::from_i64(subtract[i]);
+                                    let add_i = &Int::from_i64(add[i]);
 
                                     // if we want to solve reach in N, we need to assert after every
                                     // subtraction
@@ -201,8 +265,8 @@ impl LTC {
 
                                 for (j, (subtract, add)) in loops.iter().enumerate() {
                                     let l = &ls[j];
-                                    let sub_i = &Int::from_i64(subtract[i] as i64);
-                                    let add_i = &Int::from_i64(add[i] as i64);
+                                    let sub_i = &Int::from_i64(subtract[i]);
+                                    let add_i = &Int::from_i64(add[i]);
 
                                     let lm1 = l - &Int::from_i64(1);
 
@@ -211,11 +275,8 @@ impl LTC {
 
                                     for other in loops.iter().enumerate() {
                                         if other.0 != j {
-                                            c2 = &c2
-                                                - &Int::from_i64(other.1.0[i] as i64)
-                                                    * &ls[other.0]
-                                                + &Int::from_i64(other.1.1[i] as i64)
-                                                    * &ls[other.0];
+                                            c2 = &c2 - &Int::from_i64(other.1.0[i]) * &ls[other.0]
+                                                + &Int::from_i64(other.1.1[i]) * &ls[other.0];
                                         }
                                     }
 
@@ -234,8 +295,8 @@ impl LTC {
 
                                 for (j, (subtract, add)) in loops.iter().enumerate() {
                                     let l = &ls[j];
-                                    let sub_i = &Int::from_i64(subtract[i] as i64);
-                                    let add_i = &Int::from_i64(add[i] as i64);
+                                    let sub_i = &Int::from_i64(subtract[i]);
+                                    let add_i = &Int::from_i64(add[i]);
 
                                     sums[i] = &sums[i] - sub_i * l + add_i * l;
                                 }
@@ -243,8 +304,8 @@ impl LTC {
                         } else {
                             for (j, (subtract, add)) in loops.iter().enumerate() {
                                 let l = &ls[j];
-                                let sub_i = &Int::from_i64(subtract[i] as i64);
-                                let add_i = &Int::from_i64(add[i] as i64);
+                                let sub_i = &Int::from_i64(subtract[i]);
+                                let add_i = &Int::from_i64(add[i]);
 
                                 sums[i] = &sums[i] - sub_i * l + add_i * l;
                             }
@@ -257,7 +318,7 @@ impl LTC {
                     // for each counter, we subtract the subtract value, then assert that we are
                     // positive and add the add value
                     for i in 0..self.dimension {
-                        sums[i] = &sums[i] - &Int::from_i64(subtract[i] as i64);
+                        sums[i] = &sums[i] - &Int::from_i64(subtract[i]);
 
                         // if we want to solve reach in N, we need to assert after every subtraction
                         // that the counters are positive
@@ -265,42 +326,52 @@ impl LTC {
                             solver.assert(sums[i].ge(&zero));
                         }
 
-                        sums[i] = &sums[i] + &Int::from_i64(add[i] as i64);
+                        sums[i] = &sums[i] + &Int::from_i64(add[i]);
                     }
                 }
             }
         }
 
         for (sum, target) in sums.into_iter().zip(final_valuation.iter()) {
-            solver.assert(sum.eq(Int::from_i64(*target as i64)));
+            solver.assert(sum.eq(Int::from_i64(*target)));
         }
 
         // println!("Solver setup took: {:?}", time.elapsed());
 
-        match solver.check() {
-            z3::SatResult::Sat => true,
-            z3::SatResult::Unsat => false,
-            z3::SatResult::Unknown => panic!("Solver returned unknown"),
+        let purpose = match (n_reach, assert_n_loops) {
+            (false, _) => "ltc_reach_z",
+            (true, false) => "ltc_reach_n_relaxed",
+            (true, true) => "ltc_reach_n",
+        };
+        match check_solver(solver, &[], limits, purpose, None) {
+            SatResult::Sat => Some(true),
+            SatResult::Unsat => Some(false),
+            SatResult::Unknown => None,
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LTCSolverResult {
-    pub result: bool,
+    /// `None` if the solver gave up, e.g. because the check ran out of time.
+    pub result: Option<bool>,
     pub duration: std::time::Duration,
 }
 
 impl LTCSolverResult {
-    pub fn new(result: bool, duration: std::time::Duration) -> Self {
+    pub fn new(result: Option<bool>, duration: std::time::Duration) -> Self {
         LTCSolverResult { result, duration }
     }
 
     pub fn is_success(&self) -> bool {
-        self.result
+        self.result == Some(true)
     }
 
     pub fn is_failure(&self) -> bool {
-        !self.result
+        self.result == Some(false)
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.result.is_none()
     }
 }
@@ -127,8 +127,10 @@ impl<NIndex: GIndex> LTCTranslation<NIndex> {
     //     }
     // }
 
-    pub fn to_dfa(&self, dimension: usize, relaxed: bool) -> VASSCFG<()> {
-        let mut nfa = NFA::<(), CFGCounterUpdate>::new(CFGCounterUpdate::alphabet(dimension));
+    /// `alphabet` has to contain the letters of the translation, usually it
+    /// is the one of the CFG it came from.
+    pub fn to_dfa(&self, alphabet: &[CFGCounterUpdate], relaxed: bool) -> VASSCFG<()> {
+        let mut nfa = NFA::<(), CFGCounterUpdate>::new(alphabet.to_vec());
 
         let start = nfa.add_node(DfaNode::default());
         nfa.set_initial(start);
@@ -4,7 +4,9 @@ use crate::automaton::{
     AutomatonEdge, Deterministic, ExplicitEdgeAutomaton, GIndex, Letter, TransitionSystem,
     cfg::update::{CFGCounterUpdatable, CFGCounterUpdate},
     implicit_cfg_product::{all_approximation_indices, bounded_counting_indices},
-    vass::counter::{VASSCounterIndex, VASSCounterValuation},
+    vass::counter::{
+        CounterOverflowError, VASSCounterIndex, VASSCounterValuation, checked_counter_add,
+    },
 };
 
 pub mod parikh_image;
@@ -346,15 +348,25 @@ impl<N: GIndex> Path<N, CFGCounterUpdate> {
         &counters == final_valuation
     }
 
+    /// Panics if a counter overflows, see
+    /// [`try_get_path_final_valuation`](Self::try_get_path_final_valuation).
     pub fn get_path_final_valuation(
         &self,
         initial_valuation: &VASSCounterValuation,
     ) -> VASSCounterValuation {
+        self.try_get_path_final_valuation(initial_valuation)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_get_path_final_valuation(
+        &self,
+        initial_valuation: &VASSCounterValuation,
+    ) -> Result<VASSCounterValuation, CounterOverflowError> {
         let mut counters = initial_valuation.clone();
         for edge in &self.transitions {
-            counters.apply_cfg_update(*edge);
+            counters.try_apply_cfg_update(*edge)?;
         }
-        counters
+        Ok(counters)
     }
 
     pub fn find_negative_counter_forward(
@@ -391,11 +403,12 @@ impl<N: GIndex> Path<N, CFGCounterUpdate> {
         None
     }
 
+    /// Panics if the counter overflows.
     pub fn max_counter_value(
         &self,
         initial_valuation: &VASSCounterValuation,
         counter: VASSCounterIndex,
-    ) -> i32 {
+    ) -> i64 {
         let counter_updates = self
             .transitions
             .iter()
@@ -404,18 +417,20 @@ impl<N: GIndex> Path<N, CFGCounterUpdate> {
         let mut value = initial_valuation[counter];
         let mut max_value = initial_valuation[counter];
         for update in counter_updates {
-            value += update.op();
+            value = checked_counter_add(counter, value, update.op())
+                .unwrap_or_else(|err| panic!("{err}"));
             max_value = max_value.max(value);
         }
 
         max_value
     }
 
+    /// Panics if the counter overflows.
     pub fn max_counter_value_from_back(
         &self,
         final_valuation: &VASSCounterValuation,
         counter: VASSCounterIndex,
-    ) -> i32 {
+    ) -> i64 {
         let counter_updates = self
             .transitions
             .iter()
@@ -425,7 +440,8 @@ impl<N: GIndex> Path<N, CFGCounterUpdate> {
         let mut value = final_valuation[counter];
         let mut max_value = final_valuation[counter];
         for update in counter_updates {
-            value -= update.op();
+            value = checked_counter_add(counter, value, update.reverse().op())
+                .unwrap_or_else(|err| panic!("{err}"));
             max_value = max_value.max(value);
         }
 
@@ -263,7 +263,7 @@ impl ParikhImage<EdgeIndex> {
         for (edge_index, count) in self.image.iter() {
             let edge = cfg.get_edge_unchecked(&edge_index);
 
-            total_effect[edge.counter()] += edge.op() * (*count as i32);
+            total_effect[edge.counter()] += edge.op() * i64::from(*count);
         }
 
         total_effect
@@ -1,12 +1,21 @@
+use hashbrown::HashMap;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::automaton::{
-    ModifiableAutomaton,
+    InitializedAutomaton, ModifiableAutomaton, TransitionSystem,
+    cfg::update::CFGCounterUpdate,
     petri_net::{
         PetriNet,
         spec::{PetriNetSpec, ToSpecFormat},
     },
-    vass::{VASS, VASSEdge, counter::VASSCounterValuation, initialized::InitializedVASS},
+    vass::{
+        VASS, VASSEdge,
+        control::{ControlConstraint, control_dfa},
+        counter::{VASSCounterUpdate, VASSCounterValuation},
+        initialized::InitializedVASS,
+        valuation_spec::ValuationSpec,
+    },
 };
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -14,6 +23,16 @@ pub struct InitializedPetriNet {
     pub net: PetriNet,
     pub initial_marking: VASSCounterValuation,
     pub final_marking: VASSCounterValuation,
+    /// Set of initial markings, `initial_marking` is its base valuation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_spec: Option<ValuationSpec>,
+    /// Set of target markings, `final_marking` is its base valuation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_spec: Option<ValuationSpec>,
+    /// Constraints on the transitions a run may fire, transition `i` is the
+    /// letter `ti`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub control_constraints: Vec<ControlConstraint>,
 }
 
 impl InitializedPetriNet {
@@ -26,31 +45,203 @@ impl InitializedPetriNet {
             net,
             initial_marking,
             final_marking,
+            initial_spec: None,
+            target_spec: None,
+            control_constraints: vec![],
         }
     }
 
+    pub fn with_control_constraints(mut self, constraints: Vec<ControlConstraint>) -> Self {
+        self.control_constraints.extend(constraints);
+        self
+    }
+
+    pub fn with_initial_spec(mut self, spec: ValuationSpec) -> Self {
+        self.initial_marking = spec.base_valuation();
+        self.initial_spec = (!spec.is_exact()).then_some(spec);
+        self
+    }
+
+    pub fn with_target_spec(mut self, spec: ValuationSpec) -> Self {
+        self.final_marking = spec.base_valuation();
+        self.target_spec = (!spec.is_exact()).then_some(spec);
+        self
+    }
+
+    /// Converts the net into a VASS with a center state, from which every
+    /// transition first consumes its input and then produces its output.
+    ///
+    /// With control constraints there is one center state per state of the
+    /// constraint DFA, which advances once per fired transition. The VASS
+    /// then grows with the DFA, which is the product of the DFAs of all
+    /// constraints and can be exponential in the size of a regex. The
+    /// constraints are nevertheless compiled into the VASS rather than
+    /// intersected in the CFG product of the solver: CFG letters are counter
+    /// updates, so they no longer tell which transition fired.
     pub fn to_vass(&self) -> InitializedVASS<usize, usize> {
-        let mut vass = VASS::new(
-            self.net.place_count,
-            (0..self.net.transitions.len()).collect(),
-        );
-        let center_state = vass.add_node(0);
+        let alphabet = (0..self.net.transitions.len()).collect_vec();
+        let control = control_dfa(&self.control_constraints, &alphabet);
+
+        let mut vass = VASS::new(self.net.place_count, alphabet);
+        let mut centers = HashMap::new();
+        let mut discovered = vec![];
+
+        let control_initial = control.as_ref().map(|control| control.get_initial());
+        centers.insert(control_initial, vass.add_node(0));
+        discovered.push(control_initial);
+
+        let mut next = 0;
+        while let Some(&control_state) = discovered.get(next) {
+            let center_state = centers[&control_state];
+            next += 1;
 
-        for (i, transition) in self.net.transitions.iter().enumerate() {
-            let state = vass.add_node(i + 1);
-            let input_vec = transition.input_to_vass_update(self.net.place_count);
-            let output_vec = transition.output_to_vass_update(self.net.place_count);
+            for (i, transition) in self.net.transitions.iter().enumerate() {
+                let control_target = match (&control, control_state) {
+                    (Some(control), Some(control_state)) => {
+                        match control.successor(&control_state, &i) {
+                            Some(target) => Some(target),
+                            None => continue,
+                        }
+                    }
+                    _ => None,
+                };
 
-            vass.add_edge(&center_state, &state, VASSEdge::new(i, input_vec));
-            vass.add_edge(&state, &center_state, VASSEdge::new(i, output_vec));
+                let state = vass.add_node(vass.state_count());
+                let target_center = *centers.entry(control_target).or_insert_with(|| {
+                    discovered.push(control_target);
+                    vass.add_node(vass.state_count())
+                });
+                let input_vec = transition.input_to_vass_update(self.net.place_count);
+                let output_vec = transition.output_to_vass_update(self.net.place_count);
+
+                vass.add_edge(&center_state, &state, VASSEdge::new(i, input_vec));
+                vass.add_edge(&state, &target_center, VASSEdge::new(i, output_vec));
+            }
         }
 
-        vass.init(
+        let mut final_states = discovered
+            .iter()
+            .filter(|control_state| match (&control, control_state) {
+                (Some(control), Some(control_state)) => control.is_accepting(control_state),
+                _ => true,
+            })
+            .map(|control_state| centers[control_state])
+            .collect_vec();
+        if final_states.is_empty() {
+            // the constraints allow no run, so the final state is unreachable
+            final_states.push(vass.add_node(vass.state_count()));
+        }
+
+        let mut initialized = vass.init(
             self.initial_marking.clone(),
             self.final_marking.clone(),
-            center_state,
-            center_state,
-        )
+            centers[&control_initial],
+            final_states[0],
+        );
+        for final_state in final_states.into_iter().skip(1) {
+            initialized.add_final_state(final_state, self.final_marking.clone());
+        }
+
+        if self.initial_spec.is_none() && self.target_spec.is_none() {
+            return initialized;
+        }
+
+        let initial_spec = self
+            .initial_spec
+            .clone()
+            .unwrap_or_else(|| ValuationSpec::exactly(&self.initial_marking));
+        let target_spec = self
+            .target_spec
+            .clone()
+            .unwrap_or_else(|| ValuationSpec::exactly(&self.final_marking));
+        initialized.with_valuation_specs(&initial_spec, &target_spec)
+    }
+
+    /// Whether the transitions of `run` can fire one after another from the
+    /// initial marking and end in the final marking.
+    pub fn is_run_to_final_marking(&self, run: &[usize]) -> bool {
+        let mut marking = self.initial_marking.clone();
+        for transition in run {
+            let Some(transition) = self.net.transitions.get(*transition) else {
+                return false;
+            };
+            let input = transition.input_to_vass_update(self.net.place_count);
+            if !marking.can_apply_update(&input) {
+                return false;
+            }
+            marking.apply_update(&input);
+            marking.apply_update(&transition.output_to_vass_update(self.net.place_count));
+        }
+
+        marking == self.final_marking
+    }
+
+    /// The counter updates along `run` in the VASS of the net, see
+    /// [`Self::to_vass`], where `encode` translates VASS updates into CFG
+    /// updates, e.g. [`vass_update_to_cfg_updates`].
+    ///
+    /// [`vass_update_to_cfg_updates`]: crate::automaton::utils::vass_update_to_cfg_updates
+    pub fn update_run(
+        &self,
+        run: &[usize],
+        encode: impl Fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> Vec<CFGCounterUpdate> {
+        run.iter()
+            .flat_map(|transition| self.transition_updates(*transition, &encode))
+            .collect()
+    }
+
+    /// Splits counter updates of a run of the VASS of the net into the
+    /// transitions it fires, the inverse of [`Self::update_run`]. Returns
+    /// `None` if `run` does not split into transitions.
+    pub fn transitions_of_update_run(
+        &self,
+        run: &[CFGCounterUpdate],
+        encode: impl Fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> Option<Vec<usize>> {
+        let transitions = (0..self.net.transitions.len())
+            .map(|transition| self.transition_updates(transition, &encode))
+            .collect_vec();
+
+        // the last transition of a split of `run[..end]` and where it starts
+        let mut splits: Vec<Option<(usize, usize)>> = vec![None; run.len() + 1];
+        for start in 0..run.len() {
+            if start > 0 && splits[start].is_none() {
+                continue;
+            }
+            for (transition, updates) in transitions.iter().enumerate() {
+                let end = start + updates.len();
+                if !updates.is_empty()
+                    && run.get(start..end) == Some(updates)
+                    && splits[end].is_none()
+                {
+                    splits[end] = Some((start, transition));
+                }
+            }
+        }
+
+        let mut split = vec![];
+        let mut end = run.len();
+        while end > 0 {
+            let (start, transition) = splits[end]?;
+            split.push(transition);
+            end = start;
+        }
+        split.reverse();
+        Some(split)
+    }
+
+    fn transition_updates(
+        &self,
+        transition: usize,
+        encode: &impl Fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> Vec<CFGCounterUpdate> {
+        let transition = &self.net.transitions[transition];
+        let mut updates = encode(&transition.input_to_vass_update(self.net.place_count));
+        updates.extend(encode(
+            &transition.output_to_vass_update(self.net.place_count),
+        ));
+        updates
     }
 
     pub fn to_json(&self) -> anyhow::Result<String> {
@@ -103,10 +294,13 @@ impl TryFrom<PetriNetSpec<'_>> for InitializedPetriNet {
             net.add_transition_struct(rule.to_transition(&spec.variables)?);
         }
 
-        Ok(InitializedPetriNet::new(
-            net,
-            spec.initial.to_counter_valuation(&spec.variables)?,
-            spec.target.to_counter_valuation(&spec.variables)?,
-        ))
+        let initial = spec.initial.to_valuation_spec(&spec.variables)?;
+        let target = spec.target.to_valuation_spec(&spec.variables)?;
+
+        Ok(
+            InitializedPetriNet::new(net, initial.base_valuation(), target.base_valuation())
+                .with_initial_spec(initial)
+                .with_target_spec(target),
+        )
     }
 }
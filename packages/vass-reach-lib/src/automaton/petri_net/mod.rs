@@ -5,6 +5,7 @@ use transition::PetriNetTransition;
 use crate::automaton::vass::counter::VASSCounterValuation;
 
 pub mod initialized;
+pub mod reduction;
 pub mod spec;
 pub mod transition;
 
@@ -0,0 +1,434 @@
+//! Structural reductions in the style of Berthelot that preserve whether the
+//! final marking is reachable from the initial marking.
+//!
+//! The rules are applied until none of them changes the net:
+//!
+//! - Redundant transitions: transitions that do not change the marking and
+//!   duplicates of other transitions.
+//! - Dead transitions: transitions that need more tokens than a place that
+//!   never gains tokens can ever hold, or that move a place that only ever
+//!   grows or only ever shrinks past its final marking.
+//! - Constant places: places no transition changes, if the initial and final
+//!   marking agree on them.
+//! - Implicit places: places that every transition changes like another place,
+//!   that always hold at least `offset` tokens more than it and that no
+//!   transition needs more than `offset` tokens more of. They never disable a
+//!   transition.
+//! - Agglomeration: a place that is empty initially and finally, whose
+//!   producers put one token into it and whose consumers take one out. If all
+//!   consumers only need this place, or all producers only fill this place,
+//!   every producer can be fused with every consumer and the place vanishes.
+//!   Fusing series places and transitions is the special case of a single
+//!   producer and consumer.
+//!
+//! Every transition of the reduced net stands for a sequence of original
+//! transitions, see [`PetriNetReduction::expand_run`] and
+//! [`PetriNetReduction::expand_update_run`].
+
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::automaton::{
+    cfg::update::CFGCounterUpdate,
+    petri_net::{PetriNet, initialized::InitializedPetriNet, transition::PetriNetTransition},
+    vass::counter::{VASSCounterUpdate, VASSCounterValuation},
+};
+
+/// The result of [`InitializedPetriNet::reduce`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PetriNetReduction {
+    pub net: InitializedPetriNet,
+    /// The original transitions every transition of `net` fires in order.
+    transitions: Vec<Vec<usize>>,
+    original: InitializedPetriNet,
+    pub statistics: PetriNetReductionStatistics,
+}
+
+impl PetriNetReduction {
+    /// The original transitions that transition `transition` of the reduced
+    /// net fires in order.
+    pub fn original_transitions(&self, transition: usize) -> &[usize] {
+        &self.transitions[transition]
+    }
+
+    /// Expands a run of the reduced net into a run of the original net. A run
+    /// to the final marking of the reduced net becomes a run to the final
+    /// marking of the original net.
+    pub fn expand_run(&self, run: &[usize]) -> Vec<usize> {
+        run.iter()
+            .flat_map(|transition| self.transitions[*transition].iter().copied())
+            .collect()
+    }
+
+    /// Like [`Self::expand_run`], but for the counter updates of a run of the
+    /// VASS of the reduced net, which is how solvers report runs. `encode`
+    /// is the update encoding of the CFG the run is a word of. Returns `None`
+    /// if `run` does not split into transitions of the reduced net.
+    pub fn expand_update_run(
+        &self,
+        run: &[CFGCounterUpdate],
+        encode: impl Fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> Option<Vec<CFGCounterUpdate>> {
+        let run = self.net.transitions_of_update_run(run, &encode)?;
+        Some(self.original.update_run(&self.expand_run(&run), encode))
+    }
+}
+
+/// Sizes of the net before and after [`InitializedPetriNet::reduce`] and how
+/// often each rule applied.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PetriNetReductionStatistics {
+    pub original_places: usize,
+    pub original_transitions: usize,
+    pub places: usize,
+    pub transitions: usize,
+    pub redundant_transitions: usize,
+    pub dead_transitions: usize,
+    pub constant_places: usize,
+    pub implicit_places: usize,
+    pub agglomerated_places: usize,
+}
+
+impl PetriNetReductionStatistics {
+    /// Whether any rule applied, so that the reduced net differs from the
+    /// original one.
+    pub fn is_reduced(&self) -> bool {
+        self.redundant_transitions
+            + self.dead_transitions
+            + self.constant_places
+            + self.implicit_places
+            + self.agglomerated_places
+            > 0
+    }
+}
+
+impl InitializedPetriNet {
+    /// Applies the structural reductions of the
+    /// [`reduction`](crate::automaton::petri_net::reduction) module.
+    ///
+    /// Initial and target specs describe sets of markings and control
+    /// constraints refer to transitions by index, so nets with any of them
+    /// are returned unchanged.
+    pub fn reduce(&self) -> PetriNetReduction {
+        if self.initial_spec.is_some()
+            || self.target_spec.is_some()
+            || !self.control_constraints.is_empty()
+        {
+            let transitions = self.net.transitions.len();
+            return PetriNetReduction {
+                net: self.clone(),
+                transitions: (0..transitions)
+                    .map(|transition| vec![transition])
+                    .collect(),
+                original: self.clone(),
+                statistics: PetriNetReductionStatistics {
+                    original_places: self.net.place_count,
+                    original_transitions: transitions,
+                    places: self.net.place_count,
+                    transitions,
+                    ..Default::default()
+                },
+            };
+        }
+
+        let mut reducer = Reducer::new(self);
+        reducer.reduce();
+        reducer.finish()
+    }
+}
+
+/// A transition with one weight per place, including removed ones, whose
+/// weights are zero.
+#[derive(Clone, Debug)]
+struct Transition {
+    pre: Vec<i64>,
+    post: Vec<i64>,
+    origin: Vec<usize>,
+}
+
+impl Transition {
+    /// Firing `self` and then `next`.
+    fn then(&self, next: &Transition) -> Transition {
+        let pre = (0..self.pre.len())
+            .map(|p| self.pre[p].max(self.pre[p] - self.post[p] + next.pre[p]))
+            .collect::<Vec<_>>();
+        let post = (0..self.pre.len())
+            .map(|p| pre[p] - self.pre[p] + self.post[p] - next.pre[p] + next.post[p])
+            .collect();
+
+        Transition {
+            pre,
+            post,
+            origin: self.origin.iter().chain(&next.origin).copied().collect(),
+        }
+    }
+
+    fn only_touches(weights: &[i64], place: usize) -> bool {
+        weights
+            .iter()
+            .enumerate()
+            .all(|(p, weight)| p == place || *weight == 0)
+    }
+}
+
+struct Reducer {
+    original: InitializedPetriNet,
+    places: Vec<bool>,
+    initial: Vec<i64>,
+    target: Vec<i64>,
+    transitions: Vec<Transition>,
+    statistics: PetriNetReductionStatistics,
+}
+
+impl Reducer {
+    fn new(net: &InitializedPetriNet) -> Self {
+        let place_count = net.net.place_count;
+        let transitions = net
+            .net
+            .transitions
+            .iter()
+            .enumerate()
+            .map(|(i, transition)| Transition {
+                pre: transition
+                    .input_to_vass_update(place_count)
+                    .iter()
+                    .map(|weight| -weight)
+                    .collect(),
+                post: transition
+                    .output_to_vass_update(place_count)
+                    .iter()
+                    .copied()
+                    .collect(),
+                origin: vec![i],
+            })
+            .collect::<Vec<_>>();
+
+        Reducer {
+            original: net.clone(),
+            places: vec![true; place_count],
+            initial: net.initial_marking.iter().copied().collect(),
+            target: net.final_marking.iter().copied().collect(),
+            statistics: PetriNetReductionStatistics {
+                original_places: place_count,
+                original_transitions: transitions.len(),
+                ..Default::default()
+            },
+            transitions,
+        }
+    }
+
+    fn reduce(&mut self) {
+        loop {
+            let mut changed = self.remove_redundant_transitions();
+            changed |= self.remove_dead_transitions();
+            changed |= self.remove_constant_places();
+            changed |= self.remove_implicit_places();
+            changed |= self.agglomerate_place();
+
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn alive_places(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.places.len()).filter(|p| self.places[*p])
+    }
+
+    /// Places are only removed while another one is left, so the VASS of the
+    /// net keeps a counter.
+    fn can_remove_place(&self) -> bool {
+        self.alive_places().count() > 1
+    }
+
+    fn remove_place(&mut self, place: usize) {
+        self.places[place] = false;
+        for transition in &mut self.transitions {
+            transition.pre[place] = 0;
+            transition.post[place] = 0;
+        }
+    }
+
+    fn remove_redundant_transitions(&mut self) -> bool {
+        let before = self.transitions.len();
+        let mut seen = HashSet::new();
+        self.transitions.retain(|transition| {
+            transition.pre != transition.post
+                && seen.insert((transition.pre.clone(), transition.post.clone()))
+        });
+
+        let removed = before - self.transitions.len();
+        self.statistics.redundant_transitions += removed;
+        removed > 0
+    }
+
+    fn remove_dead_transitions(&mut self) -> bool {
+        let alive = self.alive_places().collect::<Vec<_>>();
+        let grows = |p: usize| self.transitions.iter().any(|t| t.post[p] > t.pre[p]);
+        let shrinks = |p: usize| self.transitions.iter().any(|t| t.pre[p] > t.post[p]);
+        let never_grows = alive
+            .iter()
+            .copied()
+            .filter(|p| !grows(*p))
+            .collect::<Vec<_>>();
+        let never_shrinks = alive
+            .iter()
+            .copied()
+            .filter(|p| !shrinks(*p))
+            .collect::<Vec<_>>();
+
+        let before = self.transitions.len();
+        let (initial, target) = (&self.initial, &self.target);
+        self.transitions.retain(|t| {
+            let disabled_or_overshooting = never_grows
+                .iter()
+                .any(|&p| t.pre[p] > initial[p] || t.pre[p] - t.post[p] > initial[p] - target[p]);
+            let overfilling = never_shrinks
+                .iter()
+                .any(|&p| t.post[p] - t.pre[p] > target[p] - initial[p]);
+            !disabled_or_overshooting && !overfilling
+        });
+
+        let removed = before - self.transitions.len();
+        self.statistics.dead_transitions += removed;
+        removed > 0
+    }
+
+    fn remove_constant_places(&mut self) -> bool {
+        let mut changed = false;
+        for p in 0..self.places.len() {
+            let constant = self.places[p]
+                && self.initial[p] == self.target[p]
+                && self
+                    .transitions
+                    .iter()
+                    .all(|t| t.pre[p] == t.post[p] && t.pre[p] <= self.initial[p]);
+            if constant && self.can_remove_place() {
+                self.remove_place(p);
+                self.statistics.constant_places += 1;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// If every transition changes `q` like `p`, `q` always holds `offset`
+    /// tokens more than `p`. A transition enabled in `p` is then enabled in
+    /// `q` if it needs at most `offset` tokens more of `q`.
+    fn remove_implicit_places(&mut self) -> bool {
+        let mut changed = false;
+        for q in 0..self.places.len() {
+            let implicit = self.alive_places().any(|p| {
+                let offset = self.initial[q] - self.initial[p];
+                p != q
+                    && self.places[q]
+                    && offset >= 0
+                    && self.target[q] - self.target[p] == offset
+                    && self.transitions.iter().all(|t| {
+                        t.post[q] - t.pre[q] == t.post[p] - t.pre[p]
+                            && t.pre[q] <= t.pre[p] + offset
+                    })
+            });
+            if implicit && self.can_remove_place() {
+                self.remove_place(q);
+                self.statistics.implicit_places += 1;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Agglomerates at most one place, since it replaces transitions.
+    fn agglomerate_place(&mut self) -> bool {
+        for p in 0..self.places.len() {
+            if !self.places[p] || self.initial[p] != 0 || self.target[p] != 0 {
+                continue;
+            }
+
+            let producers = (0..self.transitions.len())
+                .filter(|t| self.transitions[*t].post[p] > 0)
+                .collect::<Vec<_>>();
+            let consumers = (0..self.transitions.len())
+                .filter(|t| self.transitions[*t].pre[p] > 0)
+                .collect::<Vec<_>>();
+
+            let fusable = !producers.is_empty()
+                && !consumers.is_empty()
+                // fusing must not grow the net
+                && producers.len() * consumers.len() <= producers.len() + consumers.len()
+                && producers.iter().all(|h| {
+                    let h = &self.transitions[*h];
+                    h.pre[p] == 0 && h.post[p] == 1
+                })
+                && consumers.iter().all(|f| {
+                    let f = &self.transitions[*f];
+                    f.pre[p] == 1 && f.post[p] == 0
+                })
+                && (consumers
+                    .iter()
+                    .all(|f| Transition::only_touches(&self.transitions[*f].pre, p))
+                    || producers
+                        .iter()
+                        .all(|h| Transition::only_touches(&self.transitions[*h].post, p)));
+            if !fusable || !self.can_remove_place() {
+                continue;
+            }
+
+            let fused = producers
+                .iter()
+                .flat_map(|h| {
+                    consumers
+                        .iter()
+                        .map(|f| self.transitions[*h].then(&self.transitions[*f]))
+                })
+                .collect::<Vec<_>>();
+            self.transitions = std::mem::take(&mut self.transitions)
+                .into_iter()
+                .enumerate()
+                .filter(|(t, _)| !producers.contains(t) && !consumers.contains(t))
+                .map(|(_, transition)| transition)
+                .chain(fused)
+                .collect();
+            self.remove_place(p);
+            self.statistics.agglomerated_places += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn finish(mut self) -> PetriNetReduction {
+        // place ids start from 1
+        let mut place_ids = vec![0; self.places.len()];
+        for (id, p) in self.alive_places().enumerate() {
+            place_ids[p] = id + 1;
+        }
+        let arcs = |weights: &[i64]| {
+            self.alive_places()
+                .filter(|p| weights[*p] > 0)
+                .map(|p| (weights[p] as usize, place_ids[p]))
+                .collect::<Vec<_>>()
+        };
+
+        let mut net = PetriNet::new(self.alive_places().count());
+        for transition in &self.transitions {
+            net.add_transition_struct(PetriNetTransition::new(
+                arcs(&transition.pre),
+                arcs(&transition.post),
+            ));
+        }
+        let marking = |marking: &[i64]| {
+            VASSCounterValuation::from(self.alive_places().map(|p| marking[p]).collect::<Vec<_>>())
+        };
+        let net = net.init(marking(&self.initial), marking(&self.target));
+
+        self.statistics.places = net.net.place_count;
+        self.statistics.transitions = net.net.transitions.len();
+        PetriNetReduction {
+            net,
+            transitions: self.transitions.into_iter().map(|t| t.origin).collect(),
+            original: self.original,
+            statistics: self.statistics,
+        }
+    }
+}
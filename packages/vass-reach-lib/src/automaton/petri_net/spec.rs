@@ -23,20 +23,25 @@
 ///
 /// We also don't support invariants on places (only guards on transitions).
 ///
-/// For init and target, we only support equality constraints (only
-/// reachability, not coverability). Unnamed places are assumed to have value 0
-/// in init and target.
+/// For init and target, every atom constrains a single place with `=`, `>=` or
+/// `<=`, so coverability targets like `p3>=2` can be stated directly. Unnamed
+/// places are assumed to have value 0 in init and target.
 use nom::{Parser, bytes::complete::tag, character::complete::space1, error::ParseError};
 
 use crate::automaton::{
     petri_net::{initialized::InitializedPetriNet, transition::PetriNetTransition},
-    vass::counter::VASSCounterValuation,
+    vass::{
+        counter::VASSCounterValuation,
+        valuation_spec::{CounterConstraint, ValuationSpec},
+    },
 };
 
-fn integer<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, i32, E> {
-    let (input, num_str) = nom::character::complete::digit1(input)?;
-    let num = num_str.parse::<i32>().unwrap();
-    Ok((input, num))
+fn integer<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, i64, E> {
+    let (rest, num_str) = nom::character::complete::digit1(input)?;
+    let num = num_str.parse::<i64>().map_err(|_| {
+        nom::Err::Failure(E::from_error_kind(input, nom::error::ErrorKind::TooLarge))
+    })?;
+    Ok((rest, num))
 }
 
 fn opt_whitespace<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, &'a str, E> {
@@ -74,7 +79,7 @@ fn set_of_vars<'a, E: ParseError<&'a str>>(
 #[derive(Debug, Clone)]
 pub struct GuardAtom<'a> {
     pub var: &'a str,
-    pub value: i32,
+    pub value: i64,
 }
 
 fn guard_atom<'a, E: ParseError<&'a str>>(
@@ -155,7 +160,7 @@ fn test_guard_1() {
 pub struct Update<'a> {
     pub target: &'a str,
     pub source: &'a str,
-    pub change: i32,
+    pub change: i64,
 }
 
 fn update<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, Update<'a>, E> {
@@ -290,22 +295,88 @@ fn test_transition_1() {
     assert_eq!(transition.updates.len(), 2);
 }
 
-fn eq_guard_atom<'a, E: ParseError<&'a str>>(
+#[derive(Debug, Clone)]
+pub struct ValuationAtom<'a> {
+    pub var: &'a str,
+    pub constraint: CounterConstraint,
+}
+
+fn valuation_atom<'a, E: ParseError<&'a str>>(
     input: &'a str,
-) -> nom::IResult<&'a str, GuardAtom<'a>, E> {
+) -> nom::IResult<&'a str, ValuationAtom<'a>, E> {
     let (input, var) = variable(input)?;
     let (input, _) = opt_whitespace(input)?;
-    let (input, _) = tag("=")(input)?;
+    let (input, op) = nom::branch::alt((tag(">="), tag("<="), tag("="))).parse(input)?;
     let (input, _) = opt_whitespace(input)?;
     let (input, value) = integer(input)?;
 
-    Ok((input, GuardAtom { var, value }))
+    let constraint = match op {
+        ">=" => CounterConstraint::AtLeast(value),
+        "<=" => CounterConstraint::AtMost(value),
+        _ => CounterConstraint::Exactly(value),
+    };
+
+    Ok((input, ValuationAtom { var, constraint }))
 }
 
-fn eq_guard<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, Guard<'a>, E> {
-    let (input, atoms) = nom::multi::separated_list1(separator, eq_guard_atom).parse(input)?;
+#[derive(Debug, Clone)]
+pub struct ValuationGuard<'a> {
+    pub atoms: Vec<ValuationAtom<'a>>,
+}
 
-    Ok((input, Guard { atoms }))
+impl<'a> ValuationGuard<'a> {
+    pub fn to_valuation_spec(&self, variables: &[&'a str]) -> anyhow::Result<ValuationSpec> {
+        let mut constraints: Vec<Option<CounterConstraint>> = vec![None; variables.len()];
+
+        for atom in &self.atoms {
+            let Some(pos) = variables.iter().position(|&v| v == atom.var) else {
+                anyhow::bail!(
+                    "Variable '{}' in guard not found in variable list.",
+                    atom.var
+                );
+            };
+
+            if constraints[pos].replace(atom.constraint).is_some() {
+                anyhow::bail!(
+                    "Variable '{}' is constrained more than once. Only one constraint per variable is supported.",
+                    atom.var
+                );
+            }
+        }
+
+        let spec = ValuationSpec::new(
+            constraints
+                .into_iter()
+                .map(|constraint| constraint.unwrap_or(CounterConstraint::Exactly(0)))
+                .collect(),
+        );
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+fn valuation_guard<'a, E: ParseError<&'a str>>(
+    input: &'a str,
+) -> nom::IResult<&'a str, ValuationGuard<'a>, E> {
+    let (input, atoms) = nom::multi::separated_list1(separator, valuation_atom).parse(input)?;
+
+    Ok((input, ValuationGuard { atoms }))
+}
+
+#[test]
+fn test_valuation_guard_1() {
+    let input = "p1=2, p2 >= 1, p3<=4";
+    let (_, guard) = valuation_guard::<nom::error::Error<&str>>(input).unwrap();
+    let spec = guard.to_valuation_spec(&["p1", "p2", "p3", "p4"]).unwrap();
+    assert_eq!(
+        spec.constraints(),
+        &[
+            CounterConstraint::Exactly(2),
+            CounterConstraint::AtLeast(1),
+            CounterConstraint::AtMost(4),
+            CounterConstraint::Exactly(0),
+        ]
+    );
 }
 
 fn vars<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, Vec<&'a str>, E> {
@@ -366,12 +437,14 @@ fn test_rules_2() {
     assert_eq!(rules.len(), 2);
 }
 
-fn init<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, Guard<'a>, E> {
+fn init<'a, E: ParseError<&'a str>>(
+    input: &'a str,
+) -> nom::IResult<&'a str, ValuationGuard<'a>, E> {
     let (input, _) = opt_whitespace(input)?;
     let (input, _) = tag("init")(input)?;
     let (input, _) = whitespace(input)?;
 
-    eq_guard(input)
+    valuation_guard(input)
 }
 
 #[test]
@@ -383,19 +456,30 @@ fn test_init_1() {
     let (_, init_guard) = init::<nom::error::Error<&str>>(input).unwrap();
     assert_eq!(init_guard.atoms.len(), 3);
     assert_eq!(init_guard.atoms[0].var, "p1");
-    assert_eq!(init_guard.atoms[0].value, 2);
+    assert_eq!(
+        init_guard.atoms[0].constraint,
+        CounterConstraint::Exactly(2)
+    );
     assert_eq!(init_guard.atoms[1].var, "p2");
-    assert_eq!(init_guard.atoms[1].value, 0);
+    assert_eq!(
+        init_guard.atoms[1].constraint,
+        CounterConstraint::Exactly(0)
+    );
     assert_eq!(init_guard.atoms[2].var, "p3");
-    assert_eq!(init_guard.atoms[2].value, 0);
+    assert_eq!(
+        init_guard.atoms[2].constraint,
+        CounterConstraint::Exactly(0)
+    );
 }
 
-fn target<'a, E: ParseError<&'a str>>(input: &'a str) -> nom::IResult<&'a str, Guard<'a>, E> {
+fn target<'a, E: ParseError<&'a str>>(
+    input: &'a str,
+) -> nom::IResult<&'a str, ValuationGuard<'a>, E> {
     let (input, _) = opt_whitespace(input)?;
     let (input, _) = tag("target")(input)?;
     let (input, _) = whitespace(input)?;
 
-    eq_guard(input)
+    valuation_guard(input)
 }
 
 #[test]
@@ -407,19 +491,28 @@ fn test_target_1() {
     let (_, target_guard) = target::<nom::error::Error<&str>>(input).unwrap();
     assert_eq!(target_guard.atoms.len(), 3);
     assert_eq!(target_guard.atoms[0].var, "p1");
-    assert_eq!(target_guard.atoms[0].value, 2);
+    assert_eq!(
+        target_guard.atoms[0].constraint,
+        CounterConstraint::Exactly(2)
+    );
     assert_eq!(target_guard.atoms[1].var, "p2");
-    assert_eq!(target_guard.atoms[1].value, 0);
+    assert_eq!(
+        target_guard.atoms[1].constraint,
+        CounterConstraint::Exactly(0)
+    );
     assert_eq!(target_guard.atoms[2].var, "p3");
-    assert_eq!(target_guard.atoms[2].value, 0);
+    assert_eq!(
+        target_guard.atoms[2].constraint,
+        CounterConstraint::Exactly(0)
+    );
 }
 
 #[derive(Debug, Clone)]
 pub struct PetriNetSpec<'a> {
     pub variables: Vec<&'a str>,
     pub rules: Vec<TransitionSpec<'a>>,
-    pub initial: Guard<'a>,
-    pub target: Guard<'a>,
+    pub initial: ValuationGuard<'a>,
+    pub target: ValuationGuard<'a>,
 }
 
 impl<'a> PetriNetSpec<'a> {
@@ -510,7 +603,7 @@ impl ToSpecFormat for InitializedPetriNet {
             for i in 1..=self.net.place_count {
                 let (input, output) = transition.get_update_for_place(i);
 
-                let change = output as i32 - input as i32;
+                let change = output as i64 - input as i64;
                 if change != 0 {
                     let sign = if change > 0 { "+" } else { "-" };
                     updates.push(format!("p{}' = p{}{}{}", i, i, sign, change.abs()));
@@ -525,24 +618,38 @@ impl ToSpecFormat for InitializedPetriNet {
 
         // init
         spec.push_str("init\n    ");
-        let mut init_atoms = vec![];
-        let init_valuation = &self.initial_marking;
-        for i in 0..self.net.place_count {
-            init_atoms.push(format!("p{}={}", i + 1, init_valuation[i]));
-        }
-        spec.push_str(&init_atoms.join(", "));
+        spec.push_str(&valuation_atoms(
+            &self.initial_marking,
+            self.initial_spec.as_ref(),
+        ));
         spec.push('\n');
 
         // target
         spec.push_str("target\n    ");
-        let mut target_atoms = vec![];
-        let target_valuation = &self.final_marking;
-        for i in 0..self.net.place_count {
-            target_atoms.push(format!("p{}={}", i + 1, target_valuation[i]));
-        }
-        spec.push_str(&target_atoms.join(", "));
+        spec.push_str(&valuation_atoms(
+            &self.final_marking,
+            self.target_spec.as_ref(),
+        ));
         spec.push('\n');
 
         spec
     }
 }
+
+fn valuation_atoms(valuation: &VASSCounterValuation, spec: Option<&ValuationSpec>) -> String {
+    match spec {
+        Some(spec) => spec
+            .constraints()
+            .iter()
+            .enumerate()
+            .map(|(i, constraint)| format!("p{}{}", i + 1, constraint))
+            .collect::<Vec<String>>()
+            .join(", "),
+        None => valuation
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("p{}={}", i + 1, value))
+            .collect::<Vec<String>>()
+            .join(", "),
+    }
+}
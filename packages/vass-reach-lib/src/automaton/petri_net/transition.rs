@@ -19,15 +19,15 @@ impl PetriNetTransition {
     /// Note that the input update must all be negative or zero, and the output
     /// update must all be positive or zero.
     pub fn from_vass_updates<'a>(
-        input: impl IntoIterator<Item = &'a i32>,
-        output: impl IntoIterator<Item = &'a i32>,
+        input: impl IntoIterator<Item = &'a i64>,
+        output: impl IntoIterator<Item = &'a i64>,
     ) -> Self {
         let mut input_vec = vec![];
         let mut output_vec = vec![];
 
         for (i, &val) in input.into_iter().enumerate() {
             if val < 0 {
-                input_vec.push(((-val) as usize, i + 1));
+                input_vec.push((val.unsigned_abs() as usize, i + 1));
             } else if val > 0 {
                 panic!("input update had a positive component");
             }
@@ -35,7 +35,7 @@ impl PetriNetTransition {
 
         for (i, &val) in output.into_iter().enumerate() {
             if val > 0 {
-                output_vec.push(((val) as usize, i + 1));
+                output_vec.push((val as usize, i + 1));
             } else if val < 0 {
                 panic!("input update had a negative component");
             }
@@ -47,15 +47,15 @@ impl PetriNetTransition {
         }
     }
 
-    pub fn from_vass_update<'a>(update: impl IntoIterator<Item = &'a i32>) -> Self {
+    pub fn from_vass_update<'a>(update: impl IntoIterator<Item = &'a i64>) -> Self {
         let mut input_vec = vec![];
         let mut output_vec = vec![];
 
         for (i, &val) in update.into_iter().enumerate() {
             if val < 0 {
-                input_vec.push(((-val) as usize, i + 1));
+                input_vec.push((val.unsigned_abs() as usize, i + 1));
             } else if val > 0 {
-                output_vec.push(((val) as usize, i + 1));
+                output_vec.push((val as usize, i + 1));
             }
         }
 
@@ -69,7 +69,7 @@ impl PetriNetTransition {
         let mut vec = vec![0; place_count].into_boxed_slice();
 
         for (weight, place) in &self.input {
-            vec[*place - 1] = -(*weight as i32);
+            vec[*place - 1] = -i64::try_from(*weight).expect("transition weight fits in i64");
         }
 
         vec.into()
@@ -79,7 +79,7 @@ impl PetriNetTransition {
         let mut vec = vec![0; place_count].into_boxed_slice();
 
         for (weight, place) in &self.output {
-            vec[*place - 1] = *weight as i32;
+            vec[*place - 1] = i64::try_from(*weight).expect("transition weight fits in i64");
         }
 
         vec.into()
@@ -1,6 +1,6 @@
 use crate::automaton::{
     cfg::update::{CFGCounterUpdatable, CFGCounterUpdate},
-    vass::counter::{VASSCounterUpdate, VASSCounterValuation},
+    vass::counter::{VASSCounterIndex, VASSCounterUpdate, VASSCounterValuation},
 };
 
 /// Converts a sequence of CFG counter updates to a pair of valuations.
@@ -61,3 +61,45 @@ pub fn vass_update_to_cfg_updates(update: &VASSCounterUpdate) -> Vec<CFGCounterU
 
     vec
 }
+
+/// Like [`vass_update_to_cfg_updates`], but with one weighted update per set
+/// bit of every component, see [`CFGCounterUpdate::weighted`].
+///
+/// The only magnitude with bit 63 set is that of `i64::MIN`, which exceeds
+/// [`CFGCounterUpdate::MAX_EXPONENT`] and is emitted as two updates of
+/// `-2^62` instead.
+pub fn vass_update_to_binary_cfg_updates(update: &VASSCounterUpdate) -> Vec<CFGCounterUpdate> {
+    let mut vec = vec![];
+
+    for (i, m) in update.iter().enumerate() {
+        let counter = VASSCounterIndex::new(i as u32);
+
+        if *m == i64::MIN {
+            let update = CFGCounterUpdate::weighted(counter, false, CFGCounterUpdate::MAX_EXPONENT);
+            vec.extend([update, update]);
+            continue;
+        }
+
+        let magnitude = m.unsigned_abs();
+
+        for exponent in 0..=CFGCounterUpdate::MAX_EXPONENT {
+            if magnitude & (1 << exponent) != 0 {
+                vec.push(CFGCounterUpdate::weighted(counter, *m > 0, exponent));
+            }
+        }
+    }
+
+    vec
+}
+
+/// The largest exponent [`vass_update_to_binary_cfg_updates`] uses for
+/// `update`.
+pub fn max_binary_exponent(update: &VASSCounterUpdate) -> u8 {
+    update
+        .iter()
+        .map(|m| m.unsigned_abs())
+        .filter(|magnitude| *magnitude > 0)
+        .map(|magnitude| (magnitude.ilog2() as u8).min(CFGCounterUpdate::MAX_EXPONENT))
+        .max()
+        .unwrap_or(0)
+}
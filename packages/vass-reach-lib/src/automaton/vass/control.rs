@@ -0,0 +1,304 @@
+//! Regular constraints on the transitions a run may use.
+//!
+//! A constraint is either a regular expression or an NFA over the letters of
+//! the VASS transitions, which are written `t3` or `3`. Regular expressions
+//! support alternation `|`, concatenation by juxtaposition, the postfix
+//! operators `*`, `+` and `?`, grouping with parentheses, the wildcard `.` and
+//! letter classes `[t1 t2]` and `[^t1 t2]`. For example "t3 at most once" is
+//! `[^t3]* (t3 [^t3]*)?` and "never t5 after t7" is `[^t7]* (t7 [^t5]*)?`.
+use std::str::FromStr;
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{digit1, multispace0, one_of},
+    combinator::{all_consuming, map, map_res, opt},
+    multi::{many0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated},
+};
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::automaton::{
+    AutomatonNode, ModifiableAutomaton,
+    dfa::{DFA, node::DfaNode},
+    nfa::{NFA, NFAEdge},
+    vass::initialized::InitializedVASS,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Regex {
+    Letters(Vec<usize>),
+    AnyExcept(Vec<usize>),
+    Concat(Vec<Regex>),
+    Alternation(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+impl Regex {
+    /// Thompson's construction, returns the start and end node of the
+    /// sub-automaton.
+    fn build(&self, nfa: &mut NFA<(), usize>, alphabet: &[usize]) -> (NodeIndex, NodeIndex) {
+        let start = nfa.add_node(DfaNode::non_accepting(()));
+        let end = nfa.add_node(DfaNode::non_accepting(()));
+
+        match self {
+            Regex::Letters(letters) => {
+                for letter in alphabet.iter().filter(|letter| letters.contains(letter)) {
+                    nfa.add_edge(&start, &end, NFAEdge::Symbol(*letter));
+                }
+            }
+            Regex::AnyExcept(letters) => {
+                for letter in alphabet.iter().filter(|letter| !letters.contains(letter)) {
+                    nfa.add_edge(&start, &end, NFAEdge::Symbol(*letter));
+                }
+            }
+            Regex::Concat(parts) => {
+                let mut current = start;
+                for part in parts {
+                    let (part_start, part_end) = part.build(nfa, alphabet);
+                    nfa.add_edge(&current, &part_start, NFAEdge::Epsilon);
+                    current = part_end;
+                }
+                nfa.add_edge(&current, &end, NFAEdge::Epsilon);
+            }
+            Regex::Alternation(branches) => {
+                for branch in branches {
+                    let (branch_start, branch_end) = branch.build(nfa, alphabet);
+                    nfa.add_edge(&start, &branch_start, NFAEdge::Epsilon);
+                    nfa.add_edge(&branch_end, &end, NFAEdge::Epsilon);
+                }
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let (inner_start, inner_end) = inner.build(nfa, alphabet);
+                nfa.add_edge(&start, &inner_start, NFAEdge::Epsilon);
+                nfa.add_edge(&inner_end, &end, NFAEdge::Epsilon);
+                if !matches!(self, Regex::Plus(_)) {
+                    nfa.add_edge(&start, &end, NFAEdge::Epsilon);
+                }
+                if !matches!(self, Regex::Optional(_)) {
+                    nfa.add_edge(&inner_end, &inner_start, NFAEdge::Epsilon);
+                }
+            }
+        }
+
+        (start, end)
+    }
+}
+
+fn letter(input: &str) -> IResult<&str, usize> {
+    preceded(
+        multispace0,
+        map_res(preceded(opt(tag("t")), digit1), str::parse::<usize>),
+    )
+    .parse(input)
+}
+
+fn class(input: &str) -> IResult<&str, Regex> {
+    map(
+        delimited(
+            preceded(multispace0, tag("[")),
+            pair(opt(tag("^")), many0(letter)),
+            preceded(multispace0, tag("]")),
+        ),
+        |(negated, letters)| match negated {
+            Some(_) => Regex::AnyExcept(letters),
+            None => Regex::Letters(letters),
+        },
+    )
+    .parse(input)
+}
+
+fn atom(input: &str) -> IResult<&str, Regex> {
+    alt((
+        map(letter, |letter| Regex::Letters(vec![letter])),
+        map(preceded(multispace0, tag(".")), |_| {
+            Regex::AnyExcept(vec![])
+        }),
+        class,
+        delimited(
+            preceded(multispace0, tag("(")),
+            alternation,
+            preceded(multispace0, tag(")")),
+        ),
+    ))
+    .parse(input)
+}
+
+fn repetition(input: &str) -> IResult<&str, Regex> {
+    let (input, atom) = atom(input)?;
+    let (input, operators) = many0(preceded(multispace0, one_of("*+?"))).parse(input)?;
+
+    let regex = operators
+        .into_iter()
+        .fold(atom, |regex, operator| match operator {
+            '*' => Regex::Star(Box::new(regex)),
+            '+' => Regex::Plus(Box::new(regex)),
+            _ => Regex::Optional(Box::new(regex)),
+        });
+
+    Ok((input, regex))
+}
+
+fn alternation(input: &str) -> IResult<&str, Regex> {
+    map(
+        separated_list1(
+            preceded(multispace0, tag("|")),
+            map(many0(repetition), Regex::Concat),
+        ),
+        Regex::Alternation,
+    )
+    .parse(input)
+}
+
+/// A regular expression over transition letters, see the module
+/// documentation for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ControlRegex {
+    source: String,
+    regex: Regex,
+}
+
+impl ControlRegex {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for ControlRegex {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match all_consuming(terminated(alternation, multispace0)).parse(source) {
+            Ok((_, regex)) => Ok(ControlRegex {
+                source: source.to_string(),
+                regex,
+            }),
+            Err(e) => Err(anyhow::anyhow!(
+                "Failed to parse control regex '{}': {}",
+                source,
+                e
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for ControlRegex {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+impl From<ControlRegex> for String {
+    fn from(regex: ControlRegex) -> Self {
+        regex.source
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlNFATransition {
+    pub source: usize,
+    pub target: usize,
+    /// `None` is an epsilon transition.
+    pub letter: Option<usize>,
+}
+
+/// An NFA over transition letters. Its states are the indices used by
+/// `initial`, `accepting` and `transitions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlNFA {
+    pub initial: usize,
+    pub accepting: Vec<usize>,
+    pub transitions: Vec<ControlNFATransition>,
+}
+
+impl ControlNFA {
+    fn to_nfa(&self, alphabet: &[usize]) -> NFA<(), usize> {
+        let state_count = self
+            .transitions
+            .iter()
+            .flat_map(|transition| [transition.source, transition.target])
+            .chain(self.accepting.iter().copied())
+            .chain([self.initial])
+            .max()
+            .unwrap_or_default()
+            + 1;
+
+        let mut nfa = NFA::new(alphabet.to_vec());
+        let nodes = (0..state_count)
+            .map(|state| nfa.add_node(DfaNode::new(self.accepting.contains(&state), false, ())))
+            .collect::<Vec<_>>();
+        nfa.set_initial(nodes[self.initial]);
+
+        for transition in &self.transitions {
+            let label = match transition.letter {
+                Some(letter) if !alphabet.contains(&letter) => continue,
+                Some(letter) => NFAEdge::Symbol(letter),
+                None => NFAEdge::Epsilon,
+            };
+            nfa.add_edge(&nodes[transition.source], &nodes[transition.target], label);
+        }
+
+        nfa
+    }
+}
+
+/// Restricts the sequences of transition letters a run may use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlConstraint {
+    Regex(ControlRegex),
+    Nfa(ControlNFA),
+}
+
+impl ControlConstraint {
+    /// Compiles the constraint into a DFA over `alphabet`. Letters outside
+    /// `alphabet` can not be taken.
+    pub fn to_dfa(&self, alphabet: &[usize]) -> DFA<(), usize> {
+        let nfa = match self {
+            ControlConstraint::Regex(regex) => {
+                let mut nfa = NFA::new(alphabet.to_vec());
+                let (start, end) = regex.regex.build(&mut nfa, alphabet);
+                nfa.set_initial(start);
+                nfa.set_accepting(end);
+                nfa
+            }
+            ControlConstraint::Nfa(nfa) => nfa.to_nfa(alphabet),
+        };
+
+        nfa.determinize()
+    }
+}
+
+/// Compiles the intersection of all constraints into a single DFA over
+/// `alphabet`, or `None` if there are no constraints.
+pub fn control_dfa(
+    constraints: &[ControlConstraint],
+    alphabet: &[usize],
+) -> Option<DFA<(), usize>> {
+    constraints
+        .iter()
+        .map(|constraint| {
+            let mut dfa = constraint.to_dfa(alphabet);
+            dfa.make_complete(());
+            dfa
+        })
+        .reduce(|left, right| left.intersect(&right))
+}
+
+impl<N: AutomatonNode> InitializedVASS<N, usize> {
+    /// Restricts runs to those allowed by every constraint, see
+    /// [`InitializedVASS::with_control`].
+    pub fn with_control_constraints(&self, constraints: &[ControlConstraint]) -> Self {
+        match control_dfa(constraints, &self.vass.alphabet) {
+            Some(control) => self.with_control(&control),
+            None => self.clone(),
+        }
+    }
+}
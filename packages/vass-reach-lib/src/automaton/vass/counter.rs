@@ -2,6 +2,33 @@ use std::ops::{Index, IndexMut};
 
 use serde::{Deserialize, Serialize};
 
+/// A counter left the range of `i64`. Counter arithmetic is checked and
+/// reports this instead of wrapping around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterOverflowError {
+    pub counter: VASSCounterIndex,
+}
+
+impl std::fmt::Display for CounterOverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "counter {} overflowed the range of i64", self.counter)
+    }
+}
+
+impl std::error::Error for CounterOverflowError {}
+
+/// `value + delta` for `counter`, or an error if it leaves the range of
+/// `i64`.
+pub fn checked_counter_add(
+    counter: VASSCounterIndex,
+    value: i64,
+    delta: i64,
+) -> Result<i64, CounterOverflowError> {
+    value
+        .checked_add(delta)
+        .ok_or(CounterOverflowError { counter })
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct VASSCounterIndex {
     index: u32,
@@ -42,11 +69,11 @@ impl From<VASSCounterIndex> for u32 {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct VASSCounterValuation {
-    values: Box<[i32]>,
+    values: Box<[i64]>,
 }
 
 impl VASSCounterValuation {
-    pub fn new(values: Box<[i32]>) -> Self {
+    pub fn new(values: Box<[i64]>) -> Self {
         VASSCounterValuation { values }
     }
 
@@ -56,7 +83,7 @@ impl VASSCounterValuation {
         }
     }
 
-    pub fn extend(&self, other: impl IntoIterator<Item = i32>) -> Self {
+    pub fn extend(&self, other: impl IntoIterator<Item = i64>) -> Self {
         VASSCounterValuation::new(self.values.iter().cloned().chain(other).collect())
     }
 
@@ -71,40 +98,59 @@ impl VASSCounterValuation {
             "Valuation and update must have the same dimension"
         );
         for i in 0..self.dimension() {
-            if self.values[i] + update.values[i] < 0 {
+            // saturating keeps the sign, which is all that matters here
+            if self.values[i].saturating_add(update.values[i]) < 0 {
                 return false;
             }
         }
         true
     }
 
+    /// Panics if a counter overflows, see
+    /// [`try_apply_update`](Self::try_apply_update).
     pub fn apply_update(&mut self, update: &VASSCounterUpdate) {
+        self.try_apply_update(update)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Applies `update`, or leaves the valuation unchanged if a counter would
+    /// overflow.
+    pub fn try_apply_update(
+        &mut self,
+        update: &VASSCounterUpdate,
+    ) -> Result<(), CounterOverflowError> {
         debug_assert_eq!(
             self.dimension(),
             update.dimension(),
             "Valuation and update must have the same dimension"
         );
-        for i in 0..self.dimension() {
-            self.values[i] += update.values[i];
-        }
+        let values = VASSCounterIndex::iter_counters(self.dimension())
+            .map(|counter| checked_counter_add(counter, self[counter], update[counter]))
+            .collect::<Result<Box<[i64]>, _>>()?;
+        self.values = values;
+        Ok(())
     }
 
+    /// Panics if a counter overflows.
     pub fn apply_update_rev(&mut self, update: &VASSCounterUpdate) {
         debug_assert_eq!(
             self.dimension(),
             update.dimension(),
             "Valuation and update must have the same dimension"
         );
-        for i in 0..self.dimension() {
-            self.values[i] -= update.values[i];
+        for counter in VASSCounterIndex::iter_counters(self.dimension()) {
+            self[counter] = match self[counter].checked_sub(update[counter]) {
+                Some(value) => value,
+                None => panic!("{}", CounterOverflowError { counter }),
+            };
         }
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, i32> {
+    pub fn iter(&self) -> std::slice::Iter<'_, i64> {
         self.values.iter()
     }
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, i32> {
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, i64> {
         self.values.iter_mut()
     }
 
@@ -112,19 +158,19 @@ impl VASSCounterValuation {
         VASSCounterUpdate::new(self.values)
     }
 
-    pub fn mod_euclid(&self, modulus: i32) -> Self {
+    pub fn mod_euclid(&self, modulus: i64) -> Self {
         Self {
             values: self.values.iter().map(|x| x.rem_euclid(modulus)).collect(),
         }
     }
 
-    pub fn mod_euclid_mut(&mut self, modulus: i32) {
+    pub fn mod_euclid_mut(&mut self, modulus: i64) {
         for x in self.values.iter_mut() {
             *x = x.rem_euclid(modulus);
         }
     }
 
-    pub fn mod_euclid_slice(&self, modulus: &[i32]) -> Self {
+    pub fn mod_euclid_slice(&self, modulus: &[i64]) -> Self {
         assert_eq!(self.dimension(), modulus.len());
         Self {
             values: self
@@ -136,7 +182,7 @@ impl VASSCounterValuation {
         }
     }
 
-    pub fn mod_euclid_slice_mut(&mut self, modulus: &[i32]) {
+    pub fn mod_euclid_slice_mut(&mut self, modulus: &[i64]) {
         assert_eq!(self.dimension(), modulus.len());
         for (x, m) in self.values.iter_mut().zip(modulus.iter()) {
             *x = x.rem_euclid(*m);
@@ -162,7 +208,7 @@ impl VASSCounterValuation {
         );
         self.iter_mut()
             .zip(other.iter())
-            .for_each(|(a, b)| *a = i32::max(*a, *b))
+            .for_each(|(a, b)| *a = i64::max(*a, *b))
     }
 
     pub fn element_min(&mut self, other: &VASSCounterValuation) {
@@ -173,12 +219,12 @@ impl VASSCounterValuation {
         );
         self.iter_mut()
             .zip(other.iter())
-            .for_each(|(a, b)| *a = i32::min(*a, *b))
+            .for_each(|(a, b)| *a = i64::min(*a, *b))
     }
 
     /// Finds the first counter value mismatch between two counter valuations.
     /// Returns the counter index and the difference.
-    pub fn find_mismatch(&self, other: &VASSCounterValuation) -> Option<(VASSCounterIndex, i32)> {
+    pub fn find_mismatch(&self, other: &VASSCounterValuation) -> Option<(VASSCounterIndex, i64)> {
         debug_assert_eq!(
             self.dimension(),
             other.dimension(),
@@ -189,7 +235,7 @@ impl VASSCounterValuation {
             .zip(other.iter())
             .enumerate()
             .find(|(_, (a, b))| a != b)
-            .map(|(x, (a, b))| (VASSCounterIndex::new(x as u32), b - a))
+            .map(|(x, (a, b))| (VASSCounterIndex::new(x as u32), b.saturating_sub(*a)))
     }
 }
 
@@ -218,39 +264,39 @@ impl PartialOrd for VASSCounterValuation {
     }
 }
 
-impl From<Box<[i32]>> for VASSCounterValuation {
-    fn from(values: Box<[i32]>) -> Self {
+impl From<Box<[i64]>> for VASSCounterValuation {
+    fn from(values: Box<[i64]>) -> Self {
         VASSCounterValuation::new(values)
     }
 }
 
-impl From<&[i32]> for VASSCounterValuation {
-    fn from(values: &[i32]) -> Self {
+impl From<&[i64]> for VASSCounterValuation {
+    fn from(values: &[i64]) -> Self {
         VASSCounterValuation::new(values.to_vec().into_boxed_slice())
     }
 }
 
-impl From<Vec<i32>> for VASSCounterValuation {
-    fn from(values: Vec<i32>) -> Self {
+impl From<Vec<i64>> for VASSCounterValuation {
+    fn from(values: Vec<i64>) -> Self {
         VASSCounterValuation::new(values.into_boxed_slice())
     }
 }
 
-impl From<VASSCounterValuation> for Box<[i32]> {
+impl From<VASSCounterValuation> for Box<[i64]> {
     fn from(valuation: VASSCounterValuation) -> Self {
         valuation.values
     }
 }
 
-impl FromIterator<i32> for VASSCounterValuation {
-    fn from_iter<T: IntoIterator<Item = i32>>(iter: T) -> Self {
-        let values: Vec<i32> = iter.into_iter().collect();
+impl FromIterator<i64> for VASSCounterValuation {
+    fn from_iter<T: IntoIterator<Item = i64>>(iter: T) -> Self {
+        let values: Vec<i64> = iter.into_iter().collect();
         VASSCounterValuation::new(values.into_boxed_slice())
     }
 }
 
 impl Index<VASSCounterIndex> for VASSCounterValuation {
-    type Output = i32;
+    type Output = i64;
 
     fn index(&self, index: VASSCounterIndex) -> &Self::Output {
         &self.values[index.index as usize]
@@ -264,7 +310,7 @@ impl IndexMut<VASSCounterIndex> for VASSCounterValuation {
 }
 
 impl Index<usize> for VASSCounterValuation {
-    type Output = i32;
+    type Output = i64;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.values[index]
@@ -278,7 +324,7 @@ impl IndexMut<usize> for VASSCounterValuation {
 }
 
 impl IntoIterator for VASSCounterValuation {
-    type Item = i32;
+    type Item = i64;
 
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
@@ -288,9 +334,9 @@ impl IntoIterator for VASSCounterValuation {
 }
 
 impl<'a> IntoIterator for &'a VASSCounterUpdate {
-    type Item = &'a i32;
+    type Item = &'a i64;
 
-    type IntoIter = std::slice::Iter<'a, i32>;
+    type IntoIter = std::slice::Iter<'a, i64>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.values.iter()
@@ -300,11 +346,11 @@ impl<'a> IntoIterator for &'a VASSCounterUpdate {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct VASSCounterUpdate {
-    values: Box<[i32]>,
+    values: Box<[i64]>,
 }
 
 impl VASSCounterUpdate {
-    pub fn new(values: Box<[i32]>) -> Self {
+    pub fn new(values: Box<[i64]>) -> Self {
         VASSCounterUpdate { values }
     }
 
@@ -314,7 +360,7 @@ impl VASSCounterUpdate {
         }
     }
 
-    pub fn extend(&self, other: impl IntoIterator<Item = i32>) -> Self {
+    pub fn extend(&self, other: impl IntoIterator<Item = i64>) -> Self {
         VASSCounterUpdate::new(self.values.iter().cloned().chain(other).collect())
     }
 
@@ -322,11 +368,11 @@ impl VASSCounterUpdate {
         self.values.len()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, i32> {
+    pub fn iter(&self) -> std::slice::Iter<'_, i64> {
         self.values.iter()
     }
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, i32> {
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, i64> {
         self.values.iter_mut()
     }
 
@@ -335,39 +381,39 @@ impl VASSCounterUpdate {
     }
 }
 
-impl From<Box<[i32]>> for VASSCounterUpdate {
-    fn from(values: Box<[i32]>) -> Self {
+impl From<Box<[i64]>> for VASSCounterUpdate {
+    fn from(values: Box<[i64]>) -> Self {
         VASSCounterUpdate::new(values)
     }
 }
 
-impl From<&[i32]> for VASSCounterUpdate {
-    fn from(values: &[i32]) -> Self {
+impl From<&[i64]> for VASSCounterUpdate {
+    fn from(values: &[i64]) -> Self {
         VASSCounterUpdate::new(values.to_vec().into_boxed_slice())
     }
 }
 
-impl From<Vec<i32>> for VASSCounterUpdate {
-    fn from(values: Vec<i32>) -> Self {
+impl From<Vec<i64>> for VASSCounterUpdate {
+    fn from(values: Vec<i64>) -> Self {
         VASSCounterUpdate::new(values.into_boxed_slice())
     }
 }
 
-impl From<VASSCounterUpdate> for Box<[i32]> {
+impl From<VASSCounterUpdate> for Box<[i64]> {
     fn from(valuation: VASSCounterUpdate) -> Self {
         valuation.values
     }
 }
 
-impl FromIterator<i32> for VASSCounterUpdate {
-    fn from_iter<T: IntoIterator<Item = i32>>(iter: T) -> Self {
-        let values: Vec<i32> = iter.into_iter().collect();
+impl FromIterator<i64> for VASSCounterUpdate {
+    fn from_iter<T: IntoIterator<Item = i64>>(iter: T) -> Self {
+        let values: Vec<i64> = iter.into_iter().collect();
         VASSCounterUpdate::new(values.into_boxed_slice())
     }
 }
 
 impl Index<VASSCounterIndex> for VASSCounterUpdate {
-    type Output = i32;
+    type Output = i64;
 
     fn index(&self, index: VASSCounterIndex) -> &Self::Output {
         &self.values[index.index as usize]
@@ -381,7 +427,7 @@ impl IndexMut<VASSCounterIndex> for VASSCounterUpdate {
 }
 
 impl Index<usize> for VASSCounterUpdate {
-    type Output = i32;
+    type Output = i64;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.values[index]
@@ -395,8 +441,8 @@ impl IndexMut<usize> for VASSCounterUpdate {
 }
 
 impl IntoIterator for VASSCounterUpdate {
-    type Item = i32;
-    type IntoIter = std::vec::IntoIter<i32>;
+    type Item = i64;
+    type IntoIter = std::vec::IntoIter<i64>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.values.into_iter()
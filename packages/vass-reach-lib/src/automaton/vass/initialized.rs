@@ -1,3 +1,4 @@
+use hashbrown::HashMap;
 use itertools::Itertools;
 use petgraph::{Direction, graph::NodeIndex, prelude::EdgeRef};
 use serde::{Deserialize, Serialize};
@@ -6,15 +7,19 @@ use crate::automaton::{
     Alphabet, Automaton, AutomatonEdge, AutomatonIterators, AutomatonNode, Deterministic,
     ExplicitEdgeAutomaton, FromLetter, Frozen, InitializedAutomaton, Language, ModifiableAutomaton,
     SingleFinalStateAutomaton,
-    cfg::{update::CFGCounterUpdate, vasscfg::VASSCFG},
-    dfa::node::DfaNode,
-    index_map::IndexMap,
+    cfg::{
+        update::{CFGCounterUpdatable, CFGCounterUpdate},
+        vasscfg::VASSCFG,
+    },
+    dfa::{DFA, node::DfaNode},
     nfa::{NFA, NFAEdge},
     petri_net::{PetriNet, initialized::InitializedPetriNet, transition::PetriNetTransition},
     utils::{self},
     vass::{
         VASS, VASSEdge,
+        control::ControlConstraint,
         counter::{VASSCounterUpdate, VASSCounterValuation},
+        valuation_spec::{CounterConstraint, SpecRun, ValuationSpec, ValuationSpecStates},
     },
 };
 
@@ -25,6 +30,30 @@ pub struct InitializedVASS<N: AutomatonNode, E: AutomatonEdge + FromLetter> {
     pub final_valuation: VASSCounterValuation,
     pub initial_node: NodeIndex<u32>,
     pub final_node: NodeIndex<u32>,
+    /// Initial states besides `initial_node`, each with the valuation a run
+    /// starting there begins with.
+    pub additional_initial_states: Vec<(NodeIndex<u32>, VASSCounterValuation)>,
+    /// Final states besides `final_node`, each with the valuation a run ending
+    /// there has to reach.
+    pub additional_final_states: Vec<(NodeIndex<u32>, VASSCounterValuation)>,
+    /// Set by [`Self::with_valuation_specs`] if it added auxiliary states.
+    pub valuation_spec_states: Option<ValuationSpecStates>,
+}
+
+/// Reduces several initial and final states to a single initial and final
+/// state without modifying the VASS. States below the state count of the VASS
+/// are its own states, the others are fresh.
+///
+/// The fresh initial state starts with `initial_valuation` and has one edge
+/// to every initial state that moves to that state's valuation. Every final
+/// state has one edge to the fresh final state that moves from its valuation
+/// to `final_valuation`. Counters move in one direction per edge, so these
+/// edges can always be taken.
+struct SingleStateReduction {
+    state_count: usize,
+    initial: usize,
+    final_state: usize,
+    edges: Vec<(usize, usize, VASSCounterUpdate)>,
 }
 
 impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
@@ -32,30 +61,265 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
         self.initial_node = node;
     }
 
+    /// Lets runs also start in `node` with `valuation`.
+    pub fn add_initial_state(&mut self, node: NodeIndex<u32>, valuation: VASSCounterValuation) {
+        assert_eq!(
+            valuation.dimension(),
+            self.dimension(),
+            "Initial valuation has to have the same length as the dimension"
+        );
+        self.additional_initial_states.push((node, valuation));
+    }
+
+    /// Lets runs also end in `node` with `valuation`.
+    pub fn add_final_state(&mut self, node: NodeIndex<u32>, valuation: VASSCounterValuation) {
+        assert_eq!(
+            valuation.dimension(),
+            self.dimension(),
+            "Final valuation has to have the same length as the dimension"
+        );
+        self.additional_final_states.push((node, valuation));
+    }
+
+    /// All initial states with their valuations, starting with `initial_node`.
+    pub fn initial_states(&self) -> impl Iterator<Item = (NodeIndex<u32>, &VASSCounterValuation)> {
+        std::iter::once((self.initial_node, &self.initial_valuation)).chain(
+            self.additional_initial_states
+                .iter()
+                .map(|(node, valuation)| (*node, valuation)),
+        )
+    }
+
+    /// All final states with their valuations, starting with `final_node`.
+    pub fn final_states(&self) -> impl Iterator<Item = (NodeIndex<u32>, &VASSCounterValuation)> {
+        std::iter::once((self.final_node, &self.final_valuation)).chain(
+            self.additional_final_states
+                .iter()
+                .map(|(node, valuation)| (*node, valuation)),
+        )
+    }
+
+    pub fn has_single_initial_and_final_state(&self) -> bool {
+        self.additional_initial_states.is_empty() && self.additional_final_states.is_empty()
+    }
+
+    pub fn is_final_configuration(
+        &self,
+        node: NodeIndex<u32>,
+        valuation: &VASSCounterValuation,
+    ) -> bool {
+        self.final_states()
+            .any(|(final_node, final_valuation)| final_node == node && final_valuation == valuation)
+    }
+
+    /// Restricts runs to those whose sequence of edge letters is accepted by
+    /// `control`.
+    ///
+    /// The result is the product of the VASS with `control`, restricted to
+    /// the states reachable from an initial state. Every final state is
+    /// paired with every accepting state of `control`, counters and edge
+    /// letters are unchanged. The product has up to the number of states of
+    /// the VASS times the number of states of `control`, see
+    /// [`InitializedPetriNet::to_vass`] for why the control is not
+    /// intersected in the CFG product instead.
+    pub fn with_control<D: AutomatonNode>(&self, control: &DFA<D, E>) -> Self {
+        let control_initial = control.get_initial();
+        let mut vass = VASS::new(self.dimension(), self.vass.alphabet.clone());
+        let mut states = HashMap::new();
+        let mut discovered = vec![];
+
+        let mut product_state =
+            |vass: &mut VASS<N, E>,
+             discovered: &mut Vec<_>,
+             state: (NodeIndex<u32>, NodeIndex<u32>)| {
+                *states.entry(state).or_insert_with(|| {
+                    discovered.push(state);
+                    vass.add_node(self.vass.graph[state.0].clone())
+                })
+            };
+
+        for (node, _) in self.initial_states() {
+            product_state(&mut vass, &mut discovered, (node, control_initial));
+        }
+
+        let mut next = 0;
+        while let Some(&(node, control_node)) = discovered.get(next) {
+            let source = NodeIndex::new(next);
+            next += 1;
+
+            for edge in self.outgoing_edge_indices(&node) {
+                let target = self.edge_target_unchecked(&edge);
+                let vass_edge = self.get_edge_unchecked(&edge);
+
+                for control_edge in control
+                    .graph
+                    .edges_directed(control_node, Direction::Outgoing)
+                    .filter(|control_edge| control_edge.weight() == &vass_edge.data)
+                {
+                    let product_target =
+                        product_state(&mut vass, &mut discovered, (target, control_edge.target()));
+                    vass.add_edge(&source, &product_target, vass_edge.clone());
+                }
+            }
+        }
+
+        let mut initial_states = self
+            .initial_states()
+            .map(|(node, valuation)| (states[&(node, control_initial)], valuation.clone()));
+        let (initial_node, initial_valuation) = initial_states
+            .next()
+            .expect("there is always an initial state");
+        let additional_initial_states = initial_states.collect_vec();
+
+        let mut final_states = self
+            .final_states()
+            .flat_map(|(node, valuation)| {
+                discovered
+                    .iter()
+                    .filter(move |(product_node, control_node)| {
+                        *product_node == node && control.is_accepting(control_node)
+                    })
+                    .map(|state| (states[state], valuation.clone()))
+            })
+            .collect_vec();
+        if final_states.is_empty() {
+            // no run is allowed, so the final state is unreachable
+            let unreachable = vass.add_node(self.vass.graph[self.final_node].clone());
+            final_states.push((unreachable, self.final_valuation.clone()));
+        }
+        let (final_node, final_valuation) = final_states.remove(0);
+
+        let mut initialized =
+            vass.init(initial_valuation, final_valuation, initial_node, final_node);
+        initialized.additional_initial_states = additional_initial_states;
+        initialized.additional_final_states = final_states;
+        initialized
+    }
+
+    fn single_state_reduction(&self) -> SingleStateReduction {
+        let difference = |from: &VASSCounterValuation, to: &VASSCounterValuation| {
+            from.iter()
+                .zip(to.iter())
+                .map(|(from, to)| to - from)
+                .collect::<VASSCounterUpdate>()
+        };
+
+        let mut state_count = self.state_count();
+        let mut edges = vec![];
+
+        let mut initial = self.initial_node.index();
+        if !self.additional_initial_states.is_empty() {
+            initial = state_count;
+            state_count += 1;
+            for (node, valuation) in self.initial_states() {
+                edges.push((
+                    initial,
+                    node.index(),
+                    difference(&self.initial_valuation, valuation),
+                ));
+            }
+        }
+
+        let mut final_state = self.final_node.index();
+        if !self.additional_final_states.is_empty() {
+            final_state = state_count;
+            state_count += 1;
+            for (node, valuation) in self.final_states() {
+                edges.push((
+                    node.index(),
+                    final_state,
+                    difference(valuation, &self.final_valuation),
+                ));
+            }
+        }
+
+        SingleStateReduction {
+            state_count,
+            initial,
+            final_state,
+            edges,
+        }
+    }
+
+    /// Converts the VASS into a CFG whose letters are unit counter updates,
+    /// so an update by `n` becomes a chain of `n` letters.
     pub fn to_cfg(&self) -> VASSCFG<()> {
-        let mut cfg = NFA::new(CFGCounterUpdate::alphabet(self.vass.dimension));
+        self.to_cfg_with(
+            self.single_state_reduction(),
+            CFGCounterUpdate::alphabet(self.vass.dimension),
+            utils::vass_update_to_cfg_updates,
+        )
+    }
 
-        let cfg_start = cfg.add_node(self.state_to_cfg_state(self.initial_node));
-        cfg.set_initial(cfg_start);
+    /// Converts the VASS into a CFG whose letters are weighted counter
+    /// updates, so an update by `n` becomes a chain of one letter per set bit
+    /// of `n`. The alphabet has the weights up to the largest one of the
+    /// VASS, see [`CFGCounterUpdate::binary_alphabet`].
+    pub fn to_binary_cfg(&self) -> VASSCFG<()> {
+        let reduction = self.single_state_reduction();
+        let max_exponent = self
+            .vass
+            .graph
+            .edge_weights()
+            .map(|edge| &edge.update)
+            .chain(reduction.edges.iter().map(|(_, _, update)| update))
+            .map(utils::max_binary_exponent)
+            .max()
+            .unwrap_or(0);
+
+        self.to_cfg_with(
+            reduction,
+            CFGCounterUpdate::binary_alphabet(self.vass.dimension, max_exponent),
+            utils::vass_update_to_binary_cfg_updates,
+        )
+    }
 
-        let mut visited = IndexMap::new(self.vass.state_count());
-        let mut stack = vec![(self.initial_node, cfg_start)];
+    fn to_cfg_with(
+        &self,
+        reduction: SingleStateReduction,
+        alphabet: Vec<CFGCounterUpdate>,
+        update_letters: fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> VASSCFG<()> {
+        let mut cfg = NFA::new(alphabet);
 
-        while let Some((vass_state, cfg_state)) = stack.pop() {
-            visited.insert(vass_state, cfg_state);
+        let cfg_start = cfg.add_node(self.state_to_cfg_state(reduction.initial, &reduction));
+        cfg.set_initial(cfg_start);
 
-            for vass_edge in self.outgoing_edge_indices(&vass_state) {
-                let vass_target = self.edge_target_unchecked(&vass_edge);
-                let cfg_target = if let Some(target) = visited.get_option(vass_target) {
-                    *target
-                } else {
-                    let target = cfg.add_node(self.state_to_cfg_state(vass_target));
-                    stack.push((vass_target, target));
-                    target
+        let mut cfg_states = vec![None; reduction.state_count];
+        cfg_states[reduction.initial] = Some(cfg_start);
+        let mut stack = vec![reduction.initial];
+
+        while let Some(state) = stack.pop() {
+            let cfg_state = cfg_states[state].expect("visited state to have a CFG state");
+
+            let vass_edges = (state < self.state_count())
+                .then(|| self.outgoing_edge_indices(&NodeIndex::new(state)))
+                .into_iter()
+                .flatten()
+                .map(|edge| {
+                    (
+                        self.edge_target_unchecked(&edge).index(),
+                        &self.get_edge_unchecked(&edge).update,
+                    )
+                });
+            let reduction_edges = reduction
+                .edges
+                .iter()
+                .filter(|(source, _, _)| *source == state)
+                .map(|(_, target, update)| (*target, update));
+
+            for (target, update) in vass_edges.chain(reduction_edges) {
+                let cfg_target = match cfg_states[target] {
+                    Some(cfg_target) => cfg_target,
+                    None => {
+                        let cfg_target = cfg.add_node(self.state_to_cfg_state(target, &reduction));
+                        cfg_states[target] = Some(cfg_target);
+                        stack.push(target);
+                        cfg_target
+                    }
                 };
 
-                let vass_label = &self.get_edge_unchecked(&vass_edge).update;
-                let marking_vec = utils::vass_update_to_cfg_updates(vass_label);
+                let marking_vec = update_letters(update);
 
                 if marking_vec.is_empty() {
                     cfg.add_edge(&cfg_state, &cfg_target, NFAEdge::Epsilon);
@@ -77,26 +341,38 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
         cfg.determinize()
     }
 
-    fn state_to_cfg_state(&self, state: NodeIndex<u32>) -> DfaNode<Option<N>> {
+    fn state_to_cfg_state(
+        &self,
+        state: usize,
+        reduction: &SingleStateReduction,
+    ) -> DfaNode<Option<N>> {
         DfaNode::new(
-            state == self.final_node,
+            state == reduction.final_state,
             false,
-            Some(self.vass.graph[state].clone()),
+            self.vass.graph.node_weight(NodeIndex::new(state)).cloned(),
         )
     }
 
     /// Converts the VASS into a VAS, a Vector Addition System (without states),
     /// using Hopcroft's and Pansiot's construction from 1978.
+    ///
+    /// Several initial or final states are first reduced to a single one, see
+    /// [`InitializedVASS::add_initial_state`].
     pub fn to_vas(&self) -> InitializedVASS<(), usize> {
-        let new_alphabet = (0..(self.transition_count() + self.dimension() * 2)).collect_vec();
+        let reduction = self.single_state_reduction();
+        let state_count = reduction.state_count;
+
+        let new_alphabet =
+            (0..(self.transition_count() + reduction.edges.len() + self.dimension() * 2))
+                .collect_vec();
         let mut vas = VASS::new(self.dimension() + 3, new_alphabet);
 
         let node = vas.add_node(());
 
-        for i in 0..self.state_count() {
-            let (a, b) = self.vas_translation_ab(i as i32);
+        for i in 0..state_count {
+            let (a, b) = vas_translation_ab(state_count, i as i64);
 
-            let (other_a, other_b) = self.vas_translation_ab((self.state_count() - i - 1) as i32);
+            let (other_a, other_b) = vas_translation_ab(state_count, (state_count - i - 1) as i64);
 
             vas.add_edge(
                 &node,
@@ -132,8 +408,8 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
                 .edge_endpoints(e)
                 .expect("edge index to be present");
 
-            let (from_a, from_b) = self.vas_translation_ab(from.index() as i32);
-            let (to_a, to_b) = self.vas_translation_ab(to.index() as i32);
+            let (from_a, from_b) = vas_translation_ab(state_count, from.index() as i64);
+            let (to_a, to_b) = vas_translation_ab(state_count, to.index() as i64);
 
             let edge = self
                 .vass
@@ -151,8 +427,22 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
             );
         }
 
-        let (initial_a, initial_b) = self.vas_translation_ab(self.initial_node.index() as i32);
-        let (final_a, final_b) = self.vas_translation_ab(self.final_node.index() as i32);
+        for (i, (from, to, update)) in reduction.edges.iter().enumerate() {
+            let (from_a, from_b) = vas_translation_ab(state_count, *from as i64);
+            let (to_a, to_b) = vas_translation_ab(state_count, *to as i64);
+
+            vas.add_edge(
+                &node,
+                &node,
+                VASSEdge::new(
+                    self.transition_count() + i,
+                    update.extend([to_a - from_b, to_b, -from_a]),
+                ),
+            );
+        }
+
+        let (initial_a, initial_b) = vas_translation_ab(state_count, reduction.initial as i64);
+        let (final_a, final_b) = vas_translation_ab(state_count, reduction.final_state as i64);
 
         vas.init(
             self.initial_valuation.extend([initial_a, initial_b, 0]),
@@ -162,16 +452,11 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
         )
     }
 
-    fn vas_translation_ab(&self, node: i32) -> (i32, i32) {
-        let a = node + 1;
-        let b = (self.state_count() as i32 + 1) * (self.state_count() as i32 - node);
-        (a, b)
-    }
-
     /// Converts a VAS into a PetriNet. Panics if this is not a VAS, so has more
     /// than one state.
     pub fn to_petri_net(&self) -> InitializedPetriNet {
         assert_eq!(self.state_count(), 1);
+        assert!(self.has_single_initial_and_final_state());
 
         let mut net = PetriNet::new(self.dimension());
 
@@ -201,12 +486,26 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedVASS<N, E> {
     }
 }
 
+fn vas_translation_ab(state_count: usize, node: i64) -> (i64, i64) {
+    let a = node + 1;
+    let b = (state_count as i64 + 1) * (state_count as i64 - node);
+    (a, b)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitializedVASSJsonState {
+    pub node: usize,
+    /// Defaults to `initial_valuation` or `final_valuation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valuation: Option<Vec<i64>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitializedVASSJsonTransition {
     pub source: usize,
     pub target: usize,
     pub letter: usize,
-    pub update: Vec<i32>,
+    pub update: Vec<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -216,9 +515,22 @@ pub struct InitializedVASSJson {
     pub alphabet: Vec<usize>,
     pub initial_node: usize,
     pub final_node: usize,
-    pub initial_valuation: Vec<i32>,
-    pub final_valuation: Vec<i32>,
+    pub initial_valuation: Vec<i64>,
+    pub final_valuation: Vec<i64>,
     pub transitions: Vec<InitializedVASSJsonTransition>,
+    /// Replaces `initial_valuation` when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_spec: Option<ValuationSpec>,
+    /// Replaces `final_valuation` when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_spec: Option<ValuationSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_initial_states: Vec<InitializedVASSJsonState>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_final_states: Vec<InitializedVASSJsonState>,
+    /// Applied before `initial_spec` and `target_spec`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub control_constraints: Vec<ControlConstraint>,
 }
 
 impl InitializedVASS<usize, usize> {
@@ -252,6 +564,11 @@ impl InitializedVASS<usize, usize> {
             initial_valuation: self.initial_valuation.iter().copied().collect(),
             final_valuation: self.final_valuation.iter().copied().collect(),
             transitions,
+            initial_spec: None,
+            target_spec: None,
+            additional_initial_states: json_states(&self.additional_initial_states),
+            additional_final_states: json_states(&self.additional_final_states),
+            control_constraints: vec![],
         };
 
         Ok(serde_json::to_string_pretty(&json)?)
@@ -259,7 +576,258 @@ impl InitializedVASS<usize, usize> {
 
     pub fn from_json(json: &str) -> anyhow::Result<Self> {
         let parsed: InitializedVASSJson = serde_json::from_str(json)?;
+        Self::try_from(parsed)
+    }
+
+    /// Reduces reachability from any valuation of `initial` to any valuation
+    /// of `target` to plain reachability.
+    ///
+    /// A side that is not exact gets a fresh state. The fresh initial state
+    /// starts from the base valuation of `initial`, pumps `AtLeast` and `Any`
+    /// counters up and `AtMost` counters down, and then enters the old initial
+    /// state. The fresh final state is entered from the old final state and
+    /// drains `AtLeast` and `Any` counters down to the base valuation of
+    /// `target` and fills `AtMost` counters up to it. Counters are unchanged,
+    /// so a run of the reduced instance is a run of this one between the
+    /// auxiliary transitions, which carry a fresh letter, see
+    /// [`Self::spec_run`]. Additional initial and final states are covered by
+    /// the specs if they share the replaced valuation and keep their own
+    /// valuation otherwise.
+    ///
+    /// Panics if a spec has a negative bound, see [`ValuationSpec::validate`].
+    pub fn with_valuation_specs(&self, initial: &ValuationSpec, target: &ValuationSpec) -> Self {
+        assert_eq!(
+            initial.dimension(),
+            self.dimension(),
+            "Initial spec has to have the same dimension as the VASS"
+        );
+        assert_eq!(
+            target.dimension(),
+            self.dimension(),
+            "Target spec has to have the same dimension as the VASS"
+        );
+        for spec in [initial, target] {
+            if let Err(err) = spec.validate() {
+                panic!("{err}");
+            }
+        }
+
+        let mut vass = self.vass.clone();
+        let letter = vass.alphabet.iter().max().map_or(0, |letter| letter + 1);
+        let mut letter_used = false;
+        let unit = |counter: usize, step: i64| {
+            let mut update = vec![0; self.dimension()];
+            update[counter] = step;
+            VASSCounterUpdate::from(update)
+        };
 
+        // states sharing the replaced valuation are covered by the spec, the others
+        // keep their own valuation
+        let (spec_initial_states, mut additional_initial_states): (Vec<_>, Vec<_>) = self
+            .additional_initial_states
+            .iter()
+            .cloned()
+            .partition(|(_, valuation)| valuation == &self.initial_valuation);
+        let (spec_final_states, mut additional_final_states): (Vec<_>, Vec<_>) = self
+            .additional_final_states
+            .iter()
+            .cloned()
+            .partition(|(_, valuation)| valuation == &self.final_valuation);
+
+        let mut initial_node = self.initial_node;
+        if !initial.is_exact() {
+            let fresh = vass.add_node(vass.state_count());
+            for (counter, constraint) in initial.constraints().iter().enumerate() {
+                let step = match constraint {
+                    CounterConstraint::Exactly(_) => continue,
+                    CounterConstraint::AtLeast(_) | CounterConstraint::Any => 1,
+                    CounterConstraint::AtMost(_) => -1,
+                };
+                vass.add_edge(&fresh, &fresh, VASSEdge::new(letter, unit(counter, step)));
+            }
+            for node in std::iter::once(initial_node)
+                .chain(spec_initial_states.iter().map(|(node, _)| *node))
+            {
+                vass.add_edge(
+                    &fresh,
+                    &node,
+                    VASSEdge::new(letter, VASSCounterUpdate::zero(self.dimension())),
+                );
+            }
+            initial_node = fresh;
+            letter_used = true;
+        } else {
+            additional_initial_states.extend(spec_initial_states);
+        }
+
+        let mut final_node = self.final_node;
+        if !target.is_exact() {
+            let fresh = vass.add_node(vass.state_count());
+            for node in
+                std::iter::once(final_node).chain(spec_final_states.iter().map(|(node, _)| *node))
+            {
+                vass.add_edge(
+                    &node,
+                    &fresh,
+                    VASSEdge::new(letter, VASSCounterUpdate::zero(self.dimension())),
+                );
+            }
+            for (counter, constraint) in target.constraints().iter().enumerate() {
+                let step = match constraint {
+                    CounterConstraint::Exactly(_) => continue,
+                    CounterConstraint::AtLeast(_) | CounterConstraint::Any => -1,
+                    CounterConstraint::AtMost(_) => 1,
+                };
+                vass.add_edge(&fresh, &fresh, VASSEdge::new(letter, unit(counter, step)));
+            }
+            final_node = fresh;
+            letter_used = true;
+        } else {
+            additional_final_states.extend(spec_final_states);
+        }
+
+        if letter_used {
+            vass.alphabet.push(letter);
+        }
+
+        let mut initialized = vass.init(
+            initial.base_valuation(),
+            target.base_valuation(),
+            initial_node,
+            final_node,
+        );
+        initialized.additional_initial_states = additional_initial_states;
+        initialized.additional_final_states = additional_final_states;
+        initialized.valuation_spec_states = letter_used.then_some(ValuationSpecStates {
+            initial: (!initial.is_exact()).then_some(initial_node),
+            target: (!target.is_exact()).then_some(final_node),
+        });
+        initialized
+    }
+
+    /// Strips the auxiliary updates of [`Self::with_valuation_specs`] from a
+    /// run of this instance, given by its counter updates with VASS updates
+    /// encoded by `encode`. The result starts in the valuation of the initial
+    /// spec the pumping prefix reached and ends in the valuation of the
+    /// target spec the draining suffix started from.
+    ///
+    /// Returns `None` if there are no auxiliary states or `run` is not a run
+    /// of this instance.
+    pub fn spec_run(
+        &self,
+        run: &[CFGCounterUpdate],
+        encode: impl Fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> Option<SpecRun> {
+        let states = self.valuation_spec_states?;
+        let path = self.path_of_run(run, &encode)?;
+
+        // auxiliary edges between the fresh states and the others have no
+        // updates, so the positions of the first state after the prefix and
+        // of the first state of the suffix delimit the run
+        let start = path
+            .iter()
+            .find(|(_, node)| Some(*node) != states.initial)
+            .map_or(0, |(position, _)| *position);
+        let end = path
+            .iter()
+            .find(|(_, node)| Some(*node) == states.target)
+            .or(path.last())
+            .map_or(run.len(), |(position, _)| *position);
+
+        let mut initial_valuation = self.initial_valuation.clone();
+        for update in &run[..start] {
+            initial_valuation.apply_cfg_update(*update);
+        }
+        let mut final_valuation = initial_valuation.clone();
+        for update in &run[start..end] {
+            final_valuation.apply_cfg_update(*update);
+        }
+
+        Some(SpecRun {
+            initial_valuation,
+            final_valuation,
+            updates: run[start..end].to_vec(),
+        })
+    }
+
+    /// The states a run given by its counter updates passes, each with the
+    /// number of updates before it. The updates between initial or final
+    /// states of different valuations, see [`Self::to_cfg`], are not part of
+    /// the path.
+    fn path_of_run(
+        &self,
+        run: &[CFGCounterUpdate],
+        encode: &impl Fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate>,
+    ) -> Option<Vec<(usize, NodeIndex<u32>)>> {
+        let difference = |from: &VASSCounterValuation, to: &VASSCounterValuation| {
+            encode(
+                &from
+                    .iter()
+                    .zip(to.iter())
+                    .map(|(from, to)| to - from)
+                    .collect::<VASSCounterUpdate>(),
+            )
+        };
+        let edge_updates = self
+            .vass
+            .graph
+            .edge_weights()
+            .map(|edge| encode(&edge.update))
+            .collect_vec();
+
+        let mut predecessors = HashMap::new();
+        let mut queue = std::collections::VecDeque::new();
+        for (node, valuation) in self.initial_states() {
+            let updates = difference(&self.initial_valuation, valuation);
+            if run.starts_with(&updates)
+                && predecessors.insert((updates.len(), node), None).is_none()
+            {
+                queue.push_back((updates.len(), node));
+            }
+        }
+
+        while let Some((position, node)) = queue.pop_front() {
+            for edge in self.vass.graph.edges(node) {
+                let updates = &edge_updates[edge.id().index()];
+                let next = (position + updates.len(), edge.target());
+                if run[position..].starts_with(updates) && !predecessors.contains_key(&next) {
+                    predecessors.insert(next, Some((position, node)));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut last = self.final_states().find_map(|(node, valuation)| {
+            let updates = difference(valuation, &self.final_valuation);
+            let position = run.len().checked_sub(updates.len())?;
+            (run[position..] == updates[..] && predecessors.contains_key(&(position, node)))
+                .then_some((position, node))
+        })?;
+
+        let mut path = vec![last];
+        while let Some(previous) = predecessors[&last] {
+            path.push(previous);
+            last = previous;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn from_json_file(path: &str) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    pub fn to_json_file(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<InitializedVASSJson> for InitializedVASS<usize, usize> {
+    type Error = anyhow::Error;
+
+    fn try_from(parsed: InitializedVASSJson) -> Result<Self, Self::Error> {
         if parsed.initial_node >= parsed.state_count || parsed.final_node >= parsed.state_count {
             anyhow::bail!(
                 "initial_node and final_node must be < state_count (got initial={}, final={}, state_count={})",
@@ -321,25 +889,94 @@ impl InitializedVASS<usize, usize> {
             );
         }
 
-        Ok(vass.init(
+        for spec in [&parsed.initial_spec, &parsed.target_spec]
+            .into_iter()
+            .flatten()
+        {
+            if spec.dimension() != parsed.dimension {
+                anyhow::bail!(
+                    "valuation spec dimension must match dimension (dimension={}, spec={})",
+                    parsed.dimension,
+                    spec.dimension()
+                );
+            }
+            spec.validate()?;
+        }
+
+        let mut initialized = vass.init(
             parsed.initial_valuation.into(),
             parsed.final_valuation.into(),
             nodes[parsed.initial_node],
             nodes[parsed.final_node],
-        ))
-    }
+        );
 
-    pub fn from_json_file(path: &str) -> anyhow::Result<Self> {
-        let json = std::fs::read_to_string(path)?;
-        Self::from_json(&json)
-    }
+        for state in parsed.additional_initial_states {
+            let (node, valuation) =
+                parse_json_state(state, &nodes, &initialized.initial_valuation)?;
+            initialized.add_initial_state(node, valuation);
+        }
+        for state in parsed.additional_final_states {
+            let (node, valuation) = parse_json_state(state, &nodes, &initialized.final_valuation)?;
+            initialized.add_final_state(node, valuation);
+        }
 
-    pub fn to_json_file(&self, path: &str) -> anyhow::Result<()> {
-        std::fs::write(path, self.to_json()?)?;
-        Ok(())
+        if !parsed.control_constraints.is_empty() {
+            initialized = initialized.with_control_constraints(&parsed.control_constraints);
+        }
+
+        if parsed.initial_spec.is_none() && parsed.target_spec.is_none() {
+            return Ok(initialized);
+        }
+
+        let initial_spec = parsed
+            .initial_spec
+            .unwrap_or_else(|| ValuationSpec::exactly(&initialized.initial_valuation));
+        let target_spec = parsed
+            .target_spec
+            .unwrap_or_else(|| ValuationSpec::exactly(&initialized.final_valuation));
+        Ok(initialized.with_valuation_specs(&initial_spec, &target_spec))
     }
 }
 
+fn json_states(states: &[(NodeIndex<u32>, VASSCounterValuation)]) -> Vec<InitializedVASSJsonState> {
+    states
+        .iter()
+        .map(|(node, valuation)| InitializedVASSJsonState {
+            node: node.index(),
+            valuation: Some(valuation.iter().copied().collect()),
+        })
+        .collect()
+}
+
+fn parse_json_state(
+    state: InitializedVASSJsonState,
+    nodes: &[NodeIndex<u32>],
+    default_valuation: &VASSCounterValuation,
+) -> anyhow::Result<(NodeIndex<u32>, VASSCounterValuation)> {
+    let Some(node) = nodes.get(state.node) else {
+        anyhow::bail!(
+            "state {} must be < state_count (state_count={})",
+            state.node,
+            nodes.len()
+        );
+    };
+
+    let valuation = match state.valuation {
+        Some(valuation) if valuation.len() != default_valuation.dimension() => {
+            anyhow::bail!(
+                "valuation of state {} must match dimension (dimension={}, valuation={})",
+                state.node,
+                default_valuation.dimension(),
+                valuation.len()
+            );
+        }
+        Some(valuation) => valuation.into(),
+        None => default_valuation.clone(),
+    };
+
+    Ok((*node, valuation))
+}
+
 impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> Alphabet for InitializedVASS<N, E> {
     type Letter = <VASSEdge<E> as AutomatonEdge>::Letter;
 
@@ -461,7 +1098,8 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> InitializedAutomaton<Deter
     }
 
     fn is_accepting(&self, node: &Self::NIndex) -> bool {
-        node == &self.final_node
+        self.final_states()
+            .any(|(final_node, _)| &final_node == node)
     }
 }
 
@@ -482,34 +1120,43 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> Language for InitializedVA
     where
         E::Letter: 'a,
     {
-        let mut current_state = Some(self.initial_node);
-        let mut current_valuation = self.initial_valuation.clone();
-
-        for symbol in input {
-            if let Some(state) = current_state {
-                let next_state = self
-                    .vass
-                    .graph
-                    .edges_directed(state, Direction::Outgoing)
-                    .find(|neighbor| {
-                        let edge = neighbor.weight();
-                        // check that we can take the edge
-                        edge.matches(symbol) && current_valuation.can_apply_update(&edge.update)
-                    })
-                    .map(|edge| {
-                        // subtract the valuation of the edge from the current valuation
-                        current_valuation.apply_update(&edge.weight().update);
-                        edge.target()
-                    });
-                current_state = next_state;
-            } else {
-                return false;
-            }
-        }
+        let input = input.into_iter().collect_vec();
+
+        self.initial_states()
+            .any(|(initial_node, initial_valuation)| {
+                let mut current_state = Some(initial_node);
+                let mut current_valuation = initial_valuation.clone();
+
+                for symbol in &input {
+                    if let Some(state) = current_state {
+                        let next_state = self
+                            .vass
+                            .graph
+                            .edges_directed(state, Direction::Outgoing)
+                            .find(|neighbor| {
+                                let edge = neighbor.weight();
+                                // check that we can take the edge
+                                edge.matches(symbol)
+                                    && current_valuation.can_apply_update(&edge.update)
+                            })
+                            .and_then(|edge| {
+                                // subtract the valuation of the edge from the current valuation,
+                                // runs that leave the i64 range are not accepted
+                                current_valuation
+                                    .try_apply_update(&edge.weight().update)
+                                    .ok()?;
+                                Some(edge.target())
+                            });
+                        current_state = next_state;
+                    } else {
+                        return false;
+                    }
+                }
 
-        match current_state {
-            Some(state) => state == self.final_node && current_valuation == self.final_valuation,
-            None => false,
-        }
+                match current_state {
+                    Some(state) => self.is_final_configuration(state, &current_valuation),
+                    None => false,
+                }
+            })
     }
 }
@@ -13,9 +13,11 @@ use crate::automaton::{
     vass::counter::{VASSCounterUpdate, VASSCounterValuation},
 };
 
+pub mod control;
 pub mod counter;
 pub mod initialized;
 pub mod omega;
+pub mod valuation_spec;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VASSEdge<E: AutomatonEdge + FromLetter> {
@@ -71,6 +73,9 @@ impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> VASS<N, E> {
             final_valuation,
             initial_node,
             final_node,
+            additional_initial_states: vec![],
+            additional_final_states: vec![],
+            valuation_spec_states: None,
         }
     }
 
@@ -1,11 +1,14 @@
 use std::fmt::{Display, Formatter};
 
-use crate::automaton::{cfg::update::CFGCounterUpdate, vass::counter::VASSCounterValuation};
+use crate::automaton::{
+    cfg::update::CFGCounterUpdate,
+    vass::counter::{CounterOverflowError, VASSCounterValuation, checked_counter_add},
+};
 
 /// A single component in a coverability valuation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OmegaCounter {
-    Finite(i32),
+    Finite(i64),
     Omega,
 }
 
@@ -34,14 +37,16 @@ impl OmegaCounter {
 
         match self {
             OmegaCounter::Omega => true,
-            OmegaCounter::Finite(v) => v > 0,
+            OmegaCounter::Finite(v) => v >= update.weight(),
         }
     }
 
-    fn apply(self, update: CFGCounterUpdate) -> Self {
+    fn try_apply(self, update: CFGCounterUpdate) -> Result<Self, CounterOverflowError> {
         match self {
-            OmegaCounter::Omega => OmegaCounter::Omega,
-            OmegaCounter::Finite(v) => OmegaCounter::Finite(v + update.op()),
+            OmegaCounter::Omega => Ok(OmegaCounter::Omega),
+            OmegaCounter::Finite(v) => {
+                checked_counter_add(update.counter(), v, update.op()).map(OmegaCounter::Finite)
+            }
         }
     }
 }
@@ -88,9 +93,22 @@ impl OmegaCounterValuation {
         self.values[update.counter().to_usize()].can_apply(update)
     }
 
+    /// Panics if a counter overflows, see
+    /// [`try_apply_cfg_update`](Self::try_apply_cfg_update).
     pub fn apply_cfg_update(&mut self, update: CFGCounterUpdate) {
+        self.try_apply_cfg_update(update)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Applies `update`, or leaves the valuation unchanged if the counter
+    /// would overflow.
+    pub fn try_apply_cfg_update(
+        &mut self,
+        update: CFGCounterUpdate,
+    ) -> Result<(), CounterOverflowError> {
         let index = update.counter().to_usize();
-        self.values[index] = self.values[index].apply(update);
+        self.values[index] = self.values[index].try_apply(update)?;
+        Ok(())
     }
 
     pub fn leq(&self, other: &Self) -> bool {
@@ -0,0 +1,147 @@
+use petgraph::graph::NodeIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::automaton::{cfg::update::CFGCounterUpdate, vass::counter::VASSCounterValuation};
+
+/// A constraint on the value of a single counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CounterConstraint {
+    Exactly(i64),
+    AtLeast(i64),
+    AtMost(i64),
+    /// Any non-negative value.
+    Any,
+}
+
+impl CounterConstraint {
+    pub fn matches(&self, value: i64) -> bool {
+        match *self {
+            CounterConstraint::Exactly(bound) => value == bound,
+            CounterConstraint::AtLeast(bound) => value >= bound,
+            CounterConstraint::AtMost(bound) => (0..=bound).contains(&value),
+            CounterConstraint::Any => value >= 0,
+        }
+    }
+
+    /// The value the constraint is reduced to in plain reachability. `AtLeast`
+    /// values are drained down to it and `AtMost` values are filled up to it.
+    pub fn base_value(&self) -> i64 {
+        match *self {
+            CounterConstraint::Exactly(bound)
+            | CounterConstraint::AtLeast(bound)
+            | CounterConstraint::AtMost(bound) => bound,
+            CounterConstraint::Any => 0,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, CounterConstraint::Exactly(_))
+    }
+
+    /// Counters are never negative, so neither are bounds.
+    pub fn is_valid(&self) -> bool {
+        self.base_value() >= 0
+    }
+}
+
+impl std::fmt::Display for CounterConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CounterConstraint::Exactly(bound) => write!(f, "={bound}"),
+            CounterConstraint::AtLeast(bound) => write!(f, ">={bound}"),
+            CounterConstraint::AtMost(bound) => write!(f, "<={bound}"),
+            CounterConstraint::Any => write!(f, ">=0"),
+        }
+    }
+}
+
+/// A set of counter valuations given by one constraint per counter, e.g.
+/// "counter 0 exactly 0, counter 1 at least 3, counter 2 anything".
+///
+/// Reachability between such sets is reduced to plain reachability by
+/// [`InitializedVASS::with_valuation_specs`](crate::automaton::vass::initialized::InitializedVASS::with_valuation_specs).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ValuationSpec {
+    constraints: Box<[CounterConstraint]>,
+}
+
+impl ValuationSpec {
+    pub fn new(constraints: Box<[CounterConstraint]>) -> Self {
+        ValuationSpec { constraints }
+    }
+
+    /// The spec that only contains `valuation`.
+    pub fn exactly(valuation: &VASSCounterValuation) -> Self {
+        ValuationSpec::new(
+            valuation
+                .iter()
+                .map(|value| CounterConstraint::Exactly(*value))
+                .collect(),
+        )
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn constraints(&self) -> &[CounterConstraint] {
+        &self.constraints
+    }
+
+    /// Whether the spec contains a single valuation.
+    pub fn is_exact(&self) -> bool {
+        self.constraints.iter().all(CounterConstraint::is_exact)
+    }
+
+    /// Fails if a bound is negative, e.g. `AtMost(-1)`.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (counter, constraint) in self.constraints.iter().enumerate() {
+            if !constraint.is_valid() {
+                anyhow::bail!("counter {counter} has negative bound {constraint}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, valuation: &VASSCounterValuation) -> bool {
+        valuation.dimension() == self.dimension()
+            && self
+                .constraints
+                .iter()
+                .zip(valuation.iter())
+                .all(|(constraint, value)| constraint.matches(*value))
+    }
+
+    /// The valuation of the reduced plain reachability instance, see
+    /// [`CounterConstraint::base_value`].
+    pub fn base_valuation(&self) -> VASSCounterValuation {
+        VASSCounterValuation::new(
+            self.constraints
+                .iter()
+                .map(CounterConstraint::base_value)
+                .collect(),
+        )
+    }
+}
+
+/// The auxiliary states
+/// [`InitializedVASS::with_valuation_specs`](crate::automaton::vass::initialized::InitializedVASS::with_valuation_specs)
+/// adds for a side that is not exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValuationSpecStates {
+    /// Pumps counters up from the base valuation of the initial spec.
+    pub initial: Option<NodeIndex<u32>>,
+    /// Drains counters down to the base valuation of the target spec.
+    pub target: Option<NodeIndex<u32>>,
+}
+
+/// A run between valuations of the initial and target spec, without the
+/// auxiliary updates of the reduction to plain reachability, see
+/// [`InitializedVASS::spec_run`](crate::automaton::vass::initialized::InitializedVASS::spec_run).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecRun {
+    pub initial_valuation: VASSCounterValuation,
+    pub final_valuation: VASSCounterValuation,
+    pub updates: Vec<CFGCounterUpdate>,
+}
@@ -1,6 +1,13 @@
+mod schema;
+
 use serde::{Deserialize, Serialize};
 use vass_reach_macros::config;
 
+use self::schema::enum_config_schema;
+pub use self::schema::{ConfigSchema, schema_markdown};
+pub(crate) use self::schema::{field_schema, object_schema, root_schema, validate};
+use crate::automaton::{cfg::update::CFGCounterUpdate, utils, vass::counter::VASSCounterUpdate};
+
 pub trait IntoOr<T> {
     fn into_or(self, or: T) -> T;
 }
@@ -20,38 +27,180 @@ impl<T> IntoOr<T> for Option<T> {
     }
 }
 
+/// Merges `overrides` into `base`. Objects are merged field by field, every
+/// other value replaces the one in `base`.
+pub(crate) fn merge_overrides(base: &mut serde_json::Value, overrides: &serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(existing) => merge_overrides(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+/// Parses a `key=value` assignment into overrides for
+/// `with_overrides`. The key is one of `keys`, the value is a TOML value.
+/// Values that are no valid TOML, like enum variants, are taken as strings.
+pub(crate) fn parse_assignment(
+    assignment: &str,
+    keys: &[String],
+) -> anyhow::Result<serde_json::Value> {
+    let Some((key, value)) = assignment.split_once('=') else {
+        anyhow::bail!("Expected key=value, got {}", assignment);
+    };
+    let key = key.trim();
+    let value = value.trim();
+
+    if !keys.iter().any(|candidate| candidate == key) {
+        return Err(match closest_key(key, keys) {
+            Some(candidate) => {
+                anyhow::anyhow!("Unknown config key {}, did you mean {}?", key, candidate)
+            }
+            None => anyhow::anyhow!("Unknown config key {}", key),
+        });
+    }
+
+    let value = match toml::from_str::<toml::Table>(&format!("value = {value}")) {
+        Ok(mut table) => serde_json::to_value(table.remove("value"))?,
+        Err(_) => serde_json::Value::String(value.to_string()),
+    };
+
+    Ok(key.rsplit('.').fold(value, |value, segment| {
+        serde_json::Value::Object([(segment.to_string(), value)].into_iter().collect())
+    }))
+}
+
+/// The key a misspelled `key` most likely meant: a key that ends in `key`, as
+/// in `max_routes` for `divide_and_conquer.max_routes`, or else the key with
+/// the smallest edit distance if it is small enough.
+fn closest_key<'a>(key: &str, keys: &'a [String]) -> Option<&'a str> {
+    if let Some(candidate) = keys
+        .iter()
+        .find(|candidate| candidate.ends_with(&format!(".{key}")))
+    {
+        return Some(candidate);
+    }
+
+    keys.iter()
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 3.max(key.len() / 4))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
 config! {
+    /// Configuration of the N-reachability solver.
     pub struct VASSReachConfig {
+        /// Gives up with an unknown result after this long.
         timeout: Option<std::time::Duration> = None,
+        /// Gives up after this many refinement steps.
         max_iterations: Option<u64> = None,
+        /// Takes the modulo approximation into account when deciding whether
+        /// a path pumps.
         consider_modulo_for_pumping: bool = false,
+        /// Allows refinements that raise the forward and backward counter
+        /// bounds.
         bounded_counting_enabled: bool = true,
+        /// How the CFG encodes transitions that move more than one token.
+        update_encoding: UpdateEncoding = UpdateEncoding::Unary,
         preprocessing: PreprocessingConfig (Option<PartialPreprocessingConfig> = PreprocessingConfig::default()),
+        divide_and_conquer: DivideAndConquerConfig (Option<PartialDivideAndConquerConfig> = DivideAndConquerConfig::default()),
         modulo: ModuloConfig (Option<PartialModuloConfig> = ModuloConfig::default()),
+        refinement: RefinementConfig (Option<PartialRefinementConfig> = RefinementConfig::default()),
         lts: LTSConfig (Option<PartialLTSConfig> = LTSConfig::default()),
         linear_graph: LinearGraphConfig (Option<PartialLinearGraphConfig> = LinearGraphConfig::default()),
         short_witness: ShortWitnessConfig (Option<PartialShortWitnessConfig> = ShortWitnessConfig::default()),
         debug_trace: DebugTraceConfig (Option<PartialDebugTraceConfig> = DebugTraceConfig::default()),
+        smt_dump: SMTDumpConfig (Option<PartialSMTDumpConfig> = SMTDumpConfig::default()),
+        arithmetic_backend: ArithmeticBackendConfig (Option<PartialArithmeticBackendConfig> = ArithmeticBackendConfig::default()),
+    }
+}
+
+/// How the CFG of a VASS encodes an update by more than one token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpdateEncoding {
+    /// One unit update per token, see `InitializedVASS::to_cfg`.
+    Unary,
+    /// One weighted update per binary digit, see
+    /// `InitializedVASS::to_binary_cfg`. Keeps the CFG small for large arc
+    /// weights.
+    Binary,
+}
+
+impl UpdateEncoding {
+    /// How CFGs of this encoding spell out a VASS update.
+    pub fn cfg_updates(self) -> fn(&VASSCounterUpdate) -> Vec<CFGCounterUpdate> {
+        match self {
+            UpdateEncoding::Unary => utils::vass_update_to_cfg_updates,
+            UpdateEncoding::Binary => utils::vass_update_to_binary_cfg_updates,
+        }
     }
 }
 
 config! {
+    /// A bounded breadth-first search for short runs before the refinement
+    /// loop starts.
     pub struct ShortWitnessConfig {
         enabled: bool = true,
+        /// The maximal length of runs that are searched.
         max_depth: usize = 64,
+        /// Stops the search after visiting this many configurations.
         max_configurations: usize = 100_000,
     }
 }
 
 config! {
+    /// Checks that can refute an instance before the refinement loop.
     pub struct PreprocessingConfig {
         enabled: bool = false,
+        /// Refutes instances that are not even Z-reachable.
         z_reach_precheck_enabled: bool = false,
+        /// Refutes instances whose target is not coverable.
+        karp_miller_precheck_enabled: bool = false,
+        /// The maximal number of routes through the SCC-DAG of the CFG that
+        /// are checked with a LinearGraph.
         max_linear_graph_candidates: usize = 256,
     }
 }
 
 config! {
+    /// Solves the routes through the SCC-DAG of the CFG separately.
+    pub struct DivideAndConquerConfig {
+        enabled: bool = false,
+        /// Solves the whole instance at once if there are more routes.
+        max_routes: usize = 64,
+        /// The number of routes solved in parallel, defaults to the number of
+        /// cores.
+        threads: Option<usize> = None,
+    }
+}
+
+config! {
+    /// Records the refinement steps for the visualization.
     pub struct DebugTraceConfig {
         enabled: bool = false,
         level: DebugTraceLevel = DebugTraceLevel::Full,
@@ -61,6 +210,49 @@ config! {
     }
 }
 
+/// The solver that checks arithmetic queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArithmeticBackendKind {
+    /// Z3 through its library. Needs the `z3` feature.
+    Z3,
+    /// Any solver binary that reads SMT-LIB2 from stdin, started with
+    /// `smt_lib_command`.
+    SMTLib,
+}
+
+impl Default for ArithmeticBackendKind {
+    fn default() -> Self {
+        if cfg!(feature = "z3") {
+            ArithmeticBackendKind::Z3
+        } else {
+            ArithmeticBackendKind::SMTLib
+        }
+    }
+}
+
+config! {
+    /// The solver that checks arithmetic queries. Every solver uses the
+    /// backend of its own config, so solvers with different backends can run
+    /// side by side.
+    pub struct ArithmeticBackendConfig {
+        kind: ArithmeticBackendKind = ArithmeticBackendKind::default(),
+        /// The command of the `SMTLib` backend.
+        smt_lib_command: Vec<String> = vec!["z3".to_string(), "-in".to_string()],
+        /// Whether the `SMTLib` solver supports `minimize`, as Z3 does. If not,
+        /// queries that minimize are unknown instead of being sent.
+        smt_lib_optimization: bool = true,
+    }
+}
+
+config! {
+    /// Writes every arithmetic query as SMT-LIB2 file, see `replay-smt`.
+    pub struct SMTDumpConfig {
+        enabled: bool = false,
+        /// Defaults to `debug/smt`.
+        output_dir: Option<String> = None,
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DebugTraceLevel {
@@ -81,9 +273,47 @@ config! {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefinementPolicyKind {
+    Default,
+    LinearGraphFirst,
+    BoundsFirst,
+    CostModel,
+}
+
+/// The engine that builds the separator for a `BuildAutomaton` refinement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefinementAutomatonEngine {
+    LinearGraph,
+    LTC,
+    /// Uses the LTC separator when the relaxed LTC check already fails and
+    /// the LinearGraph extender otherwise. Without `lts.relaxed_enabled` this
+    /// is the same as `LinearGraph`.
+    LTCThenLinearGraph,
+}
+
+config! {
+    /// How the solver picks the next refinement.
+    pub struct RefinementConfig {
+        policy: RefinementPolicyKind = RefinementPolicyKind::Default,
+        /// A path pumps a counter if it revisits a state with a higher value
+        /// of the counter more than this many times.
+        pumping_threshold: u32 = 3,
+        /// The default policy only raises the modulus `mu` of a counter once
+        /// the path took it above `mu` to this power, so that counters that
+        /// stay small keep their modulus.
+        modulo_value_exponent: u32 = 2,
+        /// The engine that builds the separator of a `BuildAutomaton`
+        /// refinement.
+        automaton_engine: RefinementAutomatonEngine = RefinementAutomatonEngine::LinearGraph,
+    }
+}
+
 config! {
+    /// Refutes paths with linear transition systems. LTC separators are
+    /// used by the LTC engines of `refinement.automaton_engine`.
     pub struct LTSConfig {
-        enabled: bool = true,
+        /// Tries the cheaper relaxed check first.
         relaxed_enabled: bool = true,
     }
 }
@@ -108,6 +338,21 @@ pub enum LinearGraphSeedOrder {
     SmallerSeedFirst,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinearGraphTemplateSynthesisMode {
+    /// Enumerates coefficients up to `template_synthesis_max_coefficient`.
+    Enumeration,
+    /// Solves for coefficients of any size and inductive state bounds. The
+    /// coefficients are non-negative with at least two counters in the
+    /// support, so mixed-sign templates like `x - y` are only found by
+    /// enumeration. Consecution picks one Farkas multiplier per edge, which
+    /// is sound but incomplete: some inductive templates are missed.
+    Farkas,
+    /// Falls back to enumeration when the Farkas template does not separate,
+    /// which also covers mixed-sign templates.
+    FarkasThenEnumeration,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LinearGraphTemplateFamily {
     Singleton,
@@ -116,25 +361,43 @@ pub enum LinearGraphTemplateFamily {
 }
 
 config! {
+    /// Separators built by extending a path to a LinearGraph.
     pub struct LinearGraphConfig {
         enabled: bool = true,
+        /// Starts from the counterexample path and further paths of the
+        /// product.
         multiple_starting_paths_enabled: bool = false,
+        /// The number of further starting paths.
         extra_auxiliary_paths: usize = 0,
+        /// The default of `max_seed_checks` and `max_interpolation_steps`.
         max_refinement_steps: u64 = 10,
         max_seed_checks: Option<usize> = None,
         max_interpolation_steps: Option<usize> = None,
         check_full_scc_upper_bound: bool = true,
+        sub_scc_refinement_enabled: bool = true,
         interpolation_strategy: LinearGraphInterpolationStrategy = LinearGraphInterpolationStrategy::AdaptiveBatch,
         region_order: LinearGraphRegionOrder = LinearGraphRegionOrder::GainDescending,
         seed_order: LinearGraphSeedOrder = LinearGraphSeedOrder::MorePathsThenSize,
         reach_solver_max_iterations: Option<u32> = None,
         reach_solver_timeout: Option<std::time::Duration> = None,
+        /// The number of candidates checked in parallel.
+        candidate_threads: usize = 1,
+        incremental_sessions_enabled: bool = false,
+        modulo_congruences_enabled: bool = true,
+        /// Bounds templates from above at LinearGraph boundaries too, and
+        /// turns intervals of a single value into equalities. The analysis of
+        /// the main CFG only tracks lower bounds.
+        template_interval_bounds_enabled: bool = true,
         template_exact_transfer_enabled: bool = false,
         template_exact_transfer_max_templates: usize = 8,
         template_synthesis_enabled: bool = true,
+        template_synthesis_mode: LinearGraphTemplateSynthesisMode = LinearGraphTemplateSynthesisMode::Enumeration,
+        /// The largest coefficient `Enumeration` tries.
         template_synthesis_max_coefficient: i32 = 2,
         template_synthesis_candidate_limit: usize = 256,
         template_synthesis_round_limit: usize = 8,
+        /// Reuses templates that separated earlier paths.
+        template_store_enabled: bool = true,
         initial_template_families: Vec<LinearGraphTemplateFamily> = vec![
             LinearGraphTemplateFamily::Singleton,
             LinearGraphTemplateFamily::Pair,
@@ -143,9 +406,42 @@ config! {
     }
 }
 
+/// How Z-reachability makes sure the Parikh image forms a single run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZReachConnectivity {
+    /// Encodes connectivity of the used edges from the initial node, so one
+    /// query decides Z-reachability.
+    Encoded,
+    /// Forbids disconnected Parikh images one at a time and checks again.
+    Refinement,
+}
+
 config! {
+    /// Configuration of the Z-reachability solver.
     pub struct VASSZReachConfig {
+        /// Gives up with an unknown result after this long.
         timeout: Option<std::time::Duration> = None,
+        /// Gives up after this many connectivity refinements.
         max_iterations: Option<u64> = None,
+        connectivity: ZReachConnectivity = ZReachConnectivity::Encoded,
+        /// How the CFG encodes transitions that move more than one token.
+        update_encoding: UpdateEncoding = UpdateEncoding::Unary,
+        smt_dump: SMTDumpConfig (Option<PartialSMTDumpConfig> = SMTDumpConfig::default()),
+        arithmetic_backend: ArithmeticBackendConfig (Option<PartialArithmeticBackendConfig> = ArithmeticBackendConfig::default()),
     }
 }
+
+enum_config_schema!(
+    ArithmeticBackendKind,
+    DebugTraceLevel,
+    ModuloMode,
+    RefinementPolicyKind,
+    RefinementAutomatonEngine,
+    LinearGraphInterpolationStrategy,
+    LinearGraphRegionOrder,
+    LinearGraphSeedOrder,
+    LinearGraphTemplateSynthesisMode,
+    LinearGraphTemplateFamily,
+    ZReachConnectivity,
+    UpdateEncoding,
+);
@@ -0,0 +1,346 @@
+use std::time::Duration;
+
+use serde::{
+    Deserializer,
+    de::{DeserializeOwned, Error, Visitor, value::Error as ValueError},
+};
+use serde_json::{Map, Value, json};
+
+use super::closest_key;
+
+/// The JSON Schema of a config value. The `config!` macro implements it for
+/// config structs from the schemas of their fields.
+pub trait ConfigSchema {
+    fn config_schema() -> Value;
+}
+
+impl ConfigSchema for bool {
+    fn config_schema() -> Value {
+        json!({ "type": "boolean" })
+    }
+}
+
+macro_rules! unsigned_config_schema {
+    ($($ty:ty),*) => {
+        $(impl ConfigSchema for $ty {
+            fn config_schema() -> Value {
+                json!({ "type": "integer", "minimum": 0 })
+            }
+        })*
+    };
+}
+
+unsigned_config_schema!(u32, u64, usize);
+
+impl ConfigSchema for i32 {
+    fn config_schema() -> Value {
+        json!({ "type": "integer" })
+    }
+}
+
+impl ConfigSchema for String {
+    fn config_schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl ConfigSchema for Duration {
+    fn config_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "secs": { "type": "integer", "minimum": 0 },
+                "nanos": { "type": "integer", "minimum": 0 },
+            },
+            "required": ["secs", "nanos"],
+            "additionalProperties": false,
+        })
+    }
+}
+
+impl<T: ConfigSchema> ConfigSchema for Option<T> {
+    fn config_schema() -> Value {
+        json!({ "anyOf": [T::config_schema(), { "type": "null" }] })
+    }
+}
+
+impl<T: ConfigSchema> ConfigSchema for Vec<T> {
+    fn config_schema() -> Value {
+        json!({ "type": "array", "items": T::config_schema() })
+    }
+}
+
+/// Implements [`ConfigSchema`] for enums with unit variants only, see
+/// [`enum_schema`].
+macro_rules! enum_config_schema {
+    ($($ty:ty),* $(,)?) => {
+        $(impl ConfigSchema for $ty {
+            fn config_schema() -> serde_json::Value {
+                $crate::config::schema::enum_schema::<$ty>()
+            }
+        })*
+    };
+}
+
+pub(super) use enum_config_schema;
+
+/// The schema of an enum with unit variants only. The variants are taken from
+/// its `Deserialize` implementation, so they include serde renames.
+pub(super) fn enum_schema<T: DeserializeOwned>() -> Value {
+    let mut variants: &'static [&'static str] = &[];
+    let _ = T::deserialize(EnumVariants(&mut variants));
+    json!({ "type": "string", "enum": variants })
+}
+
+/// Records the variants serde passes to `deserialize_enum` and fails.
+struct EnumVariants<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for EnumVariants<'_> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(ValueError::custom("expected an enum"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = variants;
+        Err(ValueError::custom("only the variants are needed"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Adds the description and default of a field to the schema of its type.
+/// Nested config sections have no default of their own.
+pub(crate) fn field_schema(
+    mut schema: Value,
+    description: Option<&str>,
+    default: Option<Value>,
+) -> Value {
+    if let Some(description) = description {
+        schema["description"] = json!(description);
+    }
+    if let Some(default) = default {
+        schema["default"] = default;
+    }
+    schema
+}
+
+pub(crate) fn object_schema(description: Option<&str>, properties: Map<String, Value>) -> Value {
+    field_schema(
+        json!({
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+        }),
+        description,
+        None,
+    )
+}
+
+pub(crate) fn root_schema(title: &str, schema: Value) -> Value {
+    let mut root = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": title,
+    });
+    root.as_object_mut()
+        .unwrap()
+        .extend(schema.as_object().cloned().unwrap_or_default());
+    root
+}
+
+/// Checks `value` against `schema`, which has to be generated by
+/// [`ConfigSchema`]. Unknown keys are reported with the closest of `keys`.
+pub(crate) fn validate(value: &Value, schema: &Value, keys: &[String]) -> anyhow::Result<()> {
+    validate_at("", value, schema, keys)
+}
+
+fn validate_at(path: &str, value: &Value, schema: &Value, keys: &[String]) -> anyhow::Result<()> {
+    if let Some(alternatives) = schema.get("anyOf").and_then(Value::as_array) {
+        if alternatives
+            .iter()
+            .any(|alternative| validate_at(path, value, alternative, keys).is_ok())
+        {
+            return Ok(());
+        }
+        // the error of the first alternative is the most helpful, the others
+        // only allow null
+        return validate_at(path, value, &alternatives[0], keys);
+    }
+
+    let expected = schema.get("type").and_then(Value::as_str).unwrap_or("any");
+    let matches = match expected {
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    };
+    if !matches {
+        anyhow::bail!(
+            "Invalid value for {}: expected {}, got {}",
+            path,
+            expected,
+            value
+        );
+    }
+
+    if let Some(variants) = schema.get("enum").and_then(Value::as_array)
+        && !variants.contains(value)
+    {
+        let variants = variants
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        anyhow::bail!(
+            "Invalid value for {}: expected one of {}, got {}",
+            path,
+            variants,
+            value
+        );
+    }
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_i64)
+        && value.as_i64().is_some_and(|value| value < minimum)
+    {
+        anyhow::bail!(
+            "Invalid value for {}: expected at least {}, got {}",
+            path,
+            minimum,
+            value
+        );
+    }
+
+    if let Some(items) = schema.get("items")
+        && let Some(values) = value.as_array()
+    {
+        for (index, value) in values.iter().enumerate() {
+            validate_at(&format!("{path}[{index}]"), value, items, keys)?;
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in object {
+            let key_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(schema) => validate_at(&key_path, value, schema, keys)?,
+                None => {
+                    return Err(match closest_key(&key_path, keys) {
+                        Some(candidate) => anyhow::anyhow!(
+                            "Unknown config key {}, did you mean {}?",
+                            key_path,
+                            candidate
+                        ),
+                        None => anyhow::anyhow!("Unknown config key {}", key_path),
+                    });
+                }
+            }
+        }
+        for required in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(required) {
+                anyhow::bail!("Missing {} in {}", required, path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A Markdown table of all keys of a config schema with their types, defaults
+/// and descriptions.
+pub fn schema_markdown(schema: &Value) -> String {
+    let mut markdown = String::new();
+    if let Some(title) = schema.get("title").and_then(Value::as_str) {
+        markdown.push_str(&format!("# {title}\n\n"));
+    }
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        markdown.push_str(&format!("{description}\n\n"));
+    }
+    markdown.push_str("| Key | Type | Default | Description |\n");
+    markdown.push_str("| --- | --- | --- | --- |\n");
+    push_markdown_rows(&mut markdown, "", schema);
+    markdown
+}
+
+fn push_markdown_rows(markdown: &mut String, prefix: &str, schema: &Value) {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+
+    for (key, property) in properties {
+        let key = format!("{prefix}{key}");
+        let description = property
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        match property.get("default") {
+            // nested sections have no default, their fields have
+            None => {
+                markdown.push_str(&format!("| `{key}` | section | | {description} |\n"));
+                push_markdown_rows(markdown, &format!("{key}."), property);
+            }
+            Some(default) => markdown.push_str(&format!(
+                "| `{key}` | {} | `{default}` | {description} |\n",
+                type_name(property)
+            )),
+        }
+    }
+}
+
+fn type_name(schema: &Value) -> String {
+    if let Some(alternatives) = schema.get("anyOf").and_then(Value::as_array) {
+        return alternatives
+            .iter()
+            .map(type_name)
+            .collect::<Vec<_>>()
+            .join(" or ");
+    }
+    if let Some(variants) = schema.get("enum").and_then(Value::as_array) {
+        let variants = variants
+            .iter()
+            .map(|variant| format!("`{}`", variant.as_str().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return format!("one of {variants}");
+    }
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("array") => format!(
+            "array of {}",
+            type_name(schema.get("items").unwrap_or(&Value::Null))
+        ),
+        Some("object") => {
+            let fields = schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| properties.keys().cloned().collect::<Vec<_>>().join(", "))
+                .unwrap_or_default();
+            format!("{{ {fields} }}")
+        }
+        Some(name) => name.to_string(),
+        None => "any".to_string(),
+    }
+}
@@ -0,0 +1,416 @@
+//! Backend-independent integer and boolean terms.
+//!
+//! The constructors and operators follow the `z3` crate, so encodings read the
+//! same no matter which backend checks them. Terms are immutable and share
+//! their subterms, which keeps the long counter-sum chains of an encoding
+//! linear in size. Every traversal is iterative, since these chains can be far
+//! deeper than the stack allows for recursion.
+
+use std::{
+    borrow::Borrow,
+    fmt::{Debug, Display},
+    sync::Arc,
+};
+
+use hashbrown::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Op {
+    IntConst(i64),
+    IntVar(Arc<str>),
+    BoolVar(Arc<str>),
+    Add,
+    Sub,
+    Mul,
+    Mod,
+    Ite,
+    Not,
+    And,
+    Or,
+    Implies,
+    Eq,
+    Le,
+    Lt,
+    Ge,
+    Gt,
+}
+
+impl Op {
+    fn is_leaf(&self) -> bool {
+        matches!(self, Op::IntConst(_) | Op::IntVar(_) | Op::BoolVar(_))
+    }
+
+    /// The SMT-LIB2 function symbol of a composite term.
+    pub(crate) fn smtlib_symbol(&self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Mod => "mod",
+            Op::Ite => "ite",
+            Op::Not => "not",
+            Op::And => "and",
+            Op::Or => "or",
+            Op::Implies => "=>",
+            Op::Eq => "=",
+            Op::Le => "<=",
+            Op::Lt => "<",
+            Op::Ge => ">=",
+            Op::Gt => ">",
+            Op::IntConst(_) | Op::IntVar(_) | Op::BoolVar(_) => {
+                unreachable!("leaves have no function symbol")
+            }
+        }
+    }
+}
+
+pub(crate) struct Node {
+    pub(crate) op: Op,
+    pub(crate) args: Box<[Term]>,
+}
+
+/// An untyped term. [`Int`] and [`Bool`] wrap it with their sort.
+#[derive(Clone)]
+pub(crate) struct Term(Arc<Node>);
+
+impl Term {
+    fn new(op: Op, args: impl Into<Box<[Term]>>) -> Self {
+        Term(Arc::new(Node {
+            op,
+            args: args.into(),
+        }))
+    }
+
+    pub(crate) fn node(&self) -> &Node {
+        &self.0
+    }
+
+    /// Identifies the shared node, so traversals visit it once.
+    pub(crate) fn key(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.0.op.is_leaf()
+    }
+
+    pub(crate) fn is_int(&self) -> bool {
+        match &self.0.op {
+            Op::IntConst(_) | Op::IntVar(_) | Op::Add | Op::Sub | Op::Mul | Op::Mod => true,
+            Op::Ite => self.0.args[1].is_int(),
+            _ => false,
+        }
+    }
+}
+
+/// Structural equality, which is pointer equality for shared subterms.
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((left, right)) = stack.pop() {
+            if Arc::ptr_eq(&left.0, &right.0) {
+                continue;
+            }
+            if left.0.op != right.0.op || left.0.args.len() != right.0.args.len() {
+                return false;
+            }
+            stack.extend(left.0.args.iter().zip(right.0.args.iter()));
+        }
+        true
+    }
+}
+
+impl Eq for Term {}
+
+/// Computes `f` for every node below `root` in post-order, visiting shared
+/// nodes once. `memo` keeps the nodes alive, so it can be reused across calls.
+#[cfg_attr(not(feature = "z3"), allow(dead_code))]
+pub(crate) fn fold_term<T: Clone>(
+    root: &Term,
+    memo: &mut HashMap<usize, (Term, T)>,
+    mut f: impl FnMut(&Term, &[T]) -> T,
+) -> T {
+    let mut stack = vec![(root.clone(), false)];
+
+    while let Some((term, expanded)) = stack.pop() {
+        if memo.contains_key(&term.key()) {
+            continue;
+        }
+
+        if expanded || term.is_leaf() {
+            let args = term
+                .node()
+                .args
+                .iter()
+                .map(|arg| memo[&arg.key()].1.clone())
+                .collect::<Vec<_>>();
+            let value = f(&term, &args);
+            memo.insert(term.key(), (term, value));
+        } else {
+            stack.push((term.clone(), true));
+            for arg in term.node().args.iter() {
+                if !memo.contains_key(&arg.key()) {
+                    stack.push((arg.clone(), false));
+                }
+            }
+        }
+    }
+
+    memo[&root.key()].1.clone()
+}
+
+/// An integer term.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Int(pub(crate) Term);
+
+/// A boolean term.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Bool(pub(crate) Term);
+
+impl Int {
+    pub(crate) fn new_const(name: impl Into<String>) -> Self {
+        Int(Term::new(Op::IntVar(name.into().into()), []))
+    }
+
+    pub(crate) fn from_i64(value: i64) -> Self {
+        Int(Term::new(Op::IntConst(value), []))
+    }
+
+    pub(crate) fn add<T: Borrow<Int>>(summands: &[T]) -> Self {
+        match summands {
+            [] => Int::from_i64(0),
+            [summand] => summand.borrow().clone(),
+            _ => Int(Term::new(
+                Op::Add,
+                summands
+                    .iter()
+                    .map(|summand| summand.borrow().0.clone())
+                    .collect::<Vec<_>>(),
+            )),
+        }
+    }
+
+    fn binary(op: Op, left: &Int, right: &Int) -> Self {
+        Int(Term::new(op, [left.0.clone(), right.0.clone()]))
+    }
+
+    fn compare(&self, op: Op, other: &Int) -> Bool {
+        Bool(Term::new(op, [self.0.clone(), other.0.clone()]))
+    }
+
+    pub(crate) fn modulo(&self, modulus: impl Borrow<Int>) -> Int {
+        Int::binary(Op::Mod, self, modulus.borrow())
+    }
+
+    pub(crate) fn eq(&self, other: impl Borrow<Int>) -> Bool {
+        self.compare(Op::Eq, other.borrow())
+    }
+
+    pub(crate) fn le(&self, other: impl Borrow<Int>) -> Bool {
+        self.compare(Op::Le, other.borrow())
+    }
+
+    pub(crate) fn lt(&self, other: impl Borrow<Int>) -> Bool {
+        self.compare(Op::Lt, other.borrow())
+    }
+
+    pub(crate) fn ge(&self, other: impl Borrow<Int>) -> Bool {
+        self.compare(Op::Ge, other.borrow())
+    }
+
+    pub(crate) fn gt(&self, other: impl Borrow<Int>) -> Bool {
+        self.compare(Op::Gt, other.borrow())
+    }
+}
+
+impl Bool {
+    pub(crate) fn new_const(name: impl Into<String>) -> Self {
+        Bool(Term::new(Op::BoolVar(name.into().into()), []))
+    }
+
+    fn nary<T: Borrow<Bool>>(op: Op, operands: &[T]) -> Self {
+        Bool(Term::new(
+            op,
+            operands
+                .iter()
+                .map(|operand| operand.borrow().0.clone())
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    pub(crate) fn and<T: Borrow<Bool>>(operands: &[T]) -> Self {
+        Bool::nary(Op::And, operands)
+    }
+
+    pub(crate) fn or<T: Borrow<Bool>>(operands: &[T]) -> Self {
+        Bool::nary(Op::Or, operands)
+    }
+
+    pub(crate) fn not(&self) -> Bool {
+        Bool(Term::new(Op::Not, [self.0.clone()]))
+    }
+
+    pub(crate) fn implies(&self, consequence: impl Borrow<Bool>) -> Bool {
+        Bool(Term::new(
+            Op::Implies,
+            [self.0.clone(), consequence.borrow().0.clone()],
+        ))
+    }
+
+    pub(crate) fn ite(&self, then: &Int, otherwise: &Int) -> Int {
+        Int(Term::new(
+            Op::Ite,
+            [self.0.clone(), then.0.clone(), otherwise.0.clone()],
+        ))
+    }
+}
+
+macro_rules! int_operator {
+    ($trait:ident, $method:ident, $op:expr) => {
+        impl std::ops::$trait<Int> for Int {
+            type Output = Int;
+
+            fn $method(self, rhs: Int) -> Int {
+                Int::binary($op, &self, &rhs)
+            }
+        }
+
+        impl std::ops::$trait<&Int> for Int {
+            type Output = Int;
+
+            fn $method(self, rhs: &Int) -> Int {
+                Int::binary($op, &self, rhs)
+            }
+        }
+
+        impl std::ops::$trait<Int> for &Int {
+            type Output = Int;
+
+            fn $method(self, rhs: Int) -> Int {
+                Int::binary($op, self, &rhs)
+            }
+        }
+
+        impl std::ops::$trait<&Int> for &Int {
+            type Output = Int;
+
+            fn $method(self, rhs: &Int) -> Int {
+                Int::binary($op, self, rhs)
+            }
+        }
+
+        impl std::ops::$trait<i64> for Int {
+            type Output = Int;
+
+            fn $method(self, rhs: i64) -> Int {
+                Int::binary($op, &self, &Int::from_i64(rhs))
+            }
+        }
+
+        impl std::ops::$trait<i64> for &Int {
+            type Output = Int;
+
+            fn $method(self, rhs: i64) -> Int {
+                Int::binary($op, self, &Int::from_i64(rhs))
+            }
+        }
+    };
+}
+
+int_operator!(Add, add, Op::Add);
+int_operator!(Sub, sub, Op::Sub);
+int_operator!(Mul, mul, Op::Mul);
+
+impl std::ops::AddAssign<&Int> for Int {
+    fn add_assign(&mut self, rhs: &Int) {
+        *self = &*self + rhs;
+    }
+}
+
+impl std::ops::AddAssign<Int> for Int {
+    fn add_assign(&mut self, rhs: Int) {
+        *self = &*self + &rhs;
+    }
+}
+
+/// Quotes `name` unless it is a simple SMT-LIB2 symbol.
+pub(crate) fn smtlib_symbol(name: &str) -> String {
+    let simple = name
+        .chars()
+        .next()
+        .is_some_and(|first| !first.is_ascii_digit())
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(ch));
+    if simple {
+        name.to_string()
+    } else {
+        format!("|{name}|")
+    }
+}
+
+/// The SMT-LIB2 spelling of a leaf.
+pub(crate) fn smtlib_leaf(op: &Op) -> String {
+    match op {
+        Op::IntConst(value) if *value < 0 => format!("(- {})", value.unsigned_abs()),
+        Op::IntConst(value) => value.to_string(),
+        Op::IntVar(name) | Op::BoolVar(name) => smtlib_symbol(name),
+        _ => unreachable!("only leaves have a leaf spelling"),
+    }
+}
+
+/// Writes `term` as one nested SMT-LIB2 expression. Meant for small terms
+/// such as assumptions and debug output.
+fn write_nested(term: &Term, out: &mut String) {
+    enum Item<'t> {
+        Term(&'t Term),
+        Close,
+    }
+
+    let mut stack = vec![Item::Term(term)];
+    while let Some(item) = stack.pop() {
+        match item {
+            Item::Close => out.push(')'),
+            Item::Term(term) if term.is_leaf() => {
+                out.push(' ');
+                out.push_str(&smtlib_leaf(&term.node().op));
+            }
+            Item::Term(term) => {
+                out.push_str(" (");
+                out.push_str(term.node().op.smtlib_symbol());
+                stack.push(Item::Close);
+                stack.extend(term.node().args.iter().rev().map(Item::Term));
+            }
+        }
+    }
+}
+
+fn nested_string(term: &Term) -> String {
+    let mut out = String::new();
+    write_nested(term, &mut out);
+    out.trim_start().to_string()
+}
+
+impl Display for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", nested_string(&self.0))
+    }
+}
+
+impl Display for Bool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", nested_string(&self.0))
+    }
+}
+
+impl Debug for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Debug for Bool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
@@ -0,0 +1,353 @@
+//! Arithmetic reasoning independent of a concrete SMT solver.
+//!
+//! Encodings build [`ast`] terms and check them through an
+//! [`ArithmeticSolver`], which forwards to the selected [`ArithmeticBackend`]:
+//! Z3 through its library (behind the default `z3` feature) or any solver
+//! binary that speaks SMT-LIB2 over stdin/stdout, such as cvc5.
+//!
+//! Solvers are created from an [`ArithmeticContext`], which the reachability
+//! solver hands down to everything that checks queries on its behalf. It
+//! carries the backend selection of the solver's config, so solvers with
+//! different backends can run side by side.
+
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    sync::{Arc, atomic::AtomicBool},
+    time::Instant,
+};
+
+pub(crate) mod ast;
+mod smtlib;
+#[cfg(feature = "z3")]
+mod z3;
+
+use ast::{Bool, Int};
+use smtlib::{SMTLibProcessBackend, SMTLibWriter};
+
+use crate::{
+    config::{ArithmeticBackendConfig, ArithmeticBackendKind, SMTDumpConfig},
+    solver::smt_dump::SMTQueryDump,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SatResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+/// Bounds a single check. A check that exceeds them is unknown.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct CheckLimits<'a> {
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) stop_signal: Option<&'a AtomicBool>,
+}
+
+/// The operations encodings need from a solver: integer linear constraints
+/// (including blocking clauses for disconnected Parikh images), minimization
+/// for the template transfer, and model extraction.
+pub(crate) trait ArithmeticBackend {
+    fn assert(&mut self, constraint: &Bool);
+
+    /// Adds an objective that later checks minimize.
+    fn minimize(&mut self, objective: &Int);
+
+    fn push(&mut self);
+
+    fn pop(&mut self);
+
+    fn check(&mut self, assumptions: &[Bool], limits: CheckLimits<'_>) -> SatResult;
+
+    /// Values of `terms` in the model of the last satisfiable check.
+    /// Unconstrained variables evaluate to zero.
+    fn eval(&mut self, terms: &[&Int]) -> Option<Vec<i64>>;
+}
+
+/// How a solver is going to be used, which decides how a backend may enforce
+/// limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SolverLifetime {
+    /// Used for one query. Limits may interrupt the solver for good.
+    OneShot,
+    /// Reused across many queries. Limits never cancel the solver itself, so
+    /// a stop signal only takes effect before a check starts. Backends that
+    /// can only enforce a deadline by killing the solver do the same with the
+    /// deadline.
+    Persistent,
+}
+
+/// What the arithmetic solvers of one reachability solver share. Solvers
+/// created from a context use its backend and write their queries to its SMT
+/// dump.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ArithmeticContext {
+    backend: Arc<ArithmeticBackendConfig>,
+    dump: Option<Arc<SMTQueryDump>>,
+}
+
+impl ArithmeticContext {
+    /// The context of a new top-level solver, with a dump of its own if
+    /// dumping is enabled.
+    pub(crate) fn for_solver(backend: &ArithmeticBackendConfig, smt_dump: &SMTDumpConfig) -> Self {
+        let dump = SMTQueryDump::create(smt_dump).unwrap_or_else(|err| {
+            tracing::warn!(error = %err, "failed to initialize SMT dump; continuing without it");
+            None
+        });
+        Self {
+            backend: Arc::new(backend.clone()),
+            dump,
+        }
+    }
+
+    /// The context of a solver nested in the one of this context, with its
+    /// own subdirectory `name` of the dump.
+    pub(crate) fn nested(&self, name: &str) -> Self {
+        let dump = self.dump.as_ref().and_then(|dump| {
+            dump.nested(name)
+                .inspect_err(|err| {
+                    tracing::warn!(error = %err, "failed to initialize SMT dump; continuing without it");
+                })
+                .ok()
+        });
+        Self {
+            backend: self.backend.clone(),
+            dump,
+        }
+    }
+
+    pub(crate) fn with_dump(mut self, dump: Option<Arc<SMTQueryDump>>) -> Self {
+        self.dump = dump;
+        self
+    }
+
+    pub(crate) fn dump(&self) -> Option<&Arc<SMTQueryDump>> {
+        self.dump.as_ref()
+    }
+}
+
+/// A solver of the backend of its context. Assertions and objectives are also
+/// kept as terms, so the current query can be written as an SMT-LIB2 script.
+pub(crate) struct ArithmeticSolver {
+    backend: RefCell<Box<dyn ArithmeticBackend>>,
+    optimizer: bool,
+    lifetime: SolverLifetime,
+    dump: Option<Arc<SMTQueryDump>>,
+    /// Assertions and objectives of every open `push` scope.
+    scopes: RefCell<Vec<Vec<Command>>>,
+}
+
+#[derive(Clone)]
+enum Command {
+    Assert(Bool),
+    Minimize(Int),
+}
+
+impl ArithmeticSolver {
+    pub(crate) fn new(context: &ArithmeticContext) -> Self {
+        Self::with_lifetime(context, false, SolverLifetime::OneShot)
+    }
+
+    pub(crate) fn new_optimizer(context: &ArithmeticContext) -> Self {
+        Self::with_lifetime(context, true, SolverLifetime::OneShot)
+    }
+
+    pub(crate) fn new_persistent(context: &ArithmeticContext) -> Self {
+        Self::with_lifetime(context, false, SolverLifetime::Persistent)
+    }
+
+    fn with_lifetime(
+        context: &ArithmeticContext,
+        optimizer: bool,
+        lifetime: SolverLifetime,
+    ) -> Self {
+        Self {
+            backend: RefCell::new(create_backend(&context.backend, optimizer, lifetime)),
+            optimizer,
+            lifetime,
+            dump: context.dump.clone(),
+            scopes: RefCell::new(vec![Vec::new()]),
+        }
+    }
+
+    pub(crate) fn is_optimizer(&self) -> bool {
+        self.optimizer
+    }
+
+    /// The SMT dump of the context the solver was created from.
+    pub(crate) fn dump(&self) -> Option<&SMTQueryDump> {
+        self.dump.as_deref()
+    }
+
+    pub(crate) fn assert(&self, constraint: impl Borrow<Bool>) {
+        let constraint = constraint.borrow();
+        self.backend.borrow_mut().assert(constraint);
+        self.record(Command::Assert(constraint.clone()));
+    }
+
+    pub(crate) fn minimize(&self, objective: &Int) {
+        self.backend.borrow_mut().minimize(objective);
+        self.record(Command::Minimize(objective.clone()));
+    }
+
+    pub(crate) fn push(&self) {
+        self.backend.borrow_mut().push();
+        self.scopes.borrow_mut().push(Vec::new());
+    }
+
+    pub(crate) fn pop(&self) {
+        self.backend.borrow_mut().pop();
+        let mut scopes = self.scopes.borrow_mut();
+        if scopes.len() > 1 {
+            scopes.pop();
+        }
+    }
+
+    fn record(&self, command: Command) {
+        self.scopes
+            .borrow_mut()
+            .last_mut()
+            .expect("the base scope is never popped")
+            .push(command);
+    }
+
+    pub(crate) fn check_with(&self, assumptions: &[Bool], limits: CheckLimits<'_>) -> SatResult {
+        let limits = match self.lifetime {
+            SolverLifetime::OneShot => limits,
+            SolverLifetime::Persistent => {
+                if limits
+                    .stop_signal
+                    .is_some_and(|signal| signal.load(std::sync::atomic::Ordering::SeqCst))
+                {
+                    return SatResult::Unknown;
+                }
+                CheckLimits {
+                    stop_signal: None,
+                    ..limits
+                }
+            }
+        };
+        self.backend.borrow_mut().check(assumptions, limits)
+    }
+
+    /// The model value of `term` after a satisfiable check.
+    pub(crate) fn eval(&self, term: &Int) -> Option<i64> {
+        self.eval_many(&[term])?.pop()
+    }
+
+    pub(crate) fn eval_many(&self, terms: &[&Int]) -> Option<Vec<i64>> {
+        self.backend.borrow_mut().eval(terms)
+    }
+
+    /// The current assertions and objectives as an SMT-LIB2 script, with
+    /// `assumptions` added as assertions and without the final check.
+    pub(crate) fn to_smtlib(&self, assumptions: &[Bool]) -> String {
+        let mut writer = SMTLibWriter::default();
+        let mut out = String::new();
+        for command in self.scopes.borrow().iter().flatten() {
+            match command {
+                Command::Assert(constraint) => writer.assert(constraint, &mut out),
+                Command::Minimize(objective) => writer.minimize(objective, &mut out),
+            }
+        }
+        for assumption in assumptions {
+            writer.assert(assumption, &mut out);
+        }
+        out
+    }
+}
+
+fn create_backend(
+    config: &ArithmeticBackendConfig,
+    optimizer: bool,
+    lifetime: SolverLifetime,
+) -> Box<dyn ArithmeticBackend> {
+    match config.get_kind() {
+        #[cfg(feature = "z3")]
+        ArithmeticBackendKind::Z3 => Box::new(z3::Z3Backend::new(optimizer, lifetime)),
+        #[cfg(not(feature = "z3"))]
+        ArithmeticBackendKind::Z3 => {
+            tracing::warn!("built without the z3 feature; using the SMT-LIB2 backend instead");
+            smtlib_backend(config, optimizer, lifetime)
+        }
+        ArithmeticBackendKind::SMTLib => smtlib_backend(config, optimizer, lifetime),
+    }
+}
+
+fn smtlib_backend(
+    config: &ArithmeticBackendConfig,
+    optimizer: bool,
+    lifetime: SolverLifetime,
+) -> Box<dyn ArithmeticBackend> {
+    if optimizer && !config.get_smt_lib_optimization() {
+        tracing::debug!(
+            "SMT-LIB2 solver does not support minimize; optimization checks are unknown"
+        );
+        return Box::new(UnavailableBackend);
+    }
+
+    let command = config.get_smt_lib_command();
+    match SMTLibProcessBackend::spawn(command, lifetime) {
+        Ok(backend) => Box::new(backend),
+        Err(err) => {
+            tracing::warn!(?command, error = %err, "failed to start SMT-LIB2 solver; its checks are unknown");
+            Box::new(UnavailableBackend)
+        }
+    }
+}
+
+/// Stands in for a solver that could not be started. Every check is unknown,
+/// so callers give up the same way as on a timeout.
+struct UnavailableBackend;
+
+impl ArithmeticBackend for UnavailableBackend {
+    fn assert(&mut self, _constraint: &Bool) {}
+
+    fn minimize(&mut self, _objective: &Int) {}
+
+    fn push(&mut self) {}
+
+    fn pop(&mut self) {}
+
+    fn check(&mut self, _assumptions: &[Bool], _limits: CheckLimits<'_>) -> SatResult {
+        SatResult::Unknown
+    }
+
+    fn eval(&mut self, _terms: &[&Int]) -> Option<Vec<i64>> {
+        None
+    }
+}
+
+/// Runs `solve` with solvers that do not share state with solvers outside of
+/// it. One-shot solvers should be created in here, so that interrupting them
+/// cannot cancel anyone else.
+pub(crate) fn isolated<R: Send + Sync>(
+    context: &ArithmeticContext,
+    solve: impl FnOnce() -> R + Send + Sync,
+) -> R {
+    #[cfg(feature = "z3")]
+    if *context.backend.get_kind() == ArithmeticBackendKind::Z3 {
+        return z3::isolated(solve);
+    }
+
+    let _ = context;
+    solve()
+}
+
+/// Checks a complete SMT-LIB2 script with a fresh solver of the backend of
+/// `config` and returns the answer to its last check.
+pub(crate) fn check_script(
+    config: &ArithmeticBackendConfig,
+    script: &str,
+    optimizer: bool,
+) -> SatResult {
+    #[cfg(feature = "z3")]
+    if *config.get_kind() == ArithmeticBackendKind::Z3 {
+        return z3::check_script(script, optimizer);
+    }
+
+    if optimizer && !config.get_smt_lib_optimization() {
+        return SatResult::Unknown;
+    }
+    smtlib::check_script(config.get_smt_lib_command(), script)
+}
@@ -0,0 +1,533 @@
+//! SMT-LIB2 scripts and a backend that talks to a solver process over
+//! stdin/stdout.
+//!
+//! Composite integer terms with composite arguments become `define-fun`s, so
+//! the long counter-sum chains of an encoding are written once and never
+//! nested deeply. Declarations and definitions follow the `push`/`pop` scopes
+//! of the script.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use hashbrown::{HashMap, HashSet};
+
+use super::{
+    ArithmeticBackend, CheckLimits, SatResult, SolverLifetime,
+    ast::{Bool, Int, Op, Term, smtlib_leaf, smtlib_symbol},
+};
+
+/// Turns terms into SMT-LIB2 commands, remembering what has been declared
+/// and defined so far.
+#[derive(Default)]
+pub(super) struct SMTLibWriter {
+    declared: HashSet<Arc<str>>,
+    defined: HashMap<usize, (Term, String)>,
+    /// Declared names and defined terms of every open `push` scope.
+    scopes: Vec<(Vec<Arc<str>>, Vec<usize>)>,
+    next_definition: usize,
+}
+
+impl SMTLibWriter {
+    pub(super) fn assert(&mut self, constraint: &Bool, out: &mut String) {
+        let expression = self.expression(&constraint.0, out);
+        out.push_str(&format!("(assert {expression})\n"));
+    }
+
+    pub(super) fn minimize(&mut self, objective: &Int, out: &mut String) {
+        let expression = self.expression(&objective.0, out);
+        out.push_str(&format!("(minimize {expression})\n"));
+    }
+
+    pub(super) fn push(&mut self, out: &mut String) {
+        self.scopes.push(Default::default());
+        out.push_str("(push 1)\n");
+    }
+
+    pub(super) fn pop(&mut self, out: &mut String) {
+        if let Some((names, terms)) = self.scopes.pop() {
+            for name in names {
+                self.declared.remove(&name);
+            }
+            for term in terms {
+                self.defined.remove(&term);
+            }
+        }
+        out.push_str("(pop 1)\n");
+    }
+
+    /// Writes the declarations and definitions `term` needs to `out` and
+    /// returns its expression.
+    pub(super) fn expression(&mut self, term: &Term, out: &mut String) -> String {
+        let mut visited = HashSet::new();
+        let mut stack = vec![(term.clone(), false)];
+
+        while let Some((term, expanded)) = stack.pop() {
+            if self.defined.contains_key(&term.key()) {
+                continue;
+            }
+
+            if !expanded {
+                if !visited.insert(term.key()) {
+                    continue;
+                }
+                stack.push((term.clone(), true));
+                stack.extend(term.node().args.iter().map(|arg| (arg.clone(), false)));
+                continue;
+            }
+
+            match &term.node().op {
+                Op::IntVar(name) => self.declare(name, "Int", out),
+                Op::BoolVar(name) => self.declare(name, "Bool", out),
+                _ if term.is_int() && term.node().args.iter().any(|arg| !arg.is_leaf()) => {
+                    let body = self.inline(&term);
+                    let name = format!("_t{}", self.next_definition);
+                    self.next_definition += 1;
+                    out.push_str(&format!("(define-fun {name} () Int {body})\n"));
+                    if let Some((_, terms)) = self.scopes.last_mut() {
+                        terms.push(term.key());
+                    }
+                    self.defined.insert(term.key(), (term, name));
+                }
+                _ => {}
+            }
+        }
+
+        self.inline(term)
+    }
+
+    fn declare(&mut self, name: &Arc<str>, sort: &str, out: &mut String) {
+        if self.declared.insert(name.clone()) {
+            out.push_str(&format!(
+                "(declare-fun {} () {sort})\n",
+                smtlib_symbol(name)
+            ));
+            if let Some((names, _)) = self.scopes.last_mut() {
+                names.push(name.clone());
+            }
+        }
+    }
+
+    /// Spells out `term` down to leaves and defined terms. Only boolean
+    /// structure and integer terms over leaves are nested, so the recursion
+    /// stays shallow.
+    fn inline(&self, term: &Term) -> String {
+        if term.is_leaf() {
+            return smtlib_leaf(&term.node().op);
+        }
+
+        let args = term
+            .node()
+            .args
+            .iter()
+            .map(|arg| match self.defined.get(&arg.key()) {
+                Some((_, name)) => name.clone(),
+                None => self.inline(arg),
+            })
+            .collect::<Vec<_>>();
+
+        match (&term.node().op, args.is_empty()) {
+            (Op::And, true) => "true".to_string(),
+            (Op::Or, true) => "false".to_string(),
+            (op, _) => format!("({} {})", op.smtlib_symbol(), args.join(" ")),
+        }
+    }
+}
+
+/// A solver process that reads SMT-LIB2 commands from stdin.
+///
+/// Time limits and stop signals kill one-shot processes, since there is no
+/// portable way to interrupt a running check. Persistent processes are never
+/// killed, their checks are only skipped once the deadline has passed. Every
+/// later check of a killed process is unknown.
+///
+/// Commands other than checks and `get-value` are sent without waiting for a
+/// reply. A solver that rejects one of them answers with an `(error ...)`,
+/// which ends up in front of the answer to the next check. Such an error is
+/// treated like a crash, so answers never get out of step with their checks.
+pub(super) struct SMTLibProcessBackend {
+    process: Arc<Mutex<Child>>,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    writer: SMTLibWriter,
+    lifetime: SolverLifetime,
+    /// Cleared once the process has died, been killed or reported an error.
+    alive: bool,
+}
+
+impl SMTLibProcessBackend {
+    pub(super) fn spawn(command: &[String], lifetime: SolverLifetime) -> std::io::Result<Self> {
+        let (program, args) = command.split_first().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty solver command")
+        })?;
+        let mut process = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = process.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(process.stdout.take().expect("stdout is piped"));
+
+        let mut backend = Self {
+            process: Arc::new(Mutex::new(process)),
+            stdin,
+            stdout,
+            writer: SMTLibWriter::default(),
+            lifetime,
+            alive: true,
+        };
+        backend.send(
+            "(set-option :print-success false)\n(set-option :produce-models true)\n(set-logic ALL)\n",
+        );
+        Ok(backend)
+    }
+
+    fn send(&mut self, commands: &str) {
+        if self.alive && self.stdin.write_all(commands.as_bytes()).is_err() {
+            self.alive = false;
+        }
+    }
+
+    /// Reads one complete response, which may span several lines.
+    fn read_response(&mut self) -> Option<String> {
+        let mut response = String::new();
+        let mut depth = 0i64;
+
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            if response.is_empty() && line.trim().is_empty() {
+                continue;
+            }
+
+            depth += line.matches('(').count() as i64 - line.matches(')').count() as i64;
+            response.push_str(&line);
+            if depth <= 0 {
+                return Some(response.trim().to_string());
+            }
+        }
+    }
+
+    /// Sends `command` and waits for its response, killing one-shot processes
+    /// once the limits are exceeded.
+    fn query(&mut self, command: &str, limits: CheckLimits<'_>) -> Option<String> {
+        if !self.alive {
+            return None;
+        }
+        let watch = match self.lifetime {
+            SolverLifetime::OneShot => limits.deadline.is_some() || limits.stop_signal.is_some(),
+            SolverLifetime::Persistent => {
+                if limits
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return None;
+                }
+                false
+            }
+        };
+        self.send(command);
+        let _ = self.stdin.flush();
+
+        let done = AtomicBool::new(false);
+        let process = self.process.clone();
+        let response = thread::scope(|scope| {
+            if watch {
+                scope.spawn(|| {
+                    while !done.load(Ordering::SeqCst) {
+                        let stop = limits
+                            .stop_signal
+                            .is_some_and(|signal| signal.load(Ordering::SeqCst));
+                        let expired = limits
+                            .deadline
+                            .is_some_and(|deadline| Instant::now() >= deadline);
+                        if stop || expired {
+                            let _ = process.lock().unwrap().kill();
+                            break;
+                        }
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                });
+            }
+
+            let response = self.read_response();
+            done.store(true, Ordering::SeqCst);
+            response
+        });
+
+        match response {
+            Some(response) if is_error(&response) => {
+                tracing::warn!(
+                    response,
+                    "SMT-LIB solver reported an error; its checks are unknown"
+                );
+                self.alive = false;
+                let _ = self.process.lock().unwrap().kill();
+                None
+            }
+            None => {
+                self.alive = false;
+                None
+            }
+            response => response,
+        }
+    }
+}
+
+impl ArithmeticBackend for SMTLibProcessBackend {
+    fn assert(&mut self, constraint: &Bool) {
+        let mut out = String::new();
+        self.writer.assert(constraint, &mut out);
+        self.send(&out);
+    }
+
+    fn minimize(&mut self, objective: &Int) {
+        let mut out = String::new();
+        self.writer.minimize(objective, &mut out);
+        self.send(&out);
+    }
+
+    fn push(&mut self) {
+        let mut out = String::new();
+        self.writer.push(&mut out);
+        self.send(&out);
+    }
+
+    fn pop(&mut self) {
+        let mut out = String::new();
+        self.writer.pop(&mut out);
+        self.send(&out);
+    }
+
+    fn check(&mut self, assumptions: &[Bool], limits: CheckLimits<'_>) -> SatResult {
+        let mut command = String::new();
+        if assumptions.is_empty() {
+            command.push_str("(check-sat)\n");
+        } else {
+            let literals = assumptions
+                .iter()
+                .map(|assumption| self.writer.expression(&assumption.0, &mut command))
+                .collect::<Vec<_>>();
+            command.push_str(&format!("(check-sat-assuming ({}))\n", literals.join(" ")));
+        }
+
+        match self.query(&command, limits).as_deref() {
+            Some("sat") => SatResult::Sat,
+            Some("unsat") => SatResult::Unsat,
+            Some("unknown") => SatResult::Unknown,
+            Some(response) => {
+                tracing::warn!(response, "unexpected response from SMT-LIB solver");
+                // its next answer could belong to this check
+                self.alive = false;
+                let _ = self.process.lock().unwrap().kill();
+                SatResult::Unknown
+            }
+            None => SatResult::Unknown,
+        }
+    }
+
+    fn eval(&mut self, terms: &[&Int]) -> Option<Vec<i64>> {
+        if terms.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let mut command = String::new();
+        let expressions = terms
+            .iter()
+            .map(|term| self.writer.expression(&term.0, &mut command))
+            .collect::<Vec<_>>();
+        command.push_str(&format!("(get-value ({}))\n", expressions.join(" ")));
+
+        let response = self.query(&command, CheckLimits::default())?;
+        let values = parse_values(&response)?;
+        (values.len() == terms.len()).then_some(values)
+    }
+}
+
+impl Drop for SMTLibProcessBackend {
+    fn drop(&mut self) {
+        let _ = self.stdin.write_all(b"(exit)\n");
+        let mut process = self.process.lock().unwrap();
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+}
+
+fn is_error(response: &str) -> bool {
+    response.starts_with("(error")
+}
+
+/// Reads the integer values of a `get-value` response
+/// `((t1 v1) (t2 v2) ...)`.
+fn parse_values(response: &str) -> Option<Vec<i64>> {
+    let SExpr::List(pairs) = SExpr::parse(response)? else {
+        return None;
+    };
+
+    pairs
+        .iter()
+        .map(|pair| match pair {
+            SExpr::List(items) if items.len() == 2 => items[1].as_integer(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs a complete script, e.g. a dumped query, and returns the answer to its
+/// last check.
+pub(super) fn check_script(command: &[String], script: &str) -> SatResult {
+    let Ok(mut backend) = SMTLibProcessBackend::spawn(command, SolverLifetime::OneShot) else {
+        return SatResult::Unknown;
+    };
+
+    backend.send(script);
+    let _ = backend.stdin.flush();
+
+    let mut result = SatResult::Unknown;
+    for _ in 0..script.matches("(check-sat").count() {
+        result = match backend.read_response().as_deref() {
+            Some("sat") => SatResult::Sat,
+            Some("unsat") => SatResult::Unsat,
+            // later answers would be out of step with their checks
+            Some(response) if is_error(response) => return SatResult::Unknown,
+            _ => SatResult::Unknown,
+        };
+    }
+    result
+}
+
+enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>),
+}
+
+impl SExpr {
+    fn parse(input: &str) -> Option<SExpr> {
+        let tokens = input
+            .replace('(', " ( ")
+            .replace(')', " ) ")
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let mut stack: Vec<Vec<SExpr>> = vec![Vec::new()];
+        for token in tokens {
+            match token.as_str() {
+                "(" => stack.push(Vec::new()),
+                ")" => {
+                    let list = stack.pop()?;
+                    stack.last_mut()?.push(SExpr::List(list));
+                }
+                _ => stack.last_mut()?.push(SExpr::Atom(token)),
+            }
+        }
+
+        let mut top = stack.pop()?;
+        (stack.is_empty() && top.len() == 1).then(|| top.remove(0))
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        match self {
+            SExpr::Atom(atom) => atom.parse().ok(),
+            SExpr::List(items) => match items.as_slice() {
+                [SExpr::Atom(minus), value] if minus == "-" => value.as_integer().map(|v| -v),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::SMTLibProcessBackend;
+    use crate::{
+        config::{ArithmeticBackendConfig, ArithmeticBackendKind},
+        solver::backend::{
+            ArithmeticBackend, CheckLimits, SatResult, SolverLifetime,
+            ast::{Bool, Int},
+            create_backend,
+        },
+    };
+
+    /// Answers every check with `unsat` and rejects assertions of `x` with an
+    /// error.
+    fn stub_command() -> Vec<String> {
+        let script = r#"while IFS= read -r line; do
+  case "$line" in
+    *check-sat*) echo unsat ;;
+    *'(assert'*x*) echo '(error "unsupported assertion")' ;;
+    *'(exit)'*) exit 0 ;;
+  esac
+done"#;
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    fn stub(lifetime: SolverLifetime) -> SMTLibProcessBackend {
+        SMTLibProcessBackend::spawn(&stub_command(), lifetime).unwrap()
+    }
+
+    #[test]
+    fn errors_keep_answers_in_step_with_checks() {
+        let mut backend = stub(SolverLifetime::OneShot);
+        backend.assert(&Bool::new_const("y"));
+        assert_eq!(backend.check(&[], CheckLimits::default()), SatResult::Unsat);
+
+        // the error comes before the answer, later answers would be one check
+        // behind
+        backend.assert(&Int::new_const("x").ge(Int::from_i64(0)));
+        assert_eq!(
+            backend.check(&[], CheckLimits::default()),
+            SatResult::Unknown
+        );
+        assert_eq!(
+            backend.check(&[], CheckLimits::default()),
+            SatResult::Unknown
+        );
+    }
+
+    #[test]
+    fn expired_deadlines_skip_persistent_checks() {
+        let mut backend = stub(SolverLifetime::Persistent);
+        let expired = CheckLimits {
+            deadline: Some(Instant::now()),
+            stop_signal: None,
+        };
+
+        assert_eq!(backend.check(&[], expired), SatResult::Unknown);
+        assert_eq!(backend.check(&[], CheckLimits::default()), SatResult::Unsat);
+    }
+
+    #[test]
+    fn optimizers_are_unknown_without_smt_lib_optimization() {
+        let config = ArithmeticBackendConfig::default()
+            .with_kind(ArithmeticBackendKind::SMTLib)
+            .with_smt_lib_command(stub_command());
+
+        let mut optimizer = create_backend(&config, true, SolverLifetime::OneShot);
+        assert_eq!(
+            optimizer.check(&[], CheckLimits::default()),
+            SatResult::Unsat
+        );
+
+        let config = config.with_smt_lib_optimization(false);
+        let mut optimizer = create_backend(&config, true, SolverLifetime::OneShot);
+        assert_eq!(
+            optimizer.check(&[], CheckLimits::default()),
+            SatResult::Unknown
+        );
+        let mut solver = create_backend(&config, false, SolverLifetime::OneShot);
+        assert_eq!(solver.check(&[], CheckLimits::default()), SatResult::Unsat);
+    }
+}
@@ -0,0 +1,239 @@
+//! The Z3 backend, using the library through the `z3` crate.
+//!
+//! Z3 contexts are thread-local. Interrupting a context cancels it for good,
+//! so only one-shot solvers are interrupted, and only while a check is running
+//! on a stop signal. Deadlines become the solver's timeout.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+use z3::{Config, Context, Optimize, Params, Solver, with_z3_config};
+
+use super::{
+    ArithmeticBackend, CheckLimits, SatResult, SolverLifetime,
+    ast::{Bool, Int, Op, Term, fold_term},
+};
+
+enum Engine {
+    Solver(Solver),
+    Optimize(Optimize),
+}
+
+#[derive(Clone)]
+enum Ast {
+    Int(z3::ast::Int),
+    Bool(z3::ast::Bool),
+}
+
+impl Ast {
+    fn int(&self) -> &z3::ast::Int {
+        match self {
+            Ast::Int(int) => int,
+            Ast::Bool(_) => unreachable!("terms are well-sorted"),
+        }
+    }
+
+    fn bool(&self) -> &z3::ast::Bool {
+        match self {
+            Ast::Bool(bool) => bool,
+            Ast::Int(_) => unreachable!("terms are well-sorted"),
+        }
+    }
+}
+
+pub(super) struct Z3Backend {
+    engine: Engine,
+    lifetime: SolverLifetime,
+    /// Translated terms, so shared subterms are translated once.
+    translated: HashMap<usize, (Term, Ast)>,
+}
+
+impl Z3Backend {
+    pub(super) fn new(optimizer: bool, lifetime: SolverLifetime) -> Self {
+        Self {
+            engine: if optimizer {
+                Engine::Optimize(Optimize::new())
+            } else {
+                Engine::Solver(Solver::new())
+            },
+            lifetime,
+            translated: HashMap::new(),
+        }
+    }
+
+    fn translate(&mut self, term: &Term) -> Ast {
+        fold_term(term, &mut self.translated, |term, args| {
+            let ints = || args.iter().map(Ast::int).collect::<Vec<_>>();
+            let bools = || args.iter().map(Ast::bool).collect::<Vec<_>>();
+
+            match &term.node().op {
+                Op::IntConst(value) => Ast::Int(z3::ast::Int::from_i64(*value)),
+                Op::IntVar(name) => Ast::Int(z3::ast::Int::new_const(name.as_ref())),
+                Op::BoolVar(name) => Ast::Bool(z3::ast::Bool::new_const(name.as_ref())),
+                Op::Add => Ast::Int(z3::ast::Int::add(&ints())),
+                Op::Sub => Ast::Int(z3::ast::Int::sub(&ints())),
+                Op::Mul => Ast::Int(z3::ast::Int::mul(&ints())),
+                Op::Mod => Ast::Int(args[0].int().modulo(args[1].int())),
+                Op::Ite => match &args[1] {
+                    Ast::Int(then) => Ast::Int(args[0].bool().ite(then, args[2].int())),
+                    Ast::Bool(then) => Ast::Bool(args[0].bool().ite(then, args[2].bool())),
+                },
+                Op::Not => Ast::Bool(args[0].bool().not()),
+                Op::And => Ast::Bool(z3::ast::Bool::and(&bools())),
+                Op::Or => Ast::Bool(z3::ast::Bool::or(&bools())),
+                Op::Implies => Ast::Bool(args[0].bool().implies(args[1].bool())),
+                Op::Eq => Ast::Bool(args[0].int().eq(args[1].int())),
+                Op::Le => Ast::Bool(args[0].int().le(args[1].int())),
+                Op::Lt => Ast::Bool(args[0].int().lt(args[1].int())),
+                Op::Ge => Ast::Bool(args[0].int().ge(args[1].int())),
+                Op::Gt => Ast::Bool(args[0].int().gt(args[1].int())),
+            }
+        })
+    }
+
+    fn set_timeout(&self, deadline: Option<Instant>) {
+        let timeout = match deadline {
+            Some(deadline) => u32::try_from(
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_millis(),
+            )
+            .unwrap_or(u32::MAX),
+            None => u32::MAX,
+        };
+
+        let mut params = Params::new();
+        params.set_u32("timeout", timeout);
+        match &self.engine {
+            Engine::Solver(solver) => solver.set_params(&params),
+            Engine::Optimize(optimizer) => optimizer.set_params(&params),
+        }
+    }
+}
+
+impl ArithmeticBackend for Z3Backend {
+    fn assert(&mut self, constraint: &Bool) {
+        let constraint = self.translate(&constraint.0);
+        match &self.engine {
+            Engine::Solver(solver) => solver.assert(constraint.bool()),
+            Engine::Optimize(optimizer) => optimizer.assert(constraint.bool()),
+        }
+    }
+
+    fn minimize(&mut self, objective: &Int) {
+        let objective = self.translate(&objective.0);
+        match &self.engine {
+            Engine::Solver(_) => panic!("only optimizers can minimize"),
+            Engine::Optimize(optimizer) => optimizer.minimize(objective.int()),
+        }
+    }
+
+    fn push(&mut self) {
+        match &self.engine {
+            Engine::Solver(solver) => solver.push(),
+            Engine::Optimize(optimizer) => optimizer.push(),
+        }
+    }
+
+    fn pop(&mut self) {
+        match &self.engine {
+            Engine::Solver(solver) => solver.pop(1),
+            Engine::Optimize(optimizer) => optimizer.pop(),
+        }
+    }
+
+    fn check(&mut self, assumptions: &[Bool], limits: CheckLimits<'_>) -> SatResult {
+        let assumptions = assumptions
+            .iter()
+            .map(|assumption| self.translate(&assumption.0).bool().clone())
+            .collect::<Vec<_>>();
+        self.set_timeout(limits.deadline);
+
+        let check = || match &self.engine {
+            Engine::Solver(solver) => solver.check_assumptions(&assumptions),
+            Engine::Optimize(optimizer) => optimizer.check(&assumptions),
+        };
+
+        let result = match (self.lifetime, limits.stop_signal) {
+            (SolverLifetime::OneShot, Some(stop_signal)) => interruptible(stop_signal, check),
+            _ => check(),
+        };
+
+        match result {
+            z3::SatResult::Sat => SatResult::Sat,
+            z3::SatResult::Unsat => SatResult::Unsat,
+            z3::SatResult::Unknown => SatResult::Unknown,
+        }
+    }
+
+    fn eval(&mut self, terms: &[&Int]) -> Option<Vec<i64>> {
+        let model = match &self.engine {
+            Engine::Solver(solver) => solver.get_model(),
+            Engine::Optimize(optimizer) => optimizer.get_model(),
+        }?;
+
+        terms
+            .iter()
+            .map(|term| {
+                let term = self.translate(&term.0);
+                model.eval(term.int(), true)?.as_i64()
+            })
+            .collect()
+    }
+}
+
+/// Runs `check` while a watcher interrupts the thread-local context once
+/// `stop_signal` is raised. The watcher stops without interrupting as soon as
+/// the check returns.
+fn interruptible(stop_signal: &AtomicBool, check: impl FnOnce() -> z3::SatResult) -> z3::SatResult {
+    let context = Context::thread_local();
+    let context_handle = context.handle();
+    let done = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::SeqCst) {
+                if stop_signal.load(Ordering::SeqCst) {
+                    context_handle.interrupt();
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let result = check();
+        done.store(true, Ordering::SeqCst);
+        result
+    })
+}
+
+/// Runs `solve` in a fresh context with model generation.
+pub(super) fn isolated<R: Send + Sync>(solve: impl FnOnce() -> R + Send + Sync) -> R {
+    let mut config = Config::new();
+    config.set_model_generation(true);
+    with_z3_config(&config, solve)
+}
+
+pub(super) fn check_script(script: &str, optimizer: bool) -> SatResult {
+    let result = isolated(|| {
+        if optimizer {
+            let optimizer = Optimize::new();
+            optimizer.from_string(script);
+            optimizer.check(&[])
+        } else {
+            let solver = Solver::new();
+            solver.from_string(script);
+            solver.check()
+        }
+    });
+
+    match result {
+        z3::SatResult::Sat => SatResult::Sat,
+        z3::SatResult::Unsat => SatResult::Unsat,
+        z3::SatResult::Unknown => SatResult::Unknown,
+    }
+}
@@ -0,0 +1,432 @@
+//! Constraint encoding shared by one-shot solves and incremental sessions.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use petgraph::graph::EdgeIndex;
+
+use super::{
+    LinearGraphBoundaryConstraints, LinearGraphCongruence, LinearGraphReachSolverError,
+    LinearGraphReachSolverStatus, LinearGraphSolution,
+};
+use crate::{
+    automaton::{
+        AutomatonIterators, ExplicitEdgeAutomaton, GIndex,
+        index_map::OptionIndexMap,
+        linear_graph::{
+            LinearGraph, LinearGraphAutomaton,
+            part::{
+                LinearGraphPart, LinearGraphPathSegment, LinearGraphRegion, LinearGraphRepeatPath,
+            },
+        },
+        utils::cfg_updates_to_counter_updates,
+        vass::counter::VASSCounterValuation,
+    },
+    solver::{
+        SolverStatus,
+        backend::{
+            ArithmeticSolver, CheckLimits, SatResult,
+            ast::{Bool, Int},
+        },
+        smt_dump::check_solver,
+        utils::{
+            add_cfg_update_to_sums, assert_non_negative, forbid_parikh_image,
+            parikh_image_from_edge_map,
+        },
+    },
+};
+
+/// Asserts constraints either unconditionally or under a selector literal, so
+/// that alternative encodings can share one solver.
+pub(super) struct ConstraintScope<'s> {
+    solver: &'s ArithmeticSolver,
+    guard: Option<Bool>,
+}
+
+impl<'s> ConstraintScope<'s> {
+    pub(super) fn new(solver: &'s ArithmeticSolver) -> Self {
+        Self {
+            solver,
+            guard: None,
+        }
+    }
+
+    pub(super) fn guarded(solver: &'s ArithmeticSolver, guard: Bool) -> Self {
+        Self {
+            solver,
+            guard: Some(guard),
+        }
+    }
+
+    pub(super) fn solver(&self) -> &'s ArithmeticSolver {
+        self.solver
+    }
+
+    pub(super) fn assert(&self, constraint: &Bool) {
+        match &self.guard {
+            Some(guard) => self.solver.assert(guard.implies(constraint)),
+            None => self.solver.assert(constraint),
+        }
+    }
+}
+
+/// Variables of one encoded LinearGraph. Edge maps and repeat counts follow
+/// the graph and repeat-path indices of the LinearGraph.
+pub(super) struct LinearGraphEncoding {
+    pub(super) edge_maps: Vec<OptionIndexMap<EdgeIndex, Int>>,
+    pub(super) repeat_counts: Vec<Int>,
+    /// Counter sums at every part boundary, including the final one.
+    pub(super) boundary_sums: Vec<Box<[Int]>>,
+}
+
+/// Encodes every part of `linear_graph` on top of `sums`. Variable names get
+/// `prefix`, so several LinearGraphs can be encoded into one solver.
+/// `on_boundary` is called with the sums at every part boundary.
+pub(super) fn encode_linear_graph<NIndex: GIndex, A: LinearGraphAutomaton<NIndex>>(
+    linear_graph: &LinearGraph<'_, NIndex, A>,
+    prefix: &str,
+    scope: &ConstraintScope,
+    mut sums: Box<[Int]>,
+    mut on_boundary: impl FnMut(usize, &NIndex, &[Int]),
+) -> LinearGraphEncoding {
+    let mut edge_maps = Vec::new();
+    let mut repeat_counts = vec![None; linear_graph.repeat_paths.len()];
+    let mut boundary_sums = Vec::with_capacity(linear_graph.sequence.len() + 1);
+
+    for (i, part) in linear_graph.sequence.iter().enumerate() {
+        on_boundary(i, part.start(linear_graph), &sums);
+        boundary_sums.push(sums.clone());
+
+        match part {
+            LinearGraphPart::Path(idx) => {
+                encode_path(
+                    linear_graph.path(*idx),
+                    linear_graph.dimension,
+                    scope,
+                    &mut sums,
+                );
+            }
+            LinearGraphPart::Graph(idx) => {
+                let edge_map = encode_graph(
+                    &format!("{prefix}graph_{i}"),
+                    linear_graph.graph(*idx),
+                    scope,
+                    &mut sums,
+                );
+                edge_maps.push(edge_map);
+            }
+            LinearGraphPart::RepeatPath(idx) => {
+                let count = encode_repeat_path(
+                    &format!("{prefix}repeat_path_{i}"),
+                    linear_graph.repeat_path(*idx),
+                    linear_graph.dimension,
+                    scope,
+                    &mut sums,
+                );
+                repeat_counts[*idx] = Some(count);
+            }
+        }
+    }
+
+    if let Some(last) = linear_graph.sequence.last() {
+        on_boundary(linear_graph.sequence.len(), last.end(linear_graph), &sums);
+    }
+    boundary_sums.push(sums);
+
+    LinearGraphEncoding {
+        edge_maps,
+        repeat_counts: repeat_counts
+            .into_iter()
+            .map(|count| count.expect("every repeated path must have a variable"))
+            .collect(),
+        boundary_sums,
+    }
+}
+
+fn encode_path<NIndex: GIndex>(
+    path: &LinearGraphPathSegment<NIndex>,
+    dimension: usize,
+    scope: &ConstraintScope,
+    sums: &mut [Int],
+) {
+    let path_updates =
+        cfg_updates_to_counter_updates(path.path.transitions.iter().cloned(), dimension);
+
+    // first subtract the minimums
+    for (update, sum) in path_updates.0.iter().zip(sums.iter_mut()) {
+        let update_ast = Int::from_i64(*update);
+        *sum = &*sum - &update_ast;
+    }
+
+    // then assert non-negativity
+    for sum in sums.iter() {
+        let zero = Int::from_i64(0);
+        let geq_zero = sum.ge(&zero);
+        scope.assert(&geq_zero);
+    }
+
+    // then add the rest to get the path's effect
+    for (update, sum) in path_updates.1.iter().zip(sums.iter_mut()) {
+        let update_ast = Int::from_i64(*update);
+        *sum = &*sum + &update_ast;
+    }
+}
+
+fn encode_graph<NIndex: GIndex>(
+    name: &str,
+    graph: &LinearGraphRegion<NIndex>,
+    scope: &ConstraintScope,
+    sums: &mut [Int],
+) -> OptionIndexMap<EdgeIndex, Int> {
+    let mut edge_map = OptionIndexMap::new(graph.edge_count());
+
+    for (edge, update) in graph.iter_edges() {
+        // we need one variable for each edge
+        let edge_var = Int::new_const(format!("{name}_edge_{}", edge.index()));
+        // CONSTRAINT: an edge can only be taken positive times
+        assert_non_negative(scope.solver(), &edge_var);
+
+        // add the edges effect to the counter sum
+        add_cfg_update_to_sums(sums, &edge_var, update);
+
+        edge_map.insert(edge, edge_var);
+    }
+
+    for node in graph.iter_node_indices() {
+        let outgoing = graph.outgoing_edge_indices(&node);
+        let incoming = graph.incoming_edge_indices(&node);
+
+        // the end node has one additional outgoing connection, this works, because we
+        // always have exactly one end node
+        let mut outgoing_sum = if node == graph.end {
+            Int::from_i64(1)
+        } else {
+            Int::from_i64(0)
+        };
+        // the start node has one additional incoming connection
+        let mut incoming_sum = if node == graph.start {
+            Int::from_i64(1)
+        } else {
+            Int::from_i64(0)
+        };
+
+        for edge in outgoing {
+            let edge_var = &edge_map[edge];
+            outgoing_sum += edge_var;
+        }
+
+        for edge in incoming {
+            let edge_var = &edge_map[edge];
+            incoming_sum += edge_var;
+        }
+
+        // CONSTRAINT: the sum of all outgoing edges must be equal to the sum of all
+        // incoming edges for each node
+        scope.assert(&outgoing_sum.eq(&incoming_sum));
+    }
+
+    edge_map
+}
+
+fn encode_repeat_path<NIndex: GIndex>(
+    name: &str,
+    repeated: &LinearGraphRepeatPath<NIndex>,
+    dimension: usize,
+    scope: &ConstraintScope,
+    sums: &mut [Int],
+) -> Int {
+    let count = Int::new_const(format!("{name}_count"));
+    let one = Int::from_i64(1);
+    assert_non_negative(scope.solver(), &count);
+
+    let (required, after_credit) =
+        cfg_updates_to_counter_updates(repeated.path.transitions.iter().cloned(), dimension);
+    let positive_count = count.gt(Int::from_i64(0));
+
+    for i in 0..dimension {
+        let required_value = required[i];
+        let effect = after_credit[i] - required_value;
+        let required_ast = Int::from_i64(required_value);
+        scope.assert(&positive_count.implies(sums[i].ge(&required_ast)));
+
+        if effect < 0 {
+            let last_iteration_start = &sums[i] + (&count - &one) * Int::from_i64(effect);
+            scope.assert(&positive_count.implies(last_iteration_start.ge(&required_ast)));
+        }
+
+        sums[i] = &sums[i] + &count * Int::from_i64(effect);
+    }
+
+    count
+}
+
+pub(super) fn encode_boundary_constraints(
+    constraints: &LinearGraphBoundaryConstraints,
+    scope: &ConstraintScope,
+    sums: &[Int],
+) {
+    for template in &constraints.lower_bounds {
+        scope.assert(
+            &template_value(&template.coefficients, sums).ge(Int::from_i64(template.bound)),
+        );
+    }
+
+    for template in &constraints.upper_bounds {
+        scope.assert(
+            &template_value(&template.coefficients, sums).le(Int::from_i64(template.bound)),
+        );
+    }
+
+    for template in &constraints.equalities {
+        scope.assert(
+            &template_value(&template.coefficients, sums).eq(Int::from_i64(template.value)),
+        );
+    }
+
+    for congruence in &constraints.congruences {
+        scope.assert(&congruence_constraint(congruence, sums));
+    }
+}
+
+fn template_value(coefficients: &[i64], sums: &[Int]) -> Int {
+    sums.iter()
+        .zip(coefficients.iter())
+        .filter(|(_, coefficient)| **coefficient != 0)
+        .fold(Int::from_i64(0), |value, (sum, coefficient)| {
+            value + sum * Int::from_i64(*coefficient)
+        })
+}
+
+fn congruence_constraint(congruence: &LinearGraphCongruence, sums: &[Int]) -> Bool {
+    let residue = sums[congruence.counter].modulo(Int::from_i64(congruence.modulus));
+    let options = congruence
+        .residues
+        .iter()
+        .map(|value| residue.eq(Int::from_i64(*value)))
+        .collect::<Vec<_>>();
+    Bool::or(&options)
+}
+
+/// The encoded graph parts and repeat counts a check reads its model from, in
+/// the graph and repeat-path order of the checked LinearGraph.
+pub(super) struct ModelVariables<'v, NIndex: GIndex> {
+    pub(super) graphs: Vec<(
+        &'v LinearGraphRegion<NIndex>,
+        &'v OptionIndexMap<EdgeIndex, Int>,
+    )>,
+    pub(super) repeat_counts: Vec<&'v Int>,
+}
+
+/// Checks the solver under `assumptions` and blocks disconnected Parikh images
+/// until a connected model is found. Blocking clauses are asserted without the
+/// assumptions, because every run of a graph has a connected Parikh image.
+/// Every check is bounded by `deadline` and `stop_signal`.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn solve_connected<NIndex: GIndex>(
+    solver: &ArithmeticSolver,
+    assumptions: &[Bool],
+    variables: &ModelVariables<'_, NIndex>,
+    initial_valuation: &VASSCounterValuation,
+    final_valuation: &VASSCounterValuation,
+    max_iterations: Option<u32>,
+    stop_signal: &AtomicBool,
+    deadline: Option<Instant>,
+    step_count: &mut u32,
+) -> LinearGraphReachSolverStatus {
+    *step_count = 1;
+    let limits = CheckLimits {
+        deadline,
+        stop_signal: Some(stop_signal),
+    };
+
+    loop {
+        match check_solver(
+            solver,
+            assumptions,
+            limits,
+            "linear_graph_reach",
+            Some(*step_count),
+        ) {
+            SatResult::Sat => {
+                let parikh_image_components = variables
+                    .graphs
+                    .iter()
+                    .map(|(graph, map)| {
+                        let image = parikh_image_from_edge_map(map, solver);
+
+                        let (main_component, components) =
+                            image.split_into_connected_components(*graph);
+
+                        (*graph, *map, main_component, components)
+                    })
+                    .collect::<Vec<_>>();
+
+                if parikh_image_components
+                    .iter()
+                    .all(|(_, _, _, c)| c.is_empty())
+                {
+                    return SolverStatus::True(LinearGraphSolution {
+                        sub_graph_parikh_images: parikh_image_components
+                            .into_iter()
+                            .map(|(_, _, main_component, _)| main_component)
+                            .collect(),
+                        repeat_path_counts: variables
+                            .repeat_counts
+                            .iter()
+                            .map(|var| {
+                                let count =
+                                    solver.eval(var).expect("repeat count must be in the model");
+                                u32::try_from(count)
+                                    .expect("repeat count must be a non-negative integer")
+                            })
+                            .collect(),
+                        initial_valuation: initial_valuation.clone(),
+                        final_valuation: final_valuation.clone(),
+                    });
+                }
+
+                if max_iterations.is_some_and(|max| max <= *step_count) {
+                    return SolverStatus::Unknown(
+                        LinearGraphReachSolverError::MaxIterationsReached,
+                    );
+                }
+
+                if stop_signal.load(Ordering::SeqCst)
+                    || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return SolverStatus::Unknown(LinearGraphReachSolverError::Timeout);
+                }
+
+                tracing::debug!(
+                    "Restricting {} connected components",
+                    parikh_image_components
+                        .iter()
+                        .map(|(_, _, _, c)| c.len())
+                        .sum::<usize>()
+                );
+
+                for (graph, edge_map, _, components) in parikh_image_components.into_iter() {
+                    for component in components {
+                        forbid_parikh_image(&component, graph, edge_map, solver);
+                    }
+                }
+
+                *step_count += 1;
+            }
+            SatResult::Unsat => {
+                return SolverStatus::False(());
+            }
+            SatResult::Unknown
+                if stop_signal.load(Ordering::SeqCst)
+                    || deadline.is_some_and(|deadline| Instant::now() >= deadline) =>
+            {
+                return SolverStatus::Unknown(LinearGraphReachSolverError::Timeout);
+            }
+            SatResult::Unknown => {
+                return SolverStatus::Unknown(LinearGraphReachSolverError::SolverUnknown);
+            }
+        }
+    }
+}
@@ -1,24 +1,22 @@
 use std::{
-    sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
-    },
-    thread,
+    sync::{Arc, atomic::AtomicBool},
     time::Duration,
 };
 
 use hashbrown::HashMap;
-use petgraph::graph::EdgeIndex;
-use z3::{
-    Config, Context, Solver,
-    ast::{Bool, Int},
-    with_z3_config,
-};
 
+mod encoding;
+mod session;
 mod types;
 
+use encoding::{
+    ConstraintScope, ModelVariables, encode_boundary_constraints, encode_linear_graph,
+    solve_connected,
+};
+pub use session::{LinearGraphReachSession, LinearGraphSessionSlot};
 pub(crate) use types::{
-    LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearTemplateLowerBound,
+    LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearGraphCongruence,
+    LinearTemplateEquality, LinearTemplateLowerBound, LinearTemplateUpperBound,
 };
 pub use types::{
     LinearGraphReachSolverError, LinearGraphReachSolverOptions, LinearGraphReachSolverResult,
@@ -27,23 +25,13 @@ pub use types::{
 
 use crate::{
     automaton::{
-        AutomatonIterators, ExplicitEdgeAutomaton, GIndex,
-        index_map::OptionIndexMap,
-        linear_graph::{
-            LinearGraph, LinearGraphAutomaton,
-            part::{
-                LinearGraphPart, LinearGraphPathSegment, LinearGraphRegion, LinearGraphRepeatPath,
-            },
-        },
-        utils::cfg_updates_to_counter_updates,
+        GIndex,
+        linear_graph::{LinearGraph, LinearGraphAutomaton},
         vass::counter::VASSCounterValuation,
     },
     solver::{
-        SolverStatus,
-        utils::{
-            add_cfg_update_to_sums, assert_non_negative, assert_sums_match_valuation,
-            forbid_parikh_image, parikh_image_from_edge_map,
-        },
+        backend::{ArithmeticSolver, ast::Int, isolated},
+        utils::assert_sums_match_valuation,
     },
 };
 
@@ -112,359 +100,73 @@ where
     pub fn solve(&mut self) -> LinearGraphReachSolverResult {
         self.solver_start_time = Some(std::time::Instant::now());
 
-        let mut config = Config::new();
-        config.set_model_generation(true);
-
-        with_z3_config(&config, || {
-            let solver = Solver::new();
-
-            let context = Context::thread_local();
-            let context_handle = context.handle();
-
-            let start_time = self.solver_start_time.unwrap();
-            let stop_signal = self.stop_signal.clone();
-            let max_time = self.options.max_time;
-
-            let mut result = None;
-
-            thread::scope(|s| {
-                s.spawn(|| {
-                    loop {
-                        std::thread::sleep(Duration::from_millis(10));
-
-                        if let Some(max_time) = max_time
-                            && start_time.elapsed() >= max_time
-                        {
-                            stop_signal.store(true, Ordering::SeqCst);
-                        }
-
-                        if stop_signal.load(Ordering::SeqCst) {
-                            context_handle.interrupt();
-                            break;
-                        }
-                    }
-                });
-
-                result = Some(self.solve_inner(&solver));
-
-                stop_signal.store(true, Ordering::SeqCst);
-            });
+        let arithmetic = self.options.arithmetic.clone();
+        isolated(&arithmetic, || {
+            let solver = ArithmeticSolver::new(&arithmetic);
+            let result = self.solve_inner(&solver);
 
             tracing::debug!(
                 "Linear graph reachability solver finished in {} ms",
                 self.get_solver_time().unwrap_or_default().as_millis()
             );
 
-            result.expect("Thread panicked")
+            result
         })
     }
 
-    fn solve_inner(&mut self, solver: &Solver) -> LinearGraphReachSolverResult {
-        let mut sums: Box<[_]> = self
+    fn solve_inner(&mut self, solver: &ArithmeticSolver) -> LinearGraphReachSolverResult {
+        let initial_sums = self
             .initial_valuation
             .iter()
-            .map(|x| Int::from_i64(*x as i64))
+            .map(|x| Int::from_i64(*x))
             .collect();
-
-        let mut edge_maps = Vec::new();
-        let mut repeat_vars = vec![None; self.linear_graph.repeat_paths.len()];
-
-        for (i, part) in self.linear_graph.sequence.iter().enumerate() {
-            self.build_boundary_lower_bound_constraints(
-                i,
-                part.start(self.linear_graph),
-                solver,
-                &sums,
-            );
-
-            match part {
-                LinearGraphPart::Path(idx) => {
-                    self.build_path_constraints(self.linear_graph.path(*idx), solver, &mut sums);
-                }
-                LinearGraphPart::Graph(idx) => {
-                    let edge_map = self.build_graph_constraints(
-                        i,
-                        self.linear_graph.graph(*idx),
-                        solver,
-                        &mut sums,
-                    );
-                    edge_maps.push(edge_map);
+        let scope = ConstraintScope::new(solver);
+
+        let encoding = encode_linear_graph(
+            self.linear_graph,
+            "",
+            &scope,
+            initial_sums,
+            |index, state, sums| {
+                let point = LinearGraphBoundPoint::Boundary {
+                    index,
+                    state: state.clone(),
+                };
+                if let Some(constraints) = self.boundary_lower_bounds.get(&point) {
+                    encode_boundary_constraints(constraints, &scope, sums);
                 }
-                LinearGraphPart::RepeatPath(idx) => {
-                    let count = self.build_repeat_path_constraints(
-                        i,
-                        self.linear_graph.repeat_path(*idx),
-                        solver,
-                        &mut sums,
-                    );
-                    repeat_vars[*idx] = Some(count);
-                }
-            }
-        }
-
-        if let Some(last) = self.linear_graph.sequence.last() {
-            self.build_boundary_lower_bound_constraints(
-                self.linear_graph.sequence.len(),
-                last.end(self.linear_graph),
-                solver,
-                &sums,
-            );
-        }
-
-        assert_sums_match_valuation(solver, &sums, self.final_valuation);
-
-        self.step_count = 1;
-
-        loop {
-            match solver.check() {
-                z3::SatResult::Sat => {
-                    let model = solver.get_model().unwrap();
-
-                    let parikh_image_components = edge_maps
-                        .iter()
-                        .zip(self.linear_graph.iter_graph_parts())
-                        .map(|(map, graph)| {
-                            let image = parikh_image_from_edge_map(map, &model);
-
-                            let (main_component, components) =
-                                image.split_into_connected_components(graph);
-
-                            (graph, map, main_component, components)
-                        })
-                        .collect::<Vec<_>>();
-
-                    if parikh_image_components
-                        .iter()
-                        .all(|(_, _, _, c)| c.is_empty())
-                    {
-                        return self.get_solver_result(LinearGraphReachSolverStatus::True(
-                            LinearGraphSolution {
-                                sub_graph_parikh_images: parikh_image_components
-                                    .into_iter()
-                                    .map(|(_, _, main_component, _)| main_component)
-                                    .collect(),
-                                repeat_path_counts: repeat_vars
-                                    .iter()
-                                    .map(|var| {
-                                        let var = var
-                                            .as_ref()
-                                            .expect("every repeated path must have a variable");
-                                        model
-                                            .get_const_interp(var)
-                                            .expect("repeat count must be in the model")
-                                            .as_u64()
-                                            .expect("repeat count must be a non-negative integer")
-                                            as u32
-                                    })
-                                    .collect(),
-                                initial_valuation: self.initial_valuation.clone(),
-                                final_valuation: self.final_valuation.clone(),
-                            },
-                        ));
-                    }
-
-                    if self.max_iterations_reached() {
-                        return self.max_iterations_reached_result();
-                    }
-
-                    if self.max_time_reached() {
-                        return self.max_time_reached_result();
-                    }
-
-                    tracing::debug!(
-                        "Restricting {} connected components",
-                        parikh_image_components
-                            .iter()
-                            .map(|(_, _, _, c)| c.len())
-                            .sum::<usize>()
-                    );
-
-                    for (graph, edge_map, _, components) in parikh_image_components.into_iter() {
-                        for component in components {
-                            forbid_parikh_image(&component, graph, edge_map, solver);
-                        }
-                    }
-
-                    self.step_count += 1;
-                }
-                z3::SatResult::Unsat => {
-                    return self.get_solver_result(LinearGraphReachSolverStatus::False(()));
-                }
-                z3::SatResult::Unknown => {
-                    return self.get_solver_result(LinearGraphReachSolverStatus::Unknown(
-                        LinearGraphReachSolverError::SolverUnknown,
-                    ));
-                }
-            }
-        }
-    }
-
-    fn build_path_constraints(
-        &self,
-        path: &LinearGraphPathSegment<NIndex>,
-        solver: &Solver,
-        sums: &mut Box<[Int]>,
-    ) {
-        let path_updates = cfg_updates_to_counter_updates(
-            path.path.transitions.iter().cloned(),
-            self.linear_graph.dimension,
-        );
-
-        // first subtract the minimums
-        for (update, sum) in path_updates.0.iter().zip(sums.iter_mut()) {
-            let update_ast = Int::from_i64(*update as i64);
-            *sum = &*sum - &update_ast;
-        }
-
-        // then assert non-negativity
-        for sum in sums.iter() {
-            let zero = Int::from_i64(0);
-            let geq_zero = sum.ge(&zero);
-            solver.assert(&geq_zero);
-        }
-
-        // then add the rest to get the path's effect
-        for (update, sum) in path_updates.1.iter().zip(sums.iter_mut()) {
-            let update_ast = Int::from_i64(*update as i64);
-            *sum = &*sum + &update_ast;
-        }
-    }
-
-    fn build_graph_constraints(
-        &self,
-        part_index: usize,
-        graph: &LinearGraphRegion<NIndex>,
-        solver: &Solver,
-        sums: &mut Box<[Int]>,
-    ) -> OptionIndexMap<EdgeIndex, Int> {
-        let mut edge_map = OptionIndexMap::new(graph.edge_count());
-
-        for (edge, update) in graph.iter_edges() {
-            // we need one variable for each edge
-            let edge_var = Int::new_const(format!("graph_{}_edge_{}", part_index, edge.index()));
-            // CONSTRAINT: an edge can only be taken positive times
-            assert_non_negative(solver, &edge_var);
-
-            // add the edges effect to the counter sum
-            add_cfg_update_to_sums(sums, &edge_var, update);
-
-            edge_map.insert(edge, edge_var);
-        }
-
-        for node in graph.iter_node_indices() {
-            let outgoing = graph.outgoing_edge_indices(&node);
-            let incoming = graph.incoming_edge_indices(&node);
-
-            // the end node has one additional outgoing connection, this works, because we
-            // always have exactly one end node
-            let mut outgoing_sum = if node == graph.end {
-                Int::from_i64(1)
-            } else {
-                Int::from_i64(0)
-            };
-            // the start node has one additional incoming connection
-            let mut incoming_sum = if node == graph.start {
-                Int::from_i64(1)
-            } else {
-                Int::from_i64(0)
-            };
-
-            for edge in outgoing {
-                let edge_var = &edge_map[edge];
-                outgoing_sum += edge_var;
-            }
-
-            for edge in incoming {
-                let edge_var = &edge_map[edge];
-                incoming_sum += edge_var;
-            }
-
-            // CONSTRAINT: the sum of all outgoing edges must be equal to the sum of all
-            // incoming edges for each node
-            solver.assert(outgoing_sum.eq(&incoming_sum));
-        }
-
-        edge_map
-    }
-
-    fn build_repeat_path_constraints(
-        &self,
-        part_index: usize,
-        repeated: &LinearGraphRepeatPath<NIndex>,
-        solver: &Solver,
-        sums: &mut Box<[Int]>,
-    ) -> Int {
-        let count = Int::new_const(format!("repeat_path_{part_index}_count"));
-        let one = Int::from_i64(1);
-        assert_non_negative(solver, &count);
-
-        let (required, after_credit) = cfg_updates_to_counter_updates(
-            repeated.path.transitions.iter().cloned(),
-            self.linear_graph.dimension,
+            },
         );
-        let positive_count = count.gt(Int::from_i64(0));
 
-        for i in 0..self.linear_graph.dimension {
-            let required_value = required[i];
-            let effect = after_credit[i] - required_value;
-            let required_ast = Int::from_i64(required_value as i64);
-            solver.assert(positive_count.implies(sums[i].ge(&required_ast)));
-
-            if effect < 0 {
-                let last_iteration_start =
-                    &sums[i] + (&count - &one) * Int::from_i64(effect as i64);
-                solver.assert(positive_count.implies(last_iteration_start.ge(&required_ast)));
-            }
-
-            sums[i] = &sums[i] + &count * Int::from_i64(effect as i64);
-        }
-
-        count
-    }
-
-    fn build_boundary_lower_bound_constraints(
-        &self,
-        boundary_index: usize,
-        state: &NIndex,
-        solver: &Solver,
-        sums: &[Int],
-    ) {
-        let point = LinearGraphBoundPoint::Boundary {
-            index: boundary_index,
-            state: state.clone(),
-        };
-        let Some(constraints) = self.boundary_lower_bounds.get(&point) else {
-            return;
+        let final_sums = encoding
+            .boundary_sums
+            .last()
+            .expect("encodings always have a final boundary");
+        assert_sums_match_valuation(solver, final_sums, self.final_valuation);
+
+        let variables = ModelVariables {
+            graphs: self
+                .linear_graph
+                .iter_graph_parts()
+                .zip(&encoding.edge_maps)
+                .collect(),
+            repeat_counts: encoding.repeat_counts.iter().collect(),
         };
+        let status = solve_connected(
+            solver,
+            &[],
+            &variables,
+            self.initial_valuation,
+            self.final_valuation,
+            self.options.max_iterations,
+            &self.stop_signal,
+            self.options
+                .max_time
+                .map(|max_time| self.solver_start_time.unwrap() + max_time),
+            &mut self.step_count,
+        );
 
-        for template in &constraints.lower_bounds {
-            solver.assert(template_lower_bound_constraint(template, sums));
-        }
-    }
-
-    fn max_iterations_reached(&self) -> bool {
-        self.options
-            .max_iterations
-            .map(|x| x <= self.step_count)
-            .unwrap_or(false)
-    }
-
-    fn max_time_reached(&self) -> bool {
-        self.stop_signal.load(Ordering::SeqCst)
-    }
-
-    fn max_iterations_reached_result(&self) -> LinearGraphReachSolverResult {
-        LinearGraphReachSolverResult::new(
-            SolverStatus::Unknown(LinearGraphReachSolverError::MaxIterationsReached),
-            self.get_solver_statistics(),
-        )
-    }
-
-    fn max_time_reached_result(&self) -> LinearGraphReachSolverResult {
-        LinearGraphReachSolverResult::new(
-            SolverStatus::Unknown(LinearGraphReachSolverError::Timeout),
-            self.get_solver_statistics(),
-        )
+        self.get_solver_result(status)
     }
 
     fn get_solver_result(
@@ -485,14 +187,3 @@ where
         self.solver_start_time.map(|x| x.elapsed())
     }
 }
-
-fn template_lower_bound_constraint(template: &LinearTemplateLowerBound, sums: &[Int]) -> Bool {
-    let value = sums
-        .iter()
-        .zip(template.coefficients.iter())
-        .filter(|(_, coefficient)| **coefficient != 0)
-        .fold(Int::from_i64(0), |value, (sum, coefficient)| {
-            value + sum * Int::from_i64(*coefficient as i64)
-        });
-    value.ge(Int::from_i64(template.bound as i64))
-}
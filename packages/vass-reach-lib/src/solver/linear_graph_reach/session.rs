@@ -0,0 +1,297 @@
+//! Incremental reachability checks over a family of related LinearGraphs.
+//!
+//! Interpolation checks LinearGraphs that share their fixed paths and differ
+//! only in which regions use the seed graph and which the full SCC. A session
+//! encodes both alternatives of every region once, guarded by a selector
+//! literal, and picks one per check through assumptions. Boundary constraints
+//! are guarded by a fresh literal per check, while blocking clauses for
+//! disconnected Parikh images hold for every run and are kept for later
+//! checks.
+//!
+//! The solver is persistent, so time limits never cancel it for later checks. A
+//! stop signal is honored between refinement iterations.
+
+use std::time::Instant;
+
+use hashbrown::HashMap;
+
+use super::{
+    LinearGraphBoundPoint, LinearGraphBoundaryConstraints, LinearGraphReachSolverOptions,
+    LinearGraphReachSolverResult, LinearGraphReachSolverStatistics,
+    encoding::{
+        ConstraintScope, LinearGraphEncoding, ModelVariables, encode_boundary_constraints,
+        encode_linear_graph, solve_connected,
+    },
+};
+use crate::{
+    automaton::{
+        GIndex,
+        linear_graph::{LinearGraph, LinearGraphAutomaton},
+        vass::counter::VASSCounterValuation,
+    },
+    solver::{
+        backend::{
+            ArithmeticContext, ArithmeticSolver,
+            ast::{Bool, Int},
+        },
+        utils::assert_sums_match_valuation,
+    },
+};
+
+/// One position in the LinearGraphs checked by a [`LinearGraphReachSession`].
+/// Checked LinearGraphs are the concatenation of the selected slot contents.
+pub enum LinearGraphSessionSlot<'g, NIndex: GIndex, A: LinearGraphAutomaton<NIndex>> {
+    /// Part of every checked LinearGraph.
+    Fixed(LinearGraph<'g, NIndex, A>),
+    /// Either `seed` or `full`, selected by the mask of a check.
+    Toggle {
+        seed: LinearGraph<'g, NIndex, A>,
+        full: LinearGraph<'g, NIndex, A>,
+    },
+}
+
+/// Keeps one solver for all LinearGraphs of a slot layout.
+///
+/// Checks return the same status as a fresh [`super::LinearGraphReachSolver`]
+/// on the concatenated LinearGraph, though the model of a reachable check may
+/// differ.
+pub struct LinearGraphReachSession<'g, NIndex: GIndex, A: LinearGraphAutomaton<NIndex>> {
+    solver: ArithmeticSolver,
+    slots: Vec<EncodedSlot<'g, NIndex, A>>,
+    initial_valuation: VASSCounterValuation,
+    final_valuation: VASSCounterValuation,
+    checks: usize,
+}
+
+struct EncodedSlot<'g, NIndex: GIndex, A: LinearGraphAutomaton<NIndex>> {
+    /// Selects the second alternative of a toggled slot.
+    selector: Option<Bool>,
+    alternatives: Vec<(LinearGraph<'g, NIndex, A>, LinearGraphEncoding)>,
+}
+
+impl<'g, NIndex: GIndex, A: LinearGraphAutomaton<NIndex>> LinearGraphReachSession<'g, NIndex, A> {
+    /// Encodes every slot into a solver of the thread-local Z3 context. The
+    /// session must be used on the thread that created it.
+    pub fn new(
+        slots: Vec<LinearGraphSessionSlot<'g, NIndex, A>>,
+        initial_valuation: VASSCounterValuation,
+        final_valuation: VASSCounterValuation,
+    ) -> Self {
+        Self::with_arithmetic_context(
+            slots,
+            initial_valuation,
+            final_valuation,
+            &ArithmeticContext::default(),
+        )
+    }
+
+    /// Like [`Self::new`], with the solver created from `context`.
+    pub(crate) fn with_arithmetic_context(
+        slots: Vec<LinearGraphSessionSlot<'g, NIndex, A>>,
+        initial_valuation: VASSCounterValuation,
+        final_valuation: VASSCounterValuation,
+        context: &ArithmeticContext,
+    ) -> Self {
+        let solver = ArithmeticSolver::new_persistent(context);
+        let mut sums: Box<[Int]> = initial_valuation
+            .iter()
+            .map(|x| Int::from_i64(*x))
+            .collect();
+        let mut encoded_slots = Vec::with_capacity(slots.len());
+
+        for (index, slot) in slots.into_iter().enumerate() {
+            match slot {
+                LinearGraphSessionSlot::Fixed(linear_graph) => {
+                    let encoding = encode_linear_graph(
+                        &linear_graph,
+                        &format!("session_{index}_"),
+                        &ConstraintScope::new(&solver),
+                        sums,
+                        |_, _, _| {},
+                    );
+                    sums = end_sums(&encoding).into();
+                    encoded_slots.push(EncodedSlot {
+                        selector: None,
+                        alternatives: vec![(linear_graph, encoding)],
+                    });
+                }
+                LinearGraphSessionSlot::Toggle { seed, full } => {
+                    let selector = Bool::new_const(format!("session_{index}_full"));
+                    let seed_encoding = encode_linear_graph(
+                        &seed,
+                        &format!("session_{index}_seed_"),
+                        &ConstraintScope::guarded(&solver, selector.not()),
+                        sums.clone(),
+                        |_, _, _| {},
+                    );
+                    let full_encoding = encode_linear_graph(
+                        &full,
+                        &format!("session_{index}_full_"),
+                        &ConstraintScope::guarded(&solver, selector.clone()),
+                        sums,
+                        |_, _, _| {},
+                    );
+
+                    sums = (0..initial_valuation.dimension())
+                        .map(|counter| Int::new_const(format!("session_{index}_sum_{counter}")))
+                        .collect();
+                    for (counter, sum) in sums.iter().enumerate() {
+                        solver.assert(
+                            selector
+                                .not()
+                                .implies(sum.eq(&end_sums(&seed_encoding)[counter])),
+                        );
+                        solver.assert(selector.implies(sum.eq(&end_sums(&full_encoding)[counter])));
+                    }
+
+                    encoded_slots.push(EncodedSlot {
+                        selector: Some(selector),
+                        alternatives: vec![(seed, seed_encoding), (full, full_encoding)],
+                    });
+                }
+            }
+        }
+
+        assert_sums_match_valuation(&solver, &sums, &final_valuation);
+
+        Self {
+            solver,
+            slots: encoded_slots,
+            initial_valuation,
+            final_valuation,
+            checks: 0,
+        }
+    }
+
+    /// Number of toggled slots, i.e. the expected mask length.
+    pub fn toggle_count(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.selector.is_some())
+            .count()
+    }
+
+    /// Checks the LinearGraph that uses the full alternative exactly for the
+    /// toggled slots enabled in `mask`.
+    pub fn check(
+        &mut self,
+        mask: &[bool],
+        options: LinearGraphReachSolverOptions,
+    ) -> LinearGraphReachSolverResult {
+        self.check_with_boundary_lower_bounds(mask, &HashMap::new(), options)
+    }
+
+    /// Like [`Self::check`], with boundary constraints indexed by the parts of
+    /// the checked LinearGraph.
+    pub(crate) fn check_with_boundary_lower_bounds(
+        &mut self,
+        mask: &[bool],
+        boundary_lower_bounds: &HashMap<
+            LinearGraphBoundPoint<NIndex>,
+            LinearGraphBoundaryConstraints,
+        >,
+        options: LinearGraphReachSolverOptions,
+    ) -> LinearGraphReachSolverResult {
+        assert_eq!(
+            mask.len(),
+            self.toggle_count(),
+            "session mask must have one entry per toggled slot"
+        );
+
+        let start_time = Instant::now();
+        let check_literal = Bool::new_const(format!("session_check_{}", self.checks));
+        self.checks += 1;
+
+        let mut assumptions = vec![check_literal.clone()];
+        let mut selected = Vec::with_capacity(self.slots.len());
+        let mut toggles = mask.iter();
+        for slot in &self.slots {
+            match &slot.selector {
+                Some(selector) => {
+                    let full = *toggles.next().expect("mask length was checked");
+                    assumptions.push(if full {
+                        selector.clone()
+                    } else {
+                        selector.not()
+                    });
+                    selected.push(&slot.alternatives[usize::from(full)]);
+                }
+                None => selected.push(&slot.alternatives[0]),
+            }
+        }
+
+        let scope = ConstraintScope::guarded(&self.solver, check_literal.clone());
+        let mut boundary_index = 0;
+        let mut last_end = None;
+        for (linear_graph, encoding) in &selected {
+            for (part_index, part) in linear_graph.sequence.iter().enumerate() {
+                let point = LinearGraphBoundPoint::Boundary {
+                    index: boundary_index,
+                    state: part.start(linear_graph).clone(),
+                };
+                if let Some(constraints) = boundary_lower_bounds.get(&point) {
+                    encode_boundary_constraints(
+                        constraints,
+                        &scope,
+                        &encoding.boundary_sums[part_index],
+                    );
+                }
+                boundary_index += 1;
+            }
+            if let Some(last) = linear_graph.sequence.last() {
+                last_end = Some((last.end(linear_graph).clone(), end_sums(encoding)));
+            }
+        }
+        if let Some((state, sums)) = last_end {
+            let point = LinearGraphBoundPoint::Boundary {
+                index: boundary_index,
+                state,
+            };
+            if let Some(constraints) = boundary_lower_bounds.get(&point) {
+                encode_boundary_constraints(constraints, &scope, sums);
+            }
+        }
+
+        let variables = ModelVariables {
+            graphs: selected
+                .iter()
+                .flat_map(|(linear_graph, encoding)| {
+                    linear_graph.iter_graph_parts().zip(&encoding.edge_maps)
+                })
+                .collect(),
+            repeat_counts: selected
+                .iter()
+                .flat_map(|(_, encoding)| &encoding.repeat_counts)
+                .collect(),
+        };
+
+        let stop_signal = options.stop_signal.clone().unwrap_or_default();
+        let mut step_count = 0;
+        let status = solve_connected(
+            &self.solver,
+            &assumptions,
+            &variables,
+            &self.initial_valuation,
+            &self.final_valuation,
+            options.max_iterations,
+            &stop_signal,
+            options.max_time.map(|max_time| start_time + max_time),
+            &mut step_count,
+        );
+
+        // Retire the boundary constraints of this check for good.
+        self.solver.assert(check_literal.not());
+
+        LinearGraphReachSolverResult::new(
+            status,
+            LinearGraphReachSolverStatistics::new(step_count, start_time.elapsed()),
+        )
+    }
+}
+
+fn end_sums(encoding: &LinearGraphEncoding) -> &[Int] {
+    encoding
+        .boundary_sums
+        .last()
+        .expect("encodings always have a final boundary")
+}
@@ -14,20 +14,45 @@ use crate::{
         linear_graph::{LinearGraph, LinearGraphAutomaton, part::LinearGraphPart},
         path::{Path, parikh_image::ParikhImage},
         utils::cfg_updates_to_counter_update,
-        vass::counter::VASSCounterValuation,
+        vass::counter::{CounterOverflowError, VASSCounterValuation},
     },
-    solver::{SolverResult, SolverStatus},
+    solver::{SolverResult, SolverStatus, backend::ArithmeticContext},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct LinearTemplateLowerBound {
-    pub coefficients: Box<[i32]>,
-    pub bound: i32,
+    pub coefficients: Box<[i64]>,
+    pub bound: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinearTemplateUpperBound {
+    pub coefficients: Box<[i64]>,
+    pub bound: i64,
+}
+
+/// A template whose lower and upper bound coincide, e.g. a conservation law.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinearTemplateEquality {
+    pub coefficients: Box<[i64]>,
+    pub value: i64,
+}
+
+/// Restricts `counter mod modulus` to one of `residues`. An empty residue list
+/// makes the boundary infeasible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinearGraphCongruence {
+    pub counter: usize,
+    pub modulus: i64,
+    pub residues: Box<[i64]>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub(crate) struct LinearGraphBoundaryConstraints {
     pub lower_bounds: Vec<LinearTemplateLowerBound>,
+    pub upper_bounds: Vec<LinearTemplateUpperBound>,
+    pub equalities: Vec<LinearTemplateEquality>,
+    pub congruences: Vec<LinearGraphCongruence>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -40,6 +65,7 @@ pub struct LinearGraphReachSolverOptions {
     pub(super) max_iterations: Option<u32>,
     pub(super) max_time: Option<Duration>,
     pub(super) stop_signal: Option<Arc<AtomicBool>>,
+    pub(super) arithmetic: ArithmeticContext,
 }
 
 impl LinearGraphReachSolverOptions {
@@ -58,6 +84,11 @@ impl LinearGraphReachSolverOptions {
         self
     }
 
+    pub(crate) fn with_arithmetic_context(mut self, context: ArithmeticContext) -> Self {
+        self.arithmetic = context;
+        self
+    }
+
     pub fn with_optional_time_limit(mut self, limit: Option<Duration>) -> Self {
         self.max_time = limit;
         self
@@ -115,7 +146,7 @@ impl LinearGraphSolution {
     pub(crate) fn boundary_valuations<'a, NIndex: GIndex, A>(
         &self,
         linear_graph: &LinearGraph<'a, NIndex, A>,
-    ) -> Vec<(NIndex, VASSCounterValuation)>
+    ) -> Result<Vec<(NIndex, VASSCounterValuation)>, CounterOverflowError>
     where
         A: LinearGraphAutomaton<NIndex>,
     {
@@ -123,25 +154,25 @@ impl LinearGraphSolution {
         let mut boundaries = Vec::with_capacity(linear_graph.sequence.len() + 1);
 
         let Some(first) = linear_graph.sequence.first() else {
-            return boundaries;
+            return Ok(boundaries);
         };
         boundaries.push((first.start(linear_graph).clone(), valuation.clone()));
 
         for part in &linear_graph.sequence {
             match part {
                 LinearGraphPart::Graph(idx) => {
-                    valuation.apply_update(
+                    valuation.try_apply_update(
                         &self.sub_graph_parikh_images[*idx].get_total_counter_effect(
                             linear_graph.graph(*idx),
                             linear_graph.dimension,
                         ),
-                    );
+                    )?;
                 }
                 LinearGraphPart::Path(idx) => {
-                    valuation.apply_update(&cfg_updates_to_counter_update(
+                    valuation.try_apply_update(&cfg_updates_to_counter_update(
                         linear_graph.path(*idx).path.transitions.iter().cloned(),
                         linear_graph.dimension,
-                    ));
+                    ))?;
                 }
                 LinearGraphPart::RepeatPath(idx) => {
                     let effect = cfg_updates_to_counter_update(
@@ -154,7 +185,7 @@ impl LinearGraphSolution {
                         linear_graph.dimension,
                     );
                     for _ in 0..self.repeat_path_counts[*idx] {
-                        valuation.apply_update(&effect);
+                        valuation.try_apply_update(&effect)?;
                     }
                 }
             }
@@ -162,14 +193,16 @@ impl LinearGraphSolution {
             boundaries.push((part.end(linear_graph).clone(), valuation.clone()));
         }
 
-        boundaries
+        Ok(boundaries)
     }
 
+    /// Builds a run of the solution. Gives `None` if the run can not be built,
+    /// and an error if a counter leaves the `i64` range on the way.
     pub fn build_run<'a, NIndex: GIndex, A>(
         &self,
         linear_graph: &LinearGraph<'a, NIndex, A>,
         n_run: bool,
-    ) -> Option<Path<NIndex, CFGCounterUpdate>>
+    ) -> Result<Option<Path<NIndex, CFGCounterUpdate>>, CounterOverflowError>
     where
         A: LinearGraphAutomaton<NIndex>,
     {
@@ -181,7 +214,7 @@ impl LinearGraphSolution {
         linear_graph: &LinearGraph<'a, NIndex, A>,
         n_run: bool,
         deadline: Option<std::time::Instant>,
-    ) -> Option<Path<NIndex, CFGCounterUpdate>>
+    ) -> Result<Option<Path<NIndex, CFGCounterUpdate>>, CounterOverflowError>
     where
         A: LinearGraphAutomaton<NIndex>,
     {
@@ -193,7 +226,7 @@ impl LinearGraphSolution {
 
         for part in linear_graph.sequence.iter() {
             if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
-                return None;
+                return Ok(None);
             }
 
             match part {
@@ -203,15 +236,17 @@ impl LinearGraphSolution {
 
                     let start_valuation = current_valuation.clone();
                     current_valuation
-                        .apply_update(&image.get_total_counter_effect(graph, dimension));
+                        .try_apply_update(&image.get_total_counter_effect(graph, dimension))?;
                     let end_valuation = current_valuation.clone();
-                    let sub_path = image.build_run_with_deadline(
+                    let Some(sub_path) = image.build_run_with_deadline(
                         graph,
                         &start_valuation,
                         &end_valuation,
                         n_run,
                         deadline,
-                    )?;
+                    ) else {
+                        return Ok(None);
+                    };
                     let mapped_path = graph.map_path_to_product(&sub_path);
 
                     product_path.concat(mapped_path);
@@ -223,7 +258,7 @@ impl LinearGraphSolution {
                         dimension,
                     );
 
-                    current_valuation.apply_update(&update);
+                    current_valuation.try_apply_update(&update)?;
                     product_path.concat(path.path.clone());
                 }
                 LinearGraphPart::RepeatPath(idx) => {
@@ -236,9 +271,9 @@ impl LinearGraphSolution {
 
                     for _ in 0..count {
                         if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
-                            return None;
+                            return Ok(None);
                         }
-                        current_valuation.apply_update(&update);
+                        current_valuation.try_apply_update(&update)?;
                         product_path.concat(repeated.path.clone());
                     }
                 }
@@ -255,7 +290,7 @@ impl LinearGraphSolution {
             timer.elapsed().as_millis()
         );
 
-        Some(product_path)
+        Ok(Some(product_path))
     }
 }
 
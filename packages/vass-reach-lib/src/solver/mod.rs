@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+pub(crate) mod backend;
 pub mod linear_graph_reach;
+pub mod report;
+pub mod smt_dump;
 mod utils;
 pub mod vass_reach;
 pub mod vass_z_reach;
@@ -35,6 +38,14 @@ impl<T, F, U> SolverStatus<T, F, U> {
         matches!(self, SolverStatus::Unknown(_))
     }
 
+    pub fn map_success<T2>(self, f: impl FnOnce(T) -> T2) -> SolverStatus<T2, F, U> {
+        match self {
+            SolverStatus::True(t) => SolverStatus::True(f(t)),
+            SolverStatus::False(x) => SolverStatus::False(x),
+            SolverStatus::Unknown(u) => SolverStatus::Unknown(u),
+        }
+    }
+
     pub fn unwrap_success(self) -> T {
         match self {
             SolverStatus::True(t) => t,
@@ -0,0 +1,167 @@
+use std::fmt::{Display, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    automaton::petri_net::reduction::PetriNetReductionStatistics,
+    solver::{SerializableSolverResult, SerializableSolverStatus, SolverResult, SolverStatus},
+};
+
+/// Version of the [`SolverReport`] schema. Bump it whenever a field changes
+/// its meaning or is removed.
+pub const SOLVER_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// The instance a report is about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceProvenance {
+    pub path: String,
+    /// See [`instance_hash`].
+    pub hash: String,
+}
+
+impl InstanceProvenance {
+    pub fn new(path: impl Into<String>, content: &[u8]) -> Self {
+        InstanceProvenance {
+            path: path.into(),
+            hash: instance_hash(content),
+        }
+    }
+}
+
+/// A hash of the instance file that is stable across platforms and
+/// toolchains, 64 bit FNV-1a.
+pub fn instance_hash(content: &[u8]) -> String {
+    let hash = content.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("fnv1a64:{hash:016x}")
+}
+
+/// The result of a single solver run together with everything needed to
+/// reproduce and check it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolverReport<Witness, Statistics> {
+    pub schema_version: u32,
+    pub tool_version: String,
+    /// The reachability mode, `N` or `Z`.
+    pub mode: String,
+    pub instance: InstanceProvenance,
+    /// The configuration the solver ran with, including all defaults.
+    pub config: serde_json::Value,
+    pub status: SerializableSolverStatus,
+    /// Why the solver gave up, only present for unknown results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<serde_json::Value>,
+    /// Only present for reachable results. If the instance is a Petri net
+    /// that was reduced before solving, it is translated back to the original
+    /// net or left out if that is not possible.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub witness: Option<Witness>,
+    pub statistics: Statistics,
+    /// Only present if the instance is a Petri net that was reduced before
+    /// solving.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduction: Option<PetriNetReductionStatistics>,
+}
+
+impl<Witness, Statistics> SolverReport<Witness, Statistics> {
+    pub fn new<F, U: Serialize>(
+        mode: impl Into<String>,
+        instance: InstanceProvenance,
+        config: &impl Serialize,
+        result: SolverResult<Witness, F, U, Statistics>,
+    ) -> anyhow::Result<Self> {
+        let (status, reason, witness) = match result.status {
+            SolverStatus::True(witness) => (SerializableSolverStatus::True, None, Some(witness)),
+            SolverStatus::False(_) => (SerializableSolverStatus::False, None, None),
+            SolverStatus::Unknown(reason) => (
+                SerializableSolverStatus::Unknown,
+                Some(serde_json::to_value(reason)?),
+                None,
+            ),
+        };
+
+        Ok(SolverReport {
+            schema_version: SOLVER_REPORT_SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            mode: mode.into(),
+            instance,
+            config: serde_json::to_value(config)?,
+            status,
+            reason,
+            witness,
+            statistics: result.statistics,
+            reduction: None,
+        })
+    }
+
+    /// Records that the instance was reduced before solving. Witnesses of the
+    /// reduced net are in terms of its places and transitions, `expand`
+    /// translates them to the original net, see
+    /// [`PetriNetReduction::expand_update_run`]. Witnesses it returns `None`
+    /// for are dropped.
+    ///
+    /// [`PetriNetReduction::expand_update_run`]: crate::automaton::petri_net::reduction::PetriNetReduction::expand_update_run
+    pub fn with_reduction(
+        mut self,
+        statistics: PetriNetReductionStatistics,
+        expand: impl FnOnce(Witness) -> Option<Witness>,
+    ) -> Self {
+        self.witness = self.witness.and_then(expand);
+        self.reduction = Some(statistics);
+        self
+    }
+
+    /// Drops everything but the status and the statistics.
+    pub fn into_serializable_result(self) -> SerializableSolverResult<Statistics> {
+        SerializableSolverResult::new(self.status, self.statistics)
+    }
+}
+
+impl<Witness: Display, Statistics: Serialize> SolverReport<Witness, Statistics> {
+    /// A human readable summary. The configuration is left out, the statistics
+    /// are included as indented JSON.
+    pub fn to_text(&self) -> anyhow::Result<String> {
+        let status = match self.status {
+            SerializableSolverStatus::True => "reachable",
+            SerializableSolverStatus::False => "unreachable",
+            SerializableSolverStatus::Unknown => "unknown",
+        };
+
+        let mut text = String::new();
+        writeln!(text, "status:   {status}")?;
+        if let Some(reason) = &self.reason {
+            writeln!(text, "reason:   {reason}")?;
+        }
+        if let Some(witness) = &self.witness {
+            writeln!(text, "witness:  {witness}")?;
+        }
+        if let Some(reduction) = &self.reduction {
+            writeln!(
+                text,
+                "reduced:  {} of {} places, {} of {} transitions",
+                reduction.places,
+                reduction.original_places,
+                reduction.transitions,
+                reduction.original_transitions
+            )?;
+        }
+        writeln!(
+            text,
+            "instance: {} ({})",
+            self.instance.path, self.instance.hash
+        )?;
+        writeln!(text, "mode:     {}", self.mode)?;
+        writeln!(
+            text,
+            "version:  {} (report schema {})",
+            self.tool_version, self.schema_version
+        )?;
+        writeln!(text, "statistics:")?;
+        for line in serde_json::to_string_pretty(&self.statistics)?.lines() {
+            writeln!(text, "  {line}")?;
+        }
+
+        Ok(text)
+    }
+}
@@ -0,0 +1,315 @@
+//! SMT-LIB2 dumps of solver queries and their replay.
+//!
+//! Every query that a solver with a dump checks through [`check_solver`] is
+//! written to its own `.smt2` file. The file starts with comment lines holding
+//! the metadata (purpose, refinement step, connectivity iteration, elapsed time
+//! and result), followed by the assertions and a `(check-sat)`, so it can also
+//! be fed to `z3` directly. Assumptions of a check are written as plain
+//! assertions.
+//!
+//! Each reachability solver writes to its own `solver_<n>` subdirectory of the
+//! configured directory, and the route solvers of divide-and-conquer to
+//! `route_<n>` subdirectories of that, so the queries and steps of solvers
+//! running side by side stay apart.
+
+use std::{
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path as FsPath, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{ArithmeticBackendConfig, SMTDumpConfig},
+    solver::backend::{self, ArithmeticSolver, CheckLimits, SatResult, ast::Bool},
+    utils::sanitize_path_component,
+};
+
+const HEADER: &str = "; vass-reach SMT-LIB2 query dump";
+
+/// The answer the solver gave to a dumped query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SMTQueryResult {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+impl From<SatResult> for SMTQueryResult {
+    fn from(result: SatResult) -> Self {
+        match result {
+            SatResult::Sat => SMTQueryResult::Sat,
+            SatResult::Unsat => SMTQueryResult::Unsat,
+            SatResult::Unknown => SMTQueryResult::Unknown,
+        }
+    }
+}
+
+impl Display for SMTQueryResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SMTQueryResult::Sat => write!(f, "sat"),
+            SMTQueryResult::Unsat => write!(f, "unsat"),
+            SMTQueryResult::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl FromStr for SMTQueryResult {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sat" => Ok(SMTQueryResult::Sat),
+            "unsat" => Ok(SMTQueryResult::Unsat),
+            "unknown" => Ok(SMTQueryResult::Unknown),
+            _ => anyhow::bail!("invalid query result: {s}"),
+        }
+    }
+}
+
+/// Whether a query was checked by a plain solver or an optimizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SMTQueryKind {
+    Solver,
+    Optimize,
+}
+
+impl SMTQueryKind {
+    fn name(self) -> &'static str {
+        match self {
+            SMTQueryKind::Solver => "solver",
+            SMTQueryKind::Optimize => "optimize",
+        }
+    }
+}
+
+/// Writes queries to one directory, numbering them in the order they finish.
+#[derive(Debug)]
+pub struct SMTQueryDump {
+    dir: PathBuf,
+    next_index: AtomicU64,
+    /// The current refinement step, zero before the first one.
+    step: AtomicU64,
+}
+
+impl SMTQueryDump {
+    /// Creates a dump for one solver in a fresh subdirectory of the configured
+    /// directory if dumping is enabled.
+    pub fn create(config: &SMTDumpConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        if !*config.get_enabled() {
+            return Ok(None);
+        }
+
+        let root = PathBuf::from(config.get_output_dir().as_deref().unwrap_or("debug/smt"));
+        Self::in_fresh_subdirectory(&root, "solver").map(Some)
+    }
+
+    /// Creates a dump in the subdirectory `name` for a solver nested in this
+    /// one, such as the solver of a divide-and-conquer route.
+    pub fn nested(&self, name: &str) -> anyhow::Result<Arc<Self>> {
+        let dir = self.dir.join(name);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create SMT dump directory: {}", dir.display()))?;
+
+        Ok(Arc::new(SMTQueryDump {
+            dir,
+            next_index: AtomicU64::new(0),
+            step: AtomicU64::new(0),
+        }))
+    }
+
+    /// Dumps into the first `<name>_<n>` subdirectory of `root` that does not
+    /// exist yet. Creating the directory claims it, also against other
+    /// processes.
+    fn in_fresh_subdirectory(root: &FsPath, name: &str) -> anyhow::Result<Arc<Self>> {
+        fs::create_dir_all(root)
+            .with_context(|| format!("failed to create SMT dump directory: {}", root.display()))?;
+
+        for n in 0.. {
+            let dir = root.join(format!("{name}_{n:03}"));
+            match fs::create_dir(&dir) {
+                Ok(()) => {
+                    return Ok(Arc::new(SMTQueryDump {
+                        dir,
+                        next_index: AtomicU64::new(0),
+                        step: AtomicU64::new(0),
+                    }));
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("failed to create SMT dump directory: {}", dir.display())
+                    });
+                }
+            }
+        }
+
+        unreachable!("the subdirectory search is unbounded")
+    }
+
+    pub fn dir(&self) -> &FsPath {
+        &self.dir
+    }
+
+    /// Sets the refinement step recorded with the following queries.
+    pub fn set_step(&self, step: u64) {
+        self.step.store(step, Ordering::SeqCst);
+    }
+
+    fn record(
+        &self,
+        kind: SMTQueryKind,
+        purpose: &str,
+        iteration: Option<u32>,
+        body: &str,
+        elapsed: Duration,
+        result: SatResult,
+    ) {
+        let index = self.next_index.fetch_add(1, Ordering::SeqCst);
+        let path = self.dir.join(format!(
+            "{index:06}_{}.smt2",
+            sanitize_path_component(purpose, "query")
+        ));
+
+        let mut contents = format!("{HEADER}\n; purpose: {purpose}\n; kind: {}\n", kind.name());
+        match self.step.load(Ordering::SeqCst) {
+            0 => {}
+            step => contents.push_str(&format!("; step: {step}\n")),
+        }
+        if let Some(iteration) = iteration {
+            contents.push_str(&format!("; iteration: {iteration}\n"));
+        }
+        contents.push_str(&format!(
+            "; elapsed_ms: {}\n; result: {}\n",
+            elapsed.as_millis(),
+            SMTQueryResult::from(result)
+        ));
+        contents.push_str(body);
+        contents.push_str("(check-sat)\n");
+
+        if let Err(err) = fs::write(&path, contents) {
+            tracing::warn!(error = %err, path = %path.display(), "failed to write SMT query dump");
+        }
+    }
+}
+
+/// Checks `solver` under `assumptions` and dumps the query, including the
+/// objectives of an optimizer, if the solver was created with a dump.
+pub(crate) fn check_solver(
+    solver: &ArithmeticSolver,
+    assumptions: &[Bool],
+    limits: CheckLimits<'_>,
+    purpose: &str,
+    iteration: Option<u32>,
+) -> SatResult {
+    let Some(dump) = solver.dump() else {
+        return solver.check_with(assumptions, limits);
+    };
+
+    let body = solver.to_smtlib(assumptions);
+    let kind = if solver.is_optimizer() {
+        SMTQueryKind::Optimize
+    } else {
+        SMTQueryKind::Solver
+    };
+
+    let start = Instant::now();
+    let result = solver.check_with(assumptions, limits);
+    dump.record(kind, purpose, iteration, &body, start.elapsed(), result);
+    result
+}
+
+/// The outcome of rerunning one dumped query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SMTQueryReplay {
+    pub purpose: String,
+    pub recorded: SMTQueryResult,
+    pub replayed: SMTQueryResult,
+    pub recorded_elapsed: Option<Duration>,
+    pub replayed_elapsed: Duration,
+}
+
+impl SMTQueryReplay {
+    pub fn is_unchanged(&self) -> bool {
+        self.recorded == self.replayed
+    }
+}
+
+/// Reruns a dumped query with a fresh solver of the backend selected by
+/// `config`.
+pub fn replay_query(
+    path: &FsPath,
+    config: &ArithmeticBackendConfig,
+) -> anyhow::Result<SMTQueryReplay> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read SMT query dump: {}", path.display()))?;
+    if !contents.starts_with(HEADER) {
+        anyhow::bail!("not an SMT query dump: {}", path.display());
+    }
+
+    let metadata = |key: &str| {
+        contents
+            .lines()
+            .take_while(|line| line.starts_with(';'))
+            .find_map(|line| line.strip_prefix(&format!("; {key}: ")))
+    };
+    let purpose = metadata("purpose").unwrap_or("query").to_string();
+    let kind = match metadata("kind") {
+        Some("optimize") => SMTQueryKind::Optimize,
+        Some("solver") | None => SMTQueryKind::Solver,
+        Some(kind) => anyhow::bail!("invalid query kind: {kind}"),
+    };
+    let recorded = metadata("result")
+        .context("SMT query dump has no recorded result")?
+        .parse()?;
+    let recorded_elapsed = metadata("elapsed_ms")
+        .and_then(|ms| ms.parse().ok())
+        .map(Duration::from_millis);
+
+    let start = Instant::now();
+    let result = backend::check_script(config, &contents, kind == SMTQueryKind::Optimize);
+
+    Ok(SMTQueryReplay {
+        purpose,
+        recorded,
+        replayed: result.into(),
+        recorded_elapsed,
+        replayed_elapsed: start.elapsed(),
+    })
+}
+
+/// The dumped queries of `path`, which is a single dump or a directory of
+/// them and of the subdirectories of solvers, in path order.
+pub fn dumped_queries(path: &FsPath) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut queries = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("failed to read SMT dump directory: {}", dir.display()))?
+        {
+            let entry = entry?.path();
+            if entry.is_dir() {
+                dirs.push(entry);
+            } else if entry.extension().is_some_and(|ext| ext == "smt2") {
+                queries.push(entry);
+            }
+        }
+    }
+    queries.sort();
+    Ok(queries)
+}
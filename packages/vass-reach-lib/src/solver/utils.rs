@@ -1,31 +1,47 @@
+use std::cell::RefCell;
+
 use itertools::Itertools;
-use z3::{
-    Model,
-    ast::{Bool, Int},
-};
 
-use crate::automaton::{
-    CompactGIndex,
-    cfg::{ExplicitEdgeCFG, update::CFGCounterUpdate},
-    index_map::OptionIndexMap,
-    path::parikh_image::ParikhImage,
-    vass::counter::VASSCounterValuation,
+use crate::{
+    automaton::{
+        CompactGIndex,
+        cfg::{ExplicitEdgeCFG, update::CFGCounterUpdate},
+        index_map::OptionIndexMap,
+        path::parikh_image::ParikhImage,
+        vass::counter::VASSCounterValuation,
+    },
+    solver::backend::{
+        ArithmeticSolver,
+        ast::{Bool, Int},
+    },
 };
 
-pub fn parikh_image_from_edge_map<EIndex: CompactGIndex>(
+/// Reads the edge counts of the last satisfiable check. Edges the solver
+/// never had to assign are not taken.
+pub(crate) fn parikh_image_from_edge_map<EIndex: CompactGIndex>(
     edge_map: &OptionIndexMap<EIndex, Int>,
-    model: &Model,
+    solver: &ArithmeticSolver,
 ) -> ParikhImage<EIndex> {
-    ParikhImage::new(
-        edge_map.map(|var| model.get_const_interp(var).unwrap().as_u64().unwrap() as u32),
-    )
+    let vars = edge_map.iter().map(|(_, var)| var).collect_vec();
+    let values = RefCell::new(
+        solver
+            .eval_many(&vars)
+            .expect("edge counts must be in the model")
+            .into_iter(),
+    );
+
+    // `map` visits the filled entries in the same order as `iter`.
+    ParikhImage::new(edge_map.map(|_| {
+        let value = values.borrow_mut().next().expect("one value per edge");
+        u32::try_from(value).expect("edge count must be a non-negative integer")
+    }))
 }
 
-pub fn forbid_parikh_image<C: ExplicitEdgeCFG>(
+pub(crate) fn forbid_parikh_image<C: ExplicitEdgeCFG>(
     parikh_image: &ParikhImage<C::EIndex>,
     cfg: &C,
     edge_map: &OptionIndexMap<C::EIndex, Int>,
-    solver: &z3::Solver,
+    solver: &ArithmeticSolver,
 ) {
     // bools that represent whether each individual edge in the component is
     // taken
@@ -55,21 +71,25 @@ pub fn forbid_parikh_image<C: ExplicitEdgeCFG>(
     solver.assert(edges_ast.implies(incoming_ast));
 }
 
-pub fn assert_non_negative(solver: &z3::Solver, value: &Int) {
+pub(crate) fn assert_non_negative(solver: &ArithmeticSolver, value: &Int) {
     solver.assert(value.ge(Int::from_i64(0)));
 }
 
-pub fn add_cfg_update_to_sums(sums: &mut [Int], multiplier: &Int, update: &CFGCounterUpdate) {
+pub(crate) fn add_cfg_update_to_sums(
+    sums: &mut [Int],
+    multiplier: &Int,
+    update: &CFGCounterUpdate,
+) {
     let counter = update.counter().to_usize();
     sums[counter] = &sums[counter] + multiplier * update.op_i64();
 }
 
-pub fn assert_sums_match_valuation(
-    solver: &z3::Solver,
+pub(crate) fn assert_sums_match_valuation(
+    solver: &ArithmeticSolver,
     sums: &[Int],
     valuation: &VASSCounterValuation,
 ) {
     for (sum, target) in sums.iter().zip(valuation.iter()) {
-        solver.assert(sum.eq(Int::from_i64(*target as i64)));
+        solver.assert(sum.eq(Int::from_i64(*target)));
     }
 }
@@ -16,8 +16,8 @@ pub struct LightTraceSummary {
     pub dimension: usize,
     pub state_count: usize,
     pub transition_count: usize,
-    pub initial_valuation: Vec<i32>,
-    pub final_valuation: Vec<i32>,
+    pub initial_valuation: Vec<i64>,
+    pub final_valuation: Vec<i64>,
     pub initial_graph_dot: String,
     #[serde(default)]
     pub result: Option<LightTraceResult>,
@@ -29,7 +29,7 @@ pub struct LightTraceResult {
     pub reason: Option<String>,
     pub elapsed_ms: u128,
     pub step_count: u64,
-    pub mu: Vec<i32>,
+    pub mu: Vec<i64>,
     pub forwards_bound: Vec<u32>,
     pub backwards_bound: Vec<u32>,
 }
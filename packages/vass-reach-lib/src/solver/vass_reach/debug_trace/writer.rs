@@ -174,7 +174,7 @@ fn light_result(
     statistics: &VASSReachSolverStatistics,
 ) -> LightTraceResult {
     let (status, reason) = match status {
-        crate::solver::SolverStatus::True(()) => ("reachable", None),
+        crate::solver::SolverStatus::True(_) => ("reachable", None),
         crate::solver::SolverStatus::False(()) => ("unreachable", None),
         crate::solver::SolverStatus::Unknown(reason) => ("unknown", Some(format!("{reason:?}"))),
     };
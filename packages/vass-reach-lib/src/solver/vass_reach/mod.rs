@@ -1,34 +1,65 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
 use petgraph::graph::NodeIndex;
 
 pub mod debug_trace;
 mod preprocess;
+mod refinement;
+mod routes;
+mod session;
 mod types;
 mod witness;
 
+pub use refinement::{
+    BoundsFirstRefinementPolicy, CostModelRefinementPolicy, DefaultRefinementPolicy,
+    LinearGraphFirstRefinementPolicy, RefinementContext, RefinementPolicy,
+    refinement_policy_from_config,
+};
+pub use session::VASSReachSession;
 pub use types::{
-    VASSReachRefinementAction, VASSReachSolverError, VASSReachSolverResult,
-    VASSReachSolverStatistics, VASSReachSolverStatus,
+    VASSReachPhaseTimes, VASSReachRefinementAction, VASSReachRouteStatistics,
+    VASSReachSeparatorStatistics, VASSReachSolverError, VASSReachSolverResult,
+    VASSReachSolverStatistics, VASSReachSolverStatus, VASSReachWitness,
 };
 
 use self::debug_trace::DebugTraceWriter;
 use crate::{
     automaton::{
-        Automaton, AutomatonEdge, AutomatonNode, FromLetter, GIndex, InitializedAutomaton,
+        Alphabet, Automaton, AutomatonEdge, AutomatonNode, FromLetter, GIndex,
+        InitializedAutomaton,
         algorithms::EdgeAutomatonAlgorithms,
         cfg::{update::CFGCounterUpdate, vasscfg::VASSCFG},
         dfa::minimization::Minimizable,
         implicit_cfg_product::{ImplicitCFGProduct, state::MultiGraphState},
+        karp_miller::{
+            KarpMillerCoverabilityTree, build_karp_miller_coverability_tree,
+            build_karp_miller_coverability_tree_with_stop_signal,
+        },
         linear_graph::{
             LinearGraph,
-            extender::{LinearGraphExtender, LinearGraphExtenderOutput},
+            extender::{
+                LinearGraphExtender, LinearGraphExtenderOutput, LinearGraphTemplateStore,
+                LinearGraphTemplateStoreStatistics,
+            },
         },
         ltc::{LTC, translation::LTCTranslation},
         path::Path,
         scc::{SCCAlgorithms, SCCDag, SCCDagRouteSummary},
-        vass::initialized::InitializedVASS,
+        vass::{counter::VASSCounterValuation, initialized::InitializedVASS},
+    },
+    config::{
+        DebugTraceConfig, DivideAndConquerConfig, ModuloMode, PreprocessingConfig,
+        RefinementAutomatonEngine, ShortWitnessConfig, UpdateEncoding, VASSReachConfig,
+        VASSZReachConfig,
+    },
+    solver::{
+        SolverStatus,
+        backend::{ArithmeticContext, CheckLimits},
+        vass_z_reach::VASSZReachSolver,
     },
-    config::{ModuloMode, VASSReachConfig, VASSZReachConfig},
-    solver::{SolverStatus, vass_z_reach::VASSZReachSolver},
 };
 
 type MultiGraphPath = Path<MultiGraphState, CFGCounterUpdate>;
@@ -36,11 +67,40 @@ type MultiGraphPath = Path<MultiGraphState, CFGCounterUpdate>;
 #[derive(Debug)]
 pub struct VASSReachSolver {
     config: VASSReachConfig,
+    refinement_policy: Box<dyn RefinementPolicy>,
     state: ImplicitCFGProduct,
     initial_status: Option<VASSReachSolverStatus>,
     step_count: u64,
+    separator_statistics: VASSReachSeparatorStatistics,
+    /// Template invariants shared by the LinearGraph extenders of all steps.
+    template_store: LinearGraphTemplateStore,
+    route_statistics: VASSReachRouteStatistics,
+    /// Template store counters reported by route solvers.
+    route_template_store_statistics: LinearGraphTemplateStoreStatistics,
+    phase_times: VASSReachPhaseTimes,
+    refinement_start_time: Option<std::time::Instant>,
+    /// Raised by a parent solver once another route is known to be reachable,
+    /// or from outside to cancel the solver.
+    stop_signal: Option<Arc<AtomicBool>>,
     solver_start_time: Option<std::time::Instant>,
     debug_trace_writer: Option<DebugTraceWriter>,
+    /// Arithmetic backend and SMT query dump of the queries issued by this
+    /// solver.
+    arithmetic: ArithmeticContext,
+}
+
+/// The completed and minimized CFG of `ivass` with the given encoding of
+/// updates.
+fn minimized_cfg<N: AutomatonNode, E: AutomatonEdge + FromLetter>(
+    ivass: &InitializedVASS<N, E>,
+    encoding: UpdateEncoding,
+) -> VASSCFG<()> {
+    let mut cfg = match encoding {
+        UpdateEncoding::Unary => ivass.to_cfg(),
+        UpdateEncoding::Binary => ivass.to_binary_cfg(),
+    };
+    cfg.make_complete(());
+    cfg.minimize()
 }
 
 impl VASSReachSolver {
@@ -50,30 +110,119 @@ impl VASSReachSolver {
     ) -> Self {
         let time = std::time::Instant::now();
 
-        let short_witness = witness::find_short_witness(ivass, config.get_short_witness());
-        let mut initial_status = short_witness
-            .as_ref()
-            .map(|_| VASSReachSolverStatus::True(()));
-        if let Some(found) = &short_witness {
-            tracing::info!(
-                depth = found.depth,
-                explored_configurations = found.explored_configurations,
-                "Short witness precheck found an N-reaching run"
-            );
+        let cfg = minimized_cfg(ivass, *config.get_update_encoding());
+
+        Self::from_cfg(ivass, cfg, &mut None, &mut None, config, time, None)
+    }
+
+    /// Like [`Self::new`] followed by [`Self::with_stop_signal`], except that
+    /// `signal` also cancels the work done here: the short witness search,
+    /// the Karp-Miller pre-check and the LinearGraph preprocessing.
+    pub fn new_with_stop_signal<N: AutomatonNode, E: AutomatonEdge + FromLetter>(
+        ivass: &InitializedVASS<N, E>,
+        config: VASSReachConfig,
+        signal: Arc<AtomicBool>,
+    ) -> Self {
+        let time = std::time::Instant::now();
+
+        let cfg = minimized_cfg(ivass, *config.get_update_encoding());
+
+        Self::from_cfg(ivass, cfg, &mut None, &mut None, config, time, Some(signal))
+    }
+
+    /// Creates the solver from the completed and minimized CFG of `ivass`.
+    /// `routes` and `karp_miller_tree` cache artefacts of that CFG and are
+    /// filled in when they are first needed, so a [`VASSReachSession`] can
+    /// share them between queries.
+    fn from_cfg<N: AutomatonNode, E: AutomatonEdge + FromLetter>(
+        ivass: &InitializedVASS<N, E>,
+        mut cfg: VASSCFG<()>,
+        routes: &mut Option<preprocess::PreprocessRoutes>,
+        karp_miller_tree: &mut Option<KarpMillerCoverabilityTree>,
+        config: VASSReachConfig,
+        time: std::time::Instant,
+        stop_signal: Option<Arc<AtomicBool>>,
+    ) -> Self {
+        let mut phase_times = VASSReachPhaseTimes {
+            cfg_construction: time.elapsed(),
+            ..Default::default()
+        };
+
+        let arithmetic =
+            ArithmeticContext::for_solver(config.get_arithmetic_backend(), config.get_smt_dump());
+
+        let phase = std::time::Instant::now();
+        let short_witness =
+            witness::find_short_witness(ivass, config.get_short_witness(), stop_signal.as_deref());
+        phase_times.short_witness = phase.elapsed();
+        let mut initial_status = None;
+        match short_witness {
+            Ok(Some(found)) => {
+                tracing::info!(
+                    depth = found.depth,
+                    explored_configurations = found.explored_configurations,
+                    "Short witness precheck found an N-reaching run"
+                );
+                initial_status = Some(SolverStatus::True(VASSReachWitness::Run(found.run)));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(error = %err, "Short witness precheck overflowed a counter");
+                initial_status = Some(SolverStatus::Unknown(VASSReachSolverError::CounterOverflow));
+            }
         }
 
-        let mut cfg = ivass.to_cfg();
-        cfg.make_complete(());
-        cfg = cfg.minimize();
+        if initial_status.is_none()
+            && *config.get_preprocessing().get_enabled()
+            && *config
+                .get_preprocessing()
+                .get_karp_miller_precheck_enabled()
+        {
+            let phase = std::time::Instant::now();
+            let tree = match (karp_miller_tree.take(), stop_signal.as_deref()) {
+                (Some(tree), _) => Ok(Some(tree)),
+                (None, Some(stop_signal)) => build_karp_miller_coverability_tree_with_stop_signal(
+                    &cfg,
+                    &ivass.initial_valuation,
+                    stop_signal,
+                ),
+                (None, None) => {
+                    build_karp_miller_coverability_tree(&cfg, &ivass.initial_valuation).map(Some)
+                }
+            };
+            match tree {
+                Ok(None) => tracing::info!("Karp-Miller pre-check was cancelled"),
+                Ok(Some(tree)) => {
+                    let tree = karp_miller_tree.insert(tree);
+                    if !tree.covers_accepting(&cfg, &ivass.final_valuation) {
+                        tracing::info!(
+                            tree_size = tree.nodes().len(),
+                            "Karp-Miller pre-check proved the final valuation not coverable"
+                        );
+                        initial_status = Some(SolverStatus::False(()));
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, "Karp-Miller pre-check overflowed a counter");
+                    initial_status =
+                        Some(SolverStatus::Unknown(VASSReachSolverError::CounterOverflow));
+                }
+            }
+            phase_times.karp_miller = phase.elapsed();
+        }
 
         if initial_status.is_none() {
+            let phase = std::time::Instant::now();
             let unprocessed_cfg = cfg.clone();
             cfg = match preprocess::run_preprocess_unreachable_linear_graph_from_scc_dag(
                 cfg,
+                routes,
                 &ivass.initial_valuation,
                 &ivass.final_valuation,
                 &config,
                 Some(time),
+                stop_signal.as_ref(),
+                &arithmetic,
             ) {
                 Ok(preprocess::PreprocessOutcome::Refined(cfg)) => cfg,
                 Ok(preprocess::PreprocessOutcome::Reachable(run)) => {
@@ -81,7 +230,8 @@ impl VASSReachSolver {
                         run_length = run.len(),
                         "Reused concrete N-reaching run from LinearGraph preprocessing"
                     );
-                    initial_status = Some(SolverStatus::True(()));
+                    initial_status =
+                        Some(SolverStatus::True(VASSReachWitness::Run(run.transitions)));
                     unprocessed_cfg
                 }
                 Err(status @ SolverStatus::Unknown(VASSReachSolverError::Timeout)) => {
@@ -89,6 +239,11 @@ impl VASSReachSolver {
                     initial_status = Some(status);
                     unprocessed_cfg
                 }
+                Err(status @ SolverStatus::Unknown(VASSReachSolverError::Cancelled)) => {
+                    tracing::info!("CFG preprocessing was cancelled");
+                    initial_status = Some(status);
+                    unprocessed_cfg
+                }
                 Err(status) => {
                     tracing::warn!(
                         ?status,
@@ -97,6 +252,7 @@ impl VASSReachSolver {
                     unprocessed_cfg
                 }
             };
+            phase_times.preprocessing = phase.elapsed();
         }
 
         tracing::debug!("{}", cfg.to_graphviz(None, None));
@@ -121,16 +277,76 @@ impl VASSReachSolver {
 
         tracing::info!("Solver initialized in {:?}", time.elapsed());
 
+        let mut solver = Self::from_product(state, config, initial_status, debug_trace_writer);
+        solver.phase_times = phase_times;
+        solver.arithmetic = arithmetic;
+        solver.stop_signal = stop_signal;
+        solver
+    }
+
+    /// Creates the solver for one divide-and-conquer route. The route CFG is
+    /// already preprocessed and the short witness search has already run.
+    fn for_route(
+        cfg: VASSCFG<()>,
+        initial_valuation: VASSCounterValuation,
+        final_valuation: VASSCounterValuation,
+        config: VASSReachConfig,
+        stop_signal: Arc<AtomicBool>,
+        arithmetic: ArithmeticContext,
+    ) -> Self {
+        let state = ImplicitCFGProduct::new(
+            initial_valuation.dimension(),
+            initial_valuation,
+            final_valuation,
+            cfg,
+            *config.get_bounded_counting_enabled(),
+        );
+
+        let mut solver = Self::from_product(state, config, None, None);
+        solver.stop_signal = Some(stop_signal);
+        solver.arithmetic = arithmetic;
+        solver
+    }
+
+    fn from_product(
+        state: ImplicitCFGProduct,
+        config: VASSReachConfig,
+        initial_status: Option<VASSReachSolverStatus>,
+        debug_trace_writer: Option<DebugTraceWriter>,
+    ) -> Self {
         VASSReachSolver {
+            refinement_policy: refinement_policy_from_config(config.get_refinement()),
             config,
             state,
             initial_status,
             step_count: 0,
+            separator_statistics: VASSReachSeparatorStatistics::default(),
+            template_store: LinearGraphTemplateStore::new(),
+            route_statistics: VASSReachRouteStatistics::default(),
+            route_template_store_statistics: LinearGraphTemplateStoreStatistics::default(),
+            phase_times: VASSReachPhaseTimes::default(),
+            refinement_start_time: None,
+            stop_signal: None,
             solver_start_time: None,
             debug_trace_writer,
+            arithmetic: ArithmeticContext::default(),
         }
     }
 
+    /// Stops the solver with [`VASSReachSolverError::Cancelled`] once `signal`
+    /// is raised. The signal is checked before every refinement step, use
+    /// [`Self::new_with_stop_signal`] to also cancel the setup.
+    pub fn with_stop_signal(mut self, signal: Arc<AtomicBool>) -> Self {
+        self.stop_signal = Some(signal);
+        self
+    }
+
+    /// Replaces the refinement policy selected by the configuration.
+    pub fn with_refinement_policy(mut self, policy: Box<dyn RefinementPolicy>) -> Self {
+        self.refinement_policy = policy;
+        self
+    }
+
     pub fn solve(&mut self) -> VASSReachSolverResult {
         self.solver_start_time = Some(std::time::Instant::now());
 
@@ -157,13 +373,27 @@ impl VASSReachSolver {
             return Err(status.clone());
         }
 
-        self.z_reach_precheck()?;
+        let phase = std::time::Instant::now();
+        let precheck = self.z_reach_precheck();
+        self.phase_times.z_reach_precheck = phase.elapsed();
+        precheck?;
+
+        let phase = std::time::Instant::now();
+        let divide_and_conquer = self.divide_and_conquer();
+        self.phase_times.divide_and_conquer = phase.elapsed();
+        divide_and_conquer?;
+
+        self.refinement_start_time = Some(std::time::Instant::now());
 
         loop {
             self.step_count += 1;
+            if let Some(dump) = self.arithmetic.dump() {
+                dump.set_step(self.step_count);
+            }
 
             self.max_iterations_reached()?;
             self.max_time_reached()?;
+            self.stop_requested()?;
 
             step_time = std::time::Instant::now();
 
@@ -187,6 +417,14 @@ impl VASSReachSolver {
                 return Err(SolverStatus::False(()));
             };
 
+            // the checks and refinements below apply the path to the initial
+            // valuation, which panics on overflow
+            if let Err(err) = path.try_get_path_final_valuation(&self.state.initial_valuation) {
+                tracing::warn!("Giving up on path of length {}: {}", path.len(), err);
+
+                return Err(SolverStatus::Unknown(VASSReachSolverError::CounterOverflow));
+            }
+
             let is_n_reaching =
                 path.is_n_reaching(&self.state.initial_valuation, &self.state.final_valuation);
 
@@ -196,7 +434,7 @@ impl VASSReachSolver {
             if is_n_reaching {
                 tracing::info!("Found N-reaching path: {:?}", path.to_fancy_string());
 
-                return Err(SolverStatus::True(()));
+                return Err(SolverStatus::True(VASSReachWitness::Run(path.transitions)));
             }
 
             tracing::debug!("Spurious path of length: {:?}", path.len());
@@ -233,7 +471,8 @@ impl VASSReachSolver {
 
         let z_reach_config = VASSZReachConfig::default()
             .with_timeout(*self.config.get_timeout())
-            .with_max_iterations(*self.config.get_max_iterations());
+            .with_max_iterations(*self.config.get_max_iterations())
+            .with_arithmetic_backend(self.config.get_arithmetic_backend().clone());
 
         let z_reach_result = VASSZReachSolver::new(
             self.state.main_cfg(),
@@ -241,6 +480,7 @@ impl VASSReachSolver {
             self.state.final_valuation.clone(),
             z_reach_config,
         )
+        .with_arithmetic_context(self.arithmetic.clone())
         .solve();
 
         tracing::info!("Preprocessing finished in {:?}", presolve_time.elapsed());
@@ -261,6 +501,95 @@ impl VASSReachSolver {
         }
     }
 
+    /// Solves one subproblem per accepting SCC-DAG route in parallel. Returns
+    /// `Ok` when the mode is disabled or does not split the instance.
+    fn divide_and_conquer(&mut self) -> Result<(), VASSReachSolverStatus> {
+        let config = self.config.get_divide_and_conquer();
+        if !*config.get_enabled() {
+            return Ok(());
+        }
+
+        let Some(subproblems) =
+            routes::route_subproblems(self.state.main_cfg(), *config.get_max_routes())
+        else {
+            tracing::info!(
+                max_routes = config.get_max_routes(),
+                "Too many SCC-DAG routes for divide-and-conquer; solving the full instance"
+            );
+            return Ok(());
+        };
+        if subproblems.is_empty() {
+            return Err(SolverStatus::False(()));
+        }
+        if subproblems.len() == 1 {
+            return Ok(());
+        }
+
+        let threads = config.get_threads().unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+        });
+        tracing::info!(
+            routes = subproblems.len(),
+            threads,
+            "Solving SCC-DAG routes independently"
+        );
+
+        // Route solvers only refine; everything that looks at the full
+        // instance has already run on this solver.
+        let route_config = self
+            .config
+            .clone()
+            .with_timeout(self.remaining_solver_time())
+            .with_divide_and_conquer(DivideAndConquerConfig::default().with_enabled(false))
+            .with_preprocessing(PreprocessingConfig::default().with_enabled(false))
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+            .with_debug_trace(DebugTraceConfig::default().with_enabled(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        self.route_statistics.routes = subproblems.len() as u64;
+
+        let results = routes::solve_route_subproblems(subproblems, threads, &stop, |index, cfg| {
+            VASSReachSolver::for_route(
+                cfg,
+                self.state.initial_valuation.clone(),
+                self.state.final_valuation.clone(),
+                route_config.clone(),
+                stop.clone(),
+                self.arithmetic.nested(&format!("route_{index:03}")),
+            )
+            .solve()
+        });
+
+        let mut unknown = None;
+        let mut witness = None;
+        for result in &results {
+            self.step_count += result.statistics.step_count;
+            self.separator_statistics
+                .merge(&result.statistics.separators);
+            self.route_template_store_statistics
+                .merge(&result.statistics.template_store);
+
+            match &result.status {
+                SolverStatus::True(route_witness) => {
+                    self.route_statistics.reachable += 1;
+                    witness.get_or_insert_with(|| route_witness.clone());
+                }
+                SolverStatus::False(_) => self.route_statistics.unreachable += 1,
+                SolverStatus::Unknown(reason) => {
+                    self.route_statistics.unknown += 1;
+                    unknown.get_or_insert_with(|| reason.clone());
+                }
+            }
+        }
+
+        Err(if let Some(witness) = witness {
+            SolverStatus::True(witness)
+        } else if let Some(reason) = unknown {
+            SolverStatus::Unknown(reason)
+        } else {
+            SolverStatus::False(())
+        })
+    }
+
     fn write_debug_trace_seed(&self, path: &MultiGraphPath, is_n_reaching: bool) {
         let Some(writer) = &self.debug_trace_writer else {
             return;
@@ -291,17 +620,23 @@ impl VASSReachSolver {
             VASSReachRefinementAction::IncreaseModulo(counter_index, x) => {
                 let current_mu = self.state.get_mu(counter_index);
                 let new_mu = match self.config.get_modulo().get_mode() {
-                    ModuloMode::Increment => current_mu + 1,
+                    ModuloMode::Increment => current_mu.checked_add(1),
                     ModuloMode::LeastCommonMultiple => {
-                        let mut new_mu = current_mu;
-                        while x.rem_euclid(new_mu)
-                            == self.state.final_valuation[counter_index].rem_euclid(new_mu)
+                        let mut new_mu = Some(current_mu);
+                        while let Some(mu) = new_mu
+                            && x.rem_euclid(mu)
+                                == self.state.final_valuation[counter_index].rem_euclid(mu)
                         {
-                            new_mu += current_mu;
+                            new_mu = mu.checked_add(current_mu);
                         }
                         new_mu
                     }
                 };
+                let Some(new_mu) = new_mu else {
+                    tracing::warn!("Modulus of counter {} overflowed", counter_index);
+
+                    return Err(SolverStatus::Unknown(VASSReachSolverError::CounterOverflow));
+                };
                 self.state.set_mu(counter_index, new_mu);
 
                 tracing::debug!(
@@ -330,7 +665,7 @@ impl VASSReachSolver {
                 );
             }
             VASSReachRefinementAction::BuildAutomaton => {
-                let cfg = self.build_linear_graph_refinement_cfg(path)?;
+                let cfg = self.build_refinement_cfg(path)?;
                 self.state.add_cfg(cfg.minimize());
             }
         }
@@ -338,6 +673,73 @@ impl VASSReachSolver {
         Ok(())
     }
 
+    /// Builds the separator for the spurious path with the configured engine.
+    fn build_refinement_cfg(
+        &mut self,
+        path: MultiGraphPath,
+    ) -> Result<VASSCFG<()>, VASSReachSolverStatus> {
+        match self.automaton_engine() {
+            RefinementAutomatonEngine::LinearGraph => self.linear_graph_separator(path),
+            RefinementAutomatonEngine::LTC => {
+                let cfg = self.timed_ltc(|solver| solver.ltc_separator(&path))?;
+                self.separator_statistics.ltc += 1;
+                Ok(cfg)
+            }
+            RefinementAutomatonEngine::LTCThenLinearGraph => {
+                // The strict LTC separator only removes the loops in the order
+                // of the path, so a LinearGraph generalizes better and the
+                // strict check is skipped.
+                match self.timed_ltc(|solver| solver.ltc_relaxed_separator(&path))? {
+                    Some(cfg) => {
+                        self.separator_statistics.ltc += 1;
+                        Ok(cfg)
+                    }
+                    None => self.linear_graph_separator(path),
+                }
+            }
+        }
+    }
+
+    /// The configured automaton engine. `LTCThenLinearGraph` only uses relaxed
+    /// LTC separators, so it is a plain LinearGraph engine when the relaxed
+    /// check is disabled.
+    fn automaton_engine(&self) -> RefinementAutomatonEngine {
+        let engine = *self.config.get_refinement().get_automaton_engine();
+        if engine == RefinementAutomatonEngine::LTCThenLinearGraph
+            && !*self.config.get_lts().get_relaxed_enabled()
+        {
+            return RefinementAutomatonEngine::LinearGraph;
+        }
+
+        engine
+    }
+
+    fn linear_graph_separator(
+        &mut self,
+        path: MultiGraphPath,
+    ) -> Result<VASSCFG<()>, VASSReachSolverStatus> {
+        let time = std::time::Instant::now();
+        let result = self.build_linear_graph_refinement_cfg(path);
+        self.separator_statistics.linear_graph_time += time.elapsed();
+
+        if result.is_ok() {
+            self.separator_statistics.linear_graph += 1;
+        }
+
+        result
+    }
+
+    fn timed_ltc<T>(
+        &mut self,
+        check: impl FnOnce(&Self) -> Result<T, VASSReachSolverStatus>,
+    ) -> Result<T, VASSReachSolverStatus> {
+        let time = std::time::Instant::now();
+        let result = check(self);
+        self.separator_statistics.ltc_time += time.elapsed();
+
+        result
+    }
+
     fn build_linear_graph_refinement_cfg(
         &mut self,
         primary_path: MultiGraphPath,
@@ -346,7 +748,17 @@ impl VASSReachSolver {
 
         let starting_paths = self.linear_graph_starting_paths(primary_path);
         let fallback_primary_path = starting_paths[0].clone();
-        let product_view = self.state.full_view();
+        // With modulo congruences enabled, the residues tracked by the modulo
+        // CFGs are encoded in the LinearGraph queries instead of the graphs.
+        let product_view = if *self
+            .config
+            .get_linear_graph()
+            .get_modulo_congruences_enabled()
+        {
+            self.state.view_without_modulo_cfgs()
+        } else {
+            self.state.full_view()
+        };
         let view_paths = starting_paths
             .iter()
             .map(|path| product_view.project_path(path))
@@ -369,8 +781,17 @@ impl VASSReachSolver {
                 self.config.get_linear_graph(),
             )
         }
-        .with_scc_dag(full_dag);
-        let mut cfg = match extender.run_with_witness() {
+        .with_scc_dag(full_dag)
+        .with_arithmetic_context(self.arithmetic.clone());
+        let template_store_enabled = *self.config.get_linear_graph().get_template_store_enabled();
+        if template_store_enabled {
+            extender = extender.with_template_store(&mut self.template_store);
+        }
+        let output = extender.run_with_witness();
+        if template_store_enabled {
+            extender.save_templates(&mut self.template_store);
+        }
+        let mut cfg = match output {
             LinearGraphExtenderOutput::Refinement(cfg) => cfg,
             LinearGraphExtenderOutput::Reachable(run)
                 if product_view.is_accepting(run.end())
@@ -383,7 +804,7 @@ impl VASSReachSolver {
                     run_length = run.len(),
                     "Reused concrete N-reaching run from LinearGraph refinement"
                 );
-                return Err(SolverStatus::True(()));
+                return Err(SolverStatus::True(VASSReachWitness::Run(run.transitions)));
             }
             LinearGraphExtenderOutput::Reachable(run) => {
                 tracing::warn!(
@@ -396,6 +817,9 @@ impl VASSReachSolver {
             LinearGraphExtenderOutput::Timeout => {
                 return Err(SolverStatus::Unknown(VASSReachSolverError::Timeout));
             }
+            LinearGraphExtenderOutput::CounterOverflow => {
+                return Err(SolverStatus::Unknown(VASSReachSolverError::CounterOverflow));
+            }
         };
         cfg.invert_mut();
         Ok(cfg)
@@ -434,131 +858,116 @@ impl VASSReachSolver {
 
     /// Selects a refinement action based on the given spurious path.
     fn select_refinement_action(&self, path: &MultiGraphPath) -> VASSReachRefinementAction {
-        // return VASSReachRefinementAction::BuildAutomaton;
-
-        let path_final_valuation = path.get_path_final_valuation(&self.state.initial_valuation);
-
-        tracing::debug!("Path final valuation: {:?}", path_final_valuation);
-
-        // we find a counter that turns negative
-        if *self.config.get_bounded_counting_enabled()
-            && let Some((counter, path_index)) =
-                path.find_negative_counter_forward(&self.state.initial_valuation)
-            && !path.is_counter_forwards_pumped(
-                self.state.dimension,
-                counter,
-                3,
-                *self.config.get_consider_modulo_for_pumping(),
-            )
-        {
-            let segment = path.slice(0..path_index);
-            // if the path before wasn't pumped, we increase the bound we count up to, to
-            // cover this path
-
-            let current_bound = self.state.get_forward_bound(counter);
-            let max_value = segment.max_counter_value(&self.state.initial_valuation, counter);
-            let max_value = i32::max(1, max_value) as u32;
+        let refinement = self.config.get_refinement();
+        let context = RefinementContext {
+            product: &self.state,
+            path,
+            bounded_counting_enabled: *self.config.get_bounded_counting_enabled(),
+            consider_modulo_for_pumping: *self.config.get_consider_modulo_for_pumping(),
+            pumping_threshold: *refinement.get_pumping_threshold(),
+            modulo_value_exponent: *refinement.get_modulo_value_exponent(),
+            modulo_mode: *self.config.get_modulo().get_mode(),
+        };
 
-            if current_bound < max_value {
-                return VASSReachRefinementAction::IncreaseForwardsBound(counter, max_value);
-            }
-        }
+        self.refinement_policy.select(&context)
+    }
 
-        // same as above, but from the back of the path
-        if *self.config.get_bounded_counting_enabled()
-            && let Some((counter, path_index)) =
-                path.find_negative_counter_backward(&self.state.final_valuation)
-            && !path.is_counter_backwards_pumped(
-                self.state.dimension,
-                counter,
-                3,
-                *self.config.get_consider_modulo_for_pumping(),
-            )
-        {
-            let segment = path.slice(path_index..path.len());
-
-            let current_bound = self.state.get_backward_bound(counter);
-            let max_value =
-                segment.max_counter_value_from_back(&self.state.initial_valuation, counter);
-            let max_value = i32::max(1, max_value) as u32;
-            if current_bound < max_value {
-                return VASSReachRefinementAction::IncreaseBackwardsBound(counter, max_value);
-            }
-        }
+    /// Builds and checks the LTC automaton for the given path.
+    fn ltc_separator(&self, path: &MultiGraphPath) -> Result<VASSCFG<()>, VASSReachSolverStatus> {
+        let translation = LTCTranslation::from_multi_graph_path(&self.state, path);
+        let ltc = translation.to_ltc(self.state.dimension);
 
-        if let Some((mismatch, difference)) =
-            path_final_valuation.find_mismatch(&self.state.final_valuation)
+        if *self.config.get_lts().get_relaxed_enabled()
+            && let Some(cfg) = self.ltc_relaxed(&ltc, &translation)?
         {
-            let max_value = path.max_counter_value(&self.state.initial_valuation, mismatch);
-            let current_mu = self.state.get_mu(mismatch);
-
-            // First we want the max value to be a lot bigger than mu. This way we don't
-            // increase mu when we stay bounded. Second we want the difference
-            // between the expected and actual final value to be quite small.
-            //
-            // TODO: Maybe we want to make sure that mu always stays below our counting
-            // bounds. When we would increase mu, but it would exceed the bound, we increase
-            // the bound instead.
-            if max_value > current_mu * current_mu && difference.abs() <= current_mu * 2 {
-                return VASSReachRefinementAction::IncreaseModulo(
-                    mismatch,
-                    path_final_valuation[mismatch],
-                );
-            }
+            return Ok(cfg);
         }
 
-        VASSReachRefinementAction::BuildAutomaton
+        self.ltc_strict(&ltc, &translation)
     }
 
-    /// Builds and checks the LTC automaton for the given path.
-    #[allow(dead_code)]
-    fn ltc(&self, path: &MultiGraphPath) -> Result<VASSCFG<()>, VASSReachSolverStatus> {
+    /// The relaxed LTC separator for the given path, `None` if the relaxed
+    /// LTC is reachable.
+    fn ltc_relaxed_separator(
+        &self,
+        path: &MultiGraphPath,
+    ) -> Result<Option<VASSCFG<()>>, VASSReachSolverStatus> {
         let translation = LTCTranslation::from_multi_graph_path(&self.state, path);
         let ltc = translation.to_ltc(self.state.dimension);
 
-        if *self.config.get_lts().get_relaxed_enabled() {
-            self.ltc_relaxed(ltc, translation)
-        } else {
-            self.ltc_strict(ltc, translation)
-        }
+        self.ltc_relaxed(&ltc, &translation)
     }
 
-    #[allow(dead_code)]
     fn ltc_relaxed(
         &self,
-        ltc: LTC,
-        translation: LTCTranslation<NodeIndex>,
-    ) -> Result<VASSCFG<()>, VASSReachSolverStatus> {
-        let result_relaxed =
-            ltc.reach_n_relaxed(&self.state.initial_valuation, &self.state.final_valuation);
+        ltc: &LTC,
+        translation: &LTCTranslation<NodeIndex>,
+    ) -> Result<Option<VASSCFG<()>>, VASSReachSolverStatus> {
+        let result = ltc.reach_n_relaxed_with_context(
+            &self.arithmetic,
+            self.check_limits(),
+            &self.state.initial_valuation,
+            &self.state.final_valuation,
+        );
+
+        if result.is_unknown() {
+            return Err(self.check_gave_up());
+        }
 
-        if result_relaxed.is_success() {
+        if result.is_success() {
             tracing::debug!("LTC is relaxed reachable");
 
-            self.ltc_strict(ltc, translation)
+            Ok(None)
         } else {
             tracing::debug!("LTC is not relaxed reachable");
 
-            Ok(translation.to_dfa(self.state.dimension, true))
+            Ok(Some(translation.to_dfa(self.state.alphabet(), true)))
         }
     }
 
-    #[allow(dead_code)]
     fn ltc_strict(
         &self,
-        ltc: LTC,
-        translation: LTCTranslation<NodeIndex>,
+        ltc: &LTC,
+        translation: &LTCTranslation<NodeIndex>,
     ) -> Result<VASSCFG<()>, VASSReachSolverStatus> {
-        let result_strict = ltc.reach_n(&self.state.initial_valuation, &self.state.final_valuation);
+        let result = ltc.reach_n_with_context(
+            &self.arithmetic,
+            self.check_limits(),
+            &self.state.initial_valuation,
+            &self.state.final_valuation,
+        );
+
+        if result.is_unknown() {
+            return Err(self.check_gave_up());
+        }
 
-        if result_strict.is_success() {
+        if result.is_success() {
             tracing::debug!("LTC is N-reachable");
 
-            Err(VASSReachSolverStatus::True(()))
+            Err(VASSReachSolverStatus::True(VASSReachWitness::Ltc))
         } else {
             tracing::debug!("LTC is not N-reachable");
 
-            Ok(translation.to_dfa(self.state.dimension, false))
+            Ok(translation.to_dfa(self.state.alphabet(), false))
+        }
+    }
+
+    /// Bounds a single check by the remaining solver time and the stop
+    /// signal.
+    fn check_limits(&self) -> CheckLimits<'_> {
+        CheckLimits {
+            deadline: self
+                .remaining_solver_time()
+                .and_then(|remaining| std::time::Instant::now().checked_add(remaining)),
+            stop_signal: self.stop_signal.as_deref(),
+        }
+    }
+
+    /// Why a check bounded by [`Self::check_limits`] was unknown.
+    fn check_gave_up(&self) -> VASSReachSolverStatus {
+        match self.stop_requested() {
+            Err(status) => status,
+            Ok(()) => SolverStatus::Unknown(VASSReachSolverError::Timeout),
         }
     }
 
@@ -592,14 +1001,42 @@ impl VASSReachSolver {
         Ok(())
     }
 
+    /// Lets a route solver give up once its parent already has an answer.
+    /// The parent discards the resulting status.
+    fn stop_requested(&self) -> Result<(), VASSReachSolverStatus> {
+        if self
+            .stop_signal
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::SeqCst))
+        {
+            return Err(SolverStatus::Unknown(VASSReachSolverError::Cancelled));
+        }
+
+        Ok(())
+    }
+
     fn get_solver_statistics(&self) -> VASSReachSolverStatistics {
-        VASSReachSolverStatistics::new(
+        let mut template_store = self.template_store.statistics().clone();
+        template_store.merge(&self.route_template_store_statistics);
+
+        let mut statistics = VASSReachSolverStatistics::new(
             self.step_count,
             self.state.mu.clone(),
             self.state.get_forward_bounds(),
             self.state.get_backward_bounds(),
             self.get_solver_time().unwrap_or_default(),
-        )
+            self.separator_statistics.clone(),
+            template_store,
+        );
+        statistics.routes = self.route_statistics.clone();
+        statistics.phases = VASSReachPhaseTimes {
+            refinement: self
+                .refinement_start_time
+                .map(|start| start.elapsed())
+                .unwrap_or_default(),
+            ..self.phase_times.clone()
+        };
+        statistics
     }
 
     fn get_solver_time(&self) -> Option<std::time::Duration> {
@@ -628,6 +1065,10 @@ impl VASSReachSolver {
             forwards_bound = ?result.statistics.forwards_bound,
             backwards_bound = ?result.statistics.backwards_bound,
             step_count = %result.statistics.step_count,
+            linear_graph_separators = %result.statistics.separators.linear_graph,
+            ltc_separators = %result.statistics.separators.ltc,
+            template_store_hits = %result.statistics.template_store.hits,
+            template_store_misses = %result.statistics.template_store.misses,
             time = ?result.statistics.time,
             "Result"
         );
@@ -1,4 +1,11 @@
-use std::{collections::VecDeque, time::Instant};
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
 
 use hashbrown::{HashMap, HashSet};
 use petgraph::graph::NodeIndex;
@@ -15,7 +22,9 @@ use crate::{
         vass::counter::VASSCounterValuation,
     },
     config::VASSReachConfig,
-    solver::{SolverStatus, linear_graph_reach::LinearGraphReachSolverOptions},
+    solver::{
+        SolverStatus, backend::ArithmeticContext, linear_graph_reach::LinearGraphReachSolverOptions,
+    },
 };
 
 type CFGPath = Path<NodeIndex, CFGCounterUpdate>;
@@ -31,12 +40,35 @@ struct AcceptingRoute<NIndex: GIndex, L: Letter> {
     accepting: NIndex,
 }
 
+/// The SCC DAG of a CFG together with its accepting routes. Neither depends
+/// on the valuations, so they can be shared by preprocessing runs for
+/// different initial and final valuations of the same CFG.
+pub(super) struct PreprocessRoutes {
+    dag: SCCDag<NodeIndex, CFGCounterUpdate>,
+    routes: Vec<AcceptingRoute<NodeIndex, CFGCounterUpdate>>,
+}
+
+impl PreprocessRoutes {
+    fn new(cfg: &VASSCFG<()>, max_candidates: usize) -> Self {
+        let dag = cfg.find_scc_dag().with_rolled_trivial_paths();
+        let routes = collect_accepting_routes(&dag, max_candidates);
+        PreprocessRoutes { dag, routes }
+    }
+}
+
+/// `routes` caches the SCC DAG routes of `cfg`. They are computed on first
+/// use, pass the same cache only together with the same CFG. Raising
+/// `stop_signal` cancels the preprocessing.
+#[allow(clippy::too_many_arguments)]
 pub(super) fn run_preprocess_unreachable_linear_graph_from_scc_dag(
     cfg: VASSCFG<()>,
+    routes: &mut Option<PreprocessRoutes>,
     initial_valuation: &VASSCounterValuation,
     final_valuation: &VASSCounterValuation,
     config: &VASSReachConfig,
     solver_start_time: Option<Instant>,
+    stop_signal: Option<&Arc<AtomicBool>>,
+    arithmetic: &ArithmeticContext,
 ) -> Result<PreprocessOutcome, VASSReachSolverStatus> {
     if !*config.get_preprocessing().get_enabled() {
         return Ok(PreprocessOutcome::Refined(cfg));
@@ -56,9 +88,9 @@ pub(super) fn run_preprocess_unreachable_linear_graph_from_scc_dag(
     max_time_reached(config, solver_start_time)?;
 
     let base_cfg = cfg;
-    let dag = base_cfg.find_scc_dag().with_rolled_trivial_paths();
     let max_candidates = *config.get_preprocessing().get_max_linear_graph_candidates();
-    let routes = collect_accepting_routes(&dag, max_candidates);
+    let PreprocessRoutes { dag, routes } =
+        routes.get_or_insert_with(|| PreprocessRoutes::new(&base_cfg, max_candidates));
 
     if routes.is_empty() {
         tracing::debug!("No SCC-DAG LinearGraph preprocessing routes found");
@@ -79,13 +111,14 @@ pub(super) fn run_preprocess_unreachable_linear_graph_from_scc_dag(
 
     let dimension = initial_valuation.dimension();
 
-    for route in routes {
+    for route in routes.iter() {
         max_time_reached(config, solver_start_time)?;
+        stop_requested(stop_signal)?;
 
         let Some(linear_graph) = build_linear_graph_from_route(
             &base_cfg,
             dimension,
-            &dag,
+            dag,
             &route.edges,
             &route.accepting,
         ) else {
@@ -93,8 +126,13 @@ pub(super) fn run_preprocess_unreachable_linear_graph_from_scc_dag(
             continue;
         };
 
-        let solver_result = LinearGraphReachSolverOptions::default()
+        let mut options = LinearGraphReachSolverOptions::default()
             .with_optional_time_limit(remaining_time(config, solver_start_time))
+            .with_arithmetic_context(arithmetic.clone());
+        if let Some(signal) = stop_signal {
+            options = options.with_stop_signal(signal.clone());
+        }
+        let solver_result = options
             .into_solver(&linear_graph, initial_valuation, final_valuation)
             .solve();
 
@@ -106,11 +144,15 @@ pub(super) fn run_preprocess_unreachable_linear_graph_from_scc_dag(
                 unreachable += 1;
             }
             SolverStatus::True(solution) => {
-                if let Some(run) = solution.build_run_with_deadline(
-                    &linear_graph,
-                    true,
-                    global_deadline(config, solver_start_time),
-                ) && base_cfg.is_accepting(run.end())
+                let run = solution
+                    .build_run_with_deadline(
+                        &linear_graph,
+                        true,
+                        global_deadline(config, solver_start_time),
+                    )
+                    .map_err(|_| SolverStatus::Unknown(VASSReachSolverError::CounterOverflow))?;
+                if let Some(run) = run
+                    && base_cfg.is_accepting(run.end())
                     && run.is_n_reaching(initial_valuation, final_valuation)
                 {
                     tracing::info!(
@@ -122,6 +164,7 @@ pub(super) fn run_preprocess_unreachable_linear_graph_from_scc_dag(
                 reachable += 1;
             }
             SolverStatus::Unknown(reason) => {
+                stop_requested(stop_signal)?;
                 if matches!(
                     reason,
                     crate::solver::linear_graph_reach::LinearGraphReachSolverError::Timeout
@@ -400,3 +443,11 @@ fn max_time_reached(
 
     Ok(())
 }
+
+fn stop_requested(stop_signal: Option<&Arc<AtomicBool>>) -> Result<(), VASSReachSolverStatus> {
+    if stop_signal.is_some_and(|stop| stop.load(Ordering::SeqCst)) {
+        return Err(SolverStatus::Unknown(VASSReachSolverError::Cancelled));
+    }
+
+    Ok(())
+}
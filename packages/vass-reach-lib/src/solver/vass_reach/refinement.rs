@@ -0,0 +1,268 @@
+//! Policies that choose how a spurious path refines the product.
+//!
+//! A policy only picks a [`VASSReachRefinementAction`]; the solver applies it.
+//! Every action removes the spurious path from the approximation, so any
+//! policy keeps the refinement loop sound, but the choice decides how fast
+//! the product grows and how many steps the solver needs.
+
+use std::fmt::Debug;
+
+use hashbrown::HashSet;
+
+use super::{MultiGraphPath, types::VASSReachRefinementAction};
+use crate::{
+    automaton::implicit_cfg_product::ImplicitCFGProduct,
+    config::{ModuloMode, RefinementConfig, RefinementPolicyKind},
+};
+
+/// Everything a policy may look at when choosing a refinement action.
+#[derive(Debug, Clone, Copy)]
+pub struct RefinementContext<'a> {
+    pub product: &'a ImplicitCFGProduct,
+    pub path: &'a MultiGraphPath,
+    pub bounded_counting_enabled: bool,
+    pub consider_modulo_for_pumping: bool,
+    pub pumping_threshold: u32,
+    pub modulo_value_exponent: u32,
+    pub modulo_mode: ModuloMode,
+}
+
+pub trait RefinementPolicy: Debug + Send + Sync {
+    /// Chooses the action that removes `context.path` from the product.
+    fn select(&self, context: &RefinementContext<'_>) -> VASSReachRefinementAction;
+}
+
+/// Builds the policy selected by the configuration.
+pub fn refinement_policy_from_config(config: &RefinementConfig) -> Box<dyn RefinementPolicy> {
+    match config.get_policy() {
+        RefinementPolicyKind::Default => Box::new(DefaultRefinementPolicy),
+        RefinementPolicyKind::LinearGraphFirst => Box::new(LinearGraphFirstRefinementPolicy),
+        RefinementPolicyKind::BoundsFirst => Box::new(BoundsFirstRefinementPolicy),
+        RefinementPolicyKind::CostModel => Box::new(CostModelRefinementPolicy),
+    }
+}
+
+/// Increases counting bounds for unpumped negative prefixes or suffixes and
+/// the modulus for small final mismatches on large values. Everything else is
+/// cut with a LinearGraph automaton.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRefinementPolicy;
+
+impl RefinementPolicy for DefaultRefinementPolicy {
+    fn select(&self, context: &RefinementContext<'_>) -> VASSReachRefinementAction {
+        let path_final_valuation = context
+            .path
+            .get_path_final_valuation(&context.product.initial_valuation);
+
+        tracing::debug!("Path final valuation: {:?}", path_final_valuation);
+
+        forward_bound_action(context, true)
+            .or_else(|| backward_bound_action(context, true))
+            .or_else(|| modulo_action(context, true))
+            .unwrap_or(VASSReachRefinementAction::BuildAutomaton)
+    }
+}
+
+/// Always cuts the spurious path with a LinearGraph automaton. Bounds and
+/// moduli keep their initial values.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearGraphFirstRefinementPolicy;
+
+impl RefinementPolicy for LinearGraphFirstRefinementPolicy {
+    fn select(&self, _context: &RefinementContext<'_>) -> VASSReachRefinementAction {
+        VASSReachRefinementAction::BuildAutomaton
+    }
+}
+
+/// Increases a counting bound whenever that removes the path, even if the
+/// counter was pumped, and the modulus for every small final mismatch. Builds
+/// an automaton only when neither applies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundsFirstRefinementPolicy;
+
+impl RefinementPolicy for BoundsFirstRefinementPolicy {
+    fn select(&self, context: &RefinementContext<'_>) -> VASSReachRefinementAction {
+        forward_bound_action(context, false)
+            .or_else(|| backward_bound_action(context, false))
+            .or_else(|| modulo_action(context, false))
+            .unwrap_or(VASSReachRefinementAction::BuildAutomaton)
+    }
+}
+
+/// Picks the applicable action with the smallest estimated growth of the
+/// product state space.
+///
+/// The product has up to as many states as the product of the state counts of
+/// its CFGs, so every action is measured by the factor it multiplies that
+/// bound with. Raising a bound or modulus replaces a CFG, whose size grows
+/// linearly with the bound or modulus, so the factor is the ratio of the new
+/// and the old size. A LinearGraph separator adds a CFG, so the factor is its
+/// size, estimated by the number of distinct states on the spurious path, which
+/// bounds the size of the seed automaton. Ties go to the LinearGraph
+/// automaton.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CostModelRefinementPolicy;
+
+impl RefinementPolicy for CostModelRefinementPolicy {
+    fn select(&self, context: &RefinementContext<'_>) -> VASSReachRefinementAction {
+        let automaton = (
+            VASSReachRefinementAction::BuildAutomaton,
+            Self::automaton_growth(context),
+        );
+
+        [
+            forward_bound_action(context, false),
+            backward_bound_action(context, false),
+            modulo_action(context, false),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|action| {
+            let growth = Self::action_growth(context, &action);
+            (action, growth)
+        })
+        .fold(automaton, |best, candidate| {
+            if candidate.1 < best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+        .0
+    }
+}
+
+impl CostModelRefinementPolicy {
+    fn action_growth(context: &RefinementContext<'_>, action: &VASSReachRefinementAction) -> f64 {
+        let product = context.product;
+
+        match action {
+            VASSReachRefinementAction::IncreaseForwardsBound(counter, bound) => {
+                bounded_counting_growth(product.get_forward_bound(*counter), *bound)
+            }
+            VASSReachRefinementAction::IncreaseBackwardsBound(counter, bound) => {
+                bounded_counting_growth(product.get_backward_bound(*counter), *bound)
+            }
+            VASSReachRefinementAction::IncreaseModulo(counter, _) => {
+                let mu = product.get_mu(*counter) as f64;
+                match context.modulo_mode {
+                    ModuloMode::Increment => (mu + 1.0) / mu,
+                    // The least common multiple strategy at least doubles mu.
+                    ModuloMode::LeastCommonMultiple => 2.0,
+                }
+            }
+            VASSReachRefinementAction::BuildAutomaton => Self::automaton_growth(context),
+        }
+    }
+
+    fn automaton_growth(context: &RefinementContext<'_>) -> f64 {
+        let distinct_states = context.path.states.iter().collect::<HashSet<_>>().len();
+        // The inverted separator needs at least one extra sink state.
+        (distinct_states + 1) as f64
+    }
+}
+
+fn bounded_counting_growth(current: u32, next: u32) -> f64 {
+    // A bounded counting CFG has `bound + 1` counting states, a negative sink
+    // and an overflow sink.
+    (next as f64 + 3.0) / (current as f64 + 3.0)
+}
+
+/// Raises the forward bound of the first counter that turns negative, if the
+/// prefix before it stayed below the current bound.
+fn forward_bound_action(
+    context: &RefinementContext<'_>,
+    require_unpumped: bool,
+) -> Option<VASSReachRefinementAction> {
+    if !context.bounded_counting_enabled {
+        return None;
+    }
+
+    let product = context.product;
+    let path = context.path;
+    let (counter, path_index) = path.find_negative_counter_forward(&product.initial_valuation)?;
+
+    if require_unpumped
+        && path.is_counter_forwards_pumped(
+            product.dimension,
+            counter,
+            context.pumping_threshold,
+            context.consider_modulo_for_pumping,
+        )
+    {
+        return None;
+    }
+
+    // if the path before wasn't pumped, we increase the bound we count up to, to
+    // cover this path
+    let segment = path.slice(0..path_index);
+    let max_value = segment.max_counter_value(&product.initial_valuation, counter);
+    let max_value = u32::try_from(max_value.max(1)).unwrap_or(u32::MAX);
+
+    (product.get_forward_bound(counter) < max_value).then_some(
+        VASSReachRefinementAction::IncreaseForwardsBound(counter, max_value),
+    )
+}
+
+/// Same as [`forward_bound_action`], but from the back of the path.
+fn backward_bound_action(
+    context: &RefinementContext<'_>,
+    require_unpumped: bool,
+) -> Option<VASSReachRefinementAction> {
+    if !context.bounded_counting_enabled {
+        return None;
+    }
+
+    let product = context.product;
+    let path = context.path;
+    let (counter, path_index) = path.find_negative_counter_backward(&product.final_valuation)?;
+
+    if require_unpumped
+        && path.is_counter_backwards_pumped(
+            product.dimension,
+            counter,
+            context.pumping_threshold,
+            context.consider_modulo_for_pumping,
+        )
+    {
+        return None;
+    }
+
+    let segment = path.slice(path_index..path.len());
+    let max_value = segment.max_counter_value_from_back(&product.initial_valuation, counter);
+    let max_value = u32::try_from(max_value.max(1)).unwrap_or(u32::MAX);
+
+    (product.get_backward_bound(counter) < max_value).then_some(
+        VASSReachRefinementAction::IncreaseBackwardsBound(counter, max_value),
+    )
+}
+
+/// Raises the modulus of the first counter whose final value misses the target
+/// by a small amount.
+fn modulo_action(
+    context: &RefinementContext<'_>,
+    require_large_values: bool,
+) -> Option<VASSReachRefinementAction> {
+    let product = context.product;
+    let path = context.path;
+    let path_final_valuation = path.get_path_final_valuation(&product.initial_valuation);
+    let (mismatch, difference) = path_final_valuation.find_mismatch(&product.final_valuation)?;
+
+    let max_value = path.max_counter_value(&product.initial_valuation, mismatch);
+    let current_mu = product.get_mu(mismatch);
+
+    // First we want the max value to be a lot bigger than mu. This way we don't
+    // increase mu when we stay bounded. Second we want the difference
+    // between the expected and actual final value to be quite small.
+    //
+    // TODO: Maybe we want to make sure that mu always stays below our counting
+    // bounds. When we would increase mu, but it would exceed the bound, we increase
+    // the bound instead.
+    let large_values = !require_large_values
+        || max_value > current_mu.saturating_pow(context.modulo_value_exponent);
+    (large_values && difference.unsigned_abs() <= current_mu.unsigned_abs().saturating_mul(2))
+        .then_some(VASSReachRefinementAction::IncreaseModulo(
+            mismatch,
+            path_final_valuation[mismatch],
+        ))
+}
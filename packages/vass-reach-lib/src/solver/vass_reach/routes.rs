@@ -0,0 +1,243 @@
+//! Divide-and-conquer over the accepting routes of the main CFG's SCC DAG.
+//!
+//! Every accepting run visits a chain of SCCs from the root component to the
+//! component it ends in. Restricting the CFG to one such chain yields a
+//! subproblem, and the union of all subproblem languages is the language of
+//! the full CFG. The instance is reachable iff some subproblem is reachable,
+//! so subproblems can be solved independently and in parallel.
+
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use hashbrown::HashMap;
+use petgraph::{graph::NodeIndex, visit::EdgeRef};
+
+use super::VASSReachSolverResult;
+use crate::automaton::{
+    Alphabet, InitializedAutomaton, ModifiableAutomaton,
+    cfg::{update::CFGCounterUpdate, vasscfg::VASSCFG},
+    dfa::{minimization::Minimizable, node::DfaNode},
+    scc::{SCCAlgorithms, SCCDag},
+};
+
+/// The main CFG restricted to one root-to-accepting chain of SCCs.
+pub(super) struct RouteSubproblem {
+    pub(super) cfg: VASSCFG<()>,
+    pub(super) difficulty: RouteDifficulty,
+}
+
+/// Cheap static estimate used to schedule easy routes first. Cycles are what
+/// make refinement expensive, so edges inside cyclic components dominate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct RouteDifficulty {
+    pub(super) cyclic_edges: usize,
+    pub(super) states: usize,
+}
+
+/// Splits `cfg` into one subproblem per accepting SCC route, ordered by
+/// estimated difficulty. Returns `None` when there are more than `max_routes`
+/// routes, because refuting a partial set of routes proves nothing.
+pub(super) fn route_subproblems(
+    cfg: &VASSCFG<()>,
+    max_routes: usize,
+) -> Option<Vec<RouteSubproblem>> {
+    let dag = cfg.find_scc_dag();
+    if dag.components.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut subproblems = component_routes(&dag, max_routes)?
+        .iter()
+        .map(|route| restrict_to_route(cfg, &dag, route))
+        .collect::<Vec<_>>();
+    subproblems.sort_by_key(|subproblem| subproblem.difficulty);
+    Some(subproblems)
+}
+
+/// Enumerates the distinct component sequences from the root to components
+/// with accepting states. Parallel DAG edges between the same components lead
+/// to the same subproblem and are visited once. Routes are counted first, so
+/// the enumeration never enters components without an accepting successor and
+/// stops before it starts when there are too many routes.
+fn component_routes(
+    dag: &SCCDag<NodeIndex, CFGCounterUpdate>,
+    max_routes: usize,
+) -> Option<Vec<Vec<usize>>> {
+    let targets = (0..dag.components.len())
+        .map(|component| {
+            let mut targets = dag
+                .outgoing_edges(component)
+                .iter()
+                .map(|edge| edge.target_component)
+                .collect::<Vec<_>>();
+            targets.sort_unstable();
+            targets.dedup();
+            targets
+        })
+        .collect::<Vec<_>>();
+    let route_counts = accepting_route_counts(dag, &targets);
+    if route_counts[dag.root_component] > max_routes {
+        return None;
+    }
+
+    let mut routes = Vec::new();
+    let mut stack = vec![vec![dag.root_component]];
+
+    while let Some(route) = stack.pop() {
+        let component = *route.last().expect("routes are never empty");
+        if !dag.components[component].accepting_nodes.is_empty() {
+            routes.push(route.clone());
+        }
+
+        for &target in targets[component].iter().rev() {
+            if route_counts[target] == 0 {
+                continue;
+            }
+            let mut next = route.clone();
+            next.push(target);
+            stack.push(next);
+        }
+    }
+
+    Some(routes)
+}
+
+/// Counts the accepting routes starting in each component, saturating at
+/// `usize::MAX`.
+fn accepting_route_counts(
+    dag: &SCCDag<NodeIndex, CFGCounterUpdate>,
+    targets: &[Vec<usize>],
+) -> Vec<usize> {
+    let mut counts: Vec<Option<usize>> = vec![None; dag.components.len()];
+    let mut stack = vec![dag.root_component];
+
+    while let Some(&component) = stack.last() {
+        if counts[component].is_some() {
+            stack.pop();
+            continue;
+        }
+
+        let pending = targets[component]
+            .iter()
+            .filter(|target| counts[**target].is_none())
+            .copied()
+            .collect::<Vec<_>>();
+        if !pending.is_empty() {
+            stack.extend(pending);
+            continue;
+        }
+
+        let own = usize::from(!dag.components[component].accepting_nodes.is_empty());
+        counts[component] = Some(targets[component].iter().fold(own, |sum, target| {
+            sum.saturating_add(counts[*target].unwrap())
+        }));
+        stack.pop();
+    }
+
+    counts.into_iter().map(|count| count.unwrap_or(0)).collect()
+}
+
+fn restrict_to_route(
+    cfg: &VASSCFG<()>,
+    dag: &SCCDag<NodeIndex, CFGCounterUpdate>,
+    route: &[usize],
+) -> RouteSubproblem {
+    let last = route.len() - 1;
+    let mut restricted = VASSCFG::new(cfg.alphabet().to_vec());
+    let mut position_of = HashMap::new();
+    let mut node_map = HashMap::new();
+
+    for (position, component) in route.iter().enumerate() {
+        let component = &dag.components[*component];
+        for node in &component.nodes {
+            // Only the final component of the route may end the run.
+            let data = if position == last && component.accepting_nodes.contains(node) {
+                DfaNode::accepting(())
+            } else {
+                DfaNode::non_accepting(())
+            };
+            position_of.insert(*node, position);
+            node_map.insert(*node, restricted.add_node(data));
+        }
+    }
+    restricted.set_initial(node_map[&cfg.get_initial()]);
+
+    let mut cyclic_edges = 0;
+    for edge in cfg.graph.edge_references() {
+        let (Some(source), Some(target)) = (
+            position_of.get(&edge.source()),
+            position_of.get(&edge.target()),
+        ) else {
+            continue;
+        };
+
+        if source == target {
+            if dag.components[route[*source]].cyclic {
+                cyclic_edges += 1;
+            }
+        } else if *target != source + 1 {
+            continue;
+        }
+
+        restricted.add_edge(
+            &node_map[&edge.source()],
+            &node_map[&edge.target()],
+            *edge.weight(),
+        );
+    }
+
+    let states = node_map.len();
+    restricted.make_complete(());
+
+    RouteSubproblem {
+        cfg: restricted.minimize(),
+        difficulty: RouteDifficulty {
+            cyclic_edges,
+            states,
+        },
+    }
+}
+
+/// Solves subproblems on `threads` workers in the given order. Once one route
+/// is reachable, `stop` is raised so that workers pick up no further routes
+/// and running route solvers can give up early.
+pub(super) fn solve_route_subproblems(
+    subproblems: Vec<RouteSubproblem>,
+    threads: usize,
+    stop: &AtomicBool,
+    solve: impl Fn(usize, VASSCFG<()>) -> VASSReachSolverResult + Sync,
+) -> Vec<VASSReachSolverResult> {
+    let workers = threads.clamp(1, subproblems.len().max(1));
+    let queue = Mutex::new(
+        subproblems
+            .into_iter()
+            .map(|subproblem| subproblem.cfg)
+            .enumerate(),
+    );
+    let results = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while !stop.load(Ordering::SeqCst) {
+                    let Some((index, cfg)) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+
+                    let result = solve(index, cfg);
+                    if result.is_success() {
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                    results.lock().unwrap().push(result);
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap()
+}
@@ -0,0 +1,117 @@
+use hashbrown::HashMap;
+
+use super::{VASSReachSolver, VASSReachSolverResult, minimized_cfg, preprocess::PreprocessRoutes};
+use crate::{
+    automaton::{
+        AutomatonEdge, AutomatonNode, FromLetter,
+        cfg::vasscfg::VASSCFG,
+        karp_miller::KarpMillerCoverabilityTree,
+        linear_graph::extender::LinearGraphTemplateStore,
+        vass::{counter::VASSCounterValuation, initialized::InitializedVASS},
+    },
+    config::VASSReachConfig,
+};
+
+/// Answers many reachability queries that only differ in their initial and
+/// final valuation.
+///
+/// Everything that does not depend on the final valuation is computed once and
+/// reused: the minimized CFG and its SCC-DAG routes for all queries, the
+/// Karp-Miller tree and the LinearGraph template bounds per initial valuation.
+pub struct VASSReachSession<N: AutomatonNode, E: AutomatonEdge + FromLetter> {
+    ivass: InitializedVASS<N, E>,
+    config: VASSReachConfig,
+    cfg: VASSCFG<()>,
+    routes: Option<PreprocessRoutes>,
+    karp_miller_trees: HashMap<VASSCounterValuation, Option<KarpMillerCoverabilityTree>>,
+    template_stores: HashMap<VASSCounterValuation, LinearGraphTemplateStore>,
+}
+
+impl<N: AutomatonNode, E: AutomatonEdge + FromLetter> VASSReachSession<N, E> {
+    /// The valuations of `ivass` are replaced by those of each query. The CFG
+    /// encodes additional initial and final states relative to the primary
+    /// valuations, so `ivass` has to have a single initial and final state.
+    pub fn new(ivass: &InitializedVASS<N, E>, config: VASSReachConfig) -> Self {
+        assert!(
+            ivass.has_single_initial_and_final_state(),
+            "Reachability sessions require a single initial and final state"
+        );
+
+        let cfg = minimized_cfg(ivass, *config.get_update_encoding());
+
+        VASSReachSession {
+            ivass: ivass.clone(),
+            config,
+            cfg,
+            routes: None,
+            karp_miller_trees: HashMap::new(),
+            template_stores: HashMap::new(),
+        }
+    }
+
+    pub fn cfg(&self) -> &VASSCFG<()> {
+        &self.cfg
+    }
+
+    pub fn solve(
+        &mut self,
+        initial_valuation: VASSCounterValuation,
+        final_valuation: VASSCounterValuation,
+    ) -> VASSReachSolverResult {
+        let time = std::time::Instant::now();
+
+        assert_eq!(
+            initial_valuation.dimension(),
+            self.ivass.dimension(),
+            "Initial valuation has to have the same length as the dimension"
+        );
+        assert_eq!(
+            final_valuation.dimension(),
+            self.ivass.dimension(),
+            "Final valuation has to have the same length as the dimension"
+        );
+
+        let mut query = self.ivass.clone();
+        query.initial_valuation = initial_valuation;
+        query.final_valuation = final_valuation;
+
+        let karp_miller_tree = self
+            .karp_miller_trees
+            .entry(query.initial_valuation.clone())
+            .or_default();
+
+        let mut solver = VASSReachSolver::from_cfg(
+            &query,
+            self.cfg.clone(),
+            &mut self.routes,
+            karp_miller_tree,
+            self.config.clone(),
+            time,
+            None,
+        );
+
+        let template_store = self
+            .template_stores
+            .entry(query.initial_valuation.clone())
+            .or_default();
+        template_store.reset_statistics();
+        std::mem::swap(&mut solver.template_store, template_store);
+
+        let result = solver.solve();
+
+        std::mem::swap(&mut solver.template_store, template_store);
+        result
+    }
+
+    /// Solves the queries in order, one result per query.
+    pub fn solve_all<'a>(
+        &'a mut self,
+        queries: impl IntoIterator<Item = (VASSCounterValuation, VASSCounterValuation)> + 'a,
+    ) -> impl Iterator<Item = VASSReachSolverResult> + 'a {
+        queries
+            .into_iter()
+            .map(|(initial_valuation, final_valuation)| {
+                self.solve(initial_valuation, final_valuation)
+            })
+    }
+}
@@ -1,24 +1,30 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    automaton::vass::counter::VASSCounterIndex,
+    automaton::{
+        cfg::update::CFGCounterUpdate,
+        linear_graph::extender::LinearGraphTemplateStoreStatistics,
+        vass::{counter::VASSCounterIndex, valuation_spec::SpecRun},
+    },
     solver::{SolverResult, SolverStatus},
 };
 
 /// Enum representing the different refinement actions that the algorithm can
 /// do.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VASSReachRefinementAction {
     /// Increase the modulo for the given counter, depending on strategy, so
     /// that the given value does no longer equal the final valuation modulo mu.
-    IncreaseModulo(VASSCounterIndex, i32),
+    IncreaseModulo(VASSCounterIndex, i64),
     /// Increase the forward counting bound for the given counter to the given
     /// value.
     IncreaseForwardsBound(VASSCounterIndex, u32),
     /// Increase the backward counting bound for the given counter to the given
     /// value.
     IncreaseBackwardsBound(VASSCounterIndex, u32),
-    /// Build some automaton (LTC, LinearGraph, ...?) to cut away the spurious
-    /// path.
+    /// Build an automaton with the configured
+    /// [`RefinementAutomatonEngine`](crate::config::RefinementAutomatonEngine)
+    /// to cut away the spurious path.
     BuildAutomaton,
 }
 
@@ -29,24 +35,82 @@ pub enum VASSReachSolverError {
     Timeout,
     /// We hit the maximum number of iterations.
     MaxIterationsReached,
+    /// The stop signal was raised from outside, see
+    /// [`VASSReachSolver::with_stop_signal`](super::VASSReachSolver::with_stop_signal).
+    Cancelled,
+    /// A counter value or modulus left the range of `i64`.
+    CounterOverflow,
+}
+
+/// Evidence that an instance is reachable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VASSReachWitness {
+    /// An N-run from the initial to the final valuation, given by the counter
+    /// updates along it. They are weighted with the binary update encoding.
+    Run(Vec<CFGCounterUpdate>),
+    /// A run of an instance with valuation specs, between the valuations it
+    /// chose, see
+    /// [`InitializedVASS::spec_run`](crate::automaton::vass::initialized::InitializedVASS::spec_run).
+    SpecRun(SpecRun),
+    /// An N-reachable LTC. The solver does not extract a run from it.
+    Ltc,
+}
+
+impl std::fmt::Display for VASSReachWitness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VASSReachWitness::Run(updates) => {
+                write!(f, "run of {} updates:", updates.len())?;
+                for update in updates {
+                    write!(f, " {update}")?;
+                }
+                Ok(())
+            }
+            VASSReachWitness::SpecRun(run) => {
+                write!(
+                    f,
+                    "run of {} updates from {:?} to {:?}:",
+                    run.updates.len(),
+                    run.initial_valuation.iter().collect::<Vec<_>>(),
+                    run.final_valuation.iter().collect::<Vec<_>>()
+                )?;
+                for update in &run.updates {
+                    write!(f, " {update}")?;
+                }
+                Ok(())
+            }
+            VASSReachWitness::Ltc => write!(f, "N-reachable LTC"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VASSReachSolverStatistics {
     pub step_count: u64,
-    pub mu: Box<[i32]>,
+    pub mu: Box<[i64]>,
     pub forwards_bound: Box<[u32]>,
     pub backwards_bound: Box<[u32]>,
     pub time: std::time::Duration,
+    #[serde(default)]
+    pub separators: VASSReachSeparatorStatistics,
+    #[serde(default)]
+    pub template_store: LinearGraphTemplateStoreStatistics,
+    #[serde(default)]
+    pub routes: VASSReachRouteStatistics,
+    #[serde(default)]
+    pub phases: VASSReachPhaseTimes,
 }
 
 impl VASSReachSolverStatistics {
     pub fn new(
         step_count: u64,
-        mu: Box<[i32]>,
+        mu: Box<[i64]>,
         forwards_bound: Box<[u32]>,
         backwards_bound: Box<[u32]>,
         time: std::time::Duration,
+        separators: VASSReachSeparatorStatistics,
+        template_store: LinearGraphTemplateStoreStatistics,
     ) -> Self {
         VASSReachSolverStatistics {
             step_count,
@@ -54,11 +118,59 @@ impl VASSReachSolverStatistics {
             forwards_bound,
             backwards_bound,
             time,
+            separators,
+            template_store,
+            routes: VASSReachRouteStatistics::default(),
+            phases: VASSReachPhaseTimes::default(),
         }
     }
 }
 
-pub type VASSReachSolverStatus = SolverStatus<(), (), VASSReachSolverError>;
+/// Separators added by `BuildAutomaton` refinements and the time spent in each
+/// engine. LTC time also covers checks whose separator was discarded in favour
+/// of a LinearGraph.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VASSReachSeparatorStatistics {
+    pub linear_graph: u64,
+    pub ltc: u64,
+    pub linear_graph_time: std::time::Duration,
+    pub ltc_time: std::time::Duration,
+}
+
+impl VASSReachSeparatorStatistics {
+    pub fn merge(&mut self, other: &Self) {
+        self.linear_graph += other.linear_graph;
+        self.ltc += other.ltc;
+        self.linear_graph_time += other.linear_graph_time;
+        self.ltc_time += other.ltc_time;
+    }
+}
+
+/// Outcomes of the route subproblems solved in divide-and-conquer mode. Routes
+/// that were never started because another route was reachable are counted in
+/// `routes` only.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VASSReachRouteStatistics {
+    pub routes: u64,
+    pub reachable: u64,
+    pub unreachable: u64,
+    pub unknown: u64,
+}
+
+/// Time spent in each phase of a solver run. Phases that did not run stay
+/// zero, route solvers in divide-and-conquer mode are counted as a whole.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VASSReachPhaseTimes {
+    pub cfg_construction: std::time::Duration,
+    pub short_witness: std::time::Duration,
+    pub karp_miller: std::time::Duration,
+    pub preprocessing: std::time::Duration,
+    pub z_reach_precheck: std::time::Duration,
+    pub divide_and_conquer: std::time::Duration,
+    pub refinement: std::time::Duration,
+}
+
+pub type VASSReachSolverStatus = SolverStatus<VASSReachWitness, (), VASSReachSolverError>;
 
 pub type VASSReachSolverResult =
-    SolverResult<(), (), VASSReachSolverError, VASSReachSolverStatistics>;
+    SolverResult<VASSReachWitness, (), VASSReachSolverError, VASSReachSolverStatistics>;
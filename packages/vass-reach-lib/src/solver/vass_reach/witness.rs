@@ -1,49 +1,77 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use hashbrown::HashSet;
+use hashbrown::HashMap;
+use petgraph::graph::NodeIndex;
 
 use crate::{
     automaton::{
         AutomatonEdge, AutomatonNode, ExplicitEdgeAutomaton, FromLetter,
-        vass::initialized::InitializedVASS,
+        cfg::update::CFGCounterUpdate,
+        utils::vass_update_to_cfg_updates,
+        vass::{
+            counter::{CounterOverflowError, VASSCounterUpdate, VASSCounterValuation},
+            initialized::InitializedVASS,
+        },
     },
     config::ShortWitnessConfig,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct ShortWitness {
     pub depth: usize,
     pub explored_configurations: usize,
+    /// The unit counter updates of the run, see
+    /// [`VASSReachWitness::Run`](super::VASSReachWitness::Run).
+    pub run: Vec<CFGCounterUpdate>,
 }
 
+type Configuration = (NodeIndex, VASSCounterValuation);
+
 /// Searches a finite prefix of the concrete VASS configuration graph.
 ///
 /// Finding the target is an exact reachability proof. Exhausting either limit
-/// is inconclusive and leaves the complete solver to continue normally.
+/// is inconclusive and leaves the complete solver to continue normally, as
+/// does raising `stop_signal`. A configuration whose counters leave the `i64`
+/// range is an error.
 pub(super) fn find_short_witness<N, E>(
     instance: &InitializedVASS<N, E>,
     config: &ShortWitnessConfig,
-) -> Option<ShortWitness>
+    stop_signal: Option<&AtomicBool>,
+) -> Result<Option<ShortWitness>, CounterOverflowError>
 where
     N: AutomatonNode,
     E: AutomatonEdge + FromLetter,
 {
     if !*config.get_enabled() || *config.get_max_configurations() == 0 {
-        return None;
+        return Ok(None);
     }
 
-    let initial = (instance.initial_node, instance.initial_valuation.clone());
-    let mut visited = HashSet::new();
+    // maps every visited configuration to its predecessor and the update leading
+    // from there
+    let mut visited = HashMap::<Configuration, Option<(Configuration, VASSCounterUpdate)>>::new();
     let mut queue = VecDeque::new();
-    visited.insert(initial.clone());
-    queue.push_back((initial, 0));
+    for (node, valuation) in instance.initial_states() {
+        let initial = (node, valuation.clone());
+        if !visited.contains_key(&initial) {
+            visited.insert(initial.clone(), None);
+            queue.push_back((initial, 0));
+        }
+    }
 
     while let Some(((node, valuation), depth)) = queue.pop_front() {
-        if node == instance.final_node && valuation == instance.final_valuation {
-            return Some(ShortWitness {
+        if stop_signal.is_some_and(|stop| stop.load(Ordering::SeqCst)) {
+            return Ok(None);
+        }
+
+        if instance.is_final_configuration(node, &valuation) {
+            return Ok(Some(ShortWitness {
                 depth,
                 explored_configurations: visited.len(),
-            });
+                run: reconstruct_run(instance, &visited, (node, valuation)),
+            }));
         }
 
         if depth >= *config.get_max_depth() {
@@ -58,24 +86,65 @@ where
             }
 
             let mut next_valuation = valuation.clone();
-            next_valuation.apply_update(update);
+            next_valuation.try_apply_update(update)?;
             let next = (target, next_valuation);
-            if !visited.insert(next.clone()) {
+            if visited.contains_key(&next) {
                 continue;
             }
+            visited.insert(
+                next.clone(),
+                Some(((node, valuation.clone()), update.clone())),
+            );
 
             if visited.len() >= *config.get_max_configurations() {
-                return None;
+                return Ok(None);
             }
             queue.push_back((next, depth + 1));
         }
     }
 
-    None
+    Ok(None)
+}
+
+/// Expands the VASS run ending in `end` into unit counter updates. Runs that
+/// start or end in an additional state first move from the primary initial
+/// valuation and finally to the primary final valuation, like the gadgets of
+/// [`InitializedVASS::to_cfg`].
+fn reconstruct_run<N, E>(
+    instance: &InitializedVASS<N, E>,
+    visited: &HashMap<Configuration, Option<(Configuration, VASSCounterUpdate)>>,
+    end: Configuration,
+) -> Vec<CFGCounterUpdate>
+where
+    N: AutomatonNode,
+    E: AutomatonEdge + FromLetter,
+{
+    let difference = |from: &VASSCounterValuation, to: &VASSCounterValuation| {
+        to.iter()
+            .zip(from.iter())
+            .map(|(to, from)| to - from)
+            .collect::<VASSCounterUpdate>()
+    };
+
+    let mut updates = vec![difference(&end.1, &instance.final_valuation)];
+    let mut current = end;
+    while let Some(Some((previous, update))) = visited.get(&current) {
+        updates.push(update.clone());
+        current = previous.clone();
+    }
+    updates.push(difference(&instance.initial_valuation, &current.1));
+
+    updates
+        .iter()
+        .rev()
+        .flat_map(vass_update_to_cfg_updates)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::AtomicBool;
+
     use super::find_short_witness;
     use crate::{
         automaton::{
@@ -96,19 +165,48 @@ mod tests {
 
     #[test]
     fn finds_short_exact_witness() {
-        let witness = find_short_witness(&instance(), &ShortWitnessConfig::default()).unwrap();
+        let witness = find_short_witness(&instance(), &ShortWitnessConfig::default(), None)
+            .unwrap()
+            .unwrap();
         assert_eq!(witness.depth, 3);
+        assert_eq!(
+            witness
+                .run
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["+c0", "+c0", "-c0", "-c0"]
+        );
     }
 
     #[test]
     fn depth_limit_is_inconclusive() {
         let config = ShortWitnessConfig::default().with_max_depth(2);
-        assert_eq!(find_short_witness(&instance(), &config), None);
+        assert_eq!(find_short_witness(&instance(), &config, None), Ok(None));
     }
 
     #[test]
     fn configuration_limit_is_inconclusive() {
         let config = ShortWitnessConfig::default().with_max_configurations(2);
-        assert_eq!(find_short_witness(&instance(), &config), None);
+        assert_eq!(find_short_witness(&instance(), &config, None), Ok(None));
+    }
+
+    #[test]
+    fn raised_stop_signal_is_inconclusive() {
+        let stop = AtomicBool::new(true);
+        assert_eq!(
+            find_short_witness(&instance(), &ShortWitnessConfig::default(), Some(&stop)),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn counter_overflow_is_an_error() {
+        let mut vass = VASS::new(1, (0..1).collect());
+        let q0 = vass.add_node(());
+        vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![1].into()));
+        let instance = vass.init(vec![i64::MAX].into(), vec![0].into(), q0, q0);
+
+        assert!(find_short_witness(&instance, &ShortWitnessConfig::default(), None).is_err());
     }
 }
@@ -1,6 +1,10 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
 use petgraph::graph::EdgeIndex;
 use serde::{Deserialize, Serialize};
-use z3::{Config, Solver, ast::Int, with_z3_config};
 
 use crate::{
     automaton::{
@@ -10,9 +14,15 @@ use crate::{
         path::{Path, parikh_image::ParikhImage},
         vass::counter::VASSCounterValuation,
     },
-    config::VASSZReachConfig,
+    config::{VASSZReachConfig, ZReachConnectivity},
     solver::{
         SolverResult, SolverStatus,
+        backend::{
+            ArithmeticContext, ArithmeticSolver, CheckLimits, SatResult,
+            ast::{Bool, Int},
+            isolated,
+        },
+        smt_dump::check_solver,
         utils::{
             add_cfg_update_to_sums, assert_non_negative, assert_sums_match_valuation,
             forbid_parikh_image, parikh_image_from_edge_map,
@@ -25,6 +35,8 @@ pub enum VASSZReachSolverError {
     Timeout,
     MaxIterationsReached,
     SolverUnknown,
+    /// The stop signal was raised, see [`VASSZReachSolver::with_stop_signal`].
+    Cancelled,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -39,6 +51,58 @@ impl VASSZReachSolverStatistics {
     }
 }
 
+/// How often a Z-run takes one CFG edge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VASSZReachEdgeCount {
+    pub source: usize,
+    pub target: usize,
+    pub update: CFGCounterUpdate,
+    pub count: u32,
+}
+
+/// The Parikh image of a Z-run through the CFG of the instance, without the
+/// edges that are never taken.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VASSZReachCertificate {
+    pub edges: Vec<VASSZReachEdgeCount>,
+}
+
+impl VASSZReachCertificate {
+    pub fn new<C: ExplicitEdgeCFG>(cfg: &C, parikh_image: &ParikhImage<EdgeIndex>) -> Self {
+        let edges = parikh_image
+            .image
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(edge, count)| {
+                let (source, target) = cfg.edge_endpoints_unchecked(&edge);
+                VASSZReachEdgeCount {
+                    source: source.index(),
+                    target: target.index(),
+                    update: *cfg.get_edge_unchecked(&edge),
+                    count: *count,
+                }
+            })
+            .collect();
+
+        VASSZReachCertificate { edges }
+    }
+}
+
+impl std::fmt::Display for VASSZReachCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Parikh image over {} edges:", self.edges.len())?;
+        for edge in &self.edges {
+            write!(
+                f,
+                " {}-[{}]->{} x{}",
+                edge.source, edge.update, edge.target, edge.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
 pub type VASSZReachSolverStatus = SolverStatus<ParikhImage<EdgeIndex>, (), VASSZReachSolverError>;
 
 pub type VASSZReachSolverResult =
@@ -52,6 +116,11 @@ impl VASSZReachSolverResult {
         }
     }
 
+    pub fn certificate<C: ExplicitEdgeCFG>(&self, cfg: &C) -> Option<VASSZReachCertificate> {
+        self.get_parikh_image()
+            .map(|parikh_image| VASSZReachCertificate::new(cfg, parikh_image))
+    }
+
     pub fn build_run<C: ExplicitEdgeCFG>(
         &self,
         cfg: &C,
@@ -90,6 +159,12 @@ impl VASSZReachSolverResult {
 ///
 /// Since this constraint act's on sets of nodes and there are only a limited
 /// number of subsets of nodes, the solver terminates.
+///
+/// This refinement loop may need exponentially many iterations, so by default
+/// connectivity is encoded up front instead (see
+/// [`ZReachConnectivity::Encoded`]) and a single query decides
+/// Z-reachability. The loop stays available as
+/// [`ZReachConnectivity::Refinement`].
 pub struct VASSZReachSolver<'c, C: ExplicitEdgeCFG + Sync> {
     cfg: &'c C,
     initial_valuation: VASSCounterValuation,
@@ -97,6 +172,10 @@ pub struct VASSZReachSolver<'c, C: ExplicitEdgeCFG + Sync> {
     options: VASSZReachConfig,
     step_count: u64,
     solver_start_time: Option<std::time::Instant>,
+    stop_signal: Option<Arc<AtomicBool>>,
+    /// Set when the solver runs on behalf of a reachability solver, otherwise
+    /// built from `options`.
+    arithmetic: Option<ArithmeticContext>,
 }
 
 impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
@@ -113,22 +192,42 @@ impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
             options,
             step_count: 0,
             solver_start_time: None,
+            stop_signal: None,
+            arithmetic: None,
         }
     }
 
+    /// Checks queries in the context of the calling solver.
+    pub(crate) fn with_arithmetic_context(mut self, context: ArithmeticContext) -> Self {
+        self.arithmetic = Some(context);
+        self
+    }
+
+    /// Interrupts the solver with [`VASSZReachSolverError::Cancelled`] once
+    /// `signal` is raised.
+    pub fn with_stop_signal(mut self, signal: Arc<AtomicBool>) -> Self {
+        self.stop_signal = Some(signal);
+        self
+    }
+
     pub fn solve(&mut self) -> VASSZReachSolverResult {
         self.solver_start_time = Some(std::time::Instant::now());
 
-        let mut config = Config::new();
-        config.set_model_generation(true);
-        with_z3_config(&config, || {
-            let solver = Solver::new();
+        let arithmetic = self.arithmetic.clone().unwrap_or_else(|| {
+            ArithmeticContext::for_solver(
+                self.options.get_arithmetic_backend(),
+                self.options.get_smt_dump(),
+            )
+        });
+
+        isolated(&arithmetic, || {
+            let solver = ArithmeticSolver::new(&arithmetic);
 
             self.solve_inner(&solver)
         })
     }
 
-    fn solve_inner(&mut self, solver: &Solver) -> VASSZReachSolverResult {
+    fn solve_inner(&mut self, solver: &ArithmeticSolver) -> VASSZReachSolverResult {
         // a map that allows us to access the edge variables by their edge id
         let mut edge_map = OptionIndexMap::new(self.cfg.edge_count());
 
@@ -136,7 +235,7 @@ impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
         let mut sums: Box<[_]> = self
             .initial_valuation
             .iter()
-            .map(|x| Int::from_i64(*x as i64))
+            .map(|x| Int::from_i64(*x))
             .collect();
 
         for (edge, update) in self.cfg.iter_edges() {
@@ -196,19 +295,30 @@ impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
         // CONSTRAINT: the final valuation must be equal to the counter sums
         assert_sums_match_valuation(solver, &sums, &self.final_valuation);
 
+        if *self.options.get_connectivity() == ZReachConnectivity::Encoded {
+            self.assert_connected_support(solver, &edge_map);
+        }
+
         self.step_count = 1;
         let status;
+        let limits = CheckLimits {
+            deadline: self
+                .options
+                .get_timeout()
+                .map(|timeout| self.solver_start_time.unwrap() + timeout),
+            stop_signal: self.stop_signal.as_deref(),
+        };
 
         loop {
-            match solver.check() {
-                z3::SatResult::Sat => {
-                    let model = solver.get_model();
-                    let Some(model) = model else {
-                        status = SolverStatus::Unknown(VASSZReachSolverError::SolverUnknown);
-                        break;
-                    };
-
-                    let parikh_image = parikh_image_from_edge_map(&edge_map, &model);
+            match check_solver(
+                solver,
+                &[],
+                limits,
+                "vass_z_reach",
+                Some(self.step_count as u32),
+            ) {
+                SatResult::Sat => {
+                    let parikh_image = parikh_image_from_edge_map(&edge_map, solver);
                     let (_, components) = parikh_image
                         .clone()
                         .split_into_connected_components(self.cfg);
@@ -234,11 +344,19 @@ impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
 
                     self.step_count += 1;
                 }
-                z3::SatResult::Unsat => {
+                SatResult::Unsat => {
                     status = SolverStatus::False(());
                     break;
                 }
-                z3::SatResult::Unknown => {
+                SatResult::Unknown if self.stop_requested() => {
+                    return self.get_solver_result(SolverStatus::Unknown(
+                        VASSZReachSolverError::Cancelled,
+                    ));
+                }
+                SatResult::Unknown if self.max_time_reached() => {
+                    return self.max_time_reached_result();
+                }
+                SatResult::Unknown => {
                     status = SolverStatus::Unknown(VASSZReachSolverError::SolverUnknown);
                     break;
                 }
@@ -250,6 +368,56 @@ impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
         self.get_solver_result(status)
     }
 
+    /// CONSTRAINT: every node entered by a used edge is reached from the
+    /// initial node through used edges.
+    ///
+    /// Each node gets a distance variable, and an entered node other than the
+    /// initial one needs a used incoming edge from a node with a smaller
+    /// distance. Following these edges backwards must end in the initial node,
+    /// so together with the Kirchhoff equations the Parikh image is exactly
+    /// that of a single run (Verma, Seidl and Schwentick 2005).
+    fn assert_connected_support(
+        &self,
+        solver: &ArithmeticSolver,
+        edge_map: &OptionIndexMap<EdgeIndex, Int>,
+    ) {
+        let distances = self
+            .cfg
+            .iter_node_indices()
+            .map(|node| Int::new_const(format!("node_{}_distance", node.index())))
+            .collect::<Vec<_>>();
+        let initial = self.cfg.get_initial();
+
+        for node in self.cfg.iter_node_indices() {
+            if node == initial {
+                continue;
+            }
+
+            let incoming = self.cfg.incoming_edge_indices(&node).collect::<Vec<_>>();
+            let entered = Int::add(
+                &incoming
+                    .iter()
+                    .map(|edge| &edge_map[*edge])
+                    .collect::<Vec<_>>(),
+            )
+            .ge(Int::from_i64(1));
+            let predecessors = incoming
+                .iter()
+                .filter_map(|edge| {
+                    let (source, _) = self.cfg.edge_endpoints_unchecked(edge);
+                    (source != node).then(|| {
+                        Bool::and(&[
+                            edge_map[*edge].ge(Int::from_i64(1)),
+                            distances[source.index()].lt(&distances[node.index()]),
+                        ])
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            solver.assert(entered.implies(Bool::or(&predecessors)));
+        }
+    }
+
     fn max_iterations_reached(&self) -> bool {
         self.options
             .get_max_iterations()
@@ -257,6 +425,12 @@ impl<'c, C: ExplicitEdgeCFG + Sync> VASSZReachSolver<'c, C> {
             .unwrap_or(false)
     }
 
+    fn stop_requested(&self) -> bool {
+        self.stop_signal
+            .as_ref()
+            .is_some_and(|signal| signal.load(Ordering::SeqCst))
+    }
+
     fn max_time_reached(&self) -> bool {
         match (self.get_solver_time(), self.options.get_timeout()) {
             (Some(t), Some(max_time)) => &t > max_time,
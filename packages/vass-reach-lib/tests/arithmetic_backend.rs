@@ -0,0 +1,115 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use vass_reach_lib::{
+    automaton::{
+        ModifiableAutomaton,
+        vass::{VASS, VASSEdge},
+    },
+    config::{ArithmeticBackendConfig, ArithmeticBackendKind, VASSZReachConfig},
+    solver::vass_z_reach::VASSZReachSolver,
+};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("vass-backend-{name}-{unique}"));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A solver stub that records its input and answers every check with `unsat`.
+fn unsat_stub(dir: &Path) -> (Vec<String>, PathBuf) {
+    let transcript = dir.join("transcript.smt2");
+    let script = dir.join("stub.sh");
+    fs::write(
+        &script,
+        format!(
+            r#"while IFS= read -r line; do
+  printf '%s\n' "$line" >> '{}'
+  case "$line" in
+    *check-sat*) echo unsat ;;
+    *'(exit)'*) exit 0 ;;
+  esac
+done
+"#,
+            transcript.display()
+        ),
+    )
+    .unwrap();
+
+    (
+        vec!["sh".to_string(), script.display().to_string()],
+        transcript,
+    )
+}
+
+#[test]
+fn z_reach_runs_on_smt_lib_process_backend() {
+    let dir = scratch_dir("stub");
+    let (command, transcript) = unsat_stub(&dir);
+
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q1, VASSEdge::new('a', vec![1].into()));
+    vass.add_edge(&q1, &q1, VASSEdge::new('b', vec![1].into()));
+    let initialized_vass = vass.init(vec![0].into(), vec![0].into(), q0, q1);
+    let cfg = initialized_vass.to_cfg();
+
+    let res = VASSZReachSolver::new(
+        &cfg,
+        initialized_vass.initial_valuation.clone(),
+        initialized_vass.final_valuation.clone(),
+        VASSZReachConfig::default()
+            .with_timeout(Some(Duration::from_secs(5)))
+            .with_arithmetic_backend(
+                ArithmeticBackendConfig::default()
+                    .with_kind(ArithmeticBackendKind::SMTLib)
+                    .with_smt_lib_command(command),
+            ),
+    )
+    .solve();
+
+    assert!(res.is_failure(), "{:?}", res.status);
+
+    let transcript = fs::read_to_string(transcript).unwrap();
+    assert!(transcript.contains("(set-logic ALL)"), "{transcript}");
+    assert!(
+        transcript.contains("(declare-fun edge_0 () Int)"),
+        "{transcript}"
+    );
+    assert!(transcript.contains("(assert "), "{transcript}");
+    assert!(transcript.contains("(check-sat)"), "{transcript}");
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn missing_smt_lib_solver_gives_unknown() {
+    let mut vass = VASS::<u32, char>::new(1, vec!['a']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q1, VASSEdge::new('a', vec![1].into()));
+    let initialized_vass = vass.init(vec![0].into(), vec![1].into(), q0, q1);
+    let cfg = initialized_vass.to_cfg();
+
+    let res = VASSZReachSolver::new(
+        &cfg,
+        initialized_vass.initial_valuation.clone(),
+        initialized_vass.final_valuation.clone(),
+        VASSZReachConfig::default().with_arithmetic_backend(
+            ArithmeticBackendConfig::default()
+                .with_kind(ArithmeticBackendKind::SMTLib)
+                .with_smt_lib_command(vec!["vass-reach-missing-solver".to_string()]),
+        ),
+    )
+    .solve();
+
+    assert!(res.is_unknown(), "{:?}", res.status);
+}
@@ -0,0 +1,83 @@
+use vass_reach_lib::config::{VASSReachConfig, VASSZReachConfig};
+
+fn write_config(name: &str, content: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "vass-reach-config-schema-{}-{name}.toml",
+        std::process::id()
+    ));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn schema_lists_types_defaults_and_variants() {
+    let schema = VASSReachConfig::json_schema();
+    assert_eq!(schema["title"], "VASSReachConfig");
+    assert_eq!(schema["additionalProperties"], false);
+
+    let region_order = &schema["properties"]["linear_graph"]["properties"]["region_order"];
+    assert_eq!(region_order["type"], "string");
+    assert_eq!(
+        region_order["enum"],
+        serde_json::json!(["GainDescending", "GainAscending", "Input"])
+    );
+    assert_eq!(region_order["default"], "GainDescending");
+
+    // serde renames are respected
+    let level = &schema["properties"]["debug_trace"]["properties"]["level"];
+    assert_eq!(
+        level["enum"],
+        serde_json::json!(["disabled", "light", "full"])
+    );
+
+    let max_depth = &schema["properties"]["short_witness"]["properties"]["max_depth"];
+    assert_eq!(max_depth["type"], "integer");
+    assert_eq!(max_depth["default"], 64);
+    assert!(max_depth["description"].as_str().unwrap().contains("runs"));
+
+    let keys = VASSReachConfig::config_keys();
+    let z_keys = VASSZReachConfig::config_keys();
+    assert!(
+        z_keys
+            .iter()
+            .all(|key| { key.starts_with("connectivity") || keys.contains(key) })
+    );
+}
+
+#[test]
+fn config_files_are_validated_against_the_schema() {
+    let path = write_config(
+        "valid",
+        "timeout = { secs = 3, nanos = 0 }\n[linear_graph]\nregion_order = \"Input\"\n",
+    );
+    let config = VASSReachConfig::from_file(&path).unwrap();
+    assert_eq!(
+        *config.get_timeout(),
+        Some(std::time::Duration::from_secs(3))
+    );
+
+    let path = write_config(
+        "misspelled",
+        "[linear_graph]\ntemplate_synthesis_max_coeficient = 3\n",
+    );
+    let err = VASSReachConfig::from_file(&path).unwrap_err().to_string();
+    assert!(
+        err.contains("did you mean linear_graph.template_synthesis_max_coefficient?"),
+        "{err}"
+    );
+
+    let path = write_config("section", "[linear_graf]\nenabled = true\n");
+    let err = VASSReachConfig::from_file(&path).unwrap_err().to_string();
+    assert!(err.contains("Unknown config key linear_graf"), "{err}");
+
+    let path = write_config("variant", "[linear_graph]\nregion_order = \"Random\"\n");
+    let err = VASSReachConfig::from_file(&path).unwrap_err().to_string();
+    assert!(
+        err.contains("linear_graph.region_order: expected one of GainDescending"),
+        "{err}"
+    );
+
+    let path = write_config("type", "[short_witness]\nmax_depth = -1\n");
+    let err = VASSReachConfig::from_file(&path).unwrap_err().to_string();
+    assert!(err.contains("short_witness.max_depth"), "{err}");
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use vass_reach_lib::{
+    automaton::{
+        Language, ModifiableAutomaton,
+        petri_net::initialized::InitializedPetriNet,
+        vass::{
+            VASS, VASSEdge,
+            control::{ControlConstraint, ControlRegex},
+            initialized::InitializedVASS,
+        },
+    },
+    config::VASSReachConfig,
+    solver::vass_reach::VASSReachSolver,
+};
+
+fn regex(source: &str) -> ControlConstraint {
+    ControlConstraint::Regex(source.parse().unwrap())
+}
+
+fn solve(instance: &InitializedVASS<usize, usize>) -> bool {
+    let result = VASSReachSolver::new(
+        instance,
+        VASSReachConfig::default().with_timeout(Some(Duration::from_secs(5))),
+    )
+    .solve();
+    assert!(!result.is_unknown(), "{:?}", result.status);
+    result.is_success()
+}
+
+/// Counter 0 has to be pumped to 2 by letter 0 and then emptied by letter 1.
+fn pump_instance() -> InitializedVASS<usize, usize> {
+    let mut vass = VASS::new(1, vec![0, 1]);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![1].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![-2].into()));
+    vass.init(vec![0].into(), vec![0].into(), q0, q1)
+}
+
+#[test]
+fn control_regex_compiles_to_dfa() {
+    let at_most_once = regex("[^t3]* (t3 [^t3]*)?").to_dfa(&[0, 1, 2, 3]);
+    assert!(at_most_once.accepts(&[1, 3, 2]));
+    assert!(at_most_once.accepts(&[]));
+    assert!(!at_most_once.accepts(&[3, 1, 3]));
+
+    let never_after = regex("[^t7]* (t7 [^t5]*)?").to_dfa(&[5, 7]);
+    assert!(never_after.accepts(&[5, 7, 7]));
+    assert!(!never_after.accepts(&[7, 5]));
+
+    let alternation = regex("(0 1)+ | 2?").to_dfa(&[0, 1, 2]);
+    assert!(alternation.accepts(&[0, 1, 0, 1]));
+    assert!(alternation.accepts(&[2]));
+    assert!(!alternation.accepts(&[0, 1, 2]));
+
+    assert!("t3 (".parse::<ControlRegex>().is_err());
+    assert!("t3 & t4".parse::<ControlRegex>().is_err());
+}
+
+#[test]
+fn control_constraint_restricts_vass_runs() {
+    let instance = pump_instance();
+    assert!(solve(&instance));
+
+    let constrained = instance.with_control_constraints(&[regex("[^t0]* (t0 [^t0]*)?")]);
+    assert!(!solve(&constrained));
+
+    let constrained = instance.with_control_constraints(&[regex("0 0 1")]);
+    assert!(solve(&constrained));
+    assert!(constrained.accepts(&[0, 0, 1]));
+    assert!(!constrained.accepts(&[0, 0, 0, 1]));
+
+    // constraints are intersected, so these two together allow no run
+    let constrained = instance.with_control_constraints(&[regex("0 0 1"), regex("1*")]);
+    assert!(!solve(&constrained));
+}
+
+#[test]
+fn control_nfa_is_read_from_vass_json() {
+    let mut json =
+        serde_json::from_str::<serde_json::Value>(&pump_instance().to_json().unwrap()).unwrap();
+    // an NFA that forbids letter 1 after two uses of letter 0
+    json["control_constraints"] = serde_json::json!([{
+        "nfa": {
+            "initial": 0,
+            "accepting": [0, 1],
+            "transitions": [
+                { "source": 0, "target": 0, "letter": 1 },
+                { "source": 0, "target": 1, "letter": 0 },
+                { "source": 1, "target": 0, "letter": 1 },
+                { "source": 1, "target": 2, "letter": 0 },
+                { "source": 2, "target": 2, "letter": 0 },
+                { "source": 2, "target": 3, "letter": null }
+            ]
+        }
+    }]);
+
+    let instance = InitializedVASS::from_json(&json.to_string()).unwrap();
+    assert!(!solve(&instance));
+}
+
+#[test]
+fn control_constraint_counts_petri_net_firings() {
+    let spec = r#"
+    vars
+        p1 p2
+    rules
+        p1 >= 1 ->
+            p1' = p1-1,
+            p2' = p2+1;
+        p2 >= 1 ->
+            p2' = p2-1;
+    init
+        p1=2, p2=0
+    target
+        p1=0, p2=0"#;
+    let net = InitializedPetriNet::parse_from_spec(spec).unwrap();
+    assert!(solve(&net.to_vass()));
+
+    // every transition is one letter, although it is two edges in the VASS
+    let allowed = net
+        .clone()
+        .with_control_constraints(vec![regex("t0 t1 t0 t1")]);
+    assert!(solve(&allowed.to_vass()));
+
+    let at_most_once = net.with_control_constraints(vec![regex("[^t1]* (t1 [^t1]*)?")]);
+    assert!(!solve(&at_most_once.to_vass()));
+}
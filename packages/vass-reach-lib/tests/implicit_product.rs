@@ -35,8 +35,20 @@ fn implicit_product_test() {
     let initial_valuation = VASSCounterValuation::from(vec![1]);
     let final_valuation = VASSCounterValuation::from(vec![0]);
 
-    let lim_cfg = build_bounded_counting_cfg(1, VASSCounterIndex::new(0), 3, 1, 0);
-    let rev_lim_cfg = build_rev_bounded_counting_cfg(1, VASSCounterIndex::new(0), 3, 1, 0);
+    let lim_cfg = build_bounded_counting_cfg(
+        &CFGCounterUpdate::alphabet(1),
+        VASSCounterIndex::new(0),
+        3,
+        1,
+        0,
+    );
+    let rev_lim_cfg = build_rev_bounded_counting_cfg(
+        &CFGCounterUpdate::alphabet(1),
+        VASSCounterIndex::new(0),
+        3,
+        1,
+        0,
+    );
 
     lim_cfg.assert_complete();
     rev_lim_cfg.assert_complete();
@@ -1,8 +1,12 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use vass_reach_lib::automaton::{
     ModifiableAutomaton,
     cfg::{update::CFGCounterUpdate, vasscfg::VASSCFG},
     dfa::node::DfaNode,
-    karp_miller::build_karp_miller_coverability_tree,
+    karp_miller::{
+        build_karp_miller_coverability_tree, build_karp_miller_coverability_tree_with_stop_signal,
+    },
     vass::omega::OmegaCounter,
 };
 
@@ -13,7 +17,7 @@ fn test_karp_miller_accelerates_to_omega_on_increasing_cycle() {
     cfg.set_initial(q0);
     cfg.add_edge(&q0, &q0, CFGCounterUpdate::new(0, true));
 
-    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into());
+    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into()).unwrap();
 
     assert_eq!(tree.root(), 0);
     assert_eq!(tree.nodes().len(), 2);
@@ -23,6 +27,27 @@ fn test_karp_miller_accelerates_to_omega_on_increasing_cycle() {
     assert_eq!(child.valuation.values(), [OmegaCounter::Omega]);
 }
 
+#[test]
+fn test_karp_miller_gives_up_once_stopped() {
+    let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(1));
+    let q0 = cfg.add_node(DfaNode::non_accepting(()));
+    cfg.set_initial(q0);
+    cfg.add_edge(&q0, &q0, CFGCounterUpdate::new(0, true));
+
+    let stop = AtomicBool::new(true);
+    let tree =
+        build_karp_miller_coverability_tree_with_stop_signal(&cfg, &vec![0].into(), &stop).unwrap();
+    assert!(tree.is_none());
+
+    stop.store(false, Ordering::SeqCst);
+    let tree =
+        build_karp_miller_coverability_tree_with_stop_signal(&cfg, &vec![0].into(), &stop).unwrap();
+    assert_eq!(
+        tree,
+        Some(build_karp_miller_coverability_tree(&cfg, &vec![0].into()).unwrap())
+    );
+}
+
 #[test]
 fn test_karp_miller_skips_disabled_decrement() {
     let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(1));
@@ -30,7 +55,7 @@ fn test_karp_miller_skips_disabled_decrement() {
     cfg.set_initial(q0);
     cfg.add_edge(&q0, &q0, CFGCounterUpdate::new(0, false));
 
-    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into());
+    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into()).unwrap();
 
     assert_eq!(tree.nodes().len(), 1);
     assert!(tree.node(0).children.is_empty());
@@ -46,7 +71,7 @@ fn test_karp_miller_compares_ancestors_with_same_control() {
     cfg.add_edge(&q0, &q1, CFGCounterUpdate::new(0, true));
     cfg.add_edge(&q1, &q0, CFGCounterUpdate::new(0, false));
 
-    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into());
+    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into()).unwrap();
 
     assert_eq!(tree.nodes().len(), 3);
     assert!(!tree.node(1).closed);
@@ -61,7 +86,7 @@ fn test_karp_miller_to_graphviz_contains_nodes_and_edges() {
     cfg.set_initial(q0);
     cfg.add_edge(&q0, &q0, CFGCounterUpdate::new(0, true));
 
-    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into());
+    let tree = build_karp_miller_coverability_tree(&cfg, &vec![0].into()).unwrap();
     let dot = tree.to_graphviz();
 
     assert!(dot.contains("digraph karp_miller_tree"));
@@ -69,3 +94,14 @@ fn test_karp_miller_to_graphviz_contains_nodes_and_edges() {
     assert!(dot.contains("n0 -> n1"));
     assert!(dot.contains("label=\"+c0\""));
 }
+
+#[test]
+fn test_karp_miller_reports_counter_overflow() {
+    let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(1));
+    let q0 = cfg.add_node(DfaNode::non_accepting(()));
+    let q1 = cfg.add_node(DfaNode::accepting(()));
+    cfg.set_initial(q0);
+    cfg.add_edge(&q0, &q1, CFGCounterUpdate::new(0, true));
+
+    assert!(build_karp_miller_coverability_tree(&cfg, &vec![i64::MAX].into()).is_err());
+}
@@ -12,7 +12,10 @@ use vass_reach_lib::{
         },
         linear_graph::{
             LinearGraph,
-            extender::{LinearGraphExtender, LinearGraphExtenderOutput},
+            extender::{
+                LinearGraphExtender, LinearGraphExtenderOutput, LinearGraphTemplateStore,
+                LinearGraphTemplateStoreStatistics,
+            },
             part::{LinearGraphPart, LinearGraphRegion},
             rooted::{RootedLinearGraph, RootedLinearGraphError},
         },
@@ -20,7 +23,10 @@ use vass_reach_lib::{
         vass::{VASS, VASSEdge},
     },
     cfg_dec, cfg_inc,
-    solver::linear_graph_reach::LinearGraphReachSolverOptions,
+    config::LinearGraphConfig,
+    solver::linear_graph_reach::{
+        LinearGraphReachSession, LinearGraphReachSolverOptions, LinearGraphSessionSlot,
+    },
     validation::same_language::assert_same_language,
 };
 
@@ -273,6 +279,7 @@ fn linear_graph_reach() {
     assert!(
         res.unwrap_success()
             .build_run(&linear_graph, false)
+            .unwrap()
             .is_some()
     );
 
@@ -292,6 +299,7 @@ fn linear_graph_reach() {
     assert!(
         res.unwrap_success()
             .build_run(&linear_graph2, false)
+            .unwrap()
             .is_some()
     );
 
@@ -302,6 +310,86 @@ fn linear_graph_reach() {
     assert!(res.is_failure());
 }
 
+#[test]
+fn linear_graph_reach_session_matches_fresh_solver() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(1));
+    let s0 = cfg.add_node(DfaNode::non_accepting(()));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let s2 = cfg.add_node(DfaNode::non_accepting(()));
+    let s3 = cfg.add_node(DfaNode::accepting(()));
+
+    cfg.set_initial(s0);
+
+    cfg.add_edge(&s0, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &s2, cfg_inc!(0));
+    cfg.add_edge(&s2, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &s3, cfg_dec!(0));
+
+    let product =
+        ImplicitCFGProduct::new_without_counting_cfgs(1, vec![0].into(), vec![0].into(), cfg);
+    let path = MultiGraphPath::from_word(product.initial(), &[cfg_inc!(0), cfg_dec!(0)], &product)
+        .unwrap();
+    let refined = LinearGraph::from_path(path, &product, 1).add_scc_around_position(0, 1);
+
+    let part = |full: bool| {
+        let mut linear_graph = LinearGraph::empty(&product, 1);
+        if full {
+            linear_graph.add_graph(refined.graphs[0].clone());
+        } else {
+            linear_graph.add_path(MultiGraphPath::new(MultiGraphState::from(s1)).into());
+        }
+        linear_graph
+    };
+    let fixed = |index: usize| {
+        let mut linear_graph = LinearGraph::empty(&product, 1);
+        linear_graph.add_path(refined.paths[index].clone());
+        linear_graph
+    };
+    let candidate = |full: bool| {
+        let mut linear_graph = fixed(0);
+        for parts in [part(full), fixed(1)] {
+            for segment in parts.paths {
+                linear_graph.add_path(segment);
+            }
+            for graph in parts.graphs {
+                linear_graph.add_graph(graph);
+            }
+        }
+        linear_graph
+    };
+
+    for final_value in 0..4 {
+        let initial = vec![0].into();
+        let target = vec![final_value].into();
+        let mut session = LinearGraphReachSession::new(
+            vec![
+                LinearGraphSessionSlot::Fixed(fixed(0)),
+                LinearGraphSessionSlot::Toggle {
+                    seed: part(false),
+                    full: part(true),
+                },
+                LinearGraphSessionSlot::Fixed(fixed(1)),
+            ],
+            vec![0].into(),
+            vec![final_value].into(),
+        );
+
+        // Alternate the masks so later checks run against a reused solver.
+        for full in [true, false, true, false] {
+            let fresh = LinearGraphReachSolverOptions::default()
+                .into_solver(&candidate(full), &initial, &target)
+                .solve();
+            let incremental = session.check(&[full], LinearGraphReachSolverOptions::default());
+
+            assert_eq!(
+                (incremental.is_success(), incremental.is_failure()),
+                (fresh.is_success(), fresh.is_failure()),
+                "final value {final_value}, full region {full}"
+            );
+        }
+    }
+}
+
 #[test]
 fn add_scc_around_position_keeps_parts_connected() {
     let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(1));
@@ -371,6 +459,7 @@ fn linear_graph_reach2() {
     assert!(
         res.unwrap_success()
             .build_run(&linear_graph, false)
+            .unwrap()
             .is_some()
     );
 
@@ -390,6 +479,7 @@ fn linear_graph_reach2() {
     assert!(
         res.unwrap_success()
             .build_run(&linear_graph2, false)
+            .unwrap()
             .is_some()
     );
 
@@ -401,6 +491,7 @@ fn linear_graph_reach2() {
     assert!(
         res.unwrap_success()
             .build_run(&linear_graph2, false)
+            .unwrap()
             .is_some()
     );
 }
@@ -483,7 +574,7 @@ fn repeated_positive_path_builds_the_selected_run() {
     assert!(result.is_success());
     let solution = result.unwrap_success();
     assert_eq!(solution.repeat_path_counts, vec![1]);
-    let run = solution.build_run(&linear_graph, true).unwrap();
+    let run = solution.build_run(&linear_graph, true).unwrap().unwrap();
     assert_eq!(run.transitions, vec![cfg_inc!(0), cfg_dec!(0)]);
 }
 
@@ -629,6 +720,203 @@ fn linear_graph_extender_selects_full_scc_when_unreachable() {
     );
 }
 
+/// Exchanges `(-1, +1)`, `(0, +1)` and `(+1, -2)` around one accepting
+/// state. From `(0, 1)` the zero valuation is Z-reachable but not N-reachable.
+fn exchange_cycles_cfg() -> VASSCFG<()> {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(2));
+    let q = cfg.add_node(DfaNode::accepting(()));
+    cfg.set_initial(q);
+
+    for cycle in [
+        vec![cfg_dec!(0), cfg_inc!(1)],
+        vec![cfg_inc!(1)],
+        vec![cfg_inc!(0), cfg_dec!(1), cfg_dec!(1)],
+    ] {
+        let mut state = q;
+        for update in &cycle[..cycle.len() - 1] {
+            let next = cfg.add_node(DfaNode::non_accepting(()));
+            cfg.add_edge(&state, &next, *update);
+            state = next;
+        }
+        cfg.add_edge(&state, &q, *cycle.last().unwrap());
+    }
+
+    cfg
+}
+
+#[test]
+fn linear_graph_extender_reuses_templates_from_the_store() {
+    let product = ImplicitCFGProduct::new_without_counting_cfgs(
+        2,
+        vec![0, 1].into(),
+        vec![0, 0].into(),
+        exchange_cycles_cfg(),
+    );
+    let word = [
+        cfg_dec!(0),
+        cfg_inc!(1),
+        cfg_inc!(0),
+        cfg_dec!(1),
+        cfg_dec!(1),
+    ];
+    let path = MultiGraphPath::from_word(product.initial(), &word, &product).unwrap();
+    let product_view = product.full_view();
+    let mut store = LinearGraphTemplateStore::new();
+
+    // The first run synthesizes a template; the second starts from it and
+    // saves that synthesis call.
+    for _ in 0..2 {
+        let mut extender = LinearGraphExtender::from_product_view_with_config(
+            path.clone(),
+            &product_view,
+            &LinearGraphConfig::default(),
+        )
+        .with_template_store(&mut store);
+        let output = extender.run_with_witness();
+        extender.save_templates(&mut store);
+
+        assert!(matches!(output, LinearGraphExtenderOutput::Refinement(_)));
+    }
+
+    assert_eq!(
+        store.statistics(),
+        &LinearGraphTemplateStoreStatistics {
+            hits: 1,
+            misses: 1,
+            invalidations: 0,
+            learned_templates: 1,
+        }
+    );
+}
+
+#[test]
+fn linear_graph_template_store_drops_domains_of_other_instances() {
+    let mut store = LinearGraphTemplateStore::new();
+
+    for initial_valuation in [vec![0, 1], vec![0, 2]] {
+        let product = ImplicitCFGProduct::new_without_counting_cfgs(
+            2,
+            initial_valuation.into(),
+            vec![0, 0].into(),
+            exchange_cycles_cfg(),
+        );
+        let path = MultiGraphPath::from_word(product.initial(), &[cfg_inc!(1)], &product).unwrap();
+        let product_view = product.full_view();
+        let mut extender = LinearGraphExtender::from_product_view_with_config(
+            path,
+            &product_view,
+            &LinearGraphConfig::default(),
+        )
+        .with_template_store(&mut store);
+        extender.run_with_witness();
+        extender.save_templates(&mut store);
+    }
+
+    assert_eq!(store.statistics().hits, 0);
+    assert_eq!(store.statistics().misses, 2);
+    assert_eq!(store.statistics().invalidations, 1);
+}
+
+#[test]
+fn linear_graph_extender_checks_candidates_concurrently() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(1));
+    let s0 = cfg.add_node(DfaNode::non_accepting(()));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let s2 = cfg.add_node(DfaNode::non_accepting(()));
+    let s3 = cfg.add_node(DfaNode::accepting(()));
+    let s4 = cfg.add_node(DfaNode::non_accepting(()));
+
+    cfg.set_initial(s0);
+
+    cfg.add_edge(&s0, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &s3, cfg_dec!(0));
+    cfg.add_edge(&s1, &s2, cfg_inc!(0));
+    cfg.add_edge(&s2, &s1, cfg_dec!(0));
+    cfg.add_edge(&s3, &s4, cfg_inc!(0));
+    cfg.add_edge(&s4, &s3, cfg_inc!(0));
+
+    let product =
+        ImplicitCFGProduct::new_without_counting_cfgs(1, vec![0].into(), vec![2].into(), cfg);
+    let word = [cfg_inc!(0), cfg_dec!(0)];
+    let path = MultiGraphPath::from_word(product.initial(), &word, &product).unwrap();
+    let product_view = product.full_view();
+
+    // The full SCCs are reachable through the positive cycle, so the
+    // concurrently checked interpolation batches decide what survives.
+    let linear_graphs = [1, 4]
+        .map(|threads| {
+            LinearGraphExtender::from_product_view_with_config(
+                path.clone(),
+                &product_view,
+                &LinearGraphConfig::default().with_candidate_threads(threads),
+            )
+            .run_linear_graph()
+        })
+        .to_vec();
+
+    for linear_graph in &linear_graphs {
+        assert_linear_graph_is_unreachable(linear_graph);
+        assert!(linear_graph.accepts(&word));
+    }
+    for word in words_up_to(&CFGCounterUpdate::alphabet(1), 6) {
+        assert_eq!(
+            linear_graphs[0].accepts(&word),
+            linear_graphs[1].accepts(&word),
+            "{word:?}"
+        );
+    }
+}
+
+#[test]
+fn linear_graph_extender_checks_candidates_in_incremental_sessions() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(1));
+    let s0 = cfg.add_node(DfaNode::non_accepting(()));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let s2 = cfg.add_node(DfaNode::non_accepting(()));
+    let s3 = cfg.add_node(DfaNode::accepting(()));
+    let s4 = cfg.add_node(DfaNode::non_accepting(()));
+
+    cfg.set_initial(s0);
+
+    cfg.add_edge(&s0, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &s3, cfg_dec!(0));
+    cfg.add_edge(&s1, &s2, cfg_inc!(0));
+    cfg.add_edge(&s2, &s1, cfg_dec!(0));
+    cfg.add_edge(&s3, &s4, cfg_inc!(0));
+    cfg.add_edge(&s4, &s3, cfg_inc!(0));
+
+    let product =
+        ImplicitCFGProduct::new_without_counting_cfgs(1, vec![0].into(), vec![2].into(), cfg);
+    let word = [cfg_inc!(0), cfg_dec!(0)];
+    let path = MultiGraphPath::from_word(product.initial(), &word, &product).unwrap();
+    let product_view = product.full_view();
+
+    // Interpolation checks every region mask of the same layout, which a
+    // session answers incrementally.
+    let linear_graphs = [false, true]
+        .map(|enabled| {
+            LinearGraphExtender::from_product_view_with_config(
+                path.clone(),
+                &product_view,
+                &LinearGraphConfig::default().with_incremental_sessions_enabled(enabled),
+            )
+            .run_linear_graph()
+        })
+        .to_vec();
+
+    for linear_graph in &linear_graphs {
+        assert_linear_graph_is_unreachable(linear_graph);
+        assert!(linear_graph.accepts(&word));
+    }
+    for word in words_up_to(&CFGCounterUpdate::alphabet(1), 6) {
+        assert_eq!(
+            linear_graphs[0].accepts(&word),
+            linear_graphs[1].accepts(&word),
+            "{word:?}"
+        );
+    }
+}
+
 fn words_up_to(alphabet: &[CFGCounterUpdate], max_length: usize) -> Vec<Vec<CFGCounterUpdate>> {
     let mut words = vec![Vec::new()];
     let mut level = vec![Vec::new()];
@@ -970,6 +1258,81 @@ fn linear_graph_extender_returns_concrete_run_from_reachable_candidate() {
     assert_eq!(run.transitions, full_only_word);
 }
 
+#[test]
+fn linear_graph_extender_shrinks_reachable_full_scc_to_unreachable_sub_scc() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(2));
+    let s0 = cfg.add_node(DfaNode::non_accepting(()));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let neutral = cfg.add_node(DfaNode::non_accepting(()));
+    let borrow = cfg.add_node(DfaNode::non_accepting(()));
+    let repay = cfg.add_node(DfaNode::non_accepting(()));
+    let accepting = cfg.add_node(DfaNode::accepting(()));
+
+    cfg.set_initial(s0);
+
+    cfg.add_edge(&s0, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &accepting, cfg_dec!(0));
+    cfg.add_edge(&s1, &neutral, cfg_inc!(1));
+    cfg.add_edge(&neutral, &s1, cfg_dec!(1));
+    // This cycle needs counter 1 before it can give it back, so it is only
+    // usable in the Z-relaxation of the full SCC.
+    cfg.add_edge(&s1, &borrow, cfg_dec!(1));
+    cfg.add_edge(&borrow, &repay, cfg_inc!(0));
+    cfg.add_edge(&repay, &s1, cfg_inc!(1));
+
+    let product =
+        ImplicitCFGProduct::new_without_counting_cfgs(2, vec![0, 0].into(), vec![1, 0].into(), cfg);
+    let word = [cfg_inc!(0), cfg_dec!(0)];
+    let path = MultiGraphPath::from_word(product.initial(), &word, &product).unwrap();
+
+    let product_view = product.full_view();
+    let mut extender = LinearGraphExtender::from_product_view(path, &product_view, 10);
+    let linear_graph = extender.run_linear_graph();
+
+    assert_linear_graph_is_unreachable(&linear_graph);
+    assert!(linear_graph.accepts(&word));
+    assert!(linear_graph.accepts(&[cfg_inc!(0), cfg_inc!(1), cfg_dec!(1), cfg_dec!(0)]));
+    assert!(!linear_graph.accepts(&[
+        cfg_inc!(0),
+        cfg_dec!(1),
+        cfg_inc!(0),
+        cfg_inc!(1),
+        cfg_dec!(0)
+    ]));
+}
+
+#[test]
+fn linear_graph_extender_builds_graphs_without_modulo_cfgs() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(1));
+    let s0 = cfg.add_node(DfaNode::non_accepting(()));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let s2 = cfg.add_node(DfaNode::non_accepting(()));
+    let s3 = cfg.add_node(DfaNode::accepting(()));
+
+    cfg.set_initial(s0);
+
+    cfg.add_edge(&s0, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &s3, cfg_dec!(0));
+    cfg.add_edge(&s1, &s2, cfg_inc!(0));
+    cfg.add_edge(&s2, &s1, cfg_inc!(0));
+
+    // Every run ends with an even counter value, so only the residues tracked
+    // by the modulo CFG rule out the final valuation.
+    let product = ImplicitCFGProduct::new(1, vec![0].into(), vec![1].into(), cfg, false);
+    let word = [cfg_inc!(0), cfg_dec!(0)];
+    let path = MultiGraphPath::from_word(product.initial(), &word, &product).unwrap();
+
+    let product_view = product.view_without_modulo_cfgs();
+    assert!(!product_view.active_cfg_indices().contains(&1));
+    let path = product_view.project_path(&path);
+    let mut extender = LinearGraphExtender::from_product_view(path, &product_view, 10);
+    let linear_graph = extender.run_linear_graph();
+
+    assert_linear_graph_is_unreachable(&linear_graph);
+    assert!(linear_graph.accepts(&word));
+    assert!(linear_graph.accepts(&[cfg_inc!(0), cfg_inc!(0), cfg_inc!(0), cfg_dec!(0)]));
+}
+
 #[test]
 fn linear_graph_extender_drops_auxiliary_paths_with_different_scc_sequence() {
     let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(2));
@@ -3,17 +3,25 @@ use vass_reach_lib::{
         InitializedAutomaton, ModifiableAutomaton,
         cfg::{update::CFGCounterUpdate, vasscfg::VASSCFG},
         dfa::node::DfaNode,
-        linear_graph::extender::template_testing::{
-            analyze_incremental_template_bounds_snapshot, analyze_template_bounds_snapshot,
-            candidate_template_coefficients, default_template_coefficients,
-            default_template_coefficients_with_families, exact_successor_bound_from_coefficients,
-            guided_candidate_template_coefficients, main_cfg_template_lower_bounds_snapshot,
-            successor_bound_from_coefficients_with_exact_transfer,
-            successor_bound_from_coefficients_with_exact_transfer_limit,
-            synthesize_template_coefficients,
+        implicit_cfg_product::{ImplicitCFGProduct, state::MultiGraphState},
+        linear_graph::{
+            LinearGraph,
+            extender::template_testing::{
+                analyze_incremental_template_bounds_snapshot, analyze_template_bounds_snapshot,
+                candidate_template_coefficients, default_template_coefficients,
+                default_template_coefficients_with_families,
+                exact_successor_bound_from_coefficients, guided_candidate_template_coefficients,
+                linear_graph_boundary_template_bounds_snapshot,
+                main_cfg_template_lower_bounds_snapshot,
+                successor_bound_from_coefficients_with_exact_transfer,
+                successor_bound_from_coefficients_with_exact_transfer_limit,
+                synthesize_template_coefficients, synthesize_template_coefficients_with_mode,
+            },
         },
+        path::Path,
     },
-    config::LinearGraphTemplateFamily,
+    cfg_dec, cfg_inc,
+    config::{LinearGraphTemplateFamily, LinearGraphTemplateSynthesisMode},
 };
 
 #[test]
@@ -247,6 +255,56 @@ fn synthesis_discovers_a_weighted_separating_template() {
     assert!(template.as_slice() == [2, 1] || template.as_slice() == [1, 2]);
 }
 
+#[test]
+fn farkas_synthesis_finds_templates_beyond_the_enumeration_bound() {
+    let cfg = exchange_template_cfg(3);
+    let initial = cfg.get_initial();
+    let initial_valuation = vec![1, 0].into();
+    let model_boundaries = [(initial, vec![0, 2].into())];
+    let synthesize = |mode| {
+        synthesize_template_coefficients_with_mode(
+            &cfg,
+            &initial_valuation,
+            &model_boundaries,
+            2,
+            64,
+            mode,
+        )
+    };
+
+    assert_eq!(
+        synthesize(LinearGraphTemplateSynthesisMode::Enumeration),
+        None
+    );
+    assert_eq!(
+        synthesize(LinearGraphTemplateSynthesisMode::Farkas),
+        Some(vec![3, 1])
+    );
+    assert_eq!(
+        synthesize(LinearGraphTemplateSynthesisMode::FarkasThenEnumeration),
+        Some(vec![3, 1])
+    );
+}
+
+#[test]
+fn farkas_synthesis_rejects_models_that_satisfy_every_invariant() {
+    let cfg = exchange_template_cfg(3);
+    let initial = cfg.get_initial();
+    let initial_valuation = vec![1, 0].into();
+
+    // (0, 3) is reachable, so no inductive template can exclude it.
+    let template = synthesize_template_coefficients_with_mode(
+        &cfg,
+        &initial_valuation,
+        &[(initial, vec![0, 3].into())],
+        2,
+        64,
+        LinearGraphTemplateSynthesisMode::Farkas,
+    );
+
+    assert_eq!(template, None);
+}
+
 #[test]
 fn synthesis_uses_witness_guidance_before_candidate_limit() {
     let cfg = weighted_template_cfg();
@@ -264,20 +322,114 @@ fn synthesis_uses_witness_guidance_before_candidate_limit() {
 }
 
 fn weighted_template_cfg() -> VASSCFG<()> {
+    exchange_template_cfg(2)
+}
+
+/// One token of c0 is exchanged for `weight` tokens of c1 and back.
+fn exchange_template_cfg(weight: usize) -> VASSCFG<()> {
     let mut cfg = VASSCFG::new(CFGCounterUpdate::alphabet(2));
     let initial = cfg.add_node(DfaNode::accepting(()));
-    let dec_c0 = cfg.add_node(DfaNode::non_accepting(()));
-    let first_inc_c1 = cfg.add_node(DfaNode::non_accepting(()));
-    let inc_c0 = cfg.add_node(DfaNode::non_accepting(()));
-    let first_dec_c1 = cfg.add_node(DfaNode::non_accepting(()));
     cfg.set_initial(initial);
 
-    cfg.add_edge(&initial, &dec_c0, CFGCounterUpdate::new(0, false));
-    cfg.add_edge(&dec_c0, &first_inc_c1, CFGCounterUpdate::new(1, true));
-    cfg.add_edge(&first_inc_c1, &initial, CFGCounterUpdate::new(1, true));
-    cfg.add_edge(&initial, &inc_c0, CFGCounterUpdate::new(0, true));
-    cfg.add_edge(&inc_c0, &first_dec_c1, CFGCounterUpdate::new(1, false));
-    cfg.add_edge(&first_dec_c1, &initial, CFGCounterUpdate::new(1, false));
+    for (c0_update, c1_update) in [(false, true), (true, false)] {
+        let mut state = cfg.add_node(DfaNode::non_accepting(()));
+        cfg.add_edge(&initial, &state, CFGCounterUpdate::new(0, c0_update));
+        for _ in 1..weight {
+            let next = cfg.add_node(DfaNode::non_accepting(()));
+            cfg.add_edge(&state, &next, CFGCounterUpdate::new(1, c1_update));
+            state = next;
+        }
+        cfg.add_edge(&state, &initial, CFGCounterUpdate::new(1, c1_update));
+    }
 
     cfg
 }
+
+#[test]
+fn linear_graph_boundaries_get_conservation_equalities_and_upper_bounds() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(2));
+    let s0 = cfg.add_node(DfaNode::non_accepting(()));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let to_right = cfg.add_node(DfaNode::non_accepting(()));
+    let to_left = cfg.add_node(DfaNode::non_accepting(()));
+    let accepting = cfg.add_node(DfaNode::accepting(()));
+    cfg.set_initial(s0);
+
+    // One token moves between both counters inside the SCC of s1.
+    cfg.add_edge(&s0, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &to_right, cfg_dec!(0));
+    cfg.add_edge(&to_right, &s1, cfg_inc!(1));
+    cfg.add_edge(&s1, &to_left, cfg_dec!(1));
+    cfg.add_edge(&to_left, &s1, cfg_inc!(0));
+    cfg.add_edge(&s1, &accepting, cfg_inc!(1));
+
+    let product =
+        ImplicitCFGProduct::new_without_counting_cfgs(2, vec![0, 0].into(), vec![0, 2].into(), cfg);
+    let word = [cfg_inc!(0), cfg_dec!(0), cfg_inc!(1), cfg_inc!(1)];
+    let path =
+        Path::<MultiGraphState, CFGCounterUpdate>::from_word(product.initial(), &word, &product)
+            .unwrap();
+
+    let view = product.full_view();
+    let linear_graph = LinearGraph::from_path(path, &view, 2).add_scc_around_position(0, 1);
+    let boundaries = linear_graph_boundary_template_bounds_snapshot(
+        &linear_graph,
+        &product.initial_valuation,
+        &product.final_valuation,
+    );
+
+    // The region of s1 is followed by a path, so both boundaries around it
+    // know the single token exactly.
+    let region = linear_graph
+        .iter_parts()
+        .position(|part| part.is_graph())
+        .expect("the SCC of s1 is a region");
+
+    for boundary in [&boundaries[region], &boundaries[region + 1]] {
+        assert!(boundary.equalities.contains(&(vec![1, 1], 1)));
+        assert!(
+            !boundary
+                .lower_bounds
+                .iter()
+                .any(|(coefficients, _)| coefficients == &vec![1, 1])
+        );
+    }
+}
+
+#[test]
+fn linear_graph_boundaries_get_upper_bounds_from_decrementing_regions() {
+    let mut cfg = VASSCFG::<()>::new(CFGCounterUpdate::alphabet(2));
+    let s1 = cfg.add_node(DfaNode::non_accepting(()));
+    let s2 = cfg.add_node(DfaNode::non_accepting(()));
+    let accepting = cfg.add_node(DfaNode::accepting(()));
+    cfg.set_initial(s1);
+
+    cfg.add_edge(&s1, &s1, cfg_dec!(0));
+    cfg.add_edge(&s1, &s2, cfg_inc!(1));
+    cfg.add_edge(&s2, &s2, cfg_dec!(0));
+    cfg.add_edge(&s2, &accepting, cfg_dec!(1));
+
+    let product =
+        ImplicitCFGProduct::new_without_counting_cfgs(2, vec![2, 0].into(), vec![0, 0].into(), cfg);
+    let word = [cfg_inc!(1), cfg_dec!(1)];
+    let path =
+        Path::<MultiGraphState, CFGCounterUpdate>::from_word(product.initial(), &word, &product)
+            .unwrap();
+
+    let view = product.full_view();
+    let linear_graph = LinearGraph::from_path(path, &view, 2)
+        .add_scc_around_position(0, 0)
+        .add_scc_around_position(1, 1);
+    let boundaries = linear_graph_boundary_template_bounds_snapshot(
+        &linear_graph,
+        &product.initial_valuation,
+        &product.final_valuation,
+    );
+
+    // Between both regions, counter 0 can only have decreased from its initial
+    // value, while counter 1 has not been incremented yet.
+    let boundary = &boundaries[1];
+    assert!(boundary.upper_bounds.contains(&(vec![1, 0], 2)));
+    assert!(boundary.upper_bounds.contains(&(vec![1, 1], 2)));
+    assert!(boundary.equalities.contains(&(vec![0, 1], 0)));
+}
@@ -1,5 +1,6 @@
 use vass_reach_lib::{
     automaton::{
+        cfg::update::CFGCounterUpdate,
         ltc::{LTC, translation::LTCTranslation},
         petri_net::initialized::InitializedPetriNet,
     },
@@ -16,8 +17,14 @@ fn ltc_n_reach_1() {
     // this one should be reachable in N and Z, and the loop should be taken three
     // times
 
-    assert!(ltc.reach_n(&vec![0, 0].into(), &vec![0, 0].into()).result);
-    assert!(ltc.reach_z(&vec![0, 0].into(), &vec![0, 0].into()).result);
+    assert!(
+        ltc.reach_n(&vec![0, 0].into(), &vec![0, 0].into())
+            .is_success()
+    );
+    assert!(
+        ltc.reach_z(&vec![0, 0].into(), &vec![0, 0].into())
+            .is_success()
+    );
 }
 
 #[test]
@@ -27,8 +34,14 @@ fn ltc_n_reach_2() {
 
     // this one should not be reachable in N, but should be in Z
 
-    assert!(!ltc.reach_n(&vec![0, 0].into(), &vec![0, 0].into()).result);
-    assert!(ltc.reach_z(&vec![0, 0].into(), &vec![0, 0].into()).result);
+    assert!(
+        ltc.reach_n(&vec![0, 0].into(), &vec![0, 0].into())
+            .is_failure()
+    );
+    assert!(
+        ltc.reach_z(&vec![0, 0].into(), &vec![0, 0].into())
+            .is_success()
+    );
 }
 
 #[test]
@@ -41,8 +54,14 @@ fn ltc_n_reach_3() {
     // this one should not be reachable in N and Z, as the loop can only produce
     // even numbers on counter two
 
-    assert!(!ltc.reach_n(&vec![0, 0].into(), &vec![0, 0].into()).result);
-    assert!(!ltc.reach_z(&vec![0, 0].into(), &vec![0, 0].into()).result);
+    assert!(
+        ltc.reach_n(&vec![0, 0].into(), &vec![0, 0].into())
+            .is_failure()
+    );
+    assert!(
+        ltc.reach_z(&vec![0, 0].into(), &vec![0, 0].into())
+            .is_failure()
+    );
 }
 
 #[test]
@@ -50,8 +69,8 @@ fn ltc_n_reach_4() {
     let mut ltc = LTC::new(1);
     ltc.add_loop(vec![1].into(), vec![2].into());
 
-    assert!(!ltc.reach_n(&vec![0].into(), &vec![2].into()).result);
-    assert!(ltc.reach_z(&vec![0].into(), &vec![2].into()).result);
+    assert!(ltc.reach_n(&vec![0].into(), &vec![2].into()).is_failure());
+    assert!(ltc.reach_z(&vec![0].into(), &vec![2].into()).is_success());
 }
 
 #[test]
@@ -59,8 +78,8 @@ fn ltc_n_reach_5() {
     let mut ltc = LTC::new(1);
     ltc.add_loop(vec![2].into(), vec![1].into());
 
-    assert!(!ltc.reach_n(&vec![2].into(), &vec![0].into()).result);
-    assert!(ltc.reach_z(&vec![2].into(), &vec![0].into()).result);
+    assert!(ltc.reach_n(&vec![2].into(), &vec![0].into()).is_failure());
+    assert!(ltc.reach_z(&vec![2].into(), &vec![0].into()).is_success());
 }
 
 #[test]
@@ -80,7 +99,10 @@ fn ltc_language_1() {
 
     let translation = LTCTranslation::from(&path);
     let non_expanded_dfa = translation
-        .to_dfa(initialized_vass.dimension(), false)
+        .to_dfa(
+            &CFGCounterUpdate::alphabet(initialized_vass.dimension()),
+            false,
+        )
         .invert();
     // let expanded_translation = translation.expand(&cfg);
     // let expanded_dfa = expanded_translation
@@ -109,7 +131,10 @@ fn ltc_language_2() {
 
     let translation = LTCTranslation::from(&path);
     let non_expanded_dfa = translation
-        .to_dfa(initialized_vass.dimension(), false)
+        .to_dfa(
+            &CFGCounterUpdate::alphabet(initialized_vass.dimension()),
+            false,
+        )
         .invert();
 
     // let expanded_translation = translation.expand(&cfg);
@@ -4,7 +4,9 @@ use vass_reach_lib::automaton::{
     cfg::update::CFGCounterUpdate,
     dfa::{DFA, node::DfaNode},
     path::{Path, parikh_image::ParikhImage},
-    vass::counter::{VASSCounterIndex, VASSCounterValuation},
+    vass::counter::{
+        CounterOverflowError, VASSCounterIndex, VASSCounterUpdate, VASSCounterValuation,
+    },
 };
 
 #[test]
@@ -215,6 +217,31 @@ fn test_vass_path_max_values() {
     );
 }
 
+#[test]
+fn test_vass_path_counter_overflow() {
+    let mut path = Path::new(NodeIndex::from(0u32));
+    path.add(CFGCounterUpdate::new(0, true), NodeIndex::from(1u32));
+    path.add(CFGCounterUpdate::new(0, true), NodeIndex::from(2u32));
+
+    // MAX - 1 -> MAX -> overflow
+    let initial = VASSCounterValuation::from(vec![i64::MAX - 1]);
+    assert_eq!(
+        path.try_get_path_final_valuation(&initial),
+        Err(CounterOverflowError {
+            counter: VASSCounterIndex::from(0)
+        })
+    );
+
+    // a failed update leaves the valuation unchanged
+    let mut valuation = VASSCounterValuation::from(vec![1, i64::MAX]);
+    assert!(
+        valuation
+            .try_apply_update(&VASSCounterUpdate::from(vec![1, 1]))
+            .is_err()
+    );
+    assert_eq!(valuation, VASSCounterValuation::from(vec![1, i64::MAX]));
+}
+
 #[test]
 fn test_parikh_image_basics() {
     let mut pi = ParikhImage::<usize>::empty(5);
@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use vass_reach_lib::{
+    automaton::{
+        petri_net::{PetriNet, initialized::InitializedPetriNet},
+        utils,
+    },
+    config::{UpdateEncoding, VASSReachConfig},
+    solver::vass_reach::{VASSReachSolver, VASSReachWitness},
+};
+
+fn solve(net: &InitializedPetriNet) -> bool {
+    let result = VASSReachSolver::new(
+        &net.to_vass(),
+        VASSReachConfig::default().with_timeout(Some(Duration::from_secs(5))),
+    )
+    .solve();
+    assert!(!result.is_unknown(), "{:?}", result.status);
+    result.is_success()
+}
+
+/// p1 -t0-> p2 -t1-> p3 -t2-> p4
+fn chain(tokens: i64, target: i64) -> InitializedPetriNet {
+    let mut net = PetriNet::new(4);
+    net.add_transition(vec![(1, 1)], vec![(1, 2)]);
+    net.add_transition(vec![(1, 2)], vec![(1, 3)]);
+    net.add_transition(vec![(1, 3)], vec![(1, 4)]);
+    net.init(vec![tokens, 0, 0, 0].into(), vec![0, 0, 0, target].into())
+}
+
+#[test]
+fn reduction_fuses_series_places_and_transitions() {
+    let net = chain(2, 2);
+    let reduction = net.reduce();
+
+    assert_eq!(reduction.statistics.agglomerated_places, 2);
+    assert_eq!(reduction.statistics.places, 2);
+    assert_eq!(reduction.statistics.transitions, 1);
+    assert_eq!(reduction.original_transitions(0), &[0, 1, 2]);
+
+    assert!(reduction.net.is_run_to_final_marking(&[0, 0]));
+    let run = reduction.expand_run(&[0, 0]);
+    assert_eq!(run, vec![0, 1, 2, 0, 1, 2]);
+    assert!(net.is_run_to_final_marking(&run));
+}
+
+#[test]
+fn runs_of_reduced_nets_expand_to_runs_of_the_original_net() {
+    let net = chain(3, 3);
+    let reduction = net.reduce();
+    assert!(reduction.statistics.is_reduced());
+
+    for encoding in [UpdateEncoding::Unary, UpdateEncoding::Binary] {
+        let encode = match encoding {
+            UpdateEncoding::Unary => utils::vass_update_to_cfg_updates,
+            UpdateEncoding::Binary => utils::vass_update_to_binary_cfg_updates,
+        };
+        let result = VASSReachSolver::new(
+            &reduction.net.to_vass(),
+            VASSReachConfig::default()
+                .with_timeout(Some(Duration::from_secs(5)))
+                .with_update_encoding(encoding),
+        )
+        .solve();
+        let VASSReachWitness::Run(run) = result.unwrap_success() else {
+            panic!("expected a run");
+        };
+
+        let reduced_run = reduction
+            .net
+            .transitions_of_update_run(&run, encode)
+            .unwrap();
+        assert!(reduction.net.is_run_to_final_marking(&reduced_run));
+
+        let expanded = reduction.expand_update_run(&run, encode).unwrap();
+        let original_run = net.transitions_of_update_run(&expanded, encode).unwrap();
+        assert_eq!(original_run, reduction.expand_run(&reduced_run));
+        assert!(net.is_run_to_final_marking(&original_run));
+    }
+}
+
+#[test]
+fn reduction_removes_dead_and_redundant_transitions_and_places() {
+    // p1 is a guard with one token, p4 always holds one token more than p2
+    let mut net = PetriNet::new(4);
+    net.add_transition(vec![(1, 1), (1, 2), (1, 4)], vec![(1, 1), (1, 3)]);
+    // needs two tokens in the guard
+    net.add_transition(vec![(2, 1), (1, 3)], vec![(2, 1), (1, 2), (1, 4)]);
+    // does not change the marking
+    net.add_transition(vec![(1, 3)], vec![(1, 3)]);
+    // a duplicate of the first transition
+    net.add_transition(vec![(1, 1), (1, 2), (1, 4)], vec![(1, 1), (1, 3)]);
+    let net = net.init(vec![1, 2, 0, 3].into(), vec![1, 0, 2, 1].into());
+
+    let reduction = net.reduce();
+    let statistics = &reduction.statistics;
+    assert_eq!(statistics.dead_transitions, 1);
+    assert_eq!(statistics.redundant_transitions, 2);
+    assert_eq!(statistics.constant_places, 1);
+    assert_eq!(statistics.implicit_places, 1);
+    assert_eq!((statistics.places, statistics.transitions), (2, 1));
+
+    assert!(reduction.net.is_run_to_final_marking(&[0, 0]));
+    let run = reduction.expand_run(&[0, 0]);
+    assert_eq!(run, vec![0, 0]);
+    assert!(net.is_run_to_final_marking(&run));
+}
+
+#[test]
+fn reduction_removes_implicit_places_that_are_needed_less() {
+    // p2 loses a token whenever p1 does, but t0 needs two tokens in p1 and one
+    // in p2
+    let mut net = PetriNet::new(2);
+    net.add_transition(vec![(2, 1), (1, 2)], vec![(1, 1)]);
+    let net = net.init(vec![3, 3].into(), vec![1, 1].into());
+
+    let reduction = net.reduce();
+    assert_eq!(reduction.statistics.implicit_places, 1);
+    assert_eq!(reduction.statistics.places, 1);
+    assert!(reduction.net.is_run_to_final_marking(&[0, 0]));
+    assert!(net.is_run_to_final_marking(&reduction.expand_run(&[0, 0])));
+}
+
+#[test]
+fn reduction_preserves_reachability() {
+    // the target of the chain is reachable exactly if no tokens are lost
+    assert!(solve(&chain(3, 3)));
+    assert!(solve(&chain(3, 3).reduce().net));
+    assert!(!solve(&chain(3, 2)));
+    assert!(!solve(&chain(3, 2).reduce().net));
+
+    // p2 is an intermediate place with two producers that only fill p2 and
+    // one consumer that also needs p3
+    let mut net = PetriNet::new(4);
+    net.add_transition(vec![(1, 1)], vec![(1, 2)]);
+    net.add_transition(vec![(2, 1)], vec![(1, 2)]);
+    net.add_transition(vec![(1, 2), (1, 3)], vec![(1, 4)]);
+    for (target, reachable) in [(1, true), (2, false)] {
+        let net = net
+            .clone()
+            .init(vec![2, 0, 1, 0].into(), vec![0, 0, 0, target].into());
+        let reduction = net.reduce();
+        assert_eq!(reduction.statistics.agglomerated_places, 1);
+        assert_eq!(solve(&net), reachable);
+        assert_eq!(solve(&reduction.net), reachable);
+    }
+}
+
+#[test]
+fn reduction_keeps_nets_with_specs() {
+    let net = InitializedPetriNet::parse_from_spec(
+        r#"
+    vars
+        p1 p2 p3
+    rules
+        p1 >= 1 ->
+            p1' = p1-1,
+            p2' = p2+1;
+        p2 >= 1 ->
+            p2' = p2-1,
+            p3' = p3+1;
+    init
+        p1=2, p2=0, p3=0
+    target
+        p1=0, p2=0, p3>=1"#,
+    )
+    .unwrap();
+
+    let reduction = net.reduce();
+    assert_eq!(reduction.net, net);
+    assert_eq!(reduction.statistics.transitions, 2);
+    assert_eq!(reduction.expand_run(&[1, 0]), vec![1, 0]);
+}
@@ -1,13 +1,16 @@
 use std::time::Duration;
 
 use vass_reach_lib::{
-    automaton::petri_net::{
-        PetriNet,
-        initialized::InitializedPetriNet,
-        spec::{PetriNetSpec, ToSpecFormat},
+    automaton::{
+        cfg::update::CFGCounterUpdatable,
+        petri_net::{
+            PetriNet,
+            initialized::InitializedPetriNet,
+            spec::{PetriNetSpec, ToSpecFormat},
+        },
     },
-    config::VASSReachConfig,
-    solver::vass_reach::VASSReachSolver,
+    config::{UpdateEncoding, VASSReachConfig},
+    solver::vass_reach::{VASSReachSolver, VASSReachWitness},
 };
 
 #[test]
@@ -165,3 +168,131 @@ fn stringify_and_parse() {
 
     assert_eq!(parsed_net.unwrap(), initialized_net);
 }
+
+const GENERALIZED_TARGET_SPEC: &str = r#"
+vars
+    p1 p2 p3
+rules
+    p1 >= 1 ->
+        p1' = p1-1,
+        p2' = p2+1;
+    p2 >= 1 ->
+        p2' = p2-1,
+        p3' = p3+1;
+init
+    p1=2, p2=0, p3=0
+target
+    p1<=1, p2=0, p3>=1"#;
+
+#[test]
+fn generalized_target_reachable() {
+    let net = InitializedPetriNet::parse_from_spec(GENERALIZED_TARGET_SPEC).unwrap();
+    let vass = net.to_vass();
+
+    let res = VASSReachSolver::new(
+        &vass,
+        VASSReachConfig::default().with_timeout(Some(Duration::from_secs(5))),
+    )
+    .solve();
+
+    assert!(res.is_success());
+    // the witness runs over the original places
+    assert_eq!(vass.dimension(), 3);
+
+    // with equality only, p1=0 and p3=1 can not be reached at the same time
+    let exact =
+        InitializedPetriNet::new(net.net.clone(), vec![2, 0, 0].into(), vec![0, 0, 1].into());
+    let res = VASSReachSolver::new(
+        &exact.to_vass(),
+        VASSReachConfig::default().with_timeout(Some(Duration::from_secs(5))),
+    )
+    .solve();
+
+    assert!(res.is_failure());
+}
+
+#[test]
+fn spec_runs_start_and_end_in_valuations_of_the_specs() {
+    // moves every token of p1 to p2, so at least two tokens are needed
+    let net = InitializedPetriNet::parse_from_spec(
+        r#"
+    vars
+        p1 p2
+    rules
+        p1 >= 1 ->
+            p1' = p1-1,
+            p2' = p2+1;
+    init
+        p1>=1, p2=0
+    target
+        p1=0, p2>=2"#,
+    )
+    .unwrap();
+    let vass = net.to_vass();
+
+    for encoding in [UpdateEncoding::Unary, UpdateEncoding::Binary] {
+        let res = VASSReachSolver::new(
+            &vass,
+            VASSReachConfig::default()
+                .with_timeout(Some(Duration::from_secs(5)))
+                .with_update_encoding(encoding),
+        )
+        .solve();
+        let VASSReachWitness::Run(run) = res.unwrap_success() else {
+            panic!("expected a run");
+        };
+
+        let spec_run = vass.spec_run(&run, encoding.cfg_updates()).unwrap();
+        assert!(
+            net.initial_spec
+                .as_ref()
+                .unwrap()
+                .matches(&spec_run.initial_valuation)
+        );
+        assert!(
+            net.target_spec
+                .as_ref()
+                .unwrap()
+                .matches(&spec_run.final_valuation)
+        );
+        // two updates per fired transition, none of the pumping and draining
+        assert_eq!(
+            spec_run.updates.len(),
+            2 * spec_run.initial_valuation[0] as usize
+        );
+
+        let mut valuation = spec_run.initial_valuation.clone();
+        for update in &spec_run.updates {
+            valuation.apply_cfg_update(*update);
+            assert!(!valuation.has_negative_counter());
+        }
+        assert_eq!(valuation, spec_run.final_valuation);
+    }
+}
+
+#[test]
+fn generalized_target_parse_and_stringify() {
+    let net = InitializedPetriNet::parse_from_spec(GENERALIZED_TARGET_SPEC).unwrap();
+
+    assert_eq!(net.to_spec_format().trim(), GENERALIZED_TARGET_SPEC.trim());
+    assert_eq!(
+        InitializedPetriNet::from_json(&net.to_json().unwrap()).unwrap(),
+        net
+    );
+}
+
+#[test]
+fn parse_rejects_numbers_out_of_range() {
+    let spec_str = r#"
+    vars
+        p1
+    rules
+        p1 >= 1 ->
+            p1' = p1-1;
+    init
+        p1=9223372036854775808
+    target
+        p1=0"#;
+
+    assert!(PetriNetSpec::parse(spec_str).is_err());
+}
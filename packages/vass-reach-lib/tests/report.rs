@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use vass_reach_lib::{
+    automaton::{
+        ModifiableAutomaton,
+        cfg::update::CFGCounterUpdatable,
+        petri_net::PetriNet,
+        utils,
+        vass::{VASS, VASSEdge, initialized::InitializedVASS},
+    },
+    config::{PreprocessingConfig, ShortWitnessConfig, VASSReachConfig, VASSZReachConfig},
+    solver::{
+        SerializableSolverStatus, SolverResult,
+        report::{InstanceProvenance, SOLVER_REPORT_SCHEMA_VERSION, SolverReport, instance_hash},
+        vass_reach::{VASSReachSolver, VASSReachSolverStatistics, VASSReachWitness},
+        vass_z_reach::{VASSZReachCertificate, VASSZReachSolver, VASSZReachSolverStatistics},
+    },
+};
+
+/// Counter 0 has to be pumped to 2 and then emptied by a single transition.
+fn pump_instance() -> InitializedVASS<usize, usize> {
+    let mut vass = VASS::new(1, vec![0, 1]);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![1].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![-2].into()));
+    vass.init(vec![0].into(), vec![0].into(), q0, q1)
+}
+
+fn assert_replays(witness: &VASSReachWitness, instance: &InitializedVASS<usize, usize>) {
+    let VASSReachWitness::Run(updates) = witness else {
+        panic!("expected a run, got {witness:?}");
+    };
+
+    let mut valuation = instance.initial_valuation.clone();
+    for update in updates {
+        valuation.apply_cfg_update(*update);
+        assert!(!valuation.has_negative_counter(), "{witness}");
+    }
+    assert_eq!(valuation, instance.final_valuation);
+}
+
+#[test]
+fn n_report_contains_a_replayable_run() {
+    let instance = pump_instance();
+
+    for short_witness in [true, false] {
+        let config = VASSReachConfig::default()
+            .with_timeout(Some(Duration::from_secs(5)))
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(short_witness))
+            .with_preprocessing(PreprocessingConfig::default().with_enabled(false));
+        let result = VASSReachSolver::new(&instance, config.clone()).solve();
+
+        let report = SolverReport::new(
+            "N",
+            InstanceProvenance::new("pump.vass.json", b"pump"),
+            &config,
+            result,
+        )
+        .unwrap();
+        assert_eq!(report.schema_version, SOLVER_REPORT_SCHEMA_VERSION);
+        assert_eq!(report.status, SerializableSolverStatus::True);
+        assert_eq!(report.instance.hash, instance_hash(b"pump"));
+        assert_eq!(report.config["short_witness"]["enabled"], short_witness);
+        assert_replays(report.witness.as_ref().unwrap(), &instance);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: SolverReport<VASSReachWitness, VASSReachSolverStatistics> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+        assert!(
+            report
+                .to_text()
+                .unwrap()
+                .starts_with("status:   reachable\n")
+        );
+    }
+}
+
+#[test]
+fn reports_of_reduced_nets_translate_the_witness() {
+    // p1 -t0-> p2 -t1-> p3, p2 is agglomerated
+    let mut net = PetriNet::new(3);
+    net.add_transition(vec![(1, 1)], vec![(1, 2)]);
+    net.add_transition(vec![(1, 2)], vec![(1, 3)]);
+    let net = net.init(vec![2, 0, 0].into(), vec![0, 0, 2].into());
+    let reduction = net.reduce();
+    assert!(reduction.statistics.is_reduced());
+
+    let config = VASSReachConfig::default().with_timeout(Some(Duration::from_secs(5)));
+    let report = || {
+        let result = VASSReachSolver::new(&reduction.net.to_vass(), config.clone()).solve();
+        SolverReport::new("N", InstanceProvenance::new("a", b""), &config, result).unwrap()
+    };
+
+    let report = report().with_reduction(reduction.statistics.clone(), |witness| match witness {
+        VASSReachWitness::Run(run) => reduction
+            .expand_update_run(&run, utils::vass_update_to_cfg_updates)
+            .map(VASSReachWitness::Run),
+        witness => Some(witness),
+    });
+    assert_eq!(report.status, SerializableSolverStatus::True);
+    assert_replays(report.witness.as_ref().unwrap(), &net.to_vass());
+    assert_eq!(report.reduction.as_ref(), Some(&reduction.statistics));
+    assert!(
+        report
+            .to_text()
+            .unwrap()
+            .contains("reduced:  2 of 3 places, 1 of 2 transitions\n")
+    );
+
+    let report = report.with_reduction(reduction.statistics.clone(), |_| None);
+    assert_eq!(report.witness, None);
+}
+
+#[test]
+fn unreachable_and_unknown_reports_have_no_witness() {
+    // counter 0 only grows in steps of 2
+    let mut vass = VASS::new(1, vec![0, 1]);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![2].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![0].into()));
+    let instance = vass.init(vec![0].into(), vec![1].into(), q0, q1);
+
+    let config = VASSReachConfig::default().with_timeout(Some(Duration::from_secs(5)));
+    let result = VASSReachSolver::new(&instance, config.clone()).solve();
+    let report =
+        SolverReport::new("N", InstanceProvenance::new("a", b""), &config, result).unwrap();
+    assert_eq!(report.status, SerializableSolverStatus::False);
+    assert_eq!(report.witness, None);
+    assert_eq!(report.reason, None);
+
+    let config = VASSReachConfig::default()
+        .with_max_iterations(Some(0))
+        .with_short_witness(ShortWitnessConfig::default().with_enabled(false));
+    let result = VASSReachSolver::new(&pump_instance(), config.clone()).solve();
+    let report =
+        SolverReport::new("N", InstanceProvenance::new("a", b""), &config, result).unwrap();
+    assert_eq!(report.status, SerializableSolverStatus::Unknown);
+    assert_eq!(report.witness, None);
+    assert_eq!(report.reason, Some("MaxIterationsReached".into()));
+}
+
+#[test]
+fn z_report_contains_the_parikh_image() {
+    let instance = pump_instance();
+    let cfg = instance.to_cfg();
+    let config = VASSZReachConfig::default().with_timeout(Some(Duration::from_secs(5)));
+    let result = VASSZReachSolver::new(
+        &cfg,
+        instance.initial_valuation.clone(),
+        instance.final_valuation.clone(),
+        config.clone(),
+    )
+    .solve();
+
+    let certificate = result.certificate(&cfg).unwrap();
+    let effect = certificate
+        .edges
+        .iter()
+        .map(|edge| edge.update.op() * edge.count as i64)
+        .sum::<i64>();
+    assert_eq!(effect, 0);
+
+    let result = SolverResult::new(
+        result.status.map_success(|_| certificate.clone()),
+        result.statistics,
+    );
+    let report =
+        SolverReport::new("Z", InstanceProvenance::new("a", b""), &config, result).unwrap();
+    let json = serde_json::to_string(&report).unwrap();
+    let parsed: SolverReport<VASSZReachCertificate, VASSZReachSolverStatistics> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.witness, Some(certificate));
+}
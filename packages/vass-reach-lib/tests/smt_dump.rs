@@ -0,0 +1,126 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use vass_reach_lib::{
+    automaton::{
+        ModifiableAutomaton,
+        cfg::update::CFGCounterUpdate,
+        linear_graph::extender::template_testing::exact_successor_bound_from_coefficients_with_dump,
+        vass::{VASS, VASSEdge},
+    },
+    config::{ArithmeticBackendConfig, PreprocessingConfig, SMTDumpConfig, VASSReachConfig},
+    solver::{
+        smt_dump::{SMTQueryDump, dumped_queries, replay_query},
+        vass_reach::VASSReachSolver,
+    },
+};
+
+fn dump_root(name: &str) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("vass-smt-dump-{name}-{unique}"))
+}
+
+#[test]
+fn dumped_queries_replay_with_unchanged_answers() {
+    let root = dump_root("replay");
+    let mut vass = VASS::<usize, usize>::new(1, (0..2).collect());
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    // Z-reachable by taking the loop once, but the first edge already
+    // decrements the empty counter.
+    vass.add_edge(&q0, &q1, VASSEdge::new(0, vec![-1].into()));
+    vass.add_edge(&q1, &q1, VASSEdge::new(1, vec![1].into()));
+    let instance = vass.init(vec![0].into(), vec![0].into(), q0, q1);
+
+    let result = VASSReachSolver::new(
+        &instance,
+        VASSReachConfig::default()
+            .with_preprocessing(
+                PreprocessingConfig::default()
+                    .with_enabled(true)
+                    .with_z_reach_precheck_enabled(true),
+            )
+            .with_smt_dump(
+                SMTDumpConfig::default()
+                    .with_enabled(true)
+                    .with_output_dir(Some(root.display().to_string())),
+            ),
+    )
+    .solve();
+    assert!(result.is_failure(), "{:?}", result.status);
+
+    let queries = dumped_queries(&root).unwrap();
+    assert!(!queries.is_empty());
+
+    let mut purposes = HashSet::new();
+    for query in &queries {
+        let replay = replay_query(query, &ArithmeticBackendConfig::default()).unwrap();
+        assert!(replay.is_unchanged(), "{}: {replay:?}", query.display());
+        purposes.insert(replay.purpose);
+    }
+    assert!(purposes.contains("vass_z_reach"), "{purposes:?}");
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn optimizer_queries_replay_with_their_objectives() {
+    let root = dump_root("transfer");
+    let dump = SMTQueryDump::create(
+        &SMTDumpConfig::default()
+            .with_enabled(true)
+            .with_output_dir(Some(root.display().to_string())),
+    )
+    .unwrap()
+    .unwrap();
+    exact_successor_bound_from_coefficients_with_dump(
+        dump,
+        &[vec![1, 0], vec![1, 1]],
+        &[0, 2],
+        &CFGCounterUpdate::new(0, false),
+        1,
+        10,
+    );
+
+    let queries = dumped_queries(&root).unwrap();
+    assert_eq!(queries.len(), 1);
+    let replay = replay_query(&queries[0], &ArithmeticBackendConfig::default()).unwrap();
+    assert_eq!(replay.purpose, "template_transfer");
+    assert!(replay.is_unchanged(), "{replay:?}");
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn solvers_sharing_a_dump_directory_write_to_separate_subdirectories() {
+    let root = dump_root("separate");
+    let config = SMTDumpConfig::default()
+        .with_enabled(true)
+        .with_output_dir(Some(root.display().to_string()));
+    let first = SMTQueryDump::create(&config).unwrap().unwrap();
+    let second = SMTQueryDump::create(&config).unwrap().unwrap();
+    assert_ne!(first.dir(), second.dir());
+
+    for dump in [first, second] {
+        exact_successor_bound_from_coefficients_with_dump(
+            dump,
+            &[vec![1, 0], vec![1, 1]],
+            &[0, 2],
+            &CFGCounterUpdate::new(0, false),
+            1,
+            10,
+        );
+    }
+
+    let queries = dumped_queries(&root).unwrap();
+    assert_eq!(queries.len(), 2);
+    assert_ne!(queries[0].parent(), queries[1].parent());
+
+    let _ = std::fs::remove_dir_all(root);
+}
@@ -1,8 +1,15 @@
 use std::vec;
 
 use vass_reach_lib::automaton::{
-    Language, ModifiableAutomaton,
-    vass::{VASS, VASSEdge},
+    Alphabet, Automaton, Language, ModifiableAutomaton,
+    cfg::update::CFGCounterUpdate,
+    utils,
+    vass::{
+        VASS, VASSEdge,
+        counter::VASSCounterIndex,
+        initialized::InitializedVASS,
+        valuation_spec::{CounterConstraint, ValuationSpec},
+    },
 };
 
 #[test]
@@ -42,6 +49,95 @@ fn test_vass_to_cfg() {
     let _cfg = initialized_vass.to_cfg();
 }
 
+#[test]
+fn test_vass_to_binary_cfg() {
+    let mut vass = VASS::<u32, char>::new(2, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+
+    vass.add_edge(&q0, &q0, VASSEdge::new('a', vec![1000, 5].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new('b', vec![-3000, 0].into()));
+
+    let initialized_vass = vass.init(vec![0, 0].into(), vec![0, 15].into(), q0, q1);
+
+    let unary = initialized_vass.to_cfg();
+    let binary = initialized_vass.to_binary_cfg();
+
+    // 3000 < 2^12 needs twelve exponents per letter
+    assert_eq!(binary.alphabet(), CFGCounterUpdate::binary_alphabet(2, 11));
+    assert!(binary.node_count() * 50 < unary.node_count());
+
+    // 1000 = 2^3 + 2^5 + 2^6 + 2^7 + 2^8 + 2^9, 5 = 2^0 + 2^2
+    let loop_letters =
+        [3, 5, 6, 7, 8, 9]
+            .map(|exponent| CFGCounterUpdate::weighted(VASSCounterIndex::new(0), true, exponent))
+            .into_iter()
+            .chain([0, 2].map(|exponent| {
+                CFGCounterUpdate::weighted(VASSCounterIndex::new(1), true, exponent)
+            }))
+            .collect::<Vec<_>>();
+    // 3000 = 2^3 + 2^4 + 2^5 + 2^7 + 2^8 + 2^9 + 2^11
+    let exit_letters = [3, 4, 5, 7, 8, 9, 11]
+        .map(|exponent| CFGCounterUpdate::weighted(VASSCounterIndex::new(0), false, exponent));
+
+    let word = loop_letters
+        .iter()
+        .cycle()
+        .take(loop_letters.len() * 3)
+        .chain(exit_letters.iter())
+        .copied()
+        .collect::<Vec<_>>();
+    assert!(binary.accepts(&word));
+    assert!(!binary.accepts(&word[..word.len() - 1]));
+}
+
+#[test]
+fn test_binary_cfg_updates_of_i64_min() {
+    let update = vec![i64::MIN, i64::MAX].into();
+
+    let updates = utils::vass_update_to_binary_cfg_updates(&update);
+
+    assert_eq!(
+        utils::max_binary_exponent(&update),
+        CFGCounterUpdate::MAX_EXPONENT
+    );
+    assert!(
+        updates
+            .iter()
+            .all(|update| update.exponent() <= CFGCounterUpdate::MAX_EXPONENT)
+    );
+
+    let mut sums = [0i128; 2];
+    for update in &updates {
+        sums[update.counter().to_usize()] += update.op() as i128;
+    }
+    assert_eq!(sums, [i64::MIN as i128, i64::MAX as i128]);
+}
+
+#[test]
+fn test_vass_multiple_initial_and_final_states() {
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    let q2 = vass.add_node(2);
+
+    vass.add_edge(&q0, &q1, VASSEdge::new('a', vec![1].into()));
+    vass.add_edge(&q2, &q2, VASSEdge::new('b', vec![-1].into()));
+
+    let mut initialized_vass = vass.init(vec![0].into(), vec![1].into(), q0, q1);
+    initialized_vass.add_initial_state(q2, vec![3].into());
+    initialized_vass.add_final_state(q2, vec![0].into());
+
+    assert!(initialized_vass.accepts(&['a']));
+    assert!(initialized_vass.accepts(&['b', 'b', 'b']));
+    assert!(!initialized_vass.accepts(&['b', 'b']));
+    assert!(!initialized_vass.accepts(&[]));
+
+    let vas = initialized_vass.to_vas();
+    assert_eq!(vas.state_count(), 1);
+    assert!(vas.has_single_initial_and_final_state());
+}
+
 // #[test]
 // fn test_vass_to_vas_1() {
 //     let mut vass = VASS::<u32, char>::new(2, vec!['a', 'b']);
@@ -140,3 +236,35 @@ fn test_vass_to_cfg() {
 
 //     assert_eq!(vass_res.status, vas_res.status);
 // }
+
+#[test]
+fn test_valuation_specs_with_negative_bounds_are_rejected() {
+    assert!(
+        ValuationSpec::new(vec![CounterConstraint::AtLeast(0), CounterConstraint::Any].into())
+            .validate()
+            .is_ok()
+    );
+    for constraint in [
+        CounterConstraint::Exactly(-1),
+        CounterConstraint::AtLeast(-1),
+        CounterConstraint::AtMost(-1),
+    ] {
+        assert!(
+            ValuationSpec::new(vec![constraint].into())
+                .validate()
+                .is_err()
+        );
+    }
+
+    let mut vass = VASS::<usize, usize>::new(1, vec![0]);
+    let q0 = vass.add_node(0);
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![1].into()));
+    let json = vass
+        .init(vec![0].into(), vec![0].into(), q0, q0)
+        .to_json()
+        .unwrap();
+    let mut json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+    json["target_spec"] = serde_json::json!([{ "AtMost": -1 }]);
+
+    assert!(InitializedVASS::from_json(&json.to_string()).is_err());
+}
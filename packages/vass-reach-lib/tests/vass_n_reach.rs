@@ -1,13 +1,26 @@
-use std::time::Duration;
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
 
 use vass_reach_lib::{
     automaton::{
         ModifiableAutomaton,
+        linear_graph::extender::LinearGraphTemplateStoreStatistics,
         petri_net::PetriNet,
-        vass::{VASS, VASSEdge},
+        vass::{VASS, VASSEdge, initialized::InitializedVASS},
+    },
+    config::{
+        DivideAndConquerConfig, LTSConfig, LinearGraphConfig, LinearGraphTemplateSynthesisMode,
+        PreprocessingConfig, RefinementAutomatonEngine, RefinementConfig, RefinementPolicyKind,
+        ShortWitnessConfig, UpdateEncoding, VASSReachConfig,
+    },
+    solver::{
+        SolverStatus,
+        vass_reach::{
+            VASSReachRouteStatistics, VASSReachSession, VASSReachSolver, VASSReachSolverError,
+        },
     },
-    config::{PreprocessingConfig, ShortWitnessConfig, VASSReachConfig},
-    solver::{SolverStatus, vass_reach::VASSReachSolver},
 };
 
 #[test]
@@ -31,6 +44,28 @@ fn short_witness_precheck_finds_reachable_instance_before_refinement() {
     assert_eq!(result.statistics.step_count, 0);
 }
 
+#[test]
+fn stop_signals_given_to_the_constructor_cancel_the_prechecks() {
+    let mut vass = VASS::new(1, (0..2).collect());
+    let q0 = vass.add_node(());
+    let q1 = vass.add_node(());
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![1].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![-2].into()));
+    let instance = vass.init(vec![0].into(), vec![0].into(), q0, q1);
+
+    let result = VASSReachSolver::new_with_stop_signal(
+        &instance,
+        VASSReachConfig::default(),
+        Arc::new(AtomicBool::new(true)),
+    )
+    .solve();
+
+    assert_eq!(
+        result.status,
+        SolverStatus::Unknown(VASSReachSolverError::Cancelled)
+    );
+}
+
 #[test]
 fn short_witness_precheck_can_be_disabled() {
     let mut vass = VASS::new(1, (0..2).collect());
@@ -52,6 +87,86 @@ fn short_witness_precheck_can_be_disabled() {
     assert!(matches!(result.status, SolverStatus::Unknown(_)));
 }
 
+fn multiple_final_states_instance(exit_valuation: i64) -> InitializedVASS<(), usize> {
+    let mut vass = VASS::new(1, (0..3).collect());
+    let q0 = vass.add_node(());
+    let q1 = vass.add_node(());
+    let exit = vass.add_node(());
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![2].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![0].into()));
+    vass.add_edge(&q0, &exit, VASSEdge::new(2, vec![-1].into()));
+
+    // q1 needs exactly 1, which is odd and never reached
+    let mut instance = vass.init(vec![0].into(), vec![1].into(), q0, q1);
+    instance.add_final_state(exit, vec![exit_valuation].into());
+    instance
+}
+
+#[test]
+fn solver_accepts_any_of_several_final_states() {
+    for short_witness in [true, false] {
+        let config = VASSReachConfig::default()
+            .with_timeout(Some(Duration::from_secs(5)))
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(short_witness));
+
+        let result =
+            VASSReachSolver::new(&multiple_final_states_instance(3), config.clone()).solve();
+        assert!(result.is_success(), "{:?}", result.status);
+
+        let result = VASSReachSolver::new(&multiple_final_states_instance(2), config).solve();
+        assert!(result.is_failure(), "{:?}", result.status);
+    }
+}
+
+fn large_weights_instance(final_valuation: i64) -> InitializedVASS<(), usize> {
+    let mut vass = VASS::new(1, (0..3).collect());
+    let q0 = vass.add_node(());
+    let q1 = vass.add_node(());
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![10].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![-30].into()));
+    vass.add_edge(&q1, &q1, VASSEdge::new(2, vec![-4].into()));
+    vass.init(vec![0].into(), vec![final_valuation].into(), q0, q1)
+}
+
+#[test]
+fn binary_update_encoding_agrees_with_unary() {
+    for encoding in [UpdateEncoding::Unary, UpdateEncoding::Binary] {
+        let config = VASSReachConfig::default()
+            .with_timeout(Some(Duration::from_secs(10)))
+            .with_update_encoding(encoding)
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(false));
+
+        // 4 * 10 - 30 - 4 = 6
+        let result = VASSReachSolver::new(&large_weights_instance(6), config.clone()).solve();
+        assert!(result.is_success(), "{encoding:?}: {:?}", result.status);
+
+        // the counter is always even
+        let result = VASSReachSolver::new(&large_weights_instance(5), config).solve();
+        assert!(result.is_failure(), "{encoding:?}: {:?}", result.status);
+    }
+}
+
+#[test]
+fn multiple_final_states_survive_json_round_trip() {
+    let mut vass = VASS::new(1, vec![0]);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q1, VASSEdge::new(0, vec![1].into()));
+    let mut instance = vass.init(vec![0].into(), vec![1].into(), q0, q1);
+    instance.add_initial_state(q1, vec![0].into());
+    instance.add_final_state(q0, vec![0].into());
+
+    let parsed = InitializedVASS::from_json(&instance.to_json().unwrap()).unwrap();
+    assert_eq!(
+        parsed.additional_initial_states,
+        instance.additional_initial_states
+    );
+    assert_eq!(
+        parsed.additional_final_states,
+        instance.additional_final_states
+    );
+}
+
 #[test]
 fn main_solver_reuses_concrete_linear_graph_run() {
     let mut vass = VASS::new(3, (0..6).collect());
@@ -382,3 +497,491 @@ fn test_vass_n_reach_can_disable_z_reach_precheck() {
     assert!(res.is_failure());
     assert!(res.statistics.step_count > 0);
 }
+
+#[test]
+fn every_refinement_policy_decides_small_instances() {
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q0, VASSEdge::new('a', vec![2].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new('b', vec![-1].into()));
+
+    // The counter is odd whenever q1 is reached.
+    let odd_target = vass.clone().init(vec![0].into(), vec![1].into(), q0, q1);
+    let even_target = vass.init(vec![0].into(), vec![2].into(), q0, q1);
+
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q1, VASSEdge::new('a', vec![-1].into()));
+    vass.add_edge(&q1, &q1, VASSEdge::new('b', vec![1].into()));
+    let negative_start = vass.init(vec![0].into(), vec![0].into(), q0, q1);
+
+    for policy in [
+        RefinementPolicyKind::Default,
+        RefinementPolicyKind::LinearGraphFirst,
+        RefinementPolicyKind::BoundsFirst,
+        RefinementPolicyKind::CostModel,
+    ] {
+        let config = VASSReachConfig::default()
+            .with_timeout(Some(Duration::from_secs(5)))
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+            .with_refinement(RefinementConfig::default().with_policy(policy));
+
+        let reachable = VASSReachSolver::new(&odd_target, config.clone()).solve();
+        assert!(reachable.is_success(), "{policy:?} must find the run");
+
+        for instance in [&even_target, &negative_start] {
+            let unreachable = VASSReachSolver::new(instance, config.clone()).solve();
+            assert!(
+                unreachable.is_failure(),
+                "{policy:?} must prove the instance unreachable"
+            );
+        }
+    }
+}
+
+/// The instance of test 5. Without bounded counting the solver has to cut
+/// spurious paths with separators.
+fn separator_instance() -> InitializedVASS<usize, usize> {
+    let mut petri_net = PetriNet::new(5);
+
+    petri_net.add_transition(vec![(1, 1), (1, 5)], vec![(1, 2)]);
+    petri_net.add_transition(vec![(1, 3), (1, 5)], vec![(1, 4)]);
+    petri_net.add_transition(vec![(1, 2)], vec![(1, 1), (1, 5)]);
+    petri_net.add_transition(vec![(1, 4)], vec![(1, 3), (1, 5)]);
+
+    petri_net
+        .init(vec![1, 0, 1, 0, 0].into(), vec![0, 1, 0, 1, 0].into())
+        .to_vass()
+}
+
+fn separator_config(engine: RefinementAutomatonEngine) -> VASSReachConfig {
+    VASSReachConfig::default()
+        .with_timeout(Some(Duration::from_secs(5)))
+        .with_bounded_counting_enabled(false)
+        .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+        .with_refinement(
+            RefinementConfig::default()
+                .with_policy(RefinementPolicyKind::LinearGraphFirst)
+                .with_automaton_engine(engine),
+        )
+}
+
+#[test]
+fn every_automaton_engine_decides_and_reports_its_separators() {
+    let instance = separator_instance();
+
+    for engine in [
+        RefinementAutomatonEngine::LinearGraph,
+        RefinementAutomatonEngine::LTC,
+        RefinementAutomatonEngine::LTCThenLinearGraph,
+    ] {
+        let result = VASSReachSolver::new(&instance, separator_config(engine)).solve();
+        assert!(
+            result.is_failure(),
+            "{engine:?} must prove the instance unreachable"
+        );
+
+        let separators = &result.statistics.separators;
+        match engine {
+            RefinementAutomatonEngine::LinearGraph => {
+                assert!(separators.linear_graph > 0);
+                assert_eq!(separators.ltc, 0);
+            }
+            RefinementAutomatonEngine::LTC => {
+                assert!(separators.ltc > 0);
+                assert_eq!(separators.linear_graph, 0);
+            }
+            RefinementAutomatonEngine::LTCThenLinearGraph => {
+                assert!(separators.ltc + separators.linear_graph > 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn ltc_then_linear_graph_without_relaxed_checks_skips_ltc() {
+    let config = separator_config(RefinementAutomatonEngine::LTCThenLinearGraph)
+        .with_lts(LTSConfig::default().with_relaxed_enabled(false));
+
+    let result = VASSReachSolver::new(&separator_instance(), config).solve();
+    assert!(result.is_failure());
+    assert_eq!(result.statistics.separators.ltc, 0);
+    assert_eq!(result.statistics.separators.ltc_time, Duration::ZERO);
+    assert!(result.statistics.separators.linear_graph > 0);
+}
+
+#[test]
+fn template_store_reports_learned_templates() {
+    let mut vass = VASS::new(2, (0..3).collect());
+    let q = vass.add_node(());
+
+    vass.add_edge(&q, &q, VASSEdge::new(0, vec![-1, 1].into()));
+    vass.add_edge(&q, &q, VASSEdge::new(1, vec![0, 1].into()));
+    vass.add_edge(&q, &q, VASSEdge::new(2, vec![1, -2].into()));
+
+    let initialized = vass.init(vec![0, 1].into(), vec![0, 0].into(), q, q);
+    let with_store = VASSReachSolver::new(&initialized, difficult_instance_config()).solve();
+    let without_store = VASSReachSolver::new(
+        &initialized,
+        difficult_instance_config()
+            .with_linear_graph(LinearGraphConfig::default().with_template_store_enabled(false)),
+    )
+    .solve();
+
+    assert!(with_store.is_failure(), "{:?}", with_store.status);
+    assert!(without_store.is_failure(), "{:?}", without_store.status);
+    let statistics = &with_store.statistics.template_store;
+    assert!(statistics.learned_templates > 0);
+    // Every learned template took one synthesis call.
+    assert!(statistics.misses >= statistics.learned_templates);
+    assert_eq!(
+        without_store.statistics.template_store,
+        LinearGraphTemplateStoreStatistics::default()
+    );
+}
+
+/// Two routes from `q0` to `qf`: one through a loop adding 2, one that starts
+/// with a decrement and can never be taken from zero.
+fn two_route_instance(final_value: i64) -> InitializedVASS<(), usize> {
+    let mut vass = VASS::new(1, (0..6).collect());
+    let q0 = vass.add_node(());
+    let even = vass.add_node(());
+    let negative = vass.add_node(());
+    let qf = vass.add_node(());
+
+    vass.add_edge(&q0, &even, VASSEdge::new(0, vec![2].into()));
+    vass.add_edge(&even, &even, VASSEdge::new(1, vec![2].into()));
+    vass.add_edge(&even, &qf, VASSEdge::new(2, vec![0].into()));
+    vass.add_edge(&q0, &negative, VASSEdge::new(3, vec![-1].into()));
+    vass.add_edge(&negative, &negative, VASSEdge::new(4, vec![1].into()));
+    vass.add_edge(&negative, &qf, VASSEdge::new(5, vec![0].into()));
+
+    vass.init(vec![0].into(), vec![final_value].into(), q0, qf)
+}
+
+fn divide_and_conquer_config() -> VASSReachConfig {
+    VASSReachConfig::default()
+        .with_timeout(Some(Duration::from_secs(10)))
+        .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+        .with_divide_and_conquer(
+            DivideAndConquerConfig::default()
+                .with_enabled(true)
+                .with_threads(Some(2)),
+        )
+}
+
+#[test]
+fn divide_and_conquer_finds_the_reachable_route() {
+    let result = VASSReachSolver::new(&two_route_instance(4), divide_and_conquer_config()).solve();
+
+    assert!(result.is_success(), "{:?}", result.status);
+    assert_eq!(result.statistics.routes.routes, 2);
+    assert_eq!(result.statistics.routes.reachable, 1);
+}
+
+#[test]
+fn divide_and_conquer_refutes_every_route() {
+    let result = VASSReachSolver::new(&two_route_instance(3), divide_and_conquer_config()).solve();
+
+    assert!(result.is_failure(), "{:?}", result.status);
+    assert_eq!(
+        result.statistics.routes,
+        VASSReachRouteStatistics {
+            routes: 2,
+            reachable: 0,
+            unreachable: 2,
+            unknown: 0,
+        }
+    );
+}
+
+#[test]
+fn divide_and_conquer_falls_back_when_there_are_too_many_routes() {
+    let config = divide_and_conquer_config().with_divide_and_conquer(
+        DivideAndConquerConfig::default()
+            .with_enabled(true)
+            .with_max_routes(1),
+    );
+    let result = VASSReachSolver::new(&two_route_instance(3), config).solve();
+
+    assert!(result.is_failure(), "{:?}", result.status);
+    assert_eq!(
+        result.statistics.routes,
+        VASSReachRouteStatistics::default()
+    );
+}
+
+#[test]
+fn divide_and_conquer_skips_routes_that_cannot_accept() {
+    // Two accepting routes, next to a chain of diamonds behind `q0` that never
+    // reaches `qf` and adds no route.
+    let layers = 40;
+    let mut vass = VASS::new(1, (0..4 * layers + 3).collect());
+    let q0 = vass.add_node(());
+    let middle = vass.add_node(());
+    let qf = vass.add_node(());
+    vass.add_edge(&q0, &qf, VASSEdge::new(0, vec![1].into()));
+    vass.add_edge(&q0, &middle, VASSEdge::new(4 * layers + 1, vec![0].into()));
+    vass.add_edge(&middle, &qf, VASSEdge::new(4 * layers + 2, vec![0].into()));
+
+    let mut previous = vec![q0];
+    for layer in 0..layers {
+        let current = [vass.add_node(()), vass.add_node(())];
+        for (from, source) in previous.iter().enumerate() {
+            for (to, target) in current.iter().enumerate() {
+                let label = 1 + 4 * layer + 2 * from.min(1) + to;
+                vass.add_edge(source, target, VASSEdge::new(label, vec![1].into()));
+            }
+        }
+        previous = current.to_vec();
+    }
+
+    let result = VASSReachSolver::new(
+        &vass.init(vec![0].into(), vec![1].into(), q0, qf),
+        divide_and_conquer_config(),
+    )
+    .solve();
+
+    assert!(result.is_success(), "{:?}", result.status);
+    assert_eq!(result.statistics.routes.routes, 2);
+}
+
+#[test]
+fn session_answers_queries_like_the_solver() {
+    let instance = two_route_instance(0);
+    let config = VASSReachConfig::default()
+        .with_timeout(Some(Duration::from_secs(10)))
+        .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+        .with_preprocessing(PreprocessingConfig::default().with_enabled(true));
+    let queries = [(0, 4), (0, 3), (1, 0), (1, 3), (0, 4)];
+
+    let mut session = VASSReachSession::new(&instance, config.clone());
+    let results = session
+        .solve_all(
+            queries
+                .iter()
+                .map(|(initial, target)| (vec![*initial].into(), vec![*target].into())),
+        )
+        .collect::<Vec<_>>();
+
+    assert_eq!(results.len(), queries.len());
+    for ((initial, target), result) in queries.iter().zip(&results) {
+        let mut query = instance.clone();
+        query.initial_valuation = vec![*initial].into();
+        query.final_valuation = vec![*target].into();
+        let expected = VASSReachSolver::new(&query, config.clone()).solve();
+
+        assert!(!result.is_unknown(), "{:?}", result.status);
+        assert_eq!(
+            result.is_success(),
+            expected.is_success(),
+            "{initial} -> {target}"
+        );
+    }
+}
+
+#[test]
+fn session_karp_miller_precheck_refutes_uncoverable_targets() {
+    let mut vass = VASS::new(1, (0..2).collect());
+    let q0 = vass.add_node(());
+    let q1 = vass.add_node(());
+    vass.add_edge(&q0, &q1, VASSEdge::new(0, vec![1].into()));
+    vass.add_edge(&q1, &q1, VASSEdge::new(1, vec![-1].into()));
+    let instance = vass.init(vec![0].into(), vec![0].into(), q0, q1);
+
+    let mut session = VASSReachSession::new(
+        &instance,
+        VASSReachConfig::default()
+            .with_max_iterations(Some(0))
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+            .with_preprocessing(
+                PreprocessingConfig::default()
+                    .with_enabled(true)
+                    .with_karp_miller_precheck_enabled(true),
+            ),
+    );
+
+    for target in [2, 3] {
+        let result = session.solve(vec![0].into(), vec![target].into());
+        assert!(result.is_failure(), "{:?}", result.status);
+        assert_eq!(result.statistics.step_count, 0);
+    }
+
+    let result = session.solve(vec![2].into(), vec![4].into());
+    assert!(result.is_failure(), "{:?}", result.status);
+}
+
+#[test]
+fn config_overrides_only_replace_the_given_fields() {
+    let base = VASSReachConfig::default()
+        .with_max_iterations(Some(7))
+        .with_short_witness(ShortWitnessConfig::default().with_enabled(false));
+
+    let config = base
+        .with_overrides(&serde_json::json!({
+            "timeout": { "secs": 2, "nanos": 0 },
+            "preprocessing": { "karp_miller_precheck_enabled": true },
+        }))
+        .unwrap();
+    assert_eq!(*config.get_timeout(), Some(Duration::from_secs(2)));
+    assert_eq!(*config.get_max_iterations(), Some(7));
+    assert!(!config.get_short_witness().get_enabled());
+    assert!(
+        config
+            .get_preprocessing()
+            .get_karp_miller_precheck_enabled()
+    );
+    assert_eq!(
+        config.get_preprocessing().get_enabled(),
+        base.get_preprocessing().get_enabled()
+    );
+
+    assert!(
+        base.with_overrides(&serde_json::json!({ "bogus": 1 }))
+            .is_err()
+    );
+}
+
+#[test]
+fn raised_stop_signal_cancels_the_solver() {
+    let mut vass = VASS::new(1, (0..2).collect());
+    let q0 = vass.add_node(());
+    let q1 = vass.add_node(());
+    vass.add_edge(&q0, &q0, VASSEdge::new(0, vec![1].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new(1, vec![-2].into()));
+    let instance = vass.init(vec![0].into(), vec![0].into(), q0, q1);
+
+    let result = VASSReachSolver::new(
+        &instance,
+        VASSReachConfig::default()
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+            .with_preprocessing(PreprocessingConfig::default().with_enabled(false)),
+    )
+    .with_stop_signal(Arc::new(AtomicBool::new(true)))
+    .solve();
+    assert!(
+        matches!(
+            result.status,
+            SolverStatus::Unknown(VASSReachSolverError::Cancelled)
+        ),
+        "{:?}",
+        result.status
+    );
+}
+
+#[test]
+fn config_assignments_are_type_checked() {
+    assert!(
+        VASSReachConfig::config_keys()
+            .contains(&"linear_graph.template_synthesis_max_coefficient".to_string())
+    );
+
+    let config = VASSReachConfig::default()
+        .with_assignments(&[
+            "linear_graph.template_synthesis_max_coefficient=4",
+            "refinement.automaton_engine=LTC",
+            "max_iterations = 9",
+            "timeout={ secs = 2, nanos = 0 }",
+        ])
+        .unwrap();
+    assert_eq!(
+        *config
+            .get_linear_graph()
+            .get_template_synthesis_max_coefficient(),
+        4
+    );
+    assert_eq!(
+        *config.get_refinement().get_automaton_engine(),
+        RefinementAutomatonEngine::LTC
+    );
+    assert_eq!(*config.get_max_iterations(), Some(9));
+    assert_eq!(*config.get_timeout(), Some(Duration::from_secs(2)));
+
+    let err = VASSReachConfig::default()
+        .with_assignments(&["linear_graph.template_synthesis_max_coeficient=4"])
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("did you mean linear_graph.template_synthesis_max_coefficient?"),
+        "{err}"
+    );
+    let err = VASSReachConfig::default()
+        .with_assignments(&["max_routes=4"])
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("did you mean divide_and_conquer.max_routes?"),
+        "{err}"
+    );
+    assert!(
+        VASSReachConfig::default()
+            .with_assignments(&["max_iterations=many"])
+            .is_err()
+    );
+    assert!(
+        VASSReachConfig::default()
+            .with_assignments(&["max_iterations"])
+            .is_err()
+    );
+}
+
+#[test]
+fn modulo_value_exponent_decides_when_the_default_policy_raises_mu() {
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    vass.add_edge(&q0, &q0, VASSEdge::new('a', vec![3].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new('b', vec![0].into()));
+    // The counter is a multiple of three in q1.
+    let instance = vass.init(vec![0].into(), vec![7].into(), q0, q1);
+
+    let solve = |exponent| {
+        let config = VASSReachConfig::default()
+            .with_timeout(Some(Duration::from_secs(5)))
+            .with_short_witness(ShortWitnessConfig::default().with_enabled(false))
+            .with_preprocessing(PreprocessingConfig::default().with_enabled(false))
+            .with_bounded_counting_enabled(false)
+            .with_refinement(RefinementConfig::default().with_modulo_value_exponent(exponent));
+        VASSReachSolver::new(&instance, config).solve()
+    };
+
+    // The spurious paths take the counter above mu but not above mu².
+    let modulo = solve(1);
+    assert!(modulo.is_failure());
+    assert!(modulo.statistics.mu[0] > 2);
+    assert_eq!(modulo.statistics.separators.linear_graph, 0);
+
+    let separator = solve(2);
+    assert!(separator.is_failure());
+    assert_eq!(separator.statistics.mu[0], 2);
+    assert!(separator.statistics.separators.linear_graph > 0);
+}
+
+#[test]
+fn farkas_synthesis_decides_instances_beyond_the_enumeration_bound() {
+    // Separating the spurious models needs coefficients above the default
+    // enumeration bound of 2.
+    let mut vass = VASS::new(2, (0..3).collect());
+    let q = vass.add_node(());
+
+    vass.add_edge(&q, &q, VASSEdge::new(0, vec![1, -3].into()));
+    vass.add_edge(&q, &q, VASSEdge::new(1, vec![1, 0].into()));
+    vass.add_edge(&q, &q, VASSEdge::new(2, vec![-1, 1].into()));
+
+    let initialized = vass.init(vec![1, 0].into(), vec![0, 0].into(), q, q);
+    let solve = |mode| {
+        let config = difficult_instance_config()
+            .with_linear_graph(LinearGraphConfig::default().with_template_synthesis_mode(mode));
+        VASSReachSolver::new(&initialized, config).solve()
+    };
+
+    let enumeration = solve(LinearGraphTemplateSynthesisMode::Enumeration);
+    assert!(enumeration.is_unknown(), "{:?}", enumeration.status);
+    assert_eq!(enumeration.statistics.template_store.learned_templates, 0);
+
+    let farkas = solve(LinearGraphTemplateSynthesisMode::Farkas);
+    assert!(farkas.is_failure(), "{:?}", farkas.status);
+    assert_eq!(farkas.statistics.template_store.learned_templates, 1);
+}
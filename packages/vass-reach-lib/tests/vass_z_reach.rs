@@ -7,7 +7,7 @@ use vass_reach_lib::{
         petri_net::{PetriNet, initialized::InitializedPetriNet},
         vass::{VASS, VASSEdge},
     },
-    config::VASSZReachConfig,
+    config::{VASSZReachConfig, ZReachConnectivity},
     solver::vass_z_reach::VASSZReachSolver,
     validation::test_parikh_image,
 };
@@ -53,6 +53,41 @@ fn test_vass_z_reach_1() {
     );
 }
 
+#[test]
+fn test_vass_z_reach_binary_cfg() {
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+
+    vass.add_edge(&q0, &q0, VASSEdge::new('a', vec![1000].into()));
+    vass.add_edge(&q0, &q1, VASSEdge::new('b', vec![-3000].into()));
+
+    let reachable = vass.clone().init(vec![0].into(), vec![0].into(), q0, q1);
+    let unreachable = vass.init(vec![0].into(), vec![500].into(), q0, q1);
+
+    for (initialized_vass, expected) in [(reachable, true), (unreachable, false)] {
+        let cfg = initialized_vass.to_binary_cfg();
+
+        let res = VASSZReachSolver::new(
+            &cfg,
+            initialized_vass.initial_valuation.clone(),
+            initialized_vass.final_valuation.clone(),
+            VASSZReachConfig::default().with_timeout(Some(Duration::from_secs(5))),
+        )
+        .solve();
+
+        assert_eq!(res.is_success(), expected);
+        if expected {
+            test_parikh_image(
+                res.get_parikh_image().unwrap(),
+                &cfg,
+                &initialized_vass.initial_valuation,
+                &initialized_vass.final_valuation,
+            );
+        }
+    }
+}
+
 #[test]
 fn test_vass_z_reach_2() {
     let mut vass = VASS::<u32, char>::new(2, vec!['a', 'b']);
@@ -177,3 +212,38 @@ fn test_vass_z_reach_5() {
 
     assert!(res.is_failure());
 }
+
+#[test]
+fn test_vass_z_reach_encoded_connectivity_needs_one_query() {
+    let mut vass = VASS::<u32, char>::new(1, vec!['a', 'b']);
+    let q0 = vass.add_node(0);
+    let q1 = vass.add_node(1);
+    let q2 = vass.add_node(2);
+
+    // The loop on q2 would balance the counter, but q2 is never entered.
+    vass.add_edge(&q0, &q1, VASSEdge::new('a', vec![0].into()));
+    vass.add_edge(&q2, &q2, VASSEdge::new('b', vec![1].into()));
+
+    let initialized_vass = vass.init(vec![0].into(), vec![1].into(), q0, q1);
+    let cfg = initialized_vass.to_cfg();
+
+    let solve = |connectivity| {
+        VASSZReachSolver::new(
+            &cfg,
+            initialized_vass.initial_valuation.clone(),
+            initialized_vass.final_valuation.clone(),
+            VASSZReachConfig::default()
+                .with_timeout(Some(Duration::from_secs(5)))
+                .with_connectivity(connectivity),
+        )
+        .solve()
+    };
+
+    let encoded = solve(ZReachConnectivity::Encoded);
+    let refinement = solve(ZReachConnectivity::Refinement);
+
+    assert!(encoded.is_failure());
+    assert!(refinement.is_failure());
+    assert_eq!(encoded.statistics.step_count, 1);
+    assert!(refinement.statistics.step_count > 1);
+}
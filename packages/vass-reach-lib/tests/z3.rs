@@ -1,3 +1,4 @@
+#![cfg(feature = "z3")]
 #![allow(clippy::just_underscores_and_digits)]
 
 use z3::{SatResult, Solver, ast::Int};
@@ -1,7 +1,8 @@
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    Expr, GenericArgument, Ident, PathArguments, Token, Type, Visibility,
+    Attribute, Expr, ExprLit, GenericArgument, Ident, Lit, Meta, PathArguments, Token, Type,
+    Visibility,
     parse::{Parse, ParseStream},
     parse_macro_input,
     punctuated::Punctuated,
@@ -9,6 +10,7 @@ use syn::{
 };
 
 struct ConfigField {
+    attrs: Vec<Attribute>,
     name: Ident,
     ty: Type,
     default_value: Expr,
@@ -17,6 +19,7 @@ struct ConfigField {
 
 impl Parse for ConfigField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
         let name: Ident = input.parse()?;
         input.parse::<Token![:]>()?;
         let ty: Type = input.parse()?;
@@ -31,6 +34,7 @@ impl Parse for ConfigField {
             content.parse::<Token![=]>()?;
             let default_value: Expr = content.parse()?;
             Ok(ConfigField {
+                attrs,
                 name,
                 ty,
                 default_value,
@@ -40,6 +44,7 @@ impl Parse for ConfigField {
             input.parse::<Token![=]>()?;
             let default_value: Expr = input.parse()?;
             Ok(ConfigField {
+                attrs,
                 name,
                 ty,
                 default_value,
@@ -50,6 +55,7 @@ impl Parse for ConfigField {
 }
 
 struct ConfigInput {
+    attrs: Vec<Attribute>,
     vis: Visibility,
     name: Ident,
     fields: Punctuated<ConfigField, Token![,]>,
@@ -57,13 +63,19 @@ struct ConfigInput {
 
 impl Parse for ConfigInput {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
         let vis: Visibility = input.parse()?;
         input.parse::<Token![struct]>()?;
         let name: Ident = input.parse()?;
         let content;
         syn::braced!(content in input);
         let fields = content.parse_terminated(ConfigField::parse, Token![,])?;
-        Ok(ConfigInput { vis, name, fields })
+        Ok(ConfigInput {
+            attrs,
+            vis,
+            name,
+            fields,
+        })
     }
 }
 
@@ -79,17 +91,50 @@ fn is_option(ty: &Type) -> bool {
     false
 }
 
+/// Joins the lines of the doc comments in `attrs` into a single paragraph.
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+fn quote_option(doc: Option<String>) -> proc_macro2::TokenStream {
+    match doc {
+        Some(doc) => quote! { Some(#doc) },
+        None => quote! { None },
+    }
+}
+
 #[proc_macro]
 pub fn config(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ConfigInput);
     let vis = &input.vis;
     let struct_name = &input.name;
     let partial_struct_name = format_ident!("Partial{}", struct_name);
+    let struct_attrs = &input.attrs;
+    let struct_doc = quote_option(doc_string(&input.attrs));
 
     let fields = input.fields.iter().map(|f| {
+        let attrs = &f.attrs;
         let name = &f.name;
         let ty = &f.ty;
-        quote! { #name: #ty }
+        quote! { #( #attrs )* #name: #ty }
     });
 
     let partial_fields = input.fields.iter().map(|f| {
@@ -131,6 +176,51 @@ pub fn config(input: TokenStream) -> TokenStream {
         }
     });
 
+    // sections with a partial type are nested configs, their keys are
+    // prefixed with the field name
+    let key_pushes = input.fields.iter().map(|f| {
+        let name = &f.name;
+        let ty = &f.ty;
+        if f.partial_ty.is_some() {
+            quote! {
+                keys.extend(
+                    <#ty>::config_keys()
+                        .into_iter()
+                        .map(|key| format!("{}.{}", stringify!(#name), key)),
+                );
+            }
+        } else {
+            quote! { keys.push(stringify!(#name).to_string()); }
+        }
+    });
+
+    let schema_properties = input.fields.iter().map(|f| {
+        let name = &f.name;
+        let ty = &f.ty;
+        let doc = quote_option(doc_string(&f.attrs));
+        let default = if f.partial_ty.is_some() {
+            quote! { None }
+        } else {
+            let default_value = &f.default_value;
+            quote! {
+                Some(serde_json::to_value({
+                    let default: #ty = #default_value;
+                    default
+                }).expect("config defaults serialize"))
+            }
+        };
+        quote! {
+            properties.insert(
+                stringify!(#name).to_string(),
+                crate::config::field_schema(
+                    <#ty as crate::config::ConfigSchema>::config_schema(),
+                    #doc,
+                    #default,
+                ),
+            );
+        }
+    });
+
     let default_assignments = input.fields.iter().map(|f| {
         let name = &f.name;
         let default_value = &f.default_value;
@@ -138,6 +228,7 @@ pub fn config(input: TokenStream) -> TokenStream {
     });
 
     let expanded = quote! {
+        #( #struct_attrs )*
         #[derive(Debug, Clone, serde::Serialize)]
         #vis struct #struct_name {
             #( #fields, )*
@@ -159,6 +250,14 @@ pub fn config(input: TokenStream) -> TokenStream {
             pub fn from_file<P: AsRef<std::path::Path>>(file_path: P) -> anyhow::Result<Self> {
                 let canonic_path = std::fs::canonicalize(file_path)?;
                 let content = std::fs::read_to_string(&canonic_path)?;
+                if let Ok(table) = toml::from_str::<toml::Table>(&content) {
+                    crate::config::validate(
+                        &serde_json::to_value(table)?,
+                        &Self::json_schema(),
+                        &Self::config_keys(),
+                    )
+                    .map_err(|err| anyhow::anyhow!("Invalid config file {}: {}", canonic_path.display(), err))?;
+                }
                 let partial: #partial_struct_name = toml::from_str(&content).map_err(|err| {
                     let file_path = canonic_path.display();
                     let err_msg = err.to_string();
@@ -176,9 +275,58 @@ pub fn config(input: TokenStream) -> TokenStream {
                     None => Ok(Self::default())
                 }
             }
+            /// Replaces the fields set in `overrides`, which has the shape of a
+            /// config file. Nested sections only replace the fields they set.
+            pub fn with_overrides(&self, overrides: &serde_json::Value) -> anyhow::Result<Self> {
+                crate::config::validate(overrides, &Self::json_schema(), &Self::config_keys())
+                    .map_err(|err| anyhow::anyhow!("Invalid config override: {}", err))?;
+                let mut merged = serde_json::to_value(self)?;
+                crate::config::merge_overrides(&mut merged, overrides);
+                let partial: #partial_struct_name = serde_json::from_value(merged)
+                    .map_err(|err| anyhow::anyhow!("Invalid config override: {}", err))?;
+                Ok(Self::from_partial(partial))
+            }
+            /// The JSON Schema of config files, with the types, defaults and
+            /// doc comments of all fields.
+            pub fn json_schema() -> serde_json::Value {
+                crate::config::root_schema(
+                    stringify!(#struct_name),
+                    <Self as crate::config::ConfigSchema>::config_schema(),
+                )
+            }
+            /// The dotted keys of all fields, including those of nested
+            /// sections, e.g. `linear_graph.template_synthesis_max_coefficient`.
+            pub fn config_keys() -> Vec<String> {
+                let mut keys = Vec::new();
+                #( #key_pushes )*
+                keys
+            }
+            /// Applies `key=value` assignments in order. Keys are those of
+            /// `config_keys`, values are TOML values or, like enum variants,
+            /// plain strings.
+            pub fn with_assignments<S: AsRef<str>>(&self, assignments: &[S]) -> anyhow::Result<Self> {
+                let keys = Self::config_keys();
+                let mut config = self.clone();
+                for assignment in assignments {
+                    let assignment = assignment.as_ref();
+                    let overrides = crate::config::parse_assignment(assignment, &keys)?;
+                    config = config.with_overrides(&overrides).map_err(|err| {
+                        anyhow::anyhow!("Invalid assignment {}: {}", assignment, err)
+                    })?;
+                }
+                Ok(config)
+            }
             #( #methods )*
         }
 
+        impl crate::config::ConfigSchema for #struct_name {
+            fn config_schema() -> serde_json::Value {
+                let mut properties = serde_json::Map::new();
+                #( #schema_properties )*
+                crate::config::object_schema(#struct_doc, properties)
+            }
+        }
+
         impl Default for #struct_name {
             fn default() -> Self {
                 #struct_name {
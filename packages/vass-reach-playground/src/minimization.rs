@@ -17,18 +17,18 @@ pub enum Reduction {
     ReduceUpdate {
         transition: usize,
         counter: usize,
-        from: i32,
-        to: i32,
+        from: i64,
+        to: i64,
     },
     ReduceInitialValuation {
         counter: usize,
-        from: i32,
-        to: i32,
+        from: i64,
+        to: i64,
     },
     ReduceFinalValuation {
         counter: usize,
-        from: i32,
-        to: i32,
+        from: i64,
+        to: i64,
     },
 }
 
@@ -222,7 +222,7 @@ fn remove_counter(instance: &PlaygroundVass, removed: usize) -> PlaygroundVass {
     )
 }
 
-fn remove_coordinate<'a>(values: impl Iterator<Item = &'a i32>, removed: usize) -> Vec<i32> {
+fn remove_coordinate<'a>(values: impl Iterator<Item = &'a i64>, removed: usize) -> Vec<i64> {
     values
         .enumerate()
         .filter_map(|(counter, value)| (counter != removed).then_some(*value))
@@ -235,9 +235,9 @@ struct CanonicalForm {
     state_count: usize,
     initial_state: usize,
     final_state: usize,
-    initial_valuation: Vec<i32>,
-    final_valuation: Vec<i32>,
-    transitions: Vec<(usize, usize, Vec<i32>)>,
+    initial_valuation: Vec<i64>,
+    final_valuation: Vec<i64>,
+    transitions: Vec<(usize, usize, Vec<i64>)>,
 }
 
 fn canonical_form(instance: &PlaygroundVass) -> CanonicalForm {
@@ -180,6 +180,10 @@ pub struct TestRunConfig {
     pub tool: String,
     pub config: String,
     pub max_parallel: u64,
+    /// `key=value` overrides of fields of `config`, passed to vass-reach as
+    /// `--set`.
+    #[serde(default)]
+    pub overrides: Vec<String>,
 }
 
 // TODO: migrate to std::range::RangeInclusive, once serde supports it
@@ -33,9 +33,9 @@ pub struct HardCandidateSummary {
     pub dimension: usize,
     pub state_count: usize,
     pub transition_count: usize,
-    pub initial_valuation: Vec<i32>,
-    pub final_valuation: Vec<i32>,
-    pub max_update_magnitude: i32,
+    pub initial_valuation: Vec<i64>,
+    pub final_valuation: Vec<i64>,
+    pub max_update_magnitude: i64,
     pub initial_graph_dot: String,
 }
 
@@ -148,7 +148,7 @@ fn observe_hardness(
             reason,
             statistics: result.statistics,
         }),
-        SolverStatus::True(()) | SolverStatus::False(()) => None,
+        SolverStatus::True(_) | SolverStatus::False(()) => None,
     }
 }
 
@@ -38,10 +38,10 @@ pub fn generate_random_petri_net(
             }
 
             let initial_m: VASSCounterValuation = (0..place_count)
-                .map(|_| r.random_range(0..max_tokens_per_transition) as i32)
+                .map(|_| r.random_range(0..max_tokens_per_transition) as i64)
                 .collect();
             let final_m: VASSCounterValuation = (0..place_count)
-                .map(|_| r.random_range(0..max_tokens_per_transition) as i32)
+                .map(|_| r.random_range(0..max_tokens_per_transition) as i64)
                 .collect();
 
             petri_net.init(initial_m, final_m)
@@ -14,7 +14,7 @@ pub fn generate_random_vass(
     state_count: usize,
     dimension: usize,
     transition_count: usize,
-    max_tokens_per_transition: i32,
+    max_tokens_per_transition: i64,
 ) -> Vec<InitializedVASS<(), usize>> {
     let mut r = StdRng::seed_from_u64(options.seed);
     let alphabet = (0..transition_count).collect::<Vec<_>>();
@@ -100,7 +100,7 @@ pub fn generate_random_vass_in_ranges(
         // A forward backbone guarantees an initial-to-final control path.
         for edge in 0..minimum_edges {
             let update = (0..dimension)
-                .map(|_| r.random_range(updates.min..=updates.max))
+                .map(|_| i64::from(r.random_range(updates.min..=updates.max)))
                 .collect::<Vec<_>>();
             vass.add_edge(
                 &nodes[edge],
@@ -112,7 +112,7 @@ pub fn generate_random_vass_in_ranges(
             let source = r.random_range(0..state_count);
             let target = r.random_range(0..state_count);
             let update = (0..dimension)
-                .map(|_| r.random_range(updates.min..=updates.max))
+                .map(|_| i64::from(r.random_range(updates.min..=updates.max)))
                 .collect::<Vec<_>>();
             vass.add_edge(
                 &nodes[source],
@@ -122,10 +122,10 @@ pub fn generate_random_vass_in_ranges(
         }
 
         let initial = (0..dimension)
-            .map(|_| r.random_range(valuations.min..=valuations.max))
+            .map(|_| i64::from(r.random_range(valuations.min..=valuations.max)))
             .collect::<Vec<_>>();
         let final_valuation = (0..dimension)
-            .map(|_| r.random_range(valuations.min..=valuations.max))
+            .map(|_| i64::from(r.random_range(valuations.min..=valuations.max)))
             .collect::<Vec<_>>();
         result.push(vass.init(
             initial.into(),
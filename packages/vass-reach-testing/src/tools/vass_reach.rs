@@ -6,7 +6,7 @@ use std::{
 };
 
 use anyhow::Context;
-use vass_reach_lib::solver::{SerializableSolverResult, vass_reach::VASSReachSolverStatistics};
+use vass_reach_lib::solver::{report::SolverReport, vass_reach::VASSReachSolverStatistics};
 
 use crate::{
     config::{TestConfig, TestRunConfig, ToolConfig},
@@ -72,6 +72,7 @@ impl<'a> Tool for VASSReachTool<'a> {
         config: &TestRunConfig,
     ) -> anyhow::Result<SolverRunResult> {
         let config_override_path = self.create_temp_config_with_trace(file_path, config)?;
+        let report_path = config_override_path.with_extension("report.json");
 
         // `systemd-run --user --scope --unit=kreach_run_{file_stub} -p MemoryMax=4G -p
         // RuntimeMaxSec={self.test_config.timeout} ./target/release/vass-reach
@@ -90,7 +91,12 @@ impl<'a> Tool for VASSReachTool<'a> {
             .arg(format!("-pRuntimeMaxSec={}", self.test_config.timeout))
             .arg(binary_path.as_os_str())
             .arg(file_path.as_os_str())
-            .arg(format!("-c={}", config_override_path.display()));
+            .arg(format!("-c={}", config_override_path.display()))
+            .arg("--output")
+            .arg(report_path.as_os_str());
+        for assignment in &config.overrides {
+            command.arg("--set").arg(assignment);
+        }
         command.current_dir(self.workspace_root()?);
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
@@ -112,17 +118,16 @@ impl<'a> Tool for VASSReachTool<'a> {
         // (self.test_config.timeout as f64 * 1.5) as u64; let output =
         // run_with_watcher(&mut command, command_timeout)?;
 
+        let report = fs::read_to_string(&report_path);
+        let _ = fs::remove_file(&report_path);
+
         if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let json_payload = extract_json_payload(&stdout).with_context(|| {
-                format!(
-                    "failed to extract solver JSON output from stdout:\n{}",
-                    stdout
-                )
-            })?;
-            let res: SerializableSolverResult<VASSReachSolverStatistics> =
-                serde_json::from_str(json_payload).context("failed to parse solver JSON output")?;
-            Ok(SolverRunResult::Success(res.to_empty_status()))
+            let report: SolverReport<serde_json::Value, VASSReachSolverStatistics> =
+                serde_json::from_str(&report.context("solver did not write a report")?)
+                    .context("failed to parse solver report")?;
+            Ok(SolverRunResult::Success(
+                report.into_serializable_result().to_empty_status(),
+            ))
         } else {
             println!("Process exited with status: {}", output.status);
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -146,64 +151,6 @@ impl<'a> Tool for VASSReachTool<'a> {
     }
 }
 
-fn extract_json_payload(output: &str) -> anyhow::Result<&str> {
-    for (start, _) in output.match_indices('{') {
-        let candidate = &output[start..];
-        let Some(end) = find_matching_json_object_end(candidate) else {
-            continue;
-        };
-
-        let payload = &candidate[..end];
-        if serde_json::from_str::<serde_json::Value>(payload).is_ok() {
-            return Ok(payload);
-        }
-    }
-
-    anyhow::bail!("no valid JSON object found in solver output")
-}
-
-fn find_matching_json_object_end(input: &str) -> Option<usize> {
-    let mut depth = 0_usize;
-    let mut in_string = false;
-    let mut escaped = false;
-
-    for (idx, ch) in input.char_indices() {
-        if in_string {
-            if escaped {
-                escaped = false;
-                continue;
-            }
-
-            if ch == '\\' {
-                escaped = true;
-                continue;
-            }
-
-            if ch == '"' {
-                in_string = false;
-            }
-            continue;
-        }
-
-        match ch {
-            '"' => in_string = true,
-            '{' => depth += 1,
-            '}' => {
-                if depth == 0 {
-                    return None;
-                }
-                depth -= 1;
-                if depth == 0 {
-                    return Some(idx + ch.len_utf8());
-                }
-            }
-            _ => {}
-        }
-    }
-
-    None
-}
-
 fn is_vass_json_file(file_path: &Path) -> bool {
     file_path
         .file_name()
@@ -0,0 +1,303 @@
+//! `vass-reach batch` solves every instance in a directory or matching a glob
+//! and streams one JSON record per instance.
+//!
+//! Each instance is solved by a child process of this binary, so a crash or
+//! an exhausted memory limit only loses that instance. A child that outlives
+//! its timeout by more than [`KILL_GRACE`] is killed.
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use vass_reach_lib::automaton::vass::control::ControlRegex;
+
+use crate::Mode;
+
+/// How long a child may run past its timeout before it is killed.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+pub struct BatchOptions {
+    pub input: String,
+    pub mode: Mode,
+    pub config: Option<String>,
+    pub controls: Vec<ControlRegex>,
+    pub assignments: Vec<String>,
+    pub timeout: Option<u64>,
+    pub reduce: bool,
+    pub jobs: usize,
+    /// In MiB.
+    pub memory_limit: Option<u64>,
+    pub output: Option<PathBuf>,
+    pub resume: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchOutcome {
+    /// The solver wrote a report, its status may still be unknown.
+    Solved,
+    /// The child was killed after running past its timeout.
+    Killed,
+    /// The child exited without a report, e.g. because it ran out of memory,
+    /// or it could not be started or its report could not be read.
+    Crashed,
+}
+
+/// One line of the batch output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub instance: String,
+    pub outcome: BatchOutcome,
+    pub wall_time_ms: u64,
+    /// The `SolverReport` of solved instances.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<serde_json::Value>,
+    /// The end of stderr of crashed instances, or why the batch runner
+    /// failed to solve them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn batch(options: BatchOptions) -> anyhow::Result<()> {
+    let mut instances = find_instances(&options.input)?;
+    let total = instances.len();
+    if options.resume
+        && let Some(output) = &options.output
+        && output.exists()
+    {
+        let done = finished_instances(output)?;
+        instances.retain(|instance| !done.contains(&instance.display().to_string()));
+    }
+    tracing::info!(
+        "Solving {} instances, skipping {} finished ones",
+        instances.len(),
+        total - instances.len()
+    );
+
+    let output: Box<dyn Write + Send> = match &options.output {
+        Some(path) => {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            // an interrupted run may have left a truncated line behind
+            let content = std::fs::read(path)?;
+            if content.last().is_some_and(|byte| *byte != b'\n') {
+                writeln!(file)?;
+            }
+            Box::new(file)
+        }
+        None => Box::new(std::io::stdout()),
+    };
+    let output = Mutex::new(output);
+    let queue = Mutex::new(instances.into_iter().enumerate().collect::<VecDeque<_>>());
+
+    std::thread::scope(|scope| {
+        let workers = (0..options.jobs.max(1))
+            .map(|_| {
+                scope.spawn(|| -> anyhow::Result<()> {
+                    loop {
+                        let Some((index, instance)) = queue.lock().unwrap().pop_front() else {
+                            return Ok(());
+                        };
+                        let record = run_instance(&options, index, &instance);
+                        tracing::info!("{}: {:?}", record.instance, record.outcome);
+
+                        let mut output = output.lock().unwrap();
+                        writeln!(output, "{}", serde_json::to_string(&record)?)?;
+                        output.flush()?;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("batch worker panicked"))
+    })
+}
+
+/// Solves one instance. Failures of the batch runner itself, e.g. a full
+/// temporary directory, only crash this instance.
+fn run_instance(options: &BatchOptions, index: usize, instance: &Path) -> BatchRecord {
+    let start = Instant::now();
+
+    solve_instance(options, index, instance).unwrap_or_else(|error| BatchRecord {
+        instance: instance.display().to_string(),
+        outcome: BatchOutcome::Crashed,
+        wall_time_ms: start.elapsed().as_millis() as u64,
+        report: None,
+        error: Some(format!("{error:#}")),
+    })
+}
+
+fn solve_instance(
+    options: &BatchOptions,
+    index: usize,
+    instance: &Path,
+) -> anyhow::Result<BatchRecord> {
+    let temp_stem =
+        std::env::temp_dir().join(format!("vass-reach-batch-{}-{index}", std::process::id()));
+    let report_path = temp_stem.with_extension("report.json");
+    let stderr_path = temp_stem.with_extension("stderr");
+
+    let mut command = match options.memory_limit {
+        // the limit has to be set in the child, a shell does that without
+        // further dependencies
+        Some(limit) => {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg("ulimit -v \"$0\" && exec \"$@\"")
+                .arg((limit * 1024).to_string())
+                .arg(std::env::current_exe()?);
+            command
+        }
+        None => Command::new(std::env::current_exe()?),
+    };
+    command
+        .arg(instance)
+        .arg("--mode")
+        .arg(options.mode.to_string())
+        .arg("--output")
+        .arg(&report_path);
+    if let Some(config) = &options.config {
+        command.arg("--config").arg(config);
+    }
+    for assignment in &options.assignments {
+        command.arg("--set").arg(assignment);
+    }
+    if let Some(timeout) = options.timeout {
+        command.arg("--timeout").arg(timeout.to_string());
+    }
+    for control in &options.controls {
+        command.arg("--control").arg(control.source());
+    }
+    if options.reduce {
+        command.arg("--reduce");
+    }
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(
+        File::create(&stderr_path)
+            .with_context(|| format!("failed to create {}", stderr_path.display()))?,
+    );
+
+    let start = Instant::now();
+    let deadline = options
+        .timeout
+        .map(|timeout| Duration::from_secs(timeout) + KILL_GRACE);
+    let mut child = command.spawn().context("failed to start the solver")?;
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) => {}
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(error).context("failed to wait for the solver");
+            }
+        }
+        if deadline.is_some_and(|deadline| start.elapsed() > deadline) {
+            child.kill()?;
+            child.wait()?;
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+
+    let report = std::fs::read_to_string(&report_path).ok();
+    let stderr = std::fs::read_to_string(&stderr_path).unwrap_or_default();
+    let _ = std::fs::remove_file(&report_path);
+    let _ = std::fs::remove_file(&stderr_path);
+
+    let (outcome, report, error) = match (status, report) {
+        (None, _) => (BatchOutcome::Killed, None, None),
+        (Some(status), Some(report)) if status.success() => (
+            BatchOutcome::Solved,
+            Some(serde_json::from_str(&report).context("failed to parse solver report")?),
+            None,
+        ),
+        (Some(status), _) => {
+            let tail = stderr.lines().rev().take(20).collect::<Vec<_>>();
+            let tail = tail.into_iter().rev().collect::<Vec<_>>().join("\n");
+            (
+                BatchOutcome::Crashed,
+                None,
+                Some(format!("{status}\n{tail}")),
+            )
+        }
+    };
+
+    Ok(BatchRecord {
+        instance: instance.display().to_string(),
+        outcome,
+        wall_time_ms,
+        report,
+        error,
+    })
+}
+
+/// Collects the instance files in a directory, recursively, or matching a
+/// glob pattern, in a stable order.
+fn find_instances(input: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut instances = Vec::new();
+
+    if Path::new(input).is_dir() {
+        collect_instances(Path::new(input), &mut instances)?;
+    } else {
+        for path in glob::glob(input)? {
+            let path = path?;
+            if is_instance_file(&path) {
+                instances.push(path);
+            }
+        }
+    }
+
+    if instances.is_empty() {
+        anyhow::bail!("no .spec or .vass.json files found for {input}");
+    }
+    instances.sort();
+    Ok(instances)
+}
+
+fn collect_instances(dir: &Path, instances: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_instances(&path, instances)?;
+        } else if is_instance_file(&path) {
+            instances.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_instance_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".spec") || name.ends_with(".vass.json"))
+}
+
+/// The instances that already have a record in `output`. A truncated last
+/// line, e.g. from an interrupted run, does not count.
+fn finished_instances(output: &Path) -> anyhow::Result<HashSet<String>> {
+    let mut done = HashSet::new();
+
+    for line in BufReader::new(File::open(output)?).lines() {
+        if let Ok(record) = serde_json::from_str::<BatchRecord>(&line?) {
+            done.insert(record.instance);
+        }
+    }
+
+    Ok(done)
+}
@@ -1,11 +1,33 @@
-use std::{fmt::Display, str::FromStr};
+mod batch;
+mod serve;
 
-use clap::Parser;
+use std::{
+    fmt::Display,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
 use vass_reach_lib::{
-    automaton::petri_net::initialized::InitializedPetriNet,
-    config::{VASSReachConfig, VASSZReachConfig},
+    automaton::{
+        petri_net::{initialized::InitializedPetriNet, reduction::PetriNetReduction},
+        vass::{
+            control::{ControlConstraint, ControlRegex},
+            initialized::{InitializedVASS, InitializedVASSJson},
+        },
+    },
+    config::{
+        ArithmeticBackendConfig, UpdateEncoding, VASSReachConfig, VASSZReachConfig, schema_markdown,
+    },
     solver::{
-        SerializableSolverResult, vass_reach::VASSReachSolver, vass_z_reach::VASSZReachSolver,
+        SolverResult,
+        report::{InstanceProvenance, SolverReport},
+        smt_dump::{dumped_queries, replay_query},
+        vass_reach::{VASSReachSolver, VASSReachSolverStatistics, VASSReachWitness},
+        vass_z_reach::{VASSZReachCertificate, VASSZReachSolver, VASSZReachSolverStatistics},
     },
 };
 
@@ -38,6 +60,36 @@ impl Display for Mode {
     }
 }
 
+/// How the result of a run is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    /// A versioned `SolverReport` as JSON.
+    Json,
+    /// A human readable summary.
+    Text,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "text" => Ok(OutputFormat::Text),
+            _ => Err(format!("Invalid output format: {}", s)),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Text => write!(f, "text"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ModeWithConfig {
     N(Box<VASSReachConfig>),
@@ -51,61 +103,420 @@ impl ModeWithConfig {
             Mode::Z => Self::Z(VASSZReachConfig::from_optional_file(config)?),
         })
     }
+
+    pub fn with_assignments(self, assignments: &[String]) -> anyhow::Result<ModeWithConfig> {
+        Ok(match self {
+            Self::N(config) => Self::N(Box::new(config.with_assignments(assignments)?)),
+            Self::Z(config) => Self::Z(config.with_assignments(assignments)?),
+        })
+    }
+
+    pub fn arithmetic_backend(&self) -> &ArithmeticBackendConfig {
+        match self {
+            Self::N(config) => config.get_arithmetic_backend(),
+            Self::Z(config) => config.get_arithmetic_backend(),
+        }
+    }
+
+    pub fn update_encoding(&self) -> UpdateEncoding {
+        match self {
+            Self::N(config) => *config.get_update_encoding(),
+            Self::Z(config) => *config.get_update_encoding(),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        match self {
+            Self::N(config) => config.set_timeout(timeout),
+            Self::Z(config) => config.set_timeout(timeout),
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
 #[command(name = "VASS Reachability Tool")]
 #[command(version = "0.1")]
 #[command(about = "Solve reachability for VASS and Petri-Nets", long_about = None)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    #[arg(short, long, default_value_t = Mode::N)]
+    #[arg(required = true)]
+    file: Option<String>,
+
+    #[arg(short, long, global = true, default_value_t = Mode::N)]
     mode: Mode,
 
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     config: Option<String>,
+
+    /// Only allows runs whose transition letters match this regular
+    /// expression, e.g. `[^t3]* (t3 [^t3]*)?`. Can be given several times.
+    #[arg(long = "control", value_name = "REGEX", global = true)]
+    controls: Vec<ControlRegex>,
+
+    /// Overrides a field of the config, e.g.
+    /// `linear_graph.template_synthesis_max_coefficient=4`. Values are TOML
+    /// values, enum variants can be given as plain strings. Can be given
+    /// several times.
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    assignments: Vec<String>,
+
+    /// Overrides the timeout of the config.
+    #[arg(long, value_name = "SECONDS", global = true)]
+    timeout: Option<u64>,
+
+    /// Applies structural reductions to Petri nets before translating them
+    /// to a VASS. Nets with specs or control constraints are kept as they
+    /// are. Reports of reduced nets contain the reduction statistics, and
+    /// runs are translated back to the original net.
+    #[arg(long, global = true)]
+    reduce: bool,
+
+    /// Writes the result to this file instead of stdout. Logs always go to
+    /// stderr.
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// `json` for a versioned machine readable report, `text` for a summary.
+    #[arg(long, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Reruns dumped SMT-LIB2 queries with the arithmetic backend of the
+    /// config and checks that the answers are unchanged.
+    ReplaySmt {
+        /// Dumped `.smt2` files or directories containing them.
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Answers JSON-RPC requests on stdin and stdout. `--mode` and `--config`
+    /// set the base configuration of requests in that mode.
+    Serve,
+    /// Prints the JSON Schema of the config file of `--mode`.
+    ConfigSchema {
+        /// Prints a Markdown table of all keys instead.
+        #[arg(long)]
+        markdown: bool,
+    },
+    /// Solves every `.spec` and `.vass.json` file in a directory or matching a
+    /// glob and writes one JSON record per instance and line.
+    Batch {
+        /// A directory, searched recursively, or a glob pattern.
+        input: String,
+        /// The number of instances solved in parallel, defaults to the number
+        /// of cores.
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Limits the address space of each solver process.
+        #[arg(long, value_name = "MIB")]
+        memory_limit: Option<u64>,
+        /// Appends the records to this file instead of writing them to
+        /// stdout.
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Skips instances that already have a record in the output file.
+        #[arg(long, requires = "output")]
+        resume: bool,
+    },
+}
+
+/// The report of a run in either mode.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Report {
+    N(Box<SolverReport<VASSReachWitness, VASSReachSolverStatistics>>),
+    Z(Box<SolverReport<VASSZReachCertificate, VASSZReachSolverStatistics>>),
+}
+
+impl Report {
+    /// See [`SolverReport::with_reduction`]. Runs are expanded with
+    /// [`PetriNetReduction::expand_update_run`]. Z certificates count edges of
+    /// the CFG of the reduced net, so they are dropped unless the reduction
+    /// left the net unchanged.
+    pub fn with_reduction(
+        self,
+        reduction: Option<&PetriNetReduction>,
+        encoding: UpdateEncoding,
+    ) -> Self {
+        let Some(reduction) = reduction else {
+            return self;
+        };
+        let statistics = reduction.statistics.clone();
+        match self {
+            Report::N(report) => Report::N(Box::new(report.with_reduction(
+                statistics,
+                |witness| match witness {
+                    VASSReachWitness::Run(run) => {
+                        let expanded = reduction.expand_update_run(&run, encoding.cfg_updates());
+                        if expanded.is_none() {
+                            tracing::warn!(
+                                "Witness does not split into transitions of the reduced net"
+                            );
+                        }
+                        expanded.map(VASSReachWitness::Run)
+                    }
+                    // nets with specs are never reduced
+                    witness @ (VASSReachWitness::SpecRun(_) | VASSReachWitness::Ltc) => {
+                        Some(witness)
+                    }
+                },
+            ))),
+            Report::Z(report) => {
+                Report::Z(Box::new(report.with_reduction(statistics, |certificate| {
+                    (!reduction.statistics.is_reduced()).then_some(certificate)
+                })))
+            }
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> anyhow::Result<String> {
+        match self {
+            Report::N(report) => render_report(report, format),
+            Report::Z(report) => render_report(report, format),
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
     let args = Args::parse();
 
-    let config = ModeWithConfig::from_file(args.mode, args.config)?;
+    if let Some(Command::ConfigSchema { markdown }) = args.command {
+        let schema = match args.mode {
+            Mode::N => VASSReachConfig::json_schema(),
+            Mode::Z => VASSZReachConfig::json_schema(),
+        };
+        match markdown {
+            true => print!("{}", schema_markdown(&schema)),
+            false => println!("{}", serde_json::to_string_pretty(&schema)?),
+        }
+        return Ok(());
+    }
 
-    let vass = load_initialized_vass(&args.file)?;
+    if let Some(Command::Batch {
+        input,
+        jobs,
+        memory_limit,
+        output,
+        resume,
+    }) = args.command
+    {
+        return batch::batch(batch::BatchOptions {
+            input,
+            mode: args.mode,
+            config: args.config,
+            controls: args.controls,
+            assignments: args.assignments,
+            timeout: args.timeout,
+            reduce: args.reduce,
+            jobs: jobs.unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+            }),
+            memory_limit,
+            output,
+            resume,
+        });
+    }
 
-    match config {
-        ModeWithConfig::N(c) => {
-            let res = VASSReachSolver::new(&vass, *c).solve();
+    let mut config =
+        ModeWithConfig::from_file(args.mode, args.config)?.with_assignments(&args.assignments)?;
+    if let Some(timeout) = args.timeout {
+        config.set_timeout(Some(Duration::from_secs(timeout)));
+    }
 
-            let json_res = serde_json::to_string_pretty(&SerializableSolverResult::from(res))?;
-            println!("{}", json_res);
+    match args.command {
+        Some(Command::ReplaySmt { paths }) => {
+            return replay_smt(&paths, config.arithmetic_backend());
+        }
+        Some(Command::Serve) => return serve::serve(config, args.reduce),
+        _ => {}
+    }
+
+    let controls = args
+        .controls
+        .into_iter()
+        .map(ControlConstraint::Regex)
+        .collect();
+    let file = args.file.expect("file is required without a subcommand");
+    let (vass, reduction) = load_initialized_vass(&file, controls, args.reduce)?;
+    let instance = InstanceProvenance::new(&file, &std::fs::read(&file)?);
+
+    let encoding = config.update_encoding();
+    let output = solve(&vass, config, instance, None)?
+        .with_reduction(reduction.as_ref(), encoding)
+        .render(args.format)?;
+
+    match args.output {
+        Some(path) => std::fs::write(path, output)?,
+        None => print!("{}", output),
+    }
+
+    Ok(())
+}
+
+/// Solves `vass` with the solver of the mode of `config`. Raising
+/// `stop_signal` cancels the solver, which then reports an unknown result.
+pub fn solve(
+    vass: &InitializedVASS<usize, usize>,
+    config: ModeWithConfig,
+    instance: InstanceProvenance,
+    stop_signal: Option<Arc<AtomicBool>>,
+) -> anyhow::Result<Report> {
+    Ok(match config {
+        ModeWithConfig::N(c) => {
+            let res = match stop_signal {
+                Some(signal) => VASSReachSolver::new_with_stop_signal(vass, (*c).clone(), signal),
+                None => VASSReachSolver::new(vass, (*c).clone()),
+            }
+            .solve();
+            let res = SolverResult::new(
+                res.status
+                    .map_success(|witness| spec_witness(vass, witness, *c.get_update_encoding())),
+                res.statistics,
+            );
+            Report::N(Box::new(SolverReport::new(
+                Mode::N.to_string(),
+                instance,
+                &c,
+                res,
+            )?))
         }
         ModeWithConfig::Z(c) => {
-            let res = VASSZReachSolver::new(
-                &vass.to_cfg(),
+            let cfg = match c.get_update_encoding() {
+                UpdateEncoding::Unary => vass.to_cfg(),
+                UpdateEncoding::Binary => vass.to_binary_cfg(),
+            };
+            let mut solver = VASSZReachSolver::new(
+                &cfg,
                 vass.initial_valuation.clone(),
                 vass.final_valuation.clone(),
-                c,
-            )
-            .solve();
+                c.clone(),
+            );
+            if let Some(signal) = stop_signal {
+                solver = solver.with_stop_signal(signal);
+            }
+            let res = solver.solve();
 
-            let json_res = serde_json::to_string_pretty(&SerializableSolverResult::from(res))?;
-            println!("{}", json_res);
+            let certificate = res.certificate(&cfg);
+            let res = SolverResult::new(
+                res.status
+                    .map_success(|_| certificate.expect("reachable result")),
+                res.statistics,
+            );
+            Report::Z(Box::new(SolverReport::new(
+                Mode::Z.to_string(),
+                instance,
+                &c,
+                res,
+            )?))
         }
+    })
+}
+
+/// Reports runs of instances with valuation specs between the valuations
+/// they chose, see [`InitializedVASS::spec_run`].
+fn spec_witness(
+    vass: &InitializedVASS<usize, usize>,
+    witness: VASSReachWitness,
+    encoding: UpdateEncoding,
+) -> VASSReachWitness {
+    match witness {
+        VASSReachWitness::Run(run) if vass.valuation_spec_states.is_some() => {
+            match vass.spec_run(&run, encoding.cfg_updates()) {
+                Some(run) => VASSReachWitness::SpecRun(run),
+                None => {
+                    tracing::warn!("Witness is not a run of the instance with valuation specs");
+                    VASSReachWitness::Run(run)
+                }
+            }
+        }
+        witness => witness,
     }
+}
+
+fn render_report<W: Display + Serialize, S: Serialize>(
+    report: &SolverReport<W, S>,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
+    Ok(match format {
+        OutputFormat::Json => serde_json::to_string_pretty(report)? + "\n",
+        OutputFormat::Text => report.to_text()?,
+    })
+}
+
+fn replay_smt(paths: &[PathBuf], backend: &ArithmeticBackendConfig) -> anyhow::Result<()> {
+    let mut changed = 0;
+    let mut total = 0;
+
+    for path in paths {
+        for query in dumped_queries(path)? {
+            let replay = replay_query(&query, backend)?;
+            total += 1;
+
+            let verdict = if replay.is_unchanged() {
+                "unchanged"
+            } else {
+                changed += 1;
+                "CHANGED"
+            };
+            let recorded_elapsed = replay
+                .recorded_elapsed
+                .map(|elapsed| format!("{} ms", elapsed.as_millis()))
+                .unwrap_or_else(|| "?".to_string());
+            println!(
+                "{}: {} recorded {} in {}, replayed {} in {} ms, {}",
+                query.display(),
+                replay.purpose,
+                replay.recorded,
+                recorded_elapsed,
+                replay.replayed,
+                replay.replayed_elapsed.as_millis(),
+                verdict
+            );
+        }
+    }
+
+    if changed > 0 {
+        anyhow::bail!("{changed} of {total} replayed queries changed their answer");
+    }
+    println!("All {total} replayed queries are unchanged");
 
     Ok(())
 }
 
+/// Loads the instance in `file`. The reduction is only present
+/// if `reduce` is set and the instance is a Petri net.
 fn load_initialized_vass(
     file: &str,
-) -> anyhow::Result<vass_reach_lib::automaton::vass::initialized::InitializedVASS<usize, usize>> {
+    controls: Vec<ControlConstraint>,
+    reduce: bool,
+) -> anyhow::Result<(InitializedVASS<usize, usize>, Option<PetriNetReduction>)> {
     if file.ends_with(".vass.json") {
-        return vass_reach_lib::automaton::vass::initialized::InitializedVASS::from_json_file(file);
+        let mut json: InitializedVASSJson = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+        json.control_constraints.extend(controls);
+        return Ok((InitializedVASS::try_from(json)?, None));
+    }
+
+    let petri_net = InitializedPetriNet::from_file(file)?.with_control_constraints(controls);
+    Ok(petri_net_to_vass(petri_net, reduce))
+}
+
+/// Translates `petri_net` to a VASS, reducing it first if `reduce` is set.
+fn petri_net_to_vass(
+    petri_net: InitializedPetriNet,
+    reduce: bool,
+) -> (InitializedVASS<usize, usize>, Option<PetriNetReduction>) {
+    if !reduce {
+        return (petri_net.to_vass(), None);
     }
 
-    let petri_net = InitializedPetriNet::from_file(file)?;
-    Ok(petri_net.to_vass())
+    let reduction = petri_net.reduce();
+    tracing::info!(statistics = ?reduction.statistics, "Reduced Petri net");
+    (reduction.net.to_vass(), Some(reduction))
 }
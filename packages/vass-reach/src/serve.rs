@@ -0,0 +1,725 @@
+//! `vass-reach serve` answers JSON-RPC 2.0 requests on stdin and writes the
+//! responses to stdout. Messages are framed like in LSP: a `Content-Length`
+//! header, an empty line and the JSON body.
+//!
+//! Methods:
+//! - `loadInstance` with one of `path` (a `.spec` or `.vass.json` file), `spec`
+//!   (the text of a Petri net spec) or `vass` (an `InitializedVASSJson`
+//!   object), and optional `controls` regexes. Returns `{ "instance": id,
+//!   "hash": ... }`.
+//! - `solve` with `instance` or the fields of `loadInstance`, and optionally
+//!   `mode` (`N` or `Z`, defaults to `--mode`), `config` (overrides in the
+//!   shape of a config file) and `timeoutMs`. As many requests as there are
+//!   cores run concurrently, further ones wait for a free slot. The result is
+//!   the JSON report the CLI prints. A `solve` with the id of a running or
+//!   waiting one is rejected.
+//! - `cancel` with the `id` of a running or waiting `solve` request, which then
+//!   answers with an unknown result.
+//! - `status` lists the running and waiting requests and the loaded instances.
+//! - `shutdown` stops reading requests. The server exits once the running
+//!   requests have answered, as it does at the end of stdin.
+//!
+//! With `--reduce`, Petri nets are reduced when they are loaded, like in the
+//! CLI.
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use vass_reach_lib::{
+    automaton::{
+        petri_net::{initialized::InitializedPetriNet, reduction::PetriNetReduction},
+        vass::{
+            control::{ControlConstraint, ControlRegex},
+            initialized::{InitializedVASS, InitializedVASSJson},
+        },
+    },
+    config::{VASSReachConfig, VASSZReachConfig},
+    solver::report::InstanceProvenance,
+};
+
+use crate::{Mode, ModeWithConfig, load_initialized_vass, petri_net_to_vass, solve};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+struct RequestError {
+    code: i64,
+    message: String,
+}
+
+impl RequestError {
+    fn invalid_params(err: impl std::fmt::Display) -> Self {
+        RequestError {
+            code: INVALID_PARAMS,
+            message: err.to_string(),
+        }
+    }
+
+    fn internal(err: impl std::fmt::Display) -> Self {
+        RequestError {
+            code: INTERNAL_ERROR,
+            message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceParams {
+    path: Option<String>,
+    spec: Option<String>,
+    vass: Option<InitializedVASSJson>,
+    #[serde(default)]
+    controls: Vec<ControlRegex>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolveParams {
+    instance: Option<u64>,
+    #[serde(flatten)]
+    source: InstanceParams,
+    mode: Option<String>,
+    #[serde(default)]
+    config: Value,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelParams {
+    id: Value,
+}
+
+struct LoadedInstance {
+    vass: InitializedVASS<usize, usize>,
+    provenance: InstanceProvenance,
+    reduction: Option<PetriNetReduction>,
+}
+
+struct RunningRequest {
+    id: Value,
+    /// Whether the request still waits for a free slot.
+    waiting: bool,
+    /// Set once the instance is loaded and the params are valid.
+    instance: Option<String>,
+    mode: Option<Mode>,
+    started: Instant,
+    stop_signal: Arc<AtomicBool>,
+}
+
+struct Server {
+    /// The mode of `solve` requests without one.
+    mode: Mode,
+    n_config: VASSReachConfig,
+    z_config: VASSZReachConfig,
+    reduce: bool,
+    /// The number of `solve` requests that may still start.
+    free_slots: Mutex<usize>,
+    slot_freed: Condvar,
+    instances: Mutex<Vec<Arc<LoadedInstance>>>,
+    /// Keyed by the serialized request id.
+    running: Mutex<HashMap<String, RunningRequest>>,
+    output: Mutex<std::io::Stdout>,
+}
+
+/// Serves requests until stdin ends or a `shutdown` request arrives. `config`
+/// is the base configuration of requests in its mode, the other mode starts
+/// from the defaults. `reduce` applies to every loaded Petri net.
+pub fn serve(config: ModeWithConfig, reduce: bool) -> anyhow::Result<()> {
+    let slots = std::thread::available_parallelism().map_or(1, |slots| slots.get());
+    let server = Arc::new(Server::new(config, reduce, slots));
+
+    let mut input = MessageReader::new(std::io::stdin().lock());
+    let mut workers = Vec::new();
+
+    while let Some(message) = input.next()? {
+        let body = match message {
+            Message::Body(body) => body,
+            Message::Malformed(err) => {
+                server.respond_error(Value::Null, PARSE_ERROR, err)?;
+                continue;
+            }
+        };
+        let message = match serde_json::from_slice::<Value>(&body) {
+            Ok(message) => message,
+            Err(err) => {
+                server.respond_error(Value::Null, PARSE_ERROR, err.to_string())?;
+                continue;
+            }
+        };
+
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            server.respond_error(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "missing method".to_string(),
+            )?;
+            continue;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match method {
+            "solve" => {
+                let Some(id) = id else {
+                    tracing::warn!("ignoring solve notification without id");
+                    continue;
+                };
+                // Registered before the worker starts, so that a `cancel`
+                // right behind the request finds it.
+                let Some(stop_signal) = server.register(&id) else {
+                    server.respond_error(
+                        id.clone(),
+                        INVALID_REQUEST,
+                        format!("request {id} is already running"),
+                    )?;
+                    continue;
+                };
+                let server = server.clone();
+                workers.push(std::thread::spawn(move || {
+                    let slot = server.wait_for_slot(&id, &stop_signal);
+                    let result = server.solve(&id, params, stop_signal);
+                    drop(slot);
+                    server.running.lock().unwrap().remove(&id.to_string());
+                    if let Err(err) = server.respond(id, result) {
+                        tracing::error!(error = %err, "failed to write response");
+                    }
+                }));
+            }
+            "shutdown" => {
+                if let Some(id) = id {
+                    server.respond(id, Ok(Value::Null))?;
+                }
+                break;
+            }
+            _ => {
+                let result = match method {
+                    "loadInstance" => server.load_instance(params),
+                    "cancel" => server.cancel(params),
+                    "status" => Ok(server.status()),
+                    _ => Err(RequestError {
+                        code: METHOD_NOT_FOUND,
+                        message: format!("unknown method {method}"),
+                    }),
+                };
+                // notifications get no response
+                if let Some(id) = id {
+                    server.respond(id, result)?;
+                }
+            }
+        }
+
+        workers.retain(|worker| !worker.is_finished());
+    }
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    Ok(())
+}
+
+impl Server {
+    /// `config` is the base configuration of requests in its mode, the other
+    /// mode starts from the defaults. At most `slots` requests solve at once.
+    fn new(config: ModeWithConfig, reduce: bool, slots: usize) -> Self {
+        let (mode, n_config, z_config) = match config {
+            ModeWithConfig::N(config) => (Mode::N, *config, VASSZReachConfig::default()),
+            ModeWithConfig::Z(config) => (Mode::Z, VASSReachConfig::default(), config),
+        };
+
+        Server {
+            mode,
+            n_config,
+            z_config,
+            reduce,
+            free_slots: Mutex::new(slots.max(1)),
+            slot_freed: Condvar::new(),
+            instances: Mutex::new(Vec::new()),
+            running: Mutex::new(HashMap::new()),
+            output: Mutex::new(std::io::stdout()),
+        }
+    }
+
+    fn load_instance(&self, params: Value) -> Result<Value, RequestError> {
+        let params: InstanceParams =
+            serde_json::from_value(params).map_err(RequestError::invalid_params)?;
+        let instance = load(params, self.reduce)?;
+        let hash = instance.provenance.hash.clone();
+
+        let mut instances = self.instances.lock().unwrap();
+        instances.push(Arc::new(instance));
+        Ok(json!({ "instance": instances.len() - 1, "hash": hash }))
+    }
+
+    /// Adds a running `solve` request and returns its stop signal, or `None`
+    /// if a request with the same id is still running.
+    fn register(&self, id: &Value) -> Option<Arc<AtomicBool>> {
+        let mut running = self.running.lock().unwrap();
+        let key = id.to_string();
+        if running.contains_key(&key) {
+            return None;
+        }
+
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        running.insert(
+            key,
+            RunningRequest {
+                id: id.clone(),
+                waiting: true,
+                instance: None,
+                mode: None,
+                started: Instant::now(),
+                stop_signal: stop_signal.clone(),
+            },
+        );
+        Some(stop_signal)
+    }
+
+    /// Blocks until the request may solve. A cancelled request stops waiting
+    /// without a slot, its solver gives up right away.
+    fn wait_for_slot(&self, id: &Value, stop_signal: &AtomicBool) -> Option<SolveSlot<'_>> {
+        let mut free_slots = self.free_slots.lock().unwrap();
+        while *free_slots == 0 {
+            if stop_signal.load(Ordering::SeqCst) {
+                return None;
+            }
+            free_slots = self
+                .slot_freed
+                .wait_timeout(free_slots, Duration::from_millis(50))
+                .unwrap()
+                .0;
+        }
+        *free_slots -= 1;
+        drop(free_slots);
+
+        if let Some(request) = self.running.lock().unwrap().get_mut(&id.to_string()) {
+            request.waiting = false;
+        }
+        Some(SolveSlot { server: self })
+    }
+
+    fn solve(
+        &self,
+        id: &Value,
+        params: Value,
+        stop_signal: Arc<AtomicBool>,
+    ) -> Result<Value, RequestError> {
+        let params: SolveParams =
+            serde_json::from_value(params).map_err(RequestError::invalid_params)?;
+
+        let instance = match params.instance {
+            Some(index) => self
+                .instances
+                .lock()
+                .unwrap()
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| RequestError::invalid_params(format!("unknown instance {index}")))?,
+            None => Arc::new(load(params.source, self.reduce)?),
+        };
+
+        let mode = match params.mode {
+            Some(mode) => mode.parse().map_err(RequestError::invalid_params)?,
+            None => self.mode,
+        };
+        let timeout = params.timeout_ms.map(Duration::from_millis);
+        let config = match mode {
+            Mode::N => {
+                let mut config = self.n_config.clone();
+                if !params.config.is_null() {
+                    config = config
+                        .with_overrides(&params.config)
+                        .map_err(RequestError::invalid_params)?;
+                }
+                if timeout.is_some() {
+                    config.set_timeout(timeout);
+                }
+                ModeWithConfig::N(Box::new(config))
+            }
+            Mode::Z => {
+                let mut config = self.z_config.clone();
+                if !params.config.is_null() {
+                    config = config
+                        .with_overrides(&params.config)
+                        .map_err(RequestError::invalid_params)?;
+                }
+                if timeout.is_some() {
+                    config.set_timeout(timeout);
+                }
+                ModeWithConfig::Z(config)
+            }
+        };
+
+        if let Some(request) = self.running.lock().unwrap().get_mut(&id.to_string()) {
+            request.instance = Some(instance.provenance.path.clone());
+            request.mode = Some(mode);
+        }
+
+        let encoding = config.update_encoding();
+        let report = solve(
+            &instance.vass,
+            config,
+            instance.provenance.clone(),
+            Some(stop_signal),
+        )
+        .map_err(RequestError::internal)?
+        .with_reduction(instance.reduction.as_ref(), encoding);
+        serde_json::to_value(report).map_err(RequestError::internal)
+    }
+
+    fn cancel(&self, params: Value) -> Result<Value, RequestError> {
+        let params: CancelParams =
+            serde_json::from_value(params).map_err(RequestError::invalid_params)?;
+        let running = self.running.lock().unwrap();
+        let request = running.get(&params.id.to_string());
+        if let Some(request) = request {
+            request.stop_signal.store(true, Ordering::SeqCst);
+        }
+        Ok(json!({ "cancelled": request.is_some() }))
+    }
+
+    fn status(&self) -> Value {
+        let running = self
+            .running
+            .lock()
+            .unwrap()
+            .values()
+            .map(|request| {
+                json!({
+                    "id": request.id,
+                    "waiting": request.waiting,
+                    "instance": request.instance,
+                    "mode": request.mode.map(|mode| mode.to_string()),
+                    "elapsedMs": request.started.elapsed().as_millis() as u64,
+                    "cancelled": request.stop_signal.load(Ordering::SeqCst),
+                })
+            })
+            .collect::<Vec<_>>();
+        let instances = self
+            .instances
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| {
+                json!({
+                    "instance": index,
+                    "path": instance.provenance.path,
+                    "hash": instance.provenance.hash,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "running": running, "instances": instances })
+    }
+
+    fn respond(&self, id: Value, result: Result<Value, RequestError>) -> anyhow::Result<()> {
+        match result {
+            Ok(result) => self.write(&json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            Err(err) => self.respond_error(id, err.code, err.message),
+        }
+    }
+
+    fn respond_error(&self, id: Value, code: i64, message: String) -> anyhow::Result<()> {
+        self.write(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }))
+    }
+
+    fn write(&self, message: &Value) -> anyhow::Result<()> {
+        let body = serde_json::to_string(message)?;
+        let mut output = self.output.lock().unwrap();
+        write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// A slot of a solving request, freed on drop.
+struct SolveSlot<'a> {
+    server: &'a Server,
+}
+
+impl Drop for SolveSlot<'_> {
+    fn drop(&mut self) {
+        *self.server.free_slots.lock().unwrap() += 1;
+        self.server.slot_freed.notify_one();
+    }
+}
+
+fn load(params: InstanceParams, reduce: bool) -> Result<LoadedInstance, RequestError> {
+    let controls = params
+        .controls
+        .into_iter()
+        .map(ControlConstraint::Regex)
+        .collect::<Vec<_>>();
+
+    let (vass, reduction, provenance) = match (params.path, params.spec, params.vass) {
+        (Some(path), None, None) => {
+            let content = std::fs::read(&path)
+                .with_context(|| format!("failed to read {path}"))
+                .map_err(RequestError::invalid_params)?;
+            let (vass, reduction) = load_initialized_vass(&path, controls, reduce)
+                .map_err(RequestError::invalid_params)?;
+            (vass, reduction, InstanceProvenance::new(path, &content))
+        }
+        (None, Some(spec), None) => {
+            let petri_net = InitializedPetriNet::parse_from_spec(&spec)
+                .map_err(RequestError::invalid_params)?
+                .with_control_constraints(controls);
+            let (vass, reduction) = petri_net_to_vass(petri_net, reduce);
+            (
+                vass,
+                reduction,
+                InstanceProvenance::new("<spec>", spec.as_bytes()),
+            )
+        }
+        (None, None, Some(mut json)) => {
+            let content = serde_json::to_vec(&json).map_err(RequestError::internal)?;
+            json.control_constraints.extend(controls);
+            let vass = InitializedVASS::try_from(json).map_err(RequestError::invalid_params)?;
+            (vass, None, InstanceProvenance::new("<vass>", &content))
+        }
+        _ => {
+            return Err(RequestError::invalid_params(
+                "expected exactly one of path, spec and vass",
+            ));
+        }
+    };
+
+    Ok(LoadedInstance {
+        vass,
+        provenance,
+        reduction,
+    })
+}
+
+enum Message {
+    Body(Vec<u8>),
+    /// A message whose header could not be parsed.
+    Malformed(String),
+}
+
+/// Splits the input into messages.
+struct MessageReader<R> {
+    input: R,
+    /// Set after a malformed message, whose body has an unknown length. The
+    /// reader then skips ahead to the next `Content-Length` header.
+    resync: bool,
+}
+
+impl<R: BufRead> MessageReader<R> {
+    fn new(input: R) -> Self {
+        MessageReader {
+            input,
+            resync: false,
+        }
+    }
+
+    /// Reads the next message, or `None` at the end of the input.
+    fn next(&mut self) -> anyhow::Result<Option<Message>> {
+        let mut content_length = None;
+        let mut malformed = None;
+
+        loop {
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            let mut line = line.trim_end();
+            if self.resync {
+                // the body is not newline terminated, so the next header may
+                // start in the middle of a line
+                let Some(start) = line.to_ascii_lowercase().find("content-length:") else {
+                    continue;
+                };
+                line = &line[start..];
+                self.resync = false;
+            }
+
+            if line.is_empty() {
+                // tolerate blank lines between messages
+                if content_length.is_some() || malformed.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                match value.trim().parse::<usize>() {
+                    Ok(length) => content_length = Some(length),
+                    Err(err) => {
+                        malformed =
+                            Some(format!("invalid Content-Length {:?}: {err}", value.trim()))
+                    }
+                }
+            }
+        }
+
+        if let Some(err) = malformed {
+            self.resync = true;
+            return Ok(Some(Message::Malformed(err)));
+        }
+
+        let mut body = vec![0; content_length.unwrap()];
+        self.input.read_exact(&mut body)?;
+        Ok(Some(Message::Body(body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, thread};
+
+    use super::*;
+
+    const SPEC: &str = "vars
+    p1 p2
+rules
+    p1 >= 1 ->
+        p1' = p1-1,
+        p2' = p2+1;
+init
+    p1=1, p2=0
+target
+    p1=0, p2=1
+";
+
+    fn read_all(input: &str) -> Vec<Result<String, String>> {
+        let mut reader = MessageReader::new(Cursor::new(input.as_bytes().to_vec()));
+        let mut messages = Vec::new();
+        while let Some(message) = reader.next().unwrap() {
+            messages.push(match message {
+                Message::Body(body) => Ok(String::from_utf8(body).unwrap()),
+                Message::Malformed(err) => Err(err),
+            });
+        }
+        messages
+    }
+
+    fn server(slots: usize) -> Server {
+        let config = ModeWithConfig::Z(VASSZReachConfig::default());
+        Server::new(config, false, slots)
+    }
+
+    #[test]
+    fn messages_are_split_by_their_content_length() {
+        let messages = read_all(
+            "Content-Length: 2\r\n\r\n{}\
+             content-length: 7\r\nContent-Type: application/json\r\n\r\n[1,2,3]\
+             \r\n\r\nContent-Length: 1\r\n\r\n1",
+        );
+
+        assert_eq!(
+            messages,
+            [
+                Ok("{}".to_string()),
+                Ok("[1,2,3]".to_string()),
+                Ok("1".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn readers_resync_after_a_malformed_header() {
+        let messages = read_all(
+            "Content-Length: many\r\n\r\n{\"lost\": true}Content-Length: 2\r\n\r\n{}\
+             Content-Length: 4\r\n\r\nnull",
+        );
+
+        assert_eq!(messages.len(), 3, "{messages:?}");
+        assert!(messages[0].as_ref().is_err_and(|err| err.contains("many")));
+        assert_eq!(messages[1], Ok("{}".to_string()));
+        assert_eq!(messages[2], Ok("null".to_string()));
+    }
+
+    #[test]
+    fn truncated_bodies_are_errors() {
+        let mut reader = MessageReader::new(Cursor::new(b"Content-Length: 10\r\n\r\n{}".to_vec()));
+        assert!(reader.next().is_err());
+    }
+
+    #[test]
+    fn cancel_raises_the_stop_signal_of_the_request() {
+        let server = server(1);
+        let stop_signal = server.register(&json!(1)).unwrap();
+        assert!(server.register(&json!(1)).is_none());
+
+        let cancelled = server.cancel(json!({ "id": 1 })).ok().unwrap();
+        assert_eq!(cancelled, json!({ "cancelled": true }));
+        assert!(stop_signal.load(Ordering::SeqCst));
+
+        let cancelled = server.cancel(json!({ "id": 2 })).ok().unwrap();
+        assert_eq!(cancelled, json!({ "cancelled": false }));
+    }
+
+    #[test]
+    fn status_lists_requests_and_instances() {
+        let server = server(1);
+        server.load_instance(json!({ "spec": SPEC })).ok().unwrap();
+        let stop_signal = server.register(&json!("a")).unwrap();
+
+        let status = server.status();
+        assert_eq!(status["instances"].as_array().unwrap().len(), 1);
+        assert_eq!(status["instances"][0]["path"], "<spec>");
+        assert_eq!(status["running"][0]["id"], "a");
+        assert_eq!(status["running"][0]["waiting"], true);
+        assert_eq!(status["running"][0]["cancelled"], false);
+
+        let _slot = server.wait_for_slot(&json!("a"), &stop_signal).unwrap();
+        assert_eq!(server.status()["running"][0]["waiting"], false);
+    }
+
+    #[test]
+    fn requests_wait_for_a_free_slot_until_cancelled() {
+        let server = server(1);
+        let first = server.register(&json!(1)).unwrap();
+        let second = server.register(&json!(2)).unwrap();
+        let slot = server.wait_for_slot(&json!(1), &first).unwrap();
+
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| server.wait_for_slot(&json!(2), &second).is_some());
+            thread::sleep(Duration::from_millis(100));
+            assert!(!waiting.is_finished());
+            assert_eq!(server.status()["running"].as_array().unwrap().len(), 2);
+
+            server.cancel(json!({ "id": 2 })).ok().unwrap();
+            assert!(!waiting.join().unwrap());
+        });
+
+        drop(slot);
+        let third = server.register(&json!(3)).unwrap();
+        assert!(server.wait_for_slot(&json!(3), &third).is_some());
+    }
+
+    #[test]
+    fn solve_defaults_to_the_mode_of_the_server() {
+        let server = server(1);
+        let stop_signal = server.register(&json!(1)).unwrap();
+
+        let report = server
+            .solve(&json!(1), json!({ "spec": SPEC }), stop_signal)
+            .ok()
+            .unwrap();
+        assert_eq!(report["mode"], "Z");
+        assert_eq!(server.status()["running"][0]["mode"], "Z");
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const REACHABLE: &str = "vars
+    p1 p2
+rules
+    p1 >= 1 ->
+        p1' = p1-1,
+        p2' = p2+1;
+init
+    p1=2, p2=0
+target
+    p1=0, p2=2
+";
+
+const UNREACHABLE: &str = "vars
+    p1 p2
+rules
+    p1 >= 1 ->
+        p1' = p1-1,
+        p2' = p2+1;
+init
+    p1=1, p2=0
+target
+    p1=0, p2=2
+";
+
+fn batch_dir(name: &str) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("vass-batch-{name}-{unique}"));
+    std::fs::create_dir_all(dir.join("instances")).unwrap();
+    dir
+}
+
+fn run_batch(dir: &Path, records: &Path) {
+    let output = Command::new(env!("CARGO_BIN_EXE_vass-reach"))
+        .arg("batch")
+        .arg(dir.join("instances"))
+        .arg("--jobs")
+        .arg("1")
+        .arg("--timeout")
+        .arg("20")
+        .arg("--output")
+        .arg(records)
+        .arg("--resume")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// The parsable records of the file, skipping truncated lines.
+fn read_records(records: &Path) -> Vec<serde_json::Value> {
+    std::fs::read_to_string(records)
+        .unwrap()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+#[test]
+fn resumed_batches_only_solve_unfinished_instances() {
+    let dir = batch_dir("resume");
+    let reachable = dir.join("instances/reachable.spec");
+    let unreachable = dir.join("instances/unreachable.spec");
+    std::fs::write(&reachable, REACHABLE).unwrap();
+    std::fs::write(&unreachable, UNREACHABLE).unwrap();
+
+    // A record of an earlier run and a line of an interrupted one.
+    let records = dir.join("records.jsonl");
+    let finished = serde_json::json!({
+        "instance": reachable.display().to_string(),
+        "outcome": "killed",
+        "wall_time_ms": 1,
+    });
+    std::fs::write(
+        &records,
+        format!(
+            "{finished}\n{{\"instance\":\"{}\",\"outc",
+            unreachable.display()
+        ),
+    )
+    .unwrap();
+
+    run_batch(&dir, &records);
+    let after_first_run = read_records(&records);
+    assert_eq!(after_first_run.len(), 2, "{after_first_run:?}");
+    assert_eq!(after_first_run[0], finished);
+    assert_eq!(
+        after_first_run[1]["instance"],
+        unreachable.display().to_string()
+    );
+    assert_eq!(after_first_run[1]["outcome"], "solved");
+    assert_eq!(after_first_run[1]["report"]["status"], "False");
+
+    run_batch(&dir, &records);
+    assert_eq!(read_records(&records), after_first_run);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}